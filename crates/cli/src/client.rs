@@ -1,10 +1,36 @@
 use anyhow::{anyhow, Context, Result};
 use pastedev_core::{
-    CreateSnippetRequest, ErrorEnvelope, ListSnippetsResponse, PatchSnippetRequest,
-    SettingsRequest, Snippet, SnippetType, UserPublic,
+    AppendResponse, CreateAliasRequest, CreateShareLinkRequest, CreateSnippetRequest, ErrorEnvelope, ForkRequest,
+    ListAliasesResponse, ListRevisionsResponse, ListShareLinksResponse, ListSharesResponse, ListSnippetsResponse, ListTrashResponse, PatchSnippetRequest, SearchSnippetsResponse,
+    RenameRequest, SettingsRequest, ShareLink, ShareRequest, Snippet, SnippetAlias, SnippetEvent, SnippetRevision, SnippetSort, SnippetType, SortOrder, UserPublic, Visibility,
+    IDEMPOTENCY_KEY_HEADER,
 };
-use reqwest::{Client, Method, StatusCode};
+use reqwest::{header, Client, Method, StatusCode};
 use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+/// Tries at `create_snippet` before a transport error is final.
+const CREATE_ATTEMPTS: u64 = 3;
+
+/// Query for [`ApiClient::list_snippets`]. Every `None` is left to the
+/// server's default.
+#[derive(Debug, Default)]
+pub struct ListOptions<'a> {
+    pub kind: Option<SnippetType>,
+    pub visibility: Option<Visibility>,
+    pub burn_after_read: Option<bool>,
+    pub expired: Option<bool>,
+    pub sort: Option<SnippetSort>,
+    pub order: Option<SortOrder>,
+    pub cursor: Option<&'a str>,
+    pub limit: Option<u32>,
+    /// List this org's snippets rather than the caller's own.
+    pub org: Option<&'a str>,
+    /// Snippets carrying all of these tags.
+    pub tags: &'a [String],
+    /// Snippets in this collection of the caller's.
+    pub collection: Option<&'a str>,
+}
 
 pub struct ApiClient {
     pub base_url: String,
@@ -38,21 +64,43 @@ impl ApiClient {
             req = req.json(b);
         }
         let resp = req.send().await.context("sending request")?;
+        Self::read_json(resp).await
+    }
+
+    /// [`Self::send_json`] with an optional `If-Match`, also returning the
+    /// response's `ETag`.
+    async fn send_versioned<B: serde::Serialize, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+        if_match: Option<&str>,
+    ) -> Result<(R, Option<String>)> {
+        let mut req = self
+            .inner
+            .request(method, self.url(path))
+            .bearer_auth(&self.token);
+        if let Some(tag) = if_match {
+            req = req.header(header::IF_MATCH, tag);
+        }
+        if let Some(b) = body {
+            req = req.json(b);
+        }
+        let resp = req.send().await.context("sending request")?;
+        let etag = resp
+            .headers()
+            .get(header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        Ok((Self::read_json(resp).await?, etag))
+    }
+
+    /// The part of [`Self::send_json`] after the request went out.
+    async fn read_json<R: DeserializeOwned>(resp: reqwest::Response) -> Result<R> {
         let status = resp.status();
         let bytes = resp.bytes().await?;
         if !status.is_success() {
-            let body_str = String::from_utf8_lossy(&bytes).to_string();
-            // Try to parse the error envelope; fall back to plain text.
-            if let Ok(env) = serde_json::from_slice::<ErrorEnvelope>(&bytes) {
-                return Err(anyhow!(
-                    "{} {}: {} — {}",
-                    status.as_u16(),
-                    status.canonical_reason().unwrap_or(""),
-                    serde_json::to_string(&env.error.code).unwrap_or_default().trim_matches('"').to_string(),
-                    env.error.message,
-                ));
-            }
-            return Err(anyhow!("{}: {}", status, body_str));
+            return Err(error_from(status, &bytes));
         }
         if bytes.is_empty() {
             // For DELETE/204-style responses, R should be `()`. Construct manually if so.
@@ -61,37 +109,182 @@ impl ApiClient {
         serde_json::from_slice::<R>(&bytes).context("parsing response")
     }
 
+    /// GET a `text/plain` endpoint (e.g. the revision diff). Errors still
+    /// come back as the JSON envelope and are rendered the same way as in
+    /// [`Self::send_json`].
+    async fn get_text(&self, path: &str) -> Result<String> {
+        let resp = self
+            .inner
+            .get(self.url(path))
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .context("sending request")?;
+        let status = resp.status();
+        let bytes = resp.bytes().await?;
+        if !status.is_success() {
+            return Err(error_from(status, &bytes));
+        }
+        String::from_utf8(bytes.to_vec()).context("response is not valid UTF-8")
+    }
+
     pub async fn me(&self) -> Result<UserPublic> {
         self.send_json::<(), UserPublic>(Method::GET, "/api/v1/auth/me", None).await
     }
 
+    /// Sent with an [`IDEMPOTENCY_KEY_HEADER`], so when the connection drops
+    /// the retry gets back the snippet the server may already have created
+    /// instead of making a second one.
     pub async fn create_snippet(&self, body: &CreateSnippetRequest) -> Result<Snippet> {
-        self.send_json(Method::POST, "/api/v1/snippets", Some(body)).await
+        let key = uuid::Uuid::new_v4().to_string();
+        let req = self
+            .inner
+            .post(self.url("/api/v1/snippets"))
+            .bearer_auth(&self.token)
+            .header(IDEMPOTENCY_KEY_HEADER, &key)
+            .json(body);
+        let mut attempt = 1;
+        loop {
+            let sent = req
+                .try_clone()
+                .context("request can't be retried")?
+                .send()
+                .await;
+            match sent {
+                Ok(resp) => return Self::read_json(resp).await,
+                Err(e) if attempt < CREATE_ATTEMPTS && !e.is_builder() => {
+                    tokio::time::sleep(Duration::from_secs(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e).context("sending request"),
+            }
+        }
     }
 
-    pub async fn get_snippet(&self, slug: &str) -> Result<Snippet> {
+    /// The snippet and its `ETag`, which [`Self::update_snippet`] and
+    /// [`Self::update_settings`] take to refuse overwriting a newer version.
+    pub async fn get_snippet(&self, slug: &str) -> Result<(Snippet, Option<String>)> {
         let path = format!("/api/v1/snippets/{}", slug);
-        self.send_json::<(), _>(Method::GET, &path, None).await
+        self.send_versioned::<(), _>(Method::GET, &path, None, None).await
     }
 
+    /// With `if_match`, fails with 412 `precondition_failed` if the snippet
+    /// was changed since that `ETag` was handed out. Returns the new one.
     pub async fn update_snippet(
         &self,
         slug: &str,
         body: &PatchSnippetRequest,
-    ) -> Result<Snippet> {
+        if_match: Option<&str>,
+    ) -> Result<(Snippet, Option<String>)> {
         let path = format!("/api/v1/snippets/{}", slug);
-        self.send_json(Method::PATCH, &path, Some(body)).await
+        self.send_versioned(Method::PATCH, &path, Some(body), if_match).await
     }
 
+    /// Conditional like [`Self::update_snippet`].
     pub async fn update_settings(
         &self,
         slug: &str,
         body: &SettingsRequest,
-    ) -> Result<Snippet> {
+        if_match: Option<&str>,
+    ) -> Result<(Snippet, Option<String>)> {
         let path = format!("/api/v1/snippets/{}/settings", slug);
+        self.send_versioned(Method::PATCH, &path, Some(body), if_match).await
+    }
+
+    /// Add `text` to the end of the snippet's body. Not retried: a request
+    /// that went through before the connection dropped would append twice.
+    pub async fn append_snippet(&self, slug: &str, text: String) -> Result<AppendResponse> {
+        let path = format!("/api/v1/snippets/{}/append", slug);
+        let resp = self
+            .inner
+            .post(self.url(&path))
+            .bearer_auth(&self.token)
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(text)
+            .send()
+            .await
+            .context("sending request")?;
+        Self::read_json(resp).await
+    }
+
+    /// Open the snippet's event stream. Fails like [`Self::get_snippet`]
+    /// when the snippet can't be read.
+    pub async fn follow_events(&self, slug: &str) -> Result<EventStream> {
+        let path = format!("/api/v1/snippets/{}/events", slug);
+        let resp = self
+            .inner
+            .get(self.url(&path))
+            .bearer_auth(&self.token)
+            .header(header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .context("sending request")?;
+        let status = resp.status();
+        if !status.is_success() {
+            let bytes = resp.bytes().await?;
+            return Err(error_from(status, &bytes));
+        }
+        Ok(EventStream { resp, buf: Vec::new() })
+    }
+
+    pub async fn list_shares(&self, slug: &str) -> Result<ListSharesResponse> {
+        let path = format!("/api/v1/snippets/{}/shares", slug);
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    pub async fn update_shares(
+        &self,
+        slug: &str,
+        body: &ShareRequest,
+    ) -> Result<ListSharesResponse> {
+        let path = format!("/api/v1/snippets/{}/shares", slug);
         self.send_json(Method::PATCH, &path, Some(body)).await
     }
 
+    pub async fn create_share_link(
+        &self,
+        slug: &str,
+        body: &CreateShareLinkRequest,
+    ) -> Result<ShareLink> {
+        let path = format!("/api/v1/snippets/{}/links", slug);
+        self.send_json(Method::POST, &path, Some(body)).await
+    }
+
+    pub async fn list_share_links(&self, slug: &str) -> Result<ListShareLinksResponse> {
+        let path = format!("/api/v1/snippets/{}/links", slug);
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    pub async fn revoke_share_link(&self, slug: &str, id: &str) -> Result<()> {
+        let path = format!("/api/v1/snippets/{}/links/{}", slug, urlencoding(id));
+        self.send_json::<(), _>(Method::DELETE, &path, None).await
+    }
+
+    pub async fn fork_snippet(&self, slug: &str, body: &ForkRequest) -> Result<Snippet> {
+        let path = format!("/api/v1/snippets/{}/fork", slug);
+        self.send_json(Method::POST, &path, Some(body)).await
+    }
+
+    pub async fn rename_snippet(&self, slug: &str, body: &RenameRequest) -> Result<Snippet> {
+        let path = format!("/api/v1/snippets/{}/rename", slug);
+        self.send_json(Method::POST, &path, Some(body)).await
+    }
+
+    pub async fn list_aliases(&self, slug: &str) -> Result<ListAliasesResponse> {
+        let path = format!("/api/v1/snippets/{}/aliases", slug);
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    pub async fn create_alias(&self, slug: &str, body: &CreateAliasRequest) -> Result<SnippetAlias> {
+        let path = format!("/api/v1/snippets/{}/aliases", slug);
+        self.send_json(Method::POST, &path, Some(body)).await
+    }
+
+    pub async fn remove_alias(&self, slug: &str, alias: &str) -> Result<()> {
+        let path = format!("/api/v1/snippets/{}/aliases/{}", slug, urlencoding(alias));
+        self.send_json::<(), _>(Method::DELETE, &path, None).await
+    }
+
     pub async fn delete_snippet(&self, slug: &str) -> Result<()> {
         let path = format!("/api/v1/snippets/{}", slug);
         // Manual handling — 204 means success.
@@ -109,16 +302,103 @@ impl ApiClient {
         Err(anyhow!("{}: {}", s, body))
     }
 
-    pub async fn list_snippets(
+    pub async fn list_trash(
         &self,
-        kind: Option<SnippetType>,
         cursor: Option<&str>,
         limit: Option<u32>,
-    ) -> Result<ListSnippetsResponse> {
+    ) -> Result<ListTrashResponse> {
+        let mut qs: Vec<String> = Vec::new();
+        if let Some(c) = cursor {
+            qs.push(format!("cursor={}", urlencoding(c)));
+        }
+        if let Some(l) = limit {
+            qs.push(format!("limit={}", l));
+        }
+        let path = if qs.is_empty() {
+            "/api/v1/snippets/trash".to_string()
+        } else {
+            format!("/api/v1/snippets/trash?{}", qs.join("&"))
+        };
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    pub async fn restore_snippet(&self, slug: &str) -> Result<Snippet> {
+        let path = format!("/api/v1/snippets/{}/restore", slug);
+        self.send_json::<(), _>(Method::POST, &path, None).await
+    }
+
+    pub async fn purge_snippet(&self, slug: &str) -> Result<()> {
+        let path = format!("/api/v1/snippets/{}/purge", slug);
+        self.send_json::<(), _>(Method::POST, &path, None).await
+    }
+
+    pub async fn list_snippets(&self, opts: &ListOptions<'_>) -> Result<ListSnippetsResponse> {
         let mut qs: Vec<String> = Vec::new();
+        if let Some(k) = opts.kind {
+            qs.push(format!("type={}", k.as_str()));
+        }
+        if let Some(v) = opts.visibility {
+            qs.push(format!("visibility={}", v.as_str()));
+        }
+        if let Some(b) = opts.burn_after_read {
+            qs.push(format!("burn_after_read={}", b));
+        }
+        if let Some(e) = opts.expired {
+            qs.push(format!("expired={}", e));
+        }
+        if let Some(s) = opts.sort {
+            qs.push(format!("sort={}", s.as_str()));
+        }
+        if let Some(o) = opts.order {
+            qs.push(format!("order={}", o.as_str()));
+        }
+        if let Some(c) = opts.cursor {
+            qs.push(format!("cursor={}", urlencoding(c)));
+        }
+        if let Some(l) = opts.limit {
+            qs.push(format!("limit={}", l));
+        }
+        if let Some(o) = opts.org {
+            qs.push(format!("org={}", urlencoding(o)));
+        }
+        if !opts.tags.is_empty() {
+            qs.push(format!("tag={}", urlencoding(&opts.tags.join(","))));
+        }
+        if let Some(c) = opts.collection {
+            qs.push(format!("collection={}", urlencoding(c)));
+        }
+        let path = if qs.is_empty() {
+            "/api/v1/snippets".to_string()
+        } else {
+            format!("/api/v1/snippets?{}", qs.join("&"))
+        };
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    pub async fn search_snippets(
+        &self,
+        query: &str,
+        kind: Option<SnippetType>,
+        limit: Option<u32>,
+    ) -> Result<SearchSnippetsResponse> {
+        let mut qs: Vec<String> = vec![format!("q={}", urlencoding(query))];
         if let Some(k) = kind {
             qs.push(format!("type={}", k.as_str()));
         }
+        if let Some(l) = limit {
+            qs.push(format!("limit={}", l));
+        }
+        let path = format!("/api/v1/snippets/search?{}", qs.join("&"));
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    pub async fn list_revisions(
+        &self,
+        slug: &str,
+        cursor: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<ListRevisionsResponse> {
+        let mut qs: Vec<String> = Vec::new();
         if let Some(c) = cursor {
             qs.push(format!("cursor={}", urlencoding(c)));
         }
@@ -126,21 +406,105 @@ impl ApiClient {
             qs.push(format!("limit={}", l));
         }
         let path = if qs.is_empty() {
-            "/api/v1/snippets".to_string()
+            format!("/api/v1/snippets/{}/revisions", slug)
         } else {
-            format!("/api/v1/snippets?{}", qs.join("&"))
+            format!("/api/v1/snippets/{}/revisions?{}", slug, qs.join("&"))
         };
         self.send_json::<(), _>(Method::GET, &path, None).await
     }
+
+    pub async fn get_revision(&self, slug: &str, revision: i32) -> Result<SnippetRevision> {
+        let path = format!("/api/v1/snippets/{}/revisions/{}", slug, revision);
+        self.send_json::<(), _>(Method::GET, &path, None).await
+    }
+
+    /// Delete every revision but the latest.
+    pub async fn purge_revisions(&self, slug: &str) -> Result<()> {
+        let path = format!("/api/v1/snippets/{}/revisions", slug);
+        self.send_json::<(), _>(Method::DELETE, &path, None).await
+    }
+
+    /// Unified diff between two revisions. `None` lets the server pick its
+    /// defaults (latest revision vs. the one before it).
+    pub async fn diff_revisions(
+        &self,
+        slug: &str,
+        from: Option<i32>,
+        to: Option<i32>,
+    ) -> Result<String> {
+        let mut qs: Vec<String> = Vec::new();
+        if let Some(f) = from {
+            qs.push(format!("from={}", f));
+        }
+        if let Some(t) = to {
+            qs.push(format!("to={}", t));
+        }
+        let path = if qs.is_empty() {
+            format!("/api/v1/snippets/{}/diff", slug)
+        } else {
+            format!("/api/v1/snippets/{}/diff?{}", slug, qs.join("&"))
+        };
+        self.get_text(&path).await
+    }
+}
+
+/// Turn a non-2xx response into an error. Parses the server's error
+/// envelope when present; falls back to the raw body otherwise.
+/// The events of [`ApiClient::follow_events`], read off the SSE response.
+pub struct EventStream {
+    resp: reqwest::Response,
+    buf: Vec<u8>,
+}
+
+impl EventStream {
+    /// The next event; `None` once the server closes the stream.
+    pub async fn next(&mut self) -> Result<Option<SnippetEvent>> {
+        loop {
+            while let Some(end) = self.buf.windows(2).position(|w| w == b"\n\n") {
+                let block: Vec<u8> = self.buf.drain(..end + 2).collect();
+                // Keep-alive comments and events without data carry nothing.
+                let data: Vec<&str> = std::str::from_utf8(&block)
+                    .context("event stream is not valid UTF-8")?
+                    .lines()
+                    .filter_map(|l| l.strip_prefix("data:"))
+                    .map(|d| d.strip_prefix(' ').unwrap_or(d))
+                    .collect();
+                if !data.is_empty() {
+                    let event = serde_json::from_str(&data.join("\n")).context("parsing event")?;
+                    return Ok(Some(event));
+                }
+            }
+            match self.resp.chunk().await.context("reading event stream")? {
+                Some(chunk) => self.buf.extend_from_slice(&chunk),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+fn error_from(status: StatusCode, bytes: &[u8]) -> anyhow::Error {
+    if let Ok(env) = serde_json::from_slice::<ErrorEnvelope>(bytes) {
+        return anyhow!(
+            "{} {}: {} — {}",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or(""),
+            serde_json::to_string(&env.error.code).unwrap_or_default().trim_matches('"'),
+            env.error.message,
+        );
+    }
+    anyhow!("{}: {}", status, String::from_utf8_lossy(bytes))
 }
 
 fn urlencoding(s: &str) -> String {
-    // Tiny encoder for the cursor (we control the alphabet — base64url). Avoid
-    // pulling in a full URL-encoding crate.
-    s.chars()
-        .map(|c| match c {
-            'A'..='Z' | 'a'..='z' | '0'..='9' | '-' | '_' | '.' | '~' => c.to_string(),
-            other => format!("%{:02X}", other as u32),
+    // Tiny percent-encoder for query values. Tags and collection names can be
+    // any script, so this works on the UTF-8 bytes. Avoid pulling in a full
+    // URL-encoding crate.
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                char::from(b).to_string()
+            }
+            other => format!("%{other:02X}"),
         })
         .collect()
 }
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use pastedev_core::CreateAliasRequest;
+
+use crate::client::ApiClient;
+use crate::credentials::resolve;
+use crate::output::{fmt_ago, print, Format};
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub slug: &'a str,
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+/// No flags lists the aliases; any flag applies the changes in order and
+/// prints the result.
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    for slug in args.add {
+        client
+            .create_alias(args.slug, &CreateAliasRequest { slug })
+            .await?;
+    }
+    for alias in &args.remove {
+        client.remove_alias(args.slug, alias).await?;
+    }
+    let aliases = client.list_aliases(args.slug).await?;
+    print(args.format, &aliases, || {
+        if aliases.items.is_empty() {
+            eprintln!("no aliases");
+            return;
+        }
+        for alias in &aliases.items {
+            println!("{:<40} {}", alias.slug, fmt_ago(alias.created_at));
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use pastedev_core::{ForkRequest, Visibility};
+
+use crate::client::ApiClient;
+use crate::commands::get::parse_target;
+use crate::credentials::resolve;
+use crate::output::{print, Format};
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    /// A bare slug or a snippet URL; an encrypted source's `#key` carries
+    /// over to the fork's URL.
+    pub target: &'a str,
+    pub name: Option<String>,
+    /// Custom slug for the copy; `None` lets the server generate one.
+    pub slug: Option<String>,
+    /// `None` = public for a public source, private otherwise.
+    pub visibility: Option<Visibility>,
+    pub password: Option<String>,
+    pub org: Option<String>,
+}
+
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    let (source, key) = parse_target(args.target)?;
+    let mut snippet = client
+        .fork_snippet(
+            &source,
+            &ForkRequest {
+                name: args.name,
+                slug: args.slug,
+                org: args.org,
+                visibility: args.visibility,
+                password: args.password,
+            },
+        )
+        .await
+        .context("forking snippet")?;
+    if let Some(key) = key.filter(|_| snippet.encrypted) {
+        snippet.url = format!("{}#{key}", snippet.url);
+    }
+    print(args.format, &snippet, || {
+        eprintln!("→ forked {source} to {}", snippet.slug);
+        println!("{}", snippet.url);
+    });
+    Ok(())
+}
@@ -1,26 +1,56 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use pastedev_core::SnippetEvent;
 use std::io::Write;
+use std::time::Duration;
 
-use crate::client::ApiClient;
+use crate::client::{ApiClient, EventStream};
 use crate::credentials::resolve;
+use crate::crypto;
 use crate::output::Format;
 
 pub struct Args<'a> {
     pub format: Format,
     pub token: Option<&'a str>,
     pub base_url: Option<&'a str>,
-    pub slug: &'a str,
+    /// A bare slug or a snippet URL; the URL's `#fragment` is the key of an
+    /// encrypted snippet.
+    pub target: &'a str,
     pub meta: bool,
+    /// Pick one file out of a multi-file snippet by name.
+    pub file: Option<&'a str>,
+    /// Keep printing what is appended, like `tail -f`.
+    pub follow: bool,
 }
 
+/// Longest wait between attempts to reopen a dropped event stream.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
 pub async fn run(args: Args<'_>) -> Result<()> {
     let (token, base_url, _) = resolve(args.token, args.base_url)?;
     let client = ApiClient::new(base_url, token)?;
-    let snippet = client.get_snippet(args.slug).await?;
+    let (slug, key) = parse_target(args.target)?;
+    // Subscribed first, so nothing appended after the fetch is missed.
+    let events = match args.follow {
+        true => Some(client.follow_events(&slug).await?),
+        false => None,
+    };
+    let (mut snippet, etag) = client.get_snippet(&slug).await?;
+    if snippet.encrypted {
+        let key = key.as_deref().ok_or_else(|| {
+            anyhow!("{slug} is encrypted; pass the full URL including its #key")
+        })?;
+        snippet.body = crypto::decrypt(&snippet.body, key)?;
+    }
     match args.format {
         Format::Json => {
             let json = serde_json::to_string_pretty(&snippet)?;
             println!("{json}");
+            // Followed by the events as they come, one JSON object a line.
+            if let Some(mut events) = events {
+                while let Some(event) = events.next().await? {
+                    println!("{}", serde_json::to_string(&event)?);
+                }
+            }
             return Ok(());
         }
         Format::Human => {}
@@ -32,13 +62,182 @@ pub async fn run(args: Args<'_>) -> Result<()> {
         eprintln!("owner:      {}", snippet.owner.username);
         eprintln!("size:       {} b", snippet.size_bytes);
         eprintln!("views:      {}", snippet.views);
+        if snippet.encrypted {
+            eprintln!("encrypted:  yes");
+        }
+        if !snippet.tags.is_empty() {
+            eprintln!("tags:       {}", snippet.tags.join(", "));
+        }
         eprintln!("created_at: {}", snippet.created_at);
         eprintln!("updated_at: {}", snippet.updated_at);
+        if let Some(etag) = &etag {
+            eprintln!("etag:       {etag}");
+        }
+        if !snippet.files.is_empty() {
+            eprintln!("files:      {}", snippet.files.len());
+        }
         eprintln!("---");
     }
-    // Body to stdout, no decoration — the next thing in the pipe might be diff/jq.
-    std::io::stdout().write_all(snippet.body.as_bytes())?;
-    if !snippet.body.ends_with('\n') {
+    if let Some(events) = events {
+        if !snippet.files.is_empty() {
+            return Err(anyhow!("multi-file snippets can't be followed"));
+        }
+        write_raw(&snippet.body)?;
+        let mut follow = Follow {
+            client: &client,
+            slug: &slug,
+            key: key.as_deref(),
+            shown: snippet.body,
+        };
+        return follow.run(events).await;
+    }
+    if let Some(name) = args.file {
+        let file = snippet
+            .files
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| anyhow!("{} has no file named {name}", snippet.slug))?;
+        return write_body(&file.body);
+    }
+    if snippet.files.is_empty() {
+        // Body to stdout, no decoration — the next thing in the pipe might be diff/jq.
+        return write_body(&snippet.body);
+    }
+    // Bundle: every file, separated by `==> name <==` headers the way
+    // `head`/`tail` do for multiple files. `--file` gets one undecorated.
+    for (i, file) in snippet.files.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("==> {} <==", file.name);
+        write_body(&file.body)?;
+    }
+    Ok(())
+}
+
+/// Split a `get` argument into slug and fragment. Anything that doesn't parse
+/// as an http(s) URL is taken as a slug.
+pub(crate) fn parse_target(target: &str) -> Result<(String, Option<String>)> {
+    let url = match url::Url::parse(target) {
+        Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+        _ => return Ok((target.to_string(), None)),
+    };
+    let slug = url
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+        .ok_or_else(|| anyhow!("no snippet slug in {target}"))?
+        .to_string();
+    let key = url.fragment().filter(|f| !f.is_empty()).map(String::from);
+    Ok((slug, key))
+}
+
+/// `get --follow`: what has been printed of the body so far, kept to tell an
+/// append that continues it from an edit that rewrote it.
+struct Follow<'a> {
+    client: &'a ApiClient,
+    slug: &'a str,
+    key: Option<&'a str>,
+    shown: String,
+}
+
+impl Follow<'_> {
+    async fn run(&mut self, mut events: EventStream) -> Result<()> {
+        loop {
+            let event = match events.next().await {
+                Ok(Some(event)) => event,
+                Ok(None) | Err(_) => {
+                    events = self.reconnect().await?;
+                    self.resync().await?;
+                    continue;
+                }
+            };
+            match event {
+                SnippetEvent::Append { offset, text: Some(text), .. } => {
+                    let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+                    let shown = self.shown.len();
+                    match shown.checked_sub(offset).map(|seen| text.get(seen..)) {
+                        // Entirely printed already (sent before the fetch).
+                        Some(_) if offset + text.len() <= shown => {}
+                        Some(Some(rest)) => {
+                            write_raw(rest)?;
+                            self.shown.push_str(rest);
+                        }
+                        // A gap, or an overlap that doesn't split cleanly.
+                        _ => self.resync().await?,
+                    }
+                }
+                SnippetEvent::Append { text: None, .. } | SnippetEvent::Edit { .. } => {
+                    self.resync().await?
+                }
+                SnippetEvent::Settings => {}
+                SnippetEvent::BurnArmed { expires_at } => {
+                    eprintln!("→ {} burns at {expires_at}", self.slug);
+                }
+                SnippetEvent::Burned => {
+                    eprintln!("→ {} burned", self.slug);
+                    return Ok(());
+                }
+                SnippetEvent::Expired => {
+                    eprintln!("→ {} expired", self.slug);
+                    return Ok(());
+                }
+                SnippetEvent::Deleted => {
+                    eprintln!("→ {} was deleted", self.slug);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Reopen the event stream after it dropped. Network trouble is retried
+    /// for as long as it lasts; an answer from the server such as a 404 is
+    /// final.
+    async fn reconnect(&self) -> Result<EventStream> {
+        let mut delay = Duration::from_secs(1);
+        loop {
+            tokio::time::sleep(delay).await;
+            match self.client.follow_events(self.slug).await {
+                Ok(events) => return Ok(events),
+                Err(e) if e.downcast_ref::<reqwest::Error>().is_some() => {
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Fetch the body again and print what is new. A body that no longer
+    /// starts with what was printed was edited, and is printed again whole.
+    async fn resync(&mut self) -> Result<()> {
+        let (snippet, _) = self.client.get_snippet(self.slug).await?;
+        let body = match (snippet.encrypted, self.key) {
+            (true, Some(key)) => crypto::decrypt(&snippet.body, key)?,
+            (true, None) => return Err(anyhow!("{} is encrypted", self.slug)),
+            (false, _) => snippet.body,
+        };
+        match body.strip_prefix(self.shown.as_str()) {
+            Some(rest) => write_raw(rest)?,
+            None => {
+                eprintln!("→ {} was edited; printing it again", self.slug);
+                write_raw(&body)?;
+            }
+        }
+        self.shown = body;
+        Ok(())
+    }
+}
+
+/// Print `text` as is and flush, for output that arrives in pieces.
+fn write_raw(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn write_body(body: &str) -> Result<()> {
+    std::io::stdout().write_all(body.as_bytes())?;
+    if !body.ends_with('\n') {
         println!();
     }
     Ok(())
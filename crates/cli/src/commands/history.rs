@@ -0,0 +1,88 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::client::ApiClient;
+use crate::commands::list::truncate;
+use crate::credentials::resolve;
+use crate::output::{fmt_ago, fmt_size, print, Format};
+
+/// What `history` prints. The table is the default; `--rev`, `--diff` and
+/// `--purge` are mutually exclusive (clap enforces it).
+pub enum Mode {
+    List { limit: Option<u32> },
+    Show { revision: i32 },
+    Diff { from: Option<i32>, to: Option<i32> },
+    Purge { yes: bool },
+}
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub slug: &'a str,
+    pub mode: Mode,
+}
+
+pub async fn run(args: Args<'_>) -> Result<()> {
+    if let Mode::Purge { yes: false } = args.mode {
+        eprint!(
+            "permanently delete every revision of {} but the latest? [y/N]: ",
+            args.slug
+        );
+        std::io::stderr().flush().ok();
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            eprintln!("aborted");
+            return Ok(());
+        }
+    }
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    match args.mode {
+        Mode::List { limit } => {
+            let list = client.list_revisions(args.slug, None, limit).await?;
+            print(args.format, &list, || {
+                println!(
+                    "{:<5} {:<28} {:<16} {:<10} {:<7}",
+                    "rev", "name", "author", "created", "size"
+                );
+                for item in &list.items {
+                    let name = item.name.as_deref().unwrap_or("(unnamed)");
+                    let author = item.author.as_ref().map(|a| a.username.as_str()).unwrap_or("-");
+                    println!(
+                        "{:<5} {:<28} {:<16} {:<10} {:<7}",
+                        item.revision,
+                        truncate(name, 28),
+                        truncate(author, 16),
+                        fmt_ago(item.created_at),
+                        fmt_size(item.size_bytes),
+                    );
+                }
+            });
+        }
+        Mode::Show { revision } => {
+            let rev = client.get_revision(args.slug, revision).await?;
+            if let Format::Json = args.format {
+                println!("{}", serde_json::to_string_pretty(&rev)?);
+                return Ok(());
+            }
+            // Same contract as `get`: body only, undecorated, so it pipes.
+            std::io::stdout().write_all(rev.body.as_bytes())?;
+            if !rev.body.ends_with('\n') {
+                println!();
+            }
+        }
+        Mode::Diff { from, to } => {
+            // Always plain unified diff, even under --json; it's already a
+            // machine-readable format and `patch`/`delta` want it raw.
+            let diff = client.diff_revisions(args.slug, from, to).await?;
+            std::io::stdout().write_all(diff.as_bytes())?;
+        }
+        Mode::Purge { .. } => {
+            client.purge_revisions(args.slug).await?;
+            eprintln!("→ purged the history of {}", args.slug);
+        }
+    }
+    Ok(())
+}
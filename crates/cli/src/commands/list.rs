@@ -1,7 +1,7 @@
 use anyhow::Result;
 use pastedev_core::SnippetType;
 
-use crate::client::ApiClient;
+use crate::client::{ApiClient, ListOptions};
 use crate::credentials::resolve;
 use crate::output::{fmt_ago, fmt_size, print, Format};
 
@@ -9,14 +9,13 @@ pub struct Args<'a> {
     pub format: Format,
     pub token: Option<&'a str>,
     pub base_url: Option<&'a str>,
-    pub kind: Option<SnippetType>,
-    pub limit: Option<u32>,
+    pub options: ListOptions<'a>,
 }
 
 pub async fn run(args: Args<'_>) -> Result<()> {
     let (token, base_url, _) = resolve(args.token, args.base_url)?;
     let client = ApiClient::new(base_url, token)?;
-    let list = client.list_snippets(args.kind, None, args.limit).await?;
+    let list = client.list_snippets(&args.options).await?;
     print(args.format, &list, || {
         println!(
             "{:<5} {:<8} {:<28} {:<10} {:<7} {:<5}",
@@ -38,12 +37,18 @@ pub async fn run(args: Args<'_>) -> Result<()> {
                 fmt_size(item.size_bytes),
                 item.views,
             );
+            if !item.tags.is_empty() {
+                println!("      tags: {}", item.tags.join(", "));
+            }
+        }
+        if let Some(next) = &list.next_cursor {
+            eprintln!("more: --cursor {next}");
         }
     });
     Ok(())
 }
 
-fn truncate(s: &str, max: usize) -> String {
+pub(crate) fn truncate(s: &str, max: usize) -> String {
     if s.chars().count() <= max {
         return s.to_string();
     }
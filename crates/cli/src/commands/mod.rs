@@ -1,7 +1,18 @@
+pub mod alias;
 pub mod auth;
 pub mod delete;
+pub mod fork;
 pub mod get;
+pub mod history;
 pub mod list;
 pub mod publish;
+pub mod purge;
+pub mod rename;
+pub mod restore;
+pub mod search;
 pub mod settings;
+pub mod share;
+pub mod share_link;
+pub mod tee;
+pub mod trash;
 pub mod whoami;
@@ -1,11 +1,17 @@
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use pastedev_core::{CreateSnippetRequest, SnippetType, Visibility, MAX_SNIPPET_BYTES};
+use pastedev_core::{
+    CreateSnippetRequest, SlugStrategy, SnippetContent, SnippetFileInput, SnippetType,
+    Visibility, BURN_WINDOW_ONE_SHOT, MAX_BUNDLE_FILES,
+};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
 use crate::client::ApiClient;
 use crate::credentials::resolve;
+use crate::crypto;
 use crate::output::{print, Format};
 
 pub struct Args<'a> {
@@ -14,47 +20,127 @@ pub struct Args<'a> {
     pub base_url: Option<&'a str>,
     pub kind: Option<SnippetType>,
     pub name: Option<String>,
-    pub file: Option<PathBuf>,
+    /// Custom slug; `None` lets the server generate one.
+    pub slug: Option<String>,
+    /// How the server should generate the slug; `None` = its default.
+    pub slug_strategy: Option<SlugStrategy>,
+    /// Zero paths = stdin, one regular file = single snippet, anything else
+    /// (several paths, or a directory) = multi-file bundle.
+    pub files: Vec<PathBuf>,
     pub visibility: Option<Visibility>,
+    pub password: Option<String>,
     /// Parsed duration spec like `15m`, `1h`, `7d`. `None` = no fixed lifetime.
     pub lifetime_seconds: Option<i32>,
     pub burn_after_read: bool,
+    /// Fuse length for burn-after-read, from [`parse_burn_window`]. Setting
+    /// it turns burn-after-read on.
+    pub burn_window_seconds: Option<i32>,
+    /// Unique-viewer cap. `None` = uncapped.
+    pub max_views: Option<i32>,
+    /// Embargo: nobody but the owner can read it before this, from
+    /// [`parse_publish_at`].
+    pub publish_at: Option<OffsetDateTime>,
+    /// Encrypt the body client-side; see [`crypto`].
+    pub encrypt: bool,
+    /// Org to publish into.
+    pub org: Option<String>,
+    pub tags: Vec<String>,
 }
 
 pub async fn run(args: Args<'_>) -> Result<()> {
     let (token, base_url, _) = resolve(args.token, args.base_url)?;
-    let (body, inferred_kind, inferred_name) = read_body(args.file.as_ref())?;
-    if body.is_empty() {
-        return Err(anyhow!("body is empty — nothing to publish"));
+    let is_bundle = args.files.len() > 1 || args.files.iter().any(|p| p.is_dir());
+    if args.encrypt && is_bundle {
+        return Err(anyhow!("--encrypt only works for a single file or stdin"));
     }
-    if body.len() > MAX_SNIPPET_BYTES {
-        return Err(anyhow!(
-            "body is {} bytes, max is {}",
-            body.len(),
-            MAX_SNIPPET_BYTES
-        ));
-    }
-    let kind = args.kind.or(inferred_kind).unwrap_or(SnippetType::Code);
-    let name = args.name.or(inferred_name);
+    let mut key = None;
+    let (name, content) = if is_bundle {
+        if args.kind.is_some() {
+            return Err(anyhow!(
+                "--type only applies to single-file publishes; bundle file types are inferred per file"
+            ));
+        }
+        let files = read_bundle(&args.files)?;
+        // A lone directory names the bundle after itself unless --name says otherwise.
+        let dir_name = match args.files.as_slice() {
+            [only] => only
+                .canonicalize()
+                .ok()
+                .and_then(|p| p.file_name().and_then(|n| n.to_str()).map(String::from)),
+            _ => None,
+        };
+        (args.name.or(dir_name), SnippetContent::Bundle { files })
+    } else {
+        let (body, inferred_kind, inferred_name) = read_body(args.files.first())?;
+        if body.is_empty() {
+            return Err(anyhow!("body is empty — nothing to publish"));
+        }
+        let kind = args.kind.or(inferred_kind).unwrap_or(SnippetType::Code);
+        if args.encrypt {
+            // The name is stored in the clear, so only send one the user
+            // asked for; a file name can say as much as the body.
+            let (envelope, k) = crypto::encrypt(&body)?;
+            key = Some(k);
+            (args.name, SnippetContent::Single { kind, body: envelope })
+        } else {
+            (args.name.or(inferred_name), SnippetContent::Single { kind, body })
+        }
+    };
 
     let client = ApiClient::new(base_url, token)?;
-    let snippet = client
+    let mut snippet = client
         .create_snippet(&CreateSnippetRequest {
-            kind,
-            name: name.clone(),
-            body,
+            name,
+            content,
+            slug: args.slug,
+            slug_strategy: args.slug_strategy,
             visibility: args.visibility,
             lifetime_seconds: args.lifetime_seconds,
-            burn_after_read: Some(args.burn_after_read),
+            publish_at: args.publish_at,
+            burn_after_read: Some(args.burn_after_read || args.burn_window_seconds.is_some()),
+            burn_window_seconds: args.burn_window_seconds,
+            max_views: args.max_views,
+            password: args.password,
+            encrypted: args.encrypt.then_some(true),
+            org: args.org,
+            tags: args.tags,
         })
         .await
         .context("creating snippet")?;
+    if let Some(key) = key {
+        snippet.url = format!("{}#{key}", snippet.url);
+    }
     print(args.format, &snippet, || {
         println!("{}", snippet.url);
     });
     Ok(())
 }
 
+/// Parse a `--at` timestamp: RFC 3339, except that the seconds may be left
+/// off (`2026-11-01T09:00Z`, `2026-11-01T09:00+02:00`).
+pub fn parse_publish_at(s: &str) -> Result<OffsetDateTime> {
+    let s = s.trim();
+    let invalid = || anyhow!("invalid time '{s}'; use e.g. 2026-11-01T09:00Z");
+    if let Ok(at) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Ok(at);
+    }
+    // `YYYY-MM-DDTHH:MM` is 16 bytes; anything right after it is the offset.
+    let (head, offset) = s.split_at_checked(16).ok_or_else(invalid)?;
+    if !offset.starts_with(['Z', 'z', '+', '-']) {
+        return Err(invalid());
+    }
+    OffsetDateTime::parse(&format!("{head}:00{offset}"), &Rfc3339).map_err(|_| invalid())
+}
+
+/// Parse a `--burn-window` value: a [`parse_duration`] spec, or `once` (or
+/// `0`) for a one-shot snippet that is gone after its first non-owner read.
+pub fn parse_burn_window(s: &str) -> Result<i32> {
+    match s.trim() {
+        "once" | "0" => Ok(BURN_WINDOW_ONE_SHOT),
+        other => parse_duration(other),
+    }
+}
+
 /// Parse a duration spec like `15m`, `2h`, `1d`, `1w`, or a plain integer
 /// (interpreted as seconds). Returns the value in seconds. Rejects negatives,
 /// zero, and any input whose seconds-product overflows i64 / can't fit in i32
@@ -89,18 +175,24 @@ pub fn parse_duration(s: &str) -> Result<i32> {
     i32::try_from(secs).map_err(|_| anyhow!("duration too large"))
 }
 
+fn infer_kind(path: &Path) -> Option<SnippetType> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "md" | "markdown" => Some(SnippetType::Markdown),
+        "html" | "htm" => Some(SnippetType::Html),
+        _ => None,
+    }
+}
+
+fn read_utf8(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    String::from_utf8(bytes).with_context(|| format!("{} is not valid UTF-8", path.display()))
+}
+
 fn read_body(file: Option<&PathBuf>) -> Result<(String, Option<SnippetType>, Option<String>)> {
     if let Some(path) = file {
-        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
-        let body = String::from_utf8(bytes).context("file is not valid UTF-8")?;
-        let kind = path
-            .extension()
-            .and_then(|os| os.to_str())
-            .and_then(|ext| match ext.to_ascii_lowercase().as_str() {
-                "md" | "markdown" => Some(SnippetType::Markdown),
-                "html" | "htm" => Some(SnippetType::Html),
-                _ => None,
-            });
+        let body = read_utf8(path)?;
+        let kind = infer_kind(path);
         let name = path
             .file_name()
             .and_then(|os| os.to_str())
@@ -113,3 +205,61 @@ fn read_body(file: Option<&PathBuf>) -> Result<(String, Option<SnippetType>, Opt
         .context("reading stdin")?;
     Ok((buf, None, None))
 }
+
+/// Expand `paths` into bundle files. Directories contribute their regular,
+/// non-hidden files (one level, sorted by name); explicit file arguments are
+/// taken as given. Bundle file names are base names, so two inputs with the
+/// same base name are an error rather than a silent overwrite.
+fn read_bundle(paths: &[PathBuf]) -> Result<Vec<SnippetFileInput>> {
+    let mut expanded: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("reading {}", path.display()))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .filter(|p| {
+                    !p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with('.'))
+                })
+                .collect();
+            entries.sort();
+            expanded.extend(entries);
+        } else {
+            expanded.push(path.clone());
+        }
+    }
+    if expanded.is_empty() {
+        return Err(anyhow!("no files to publish"));
+    }
+    if expanded.len() > MAX_BUNDLE_FILES {
+        return Err(anyhow!(
+            "{} files, max is {} per snippet",
+            expanded.len(),
+            MAX_BUNDLE_FILES
+        ));
+    }
+
+    let mut files: Vec<SnippetFileInput> = Vec::with_capacity(expanded.len());
+    for path in &expanded {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("{}: file name is not valid UTF-8", path.display()))?
+            .to_string();
+        if files.iter().any(|f| f.name == name) {
+            return Err(anyhow!("two files are named {name}; bundle file names must be unique"));
+        }
+        let body = read_utf8(path)?;
+        if body.is_empty() {
+            return Err(anyhow!("{} is empty", path.display()));
+        }
+        files.push(SnippetFileInput {
+            name,
+            kind: infer_kind(path).unwrap_or(SnippetType::Code),
+            body,
+        });
+    }
+    Ok(files)
+}
@@ -0,0 +1,23 @@
+use anyhow::Result;
+
+use crate::client::ApiClient;
+use crate::credentials::resolve;
+use crate::output::{print, Format};
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub slug: &'a str,
+}
+
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    let snippet = client.restore_snippet(args.slug).await?;
+    print(args.format, &snippet, || {
+        eprintln!("→ restored {}", snippet.slug);
+        println!("{}", snippet.url);
+    });
+    Ok(())
+}
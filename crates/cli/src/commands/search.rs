@@ -0,0 +1,44 @@
+use anyhow::Result;
+use pastedev_core::SnippetType;
+
+use crate::client::ApiClient;
+use crate::commands::list::truncate;
+use crate::credentials::resolve;
+use crate::output::{fmt_ago, print, Format};
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub query: &'a str,
+    pub kind: Option<SnippetType>,
+    pub limit: Option<u32>,
+}
+
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    let results = client
+        .search_snippets(args.query, args.kind, args.limit)
+        .await?;
+    print(args.format, &results, || {
+        if results.items.is_empty() {
+            eprintln!("no matches");
+            return;
+        }
+        for hit in &results.items {
+            let item = &hit.snippet;
+            let name = item.name.as_deref().unwrap_or("(unnamed)");
+            println!(
+                "{:<8} {:<40} {:<10}",
+                item.slug,
+                truncate(name, 40),
+                fmt_ago(item.created_at),
+            );
+            if let Some(h) = &hit.highlight {
+                println!("         {}", h);
+            }
+        }
+    });
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
 use pastedev_core::{SettingsRequest, Visibility};
+use time::OffsetDateTime;
 
 use crate::client::ApiClient;
 use crate::credentials::resolve;
@@ -11,29 +12,49 @@ pub struct Args<'a> {
     pub base_url: Option<&'a str>,
     pub slug: &'a str,
     pub visibility: Option<Visibility>,
+    pub password: Option<String>,
     /// Three states mirroring the wire format: `Some(Some(n))` set,
     /// `Some(None)` clear, `None` leave alone. main.rs reconciles the
     /// `--lifetime` / `--no-lifetime` flag pair into this.
     pub lifetime_seconds: Option<Option<i32>>,
     pub burn_after_read: Option<bool>,
+    pub burn_window_seconds: Option<i32>,
+    /// Same three states, from `--max-views` / `--no-max-views`.
+    pub max_views: Option<Option<i32>>,
+    /// Same three states, from `--at` / `--publish-now`.
+    pub publish_at: Option<Option<OffsetDateTime>>,
+    /// Only apply if the snippet still has this ETag (`get --meta` shows it).
+    pub if_match: Option<&'a str>,
 }
 
 pub async fn run(args: Args<'_>) -> Result<()> {
-    if args.visibility.is_none() && args.lifetime_seconds.is_none() && args.burn_after_read.is_none() {
+    if args.visibility.is_none()
+        && args.password.is_none()
+        && args.lifetime_seconds.is_none()
+        && args.burn_after_read.is_none()
+        && args.burn_window_seconds.is_none()
+        && args.max_views.is_none()
+        && args.publish_at.is_none()
+    {
         return Err(anyhow!(
-            "specify at least one of --visibility, --lifetime/--no-lifetime, --burn-after-read/--no-burn-after-read",
+            "specify at least one of --visibility, --password, --lifetime/--no-lifetime, --burn-after-read/--no-burn-after-read, --burn-window, --max-views/--no-max-views, --at/--publish-now",
         ));
     }
     let (token, base_url, _) = resolve(args.token, args.base_url)?;
     let client = ApiClient::new(base_url, token)?;
-    let snippet = client
+    let (snippet, _) = client
         .update_settings(
             args.slug,
             &SettingsRequest {
                 visibility: args.visibility,
                 lifetime_seconds: args.lifetime_seconds,
+                publish_at: args.publish_at,
                 burn_after_read: args.burn_after_read,
+                burn_window_seconds: args.burn_window_seconds,
+                max_views: args.max_views,
+                password: args.password,
             },
+            args.if_match,
         )
         .await?;
     print(args.format, &snippet, || {
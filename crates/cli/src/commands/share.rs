@@ -0,0 +1,51 @@
+use anyhow::Result;
+use pastedev_core::ShareRequest;
+
+use crate::client::ApiClient;
+use crate::credentials::resolve;
+use crate::output::{fmt_ago, print, Format};
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub slug: &'a str,
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+    /// `Some(true)` / `Some(false)` from `--strict` / `--no-strict`.
+    pub strict_private: Option<bool>,
+}
+
+/// No flags lists the share list; any flag updates it and prints the result.
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    let shares = if args.add.is_empty() && args.remove.is_empty() && args.strict_private.is_none() {
+        client.list_shares(args.slug).await?
+    } else {
+        client
+            .update_shares(
+                args.slug,
+                &ShareRequest {
+                    add: args.add,
+                    remove: args.remove,
+                    strict_private: args.strict_private,
+                },
+            )
+            .await?
+    };
+    print(args.format, &shares, || {
+        eprintln!(
+            "strict private: {}",
+            if shares.strict_private { "on" } else { "off" }
+        );
+        if shares.shares.is_empty() {
+            eprintln!("not shared with anyone");
+            return;
+        }
+        for share in &shares.shares {
+            println!("{:<40} {}", share.username, fmt_ago(share.created_at));
+        }
+    });
+    Ok(())
+}
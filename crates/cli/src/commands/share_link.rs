@@ -0,0 +1,76 @@
+use anyhow::Result;
+use pastedev_core::CreateShareLinkRequest;
+
+use crate::client::ApiClient;
+use crate::credentials::resolve;
+use crate::output::{fmt_ago, fmt_in, print, Format};
+
+pub enum Mode {
+    Create { ttl_seconds: i32, max_uses: Option<i32> },
+    List,
+    Revoke { id: String },
+}
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub slug: &'a str,
+    pub mode: Mode,
+}
+
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    match args.mode {
+        Mode::Create { ttl_seconds, max_uses } => {
+            let link = client
+                .create_share_link(
+                    args.slug,
+                    &CreateShareLinkRequest {
+                        ttl_seconds,
+                        max_uses,
+                    },
+                )
+                .await?;
+            print(args.format, &link, || {
+                println!("{}", link.url.as_deref().unwrap_or_default());
+            });
+        }
+        Mode::List => {
+            let links = client.list_share_links(args.slug).await?;
+            print(args.format, &links, || {
+                if links.items.is_empty() {
+                    eprintln!("no share links");
+                    return;
+                }
+                for link in &links.items {
+                    let status = match link.revoked_at {
+                        Some(at) => format!("revoked {}", fmt_ago(at)),
+                        None if link.expires_at <= time::OffsetDateTime::now_utc() => {
+                            format!("expired {}", fmt_ago(link.expires_at))
+                        }
+                        None => format!("expires {}", fmt_in(link.expires_at)),
+                    };
+                    let uses = match link.max_uses {
+                        Some(max) => format!("{}/{max} uses", link.uses),
+                        None if link.uses == 1 => "1 use".to_string(),
+                        None => format!("{} uses", link.uses),
+                    };
+                    println!(
+                        "{}  {:<14} {:<20} created {}",
+                        link.id,
+                        uses,
+                        status,
+                        fmt_ago(link.created_at)
+                    );
+                }
+            });
+        }
+        Mode::Revoke { id } => {
+            client.revoke_share_link(args.slug, &id).await?;
+            eprintln!("revoked {id}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::client::ApiClient;
+use crate::credentials::resolve;
+
+/// Batch size at which lines go out without waiting for the interval.
+const FLUSH_BYTES: usize = 64 * 1024;
+
+pub struct Args<'a> {
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub slug: &'a str,
+    /// Longest a line waits in the batch before it is appended.
+    pub interval: Duration,
+}
+
+/// Copy stdin to stdout line by line and append it to the snippet in
+/// batches. If an append fails, the rest of the input is still echoed but no
+/// longer uploaded, and the command fails once stdin ends.
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    let mut stdin = BufReader::new(tokio::io::stdin());
+    let mut stdout = tokio::io::stdout();
+    let mut ticker = tokio::time::interval(args.interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    // `read_until` keeps what it has read so far in `line` when the tick
+    // wins the race, so a line is only moved to `batch` once it is whole.
+    let mut line = Vec::new();
+    let mut batch = Vec::new();
+    let mut failed: Option<anyhow::Error> = None;
+    loop {
+        tokio::select! {
+            read = stdin.read_until(b'\n', &mut line) => {
+                let n = read.context("reading stdin")?;
+                if n == 0 {
+                    break;
+                }
+                stdout.write_all(&line).await?;
+                stdout.flush().await?;
+                if failed.is_none() {
+                    batch.append(&mut line);
+                } else {
+                    line.clear();
+                }
+                if batch.len() >= FLUSH_BYTES {
+                    flush(&client, args.slug, &mut batch, &mut failed).await;
+                }
+            }
+            _ = ticker.tick() => {
+                flush(&client, args.slug, &mut batch, &mut failed).await;
+            }
+        }
+    }
+    flush(&client, args.slug, &mut batch, &mut failed).await;
+    match failed {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+async fn flush(
+    client: &ApiClient,
+    slug: &str,
+    batch: &mut Vec<u8>,
+    failed: &mut Option<anyhow::Error>,
+) {
+    if batch.is_empty() || failed.is_some() {
+        return;
+    }
+    let text = String::from_utf8_lossy(batch).into_owned();
+    batch.clear();
+    if let Err(e) = client.append_snippet(slug, text).await {
+        eprintln!("warning: append failed, no longer uploading: {e:#}");
+        *failed = Some(e.context(format!("appending to {slug}")));
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use crate::client::ApiClient;
+use crate::commands::list::truncate;
+use crate::credentials::resolve;
+use crate::output::{fmt_ago, fmt_in, fmt_size, print, Format};
+
+pub struct Args<'a> {
+    pub format: Format,
+    pub token: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub cursor: Option<&'a str>,
+    pub limit: Option<u32>,
+}
+
+pub async fn run(args: Args<'_>) -> Result<()> {
+    let (token, base_url, _) = resolve(args.token, args.base_url)?;
+    let client = ApiClient::new(base_url, token)?;
+    let trash = client.list_trash(args.cursor, args.limit).await?;
+    print(args.format, &trash, || {
+        if trash.items.is_empty() {
+            eprintln!("trash is empty");
+            return;
+        }
+        println!(
+            "{:<8} {:<28} {:<10} {:<7} {:<8}",
+            "slug", "name", "deleted", "size", "purge"
+        );
+        for item in &trash.items {
+            let name = item.snippet.name.as_deref().unwrap_or("(unnamed)");
+            println!(
+                "{:<8} {:<28} {:<10} {:<7} {:<8}",
+                item.snippet.slug,
+                truncate(name, 28),
+                fmt_ago(item.deleted_at),
+                fmt_size(item.snippet.size_bytes),
+                item.purge_at.map(fmt_in).unwrap_or_else(|| "never".into()),
+            );
+        }
+        if let Some(next) = &trash.next_cursor {
+            eprintln!("more: --cursor {next}");
+        }
+    });
+    Ok(())
+}
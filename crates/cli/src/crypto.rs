@@ -0,0 +1,49 @@
+//! Client-side encryption for `publish --encrypt`.
+//!
+//! The body is sealed with XChaCha20-Poly1305 under a fresh random key and
+//! uploaded as a `v1.` envelope (see `pastedev_core::CIPHERTEXT_PREFIX`). The
+//! key never reaches the server: it rides in the share URL's `#fragment`,
+//! base64url without padding, and `get <url>` reads it back from there.
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use pastedev_core::CIPHERTEXT_PREFIX;
+
+const NONCE_LEN: usize = 24;
+
+/// Encrypt `plaintext` under a new key. Returns the envelope to upload and
+/// the key to put in the URL fragment.
+pub fn encrypt(plaintext: &str) -> Result<(String, String)> {
+    let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = XChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow!("encryption failed"))?;
+    let mut payload = Vec::with_capacity(NONCE_LEN + sealed.len());
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&sealed);
+    let envelope = format!("{CIPHERTEXT_PREFIX}{}", URL_SAFE_NO_PAD.encode(payload));
+    Ok((envelope, URL_SAFE_NO_PAD.encode(key)))
+}
+
+/// Open an envelope produced by [`encrypt`] with the key from the URL.
+pub fn decrypt(envelope: &str, key: &str) -> Result<String> {
+    let key = URL_SAFE_NO_PAD
+        .decode(key.trim())
+        .ok()
+        .filter(|k| k.len() == 32)
+        .ok_or_else(|| anyhow!("malformed decryption key in URL fragment"))?;
+    let payload = envelope
+        .strip_prefix(CIPHERTEXT_PREFIX)
+        .and_then(|b64| URL_SAFE_NO_PAD.decode(b64.trim_end()).ok())
+        .filter(|p| p.len() > NONCE_LEN)
+        .ok_or_else(|| anyhow!("snippet body is not a supported ciphertext envelope"))?;
+    let (nonce, sealed) = payload.split_at(NONCE_LEN);
+    let plain = XChaCha20Poly1305::new(key.as_slice().into())
+        .decrypt(XNonce::from_slice(nonce), sealed)
+        .map_err(|_| anyhow!("decryption failed: wrong key or tampered body"))?;
+    String::from_utf8(plain).map_err(|_| anyhow!("decrypted body is not valid UTF-8"))
+}
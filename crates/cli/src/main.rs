@@ -2,11 +2,12 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::{ArgAction, Parser, Subcommand};
-use pastedev_core::{SnippetType, Visibility};
+use pastedev_core::{SlugStrategy, SnippetSort, SnippetType, SortOrder, Visibility};
 
 mod client;
 mod commands;
 mod credentials;
+mod crypto;
 mod mcp;
 mod output;
 
@@ -44,26 +45,65 @@ enum Cmd {
     },
     /// Print current identity.
     Whoami,
-    /// Create a snippet from stdin or a file.
+    /// Create a snippet from stdin, a file, or several files / a directory
+    /// (uploaded together as one multi-file snippet).
     #[command(alias = "put")]
     Publish {
-        /// Path to read. If omitted, reads stdin.
-        file: Option<PathBuf>,
-        /// Force snippet type (overrides extension inference).
+        /// Paths to read. If omitted, reads stdin.
+        files: Vec<PathBuf>,
+        /// Force snippet type (overrides extension inference). Single file only.
         #[arg(long = "type", value_enum)]
         kind: Option<KindArg>,
-        /// Display filename.
+        /// Display filename (the bundle title for multi-file publishes).
         #[arg(long)]
         name: Option<String>,
-        /// Snippet visibility — public (default) or private (auth required to view).
+        /// Pick the slug instead of getting a random one: lowercase words
+        /// joined by hyphens, e.g. `oncall-runbook`.
+        #[arg(long)]
+        slug: Option<String>,
+        /// How to generate the slug: `random-N` (7 to 48 characters), `long`
+        /// or `words-N` (3 to 8 words, e.g. `otter-maple-kayak`). Defaults
+        /// to the server's choice for the visibility.
+        #[arg(long, value_name = "STRATEGY", conflicts_with = "slug")]
+        slug_strategy: Option<SlugStrategy>,
+        /// Snippet visibility — public (default), private (auth required to
+        /// view), password (readers unlock it with --password), or team
+        /// (members of the owning org only; needs --org or an org key).
         #[arg(long, value_enum)]
         visibility: Option<VisibilityArg>,
+        /// Passphrase for `--visibility password`. Prefer the env var: a
+        /// flag value ends up in shell history.
+        #[arg(long, env = "PASTEDEV_SNIPPET_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
         /// Lifetime from creation. Accepts `15m`, `2h`, `1d`, `1w`, or seconds.
         #[arg(long = "lifetime")]
         lifetime: Option<String>,
         /// Burn the snippet 15 min after the first non-owner view.
         #[arg(long)]
         burn_after_read: bool,
+        /// Burn this long after the first non-owner view instead (`5m`,
+        /// `2h`, `1d`, ...), or `once` to burn on that very read. Implies
+        /// --burn-after-read.
+        #[arg(long, value_name = "DURATION")]
+        burn_window: Option<String>,
+        /// Only let this many unique viewers (besides you) read it.
+        #[arg(long)]
+        max_views: Option<i32>,
+        /// Keep it hidden from everyone but you until this time, e.g.
+        /// `2026-11-01T09:00Z`. A --lifetime counts from then.
+        #[arg(long = "at", value_name = "TIME")]
+        publish_at: Option<String>,
+        /// Encrypt the body locally and upload only ciphertext. The key is
+        /// printed in the URL's `#fragment`; keep the whole URL. Single file
+        /// or stdin only, and the file name is not sent unless `--name` is.
+        #[arg(long)]
+        encrypt: bool,
+        /// Publish into this org instead of your personal space.
+        #[arg(long)]
+        org: Option<String>,
+        /// Label the snippet; repeat for several (`--tag rust --tag ci`).
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
     },
     /// List your snippets.
     #[command(alias = "ls")]
@@ -71,17 +111,123 @@ enum Cmd {
         /// Filter by type.
         #[arg(long = "type", value_enum)]
         kind: Option<KindArg>,
+        /// Filter by visibility.
+        #[arg(long, value_enum)]
+        visibility: Option<VisibilityArg>,
+        /// Only burn-after-read snippets.
+        #[arg(long = "burn-after-read", conflicts_with = "no_burn_after_read")]
+        burn_after_read: bool,
+        /// Only snippets without burn-after-read.
+        #[arg(long = "no-burn-after-read")]
+        no_burn_after_read: bool,
+        /// Only snippets past their expiry (still visible to you as the owner).
+        #[arg(long, conflicts_with = "live")]
+        expired: bool,
+        /// Only snippets that haven't expired.
+        #[arg(long)]
+        live: bool,
+        /// Sort key (default: created).
+        #[arg(long, value_enum)]
+        sort: Option<SortArg>,
+        /// Sort direction (default: desc, or asc for expires_at).
+        #[arg(long, value_enum)]
+        order: Option<OrderArg>,
+        /// Resume from a previous page's cursor.
+        #[arg(long)]
+        cursor: Option<String>,
         /// Page size (default 50, max 200).
         #[arg(long)]
         limit: Option<u32>,
+        /// List this org's snippets instead of your own.
+        #[arg(long)]
+        org: Option<String>,
+        /// Only snippets with this tag; repeat to require several.
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Only snippets in this collection of yours.
+        #[arg(long)]
+        collection: Option<String>,
     },
-    /// Fetch a snippet body by slug. Body goes to stdout, metadata to stderr.
+    /// Search your snippets by name and content, best match first.
+    #[command(alias = "find")]
+    Search {
+        /// Words to look for. Supports "quoted phrases", `or`, and `-exclude`.
+        query: String,
+        /// Filter by type.
+        #[arg(long = "type", value_enum)]
+        kind: Option<KindArg>,
+        /// Max results (default 20, max 100).
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+    /// Fetch a snippet body by slug or URL. Body goes to stdout, metadata to
+    /// stderr. Encrypted snippets need the full URL, `#key` included.
     #[command(alias = "cat")]
     Get {
+        /// Slug, or a snippet URL (required for encrypted snippets).
         slug: String,
         /// Also print metadata block to stderr.
         #[arg(long)]
         meta: bool,
+        /// Print only this file of a multi-file snippet.
+        #[arg(long)]
+        file: Option<String>,
+        /// Keep running and print text as it is appended, like `tail -f`.
+        /// Ends when the snippet is deleted, expires or burns.
+        #[arg(long, short = 'f', conflicts_with = "file")]
+        follow: bool,
+    },
+    /// Copy a snippet you can read into a new one of your own, which
+    /// remembers where it came from. Prints the new URL.
+    Fork {
+        /// Slug, or a snippet URL (keeps the `#key` of an encrypted one).
+        slug: String,
+        /// Name the copy instead of keeping the original's name.
+        #[arg(long)]
+        name: Option<String>,
+        /// Pick the copy's slug, as with `publish --slug`.
+        #[arg(long = "slug", value_name = "SLUG")]
+        new_slug: Option<String>,
+        /// Visibility of the copy. Defaults to no wider than the original's:
+        /// a team snippet's copy stays with the team, any other non-public
+        /// one is private.
+        #[arg(long, value_enum)]
+        visibility: Option<VisibilityArg>,
+        /// Passphrase for `--visibility password`.
+        #[arg(long, env = "PASTEDEV_SNIPPET_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Org to put the copy in.
+        #[arg(long)]
+        org: Option<String>,
+    },
+    /// Show a snippet's edit history. Lists revisions by default; `--rev N`
+    /// prints one revision's body, `--diff` prints a unified diff and
+    /// `--purge` deletes all but the latest revision.
+    #[command(alias = "log")]
+    History {
+        slug: String,
+        /// Print the body of this revision instead of the list.
+        #[arg(long, conflicts_with = "diff")]
+        rev: Option<i32>,
+        /// Print a unified diff (default: latest revision vs. the one before).
+        #[arg(long)]
+        diff: bool,
+        /// Diff base revision.
+        #[arg(long, requires = "diff")]
+        from: Option<i32>,
+        /// Diff target revision.
+        #[arg(long, requires = "diff")]
+        to: Option<i32>,
+        /// Page size (default 50, max 200).
+        #[arg(long, conflicts_with_all = ["rev", "diff"])]
+        limit: Option<u32>,
+        /// Delete every revision but the latest, e.g. after editing out a
+        /// secret.
+        #[arg(long, conflicts_with_all = ["rev", "diff", "limit"])]
+        purge: bool,
+        /// Skip the confirmation prompt of `--purge`.
+        #[arg(short, long, requires = "purge")]
+        yes: bool,
     },
     /// Remove a snippet.
     #[command(alias = "rm")]
@@ -91,13 +237,36 @@ enum Cmd {
         #[arg(short, long)]
         yes: bool,
     },
+    /// List your deleted snippets. They can be restored until the server's
+    /// retention window runs out.
+    Trash {
+        /// Resume from a `next_cursor` printed by a previous call.
+        #[arg(long)]
+        cursor: Option<String>,
+        /// Page size (default 50, max 200).
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+    /// Bring a deleted snippet back from the trash.
+    Restore { slug: String },
+    /// Permanently delete a snippet that is already in the trash.
+    Purge {
+        slug: String,
+        /// Skip the confirmation prompt.
+        #[arg(short, long)]
+        yes: bool,
+    },
     /// Update an existing snippet's sharing policy (visibility / expiry /
-    /// burn-after-read). At least one option must be passed.
+    /// burn-after-read / view cap). At least one option must be passed.
     Settings {
         slug: String,
         /// Set visibility.
         #[arg(long, value_enum)]
         visibility: Option<VisibilityArg>,
+        /// Set or replace the passphrase of a password-protected snippet;
+        /// readers who unlocked it with the old one have to unlock again.
+        #[arg(long, env = "PASTEDEV_SNIPPET_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
         /// Set lifetime from now. Accepts `15m`, `2h`, `1d`, `1w`, or seconds.
         #[arg(long = "lifetime", conflicts_with = "no_lifetime")]
         lifetime: Option<String>,
@@ -110,6 +279,87 @@ enum Cmd {
         /// Disable burn-after-read and clear any armed timer.
         #[arg(long = "no-burn-after-read")]
         no_burn_after_read: bool,
+        /// Set the burn-after-read fuse length (`5m`, `2h`, `1d`, ..., or
+        /// `once`). A fuse that is already burning keeps its expiry.
+        #[arg(long, value_name = "DURATION")]
+        burn_window: Option<String>,
+        /// Cap unique viewers (besides you); at or below the current view
+        /// count, nobody new gets in.
+        #[arg(long, conflicts_with = "no_max_views")]
+        max_views: Option<i32>,
+        /// Lift the view cap.
+        #[arg(long = "no-max-views")]
+        no_max_views: bool,
+        /// Hide it from everyone but you until this time, e.g.
+        /// `2026-11-01T09:00Z`.
+        #[arg(long = "at", value_name = "TIME", conflicts_with = "publish_now")]
+        publish_at: Option<String>,
+        /// Lift the embargo and make it readable right away.
+        #[arg(long = "publish-now")]
+        publish_now: bool,
+        /// Refuse the change if the snippet was edited since this ETag
+        /// (from `get --meta`) was handed out.
+        #[arg(long, value_name = "ETAG")]
+        if_match: Option<String>,
+    },
+    /// Show or change who a private snippet is shared with. Without flags,
+    /// lists the share list.
+    Share {
+        slug: String,
+        /// Give this user read access (repeatable).
+        #[arg(long, value_name = "USERNAME")]
+        add: Vec<String>,
+        /// Take this user's access away (repeatable).
+        #[arg(long, value_name = "USERNAME")]
+        remove: Vec<String>,
+        /// Strict private mode: only you and the share list can read it,
+        /// not every signed-in user. Applies while the snippet is private.
+        #[arg(long, conflicts_with = "no_strict")]
+        strict: bool,
+        /// Back to plain private: any signed-in user with the link can read it.
+        #[arg(long = "no-strict")]
+        no_strict: bool,
+    },
+    /// Print a signed link that lets anyone read a non-public snippet for a
+    /// limited time, without signing in.
+    ShareLink {
+        slug: String,
+        /// How long the link works. Accepts `15m`, `2h`, `1d`, or seconds
+        /// (at most 30 days).
+        #[arg(long, default_value = "1d", conflicts_with_all = ["list", "revoke"])]
+        ttl: String,
+        /// Stop letting new viewers in after this many.
+        #[arg(long, conflicts_with_all = ["list", "revoke"])]
+        max_uses: Option<i32>,
+        /// List the snippet's links instead of minting one.
+        #[arg(long, conflicts_with = "revoke")]
+        list: bool,
+        /// Revoke the link with this id.
+        #[arg(long, value_name = "ID")]
+        revoke: Option<String>,
+    },
+    /// Give a snippet a new slug, such as `oncall-runbook`. The old one
+    /// becomes an alias, so existing links keep working.
+    Rename { slug: String, new_slug: String },
+    /// Show or change the other slugs that lead to a snippet. Without flags,
+    /// lists them.
+    Alias {
+        slug: String,
+        /// Add this alias (repeatable).
+        #[arg(long, value_name = "ALIAS")]
+        add: Vec<String>,
+        /// Remove this alias for good; it can't be used again (repeatable).
+        #[arg(long, value_name = "ALIAS")]
+        remove: Vec<String>,
+    },
+    /// Copy stdin to stdout and append it to a snippet as it comes in, e.g.
+    /// `make 2>&1 | pastedev-cli tee build-log`.
+    Tee {
+        slug: String,
+        /// Seconds a line may wait before it is sent along with the lines
+        /// after it.
+        #[arg(long, default_value_t = 1, value_name = "SECS")]
+        interval: u64,
     },
     /// Run as an MCP server over stdio.
     Mcp,
@@ -136,6 +386,8 @@ impl From<KindArg> for SnippetType {
 enum VisibilityArg {
     Public,
     Private,
+    Password,
+    Team,
 }
 
 impl From<VisibilityArg> for Visibility {
@@ -143,6 +395,45 @@ impl From<VisibilityArg> for Visibility {
         match value {
             VisibilityArg::Public => Visibility::Public,
             VisibilityArg::Private => Visibility::Private,
+            VisibilityArg::Password => Visibility::Password,
+            VisibilityArg::Team => Visibility::Team,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum SortArg {
+    Created,
+    Updated,
+    Views,
+    Size,
+    #[value(name = "expires_at", alias = "expires-at")]
+    ExpiresAt,
+}
+
+impl From<SortArg> for SnippetSort {
+    fn from(value: SortArg) -> Self {
+        match value {
+            SortArg::Created => SnippetSort::Created,
+            SortArg::Updated => SnippetSort::Updated,
+            SortArg::Views => SnippetSort::Views,
+            SortArg::Size => SnippetSort::Size,
+            SortArg::ExpiresAt => SnippetSort::ExpiresAt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum OrderArg {
+    Asc,
+    Desc,
+}
+
+impl From<OrderArg> for SortOrder {
+    fn from(value: OrderArg) -> Self {
+        match value {
+            OrderArg::Asc => SortOrder::Asc,
+            OrderArg::Desc => SortOrder::Desc,
         }
     }
 }
@@ -168,12 +459,21 @@ async fn main() {
             .await
         }
         Cmd::Publish {
-            file,
+            files,
             kind,
             name,
+            slug,
+            slug_strategy,
             visibility,
+            password,
             lifetime,
             burn_after_read,
+            burn_window,
+            max_views,
+            publish_at,
+            encrypt,
+            org,
+            tags,
         } => {
             let lifetime_seconds = match lifetime.as_deref() {
                 Some(s) => match commands::publish::parse_duration(s) {
@@ -185,36 +485,152 @@ async fn main() {
                 },
                 None => None,
             };
+            let burn_window_seconds = match burn_window.as_deref() {
+                Some(s) => match commands::publish::parse_burn_window(s) {
+                    Ok(n) => Some(n),
+                    Err(e) => {
+                        eprintln!("error: {e:#}");
+                        std::process::exit(2);
+                    }
+                },
+                None => None,
+            };
+            let publish_at = match publish_at.as_deref() {
+                Some(s) => match commands::publish::parse_publish_at(s) {
+                    Ok(at) => Some(at),
+                    Err(e) => {
+                        eprintln!("error: {e:#}");
+                        std::process::exit(2);
+                    }
+                },
+                None => None,
+            };
             commands::publish::run(commands::publish::Args {
                 format,
                 token: cli.token.as_deref(),
                 base_url: cli.base_url.as_deref(),
                 kind: kind.map(Into::into),
                 name,
-                file,
+                slug,
+                slug_strategy,
+                files,
                 visibility: visibility.map(Into::into),
+                password,
                 lifetime_seconds,
                 burn_after_read,
+                burn_window_seconds,
+                max_views,
+                publish_at,
+                encrypt,
+                org,
+                tags,
             })
             .await
         }
-        Cmd::List { kind, limit } => {
+        Cmd::List {
+            kind,
+            visibility,
+            burn_after_read,
+            no_burn_after_read,
+            expired,
+            live,
+            sort,
+            order,
+            cursor,
+            limit,
+            org,
+            tags,
+            collection,
+        } => {
+            // Flag pairs → tri-state filters; clap's `conflicts_with` rules
+            // out the "both" case.
+            let burn_after_read = match (burn_after_read, no_burn_after_read) {
+                (true, _) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            };
+            let expired = match (expired, live) {
+                (true, _) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            };
             commands::list::run(commands::list::Args {
                 format,
                 token: cli.token.as_deref(),
                 base_url: cli.base_url.as_deref(),
+                options: client::ListOptions {
+                    kind: kind.map(Into::into),
+                    visibility: visibility.map(Into::into),
+                    burn_after_read,
+                    expired,
+                    sort: sort.map(Into::into),
+                    order: order.map(Into::into),
+                    cursor: cursor.as_deref(),
+                    limit,
+                    org: org.as_deref(),
+                    tags: &tags,
+                    collection: collection.as_deref(),
+                },
+            })
+            .await
+        }
+        Cmd::Search { query, kind, limit } => {
+            commands::search::run(commands::search::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                query: &query,
                 kind: kind.map(Into::into),
                 limit,
             })
             .await
         }
-        Cmd::Get { slug, meta } => {
+        Cmd::Get { slug, meta, file, follow } => {
             commands::get::run(commands::get::Args {
                 format,
                 token: cli.token.as_deref(),
                 base_url: cli.base_url.as_deref(),
-                slug: &slug,
+                target: &slug,
                 meta,
+                file: file.as_deref(),
+                follow,
+            })
+            .await
+        }
+        Cmd::Fork {
+            slug,
+            name,
+            new_slug,
+            visibility,
+            password,
+            org,
+        } => {
+            commands::fork::run(commands::fork::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                target: &slug,
+                name,
+                slug: new_slug,
+                visibility: visibility.map(Into::into),
+                password,
+                org,
+            })
+            .await
+        }
+        Cmd::History { slug, rev, diff, from, to, limit, purge, yes } => {
+            let mode = match (rev, diff) {
+                _ if purge => commands::history::Mode::Purge { yes },
+                (Some(revision), _) => commands::history::Mode::Show { revision },
+                (None, true) => commands::history::Mode::Diff { from, to },
+                (None, false) => commands::history::Mode::List { limit },
+            };
+            commands::history::run(commands::history::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                mode,
             })
             .await
         }
@@ -227,13 +643,48 @@ async fn main() {
             })
             .await
         }
+        Cmd::Trash { cursor, limit } => {
+            commands::trash::run(commands::trash::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                cursor: cursor.as_deref(),
+                limit,
+            })
+            .await
+        }
+        Cmd::Restore { slug } => {
+            commands::restore::run(commands::restore::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+            })
+            .await
+        }
+        Cmd::Purge { slug, yes } => {
+            commands::purge::run(commands::purge::Args {
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                yes,
+            })
+            .await
+        }
         Cmd::Settings {
             slug,
             visibility,
+            password,
             lifetime,
             no_lifetime,
             burn_after_read,
             no_burn_after_read,
+            burn_window,
+            max_views,
+            no_max_views,
+            publish_at,
+            publish_now,
+            if_match,
         } => {
             // Collapse the `--lifetime` / `--no-lifetime` pair into the
             // three-state `Option<Option<i32>>` that matches the wire.
@@ -256,14 +707,131 @@ async fn main() {
                 (false, false) => None,
                 (true, true) => unreachable!("clap enforces conflicts_with"),
             };
+            let burn_window_seconds = match burn_window.as_deref() {
+                Some(s) => match commands::publish::parse_burn_window(s) {
+                    Ok(n) => Some(n),
+                    Err(e) => {
+                        eprintln!("error: {e:#}");
+                        std::process::exit(2);
+                    }
+                },
+                None => None,
+            };
+            let max_views = match (max_views, no_max_views) {
+                (Some(n), false) => Some(Some(n)),
+                (None, true) => Some(None),
+                (None, false) => None,
+                (Some(_), true) => unreachable!("clap enforces conflicts_with"),
+            };
+            let publish_at = match (publish_at.as_deref(), publish_now) {
+                (Some(s), false) => match commands::publish::parse_publish_at(s) {
+                    Ok(at) => Some(Some(at)),
+                    Err(e) => {
+                        eprintln!("error: {e:#}");
+                        std::process::exit(2);
+                    }
+                },
+                (None, true) => Some(None),
+                (None, false) => None,
+                (Some(_), true) => unreachable!("clap enforces conflicts_with"),
+            };
             commands::settings::run(commands::settings::Args {
                 format,
                 token: cli.token.as_deref(),
                 base_url: cli.base_url.as_deref(),
                 slug: &slug,
                 visibility: visibility.map(Into::into),
+                password,
                 lifetime_seconds,
                 burn_after_read: burn,
+                burn_window_seconds,
+                max_views,
+                publish_at,
+                if_match: if_match.as_deref(),
+            })
+            .await
+        }
+        Cmd::Share {
+            slug,
+            add,
+            remove,
+            strict,
+            no_strict,
+        } => {
+            let strict_private = match (strict, no_strict) {
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+                (true, true) => unreachable!("clap enforces conflicts_with"),
+            };
+            commands::share::run(commands::share::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                add,
+                remove,
+                strict_private,
+            })
+            .await
+        }
+        Cmd::ShareLink {
+            slug,
+            ttl,
+            max_uses,
+            list,
+            revoke,
+        } => {
+            let mode = match (list, revoke) {
+                (true, _) => commands::share_link::Mode::List,
+                (false, Some(id)) => commands::share_link::Mode::Revoke { id },
+                (false, None) => match commands::publish::parse_duration(&ttl) {
+                    Ok(ttl_seconds) => commands::share_link::Mode::Create {
+                        ttl_seconds,
+                        max_uses,
+                    },
+                    Err(e) => {
+                        eprintln!("error: {e:#}");
+                        std::process::exit(2);
+                    }
+                },
+            };
+            commands::share_link::run(commands::share_link::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                mode,
+            })
+            .await
+        }
+        Cmd::Rename { slug, new_slug } => {
+            commands::rename::run(commands::rename::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                new_slug,
+            })
+            .await
+        }
+        Cmd::Alias { slug, add, remove } => {
+            commands::alias::run(commands::alias::Args {
+                format,
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                add,
+                remove,
+            })
+            .await
+        }
+        Cmd::Tee { slug, interval } => {
+            commands::tee::run(commands::tee::Args {
+                token: cli.token.as_deref(),
+                base_url: cli.base_url.as_deref(),
+                slug: &slug,
+                interval: std::time::Duration::from_secs(interval.max(1)),
             })
             .await
         }
@@ -15,13 +15,17 @@ use std::io::Write;
 use anyhow::{anyhow, Context, Result};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use tokio::io::{AsyncBufReadExt, BufReader};
 
-use crate::client::ApiClient;
+use crate::client::{ApiClient, ListOptions};
 use crate::credentials::resolve;
 use pastedev_core::{
-    CreateSnippetRequest, PatchSnippetRequest, SettingsRequest, SnippetType, Visibility,
-    LIFETIME_SECONDS_MAX, LIFETIME_SECONDS_MIN,
+    is_valid_burn_window, CreateSnippetRequest, PatchSnippetRequest, SettingsRequest, SlugStrategy,
+    Snippet, SnippetContent, SnippetSort, SnippetType, SortOrder, Visibility, BURN_WINDOW_ONE_SHOT,
+    BURN_WINDOW_SECONDS_MAX, BURN_WINDOW_SECONDS_MIN, LIFETIME_SECONDS_MAX, LIFETIME_SECONDS_MIN,
+    MAX_TAGS,
 };
 
 const PROTOCOL_VERSION: &str = "2024-11-05";
@@ -121,22 +125,44 @@ fn tool_definitions() -> Vec<Value> {
         json!({
             "name": "pastedev_publish",
             "description": "Create a new snippet with an in-memory body. \
-                            Optional `visibility` (public/private), \
-                            `lifetime_seconds` (60..=31_536_000), and \
-                            `burn_after_read` (15 min after first non-owner view).",
+                            Optional `visibility` (public/private/password/team; \
+                            password needs `password`, the passphrase readers \
+                            unlock it with; team needs an org), `org` (publish into \
+                            that org), `lifetime_seconds` (60..=31_536_000), \
+                            `burn_after_read` (15 min after first non-owner view, or \
+                            `burn_window_seconds` — 0 burns on that very read), \
+                            `max_views` (readable by that many unique viewers, then gone), \
+                            `publish_at` (hidden from everyone but you until then), \
+                            `slug` (a custom slug such as `oncall-runbook`: lowercase words \
+                            joined by hyphens), and `slug_strategy` (how to generate one \
+                            instead: `random-N` for N=7..48, `long`, or `words-N` for \
+                            N=3..8; not together with `slug`). `tags` labels it with up \
+                            to 16 lowercase words (letters, digits, `-_.+#:/`).",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "type": { "type": "string", "enum": ["code", "markdown", "html"] },
                     "body": { "type": "string" },
                     "name": { "type": "string" },
-                    "visibility": { "type": "string", "enum": ["public", "private"] },
+                    "slug": { "type": "string" },
+                    "slug_strategy": { "type": "string" },
+                    "visibility": { "type": "string", "enum": ["public", "private", "password", "team"] },
+                    "password": { "type": "string" },
+                    "org": { "type": "string" },
                     "lifetime_seconds": {
                         "type": "integer",
                         "minimum": LIFETIME_SECONDS_MIN,
                         "maximum": LIFETIME_SECONDS_MAX
                     },
-                    "burn_after_read": { "type": "boolean" }
+                    "burn_after_read": { "type": "boolean" },
+                    "burn_window_seconds": {
+                        "type": "integer",
+                        "minimum": BURN_WINDOW_ONE_SHOT,
+                        "maximum": BURN_WINDOW_SECONDS_MAX
+                    },
+                    "max_views": { "type": "integer", "minimum": 1 },
+                    "publish_at": { "type": "string", "format": "date-time" },
+                    "tags": { "type": "array", "items": { "type": "string" }, "maxItems": MAX_TAGS }
                 },
                 "required": ["type", "body"],
                 "additionalProperties": false
@@ -147,21 +173,34 @@ fn tool_definitions() -> Vec<Value> {
             "name": "pastedev_publish_file",
             "description": "Create a snippet whose body is read from a local file path. \
                             Type is inferred from extension if not provided. \
-                            Accepts the same visibility / lifetime_seconds / burn_after_read \
-                            options as pastedev_publish.",
+                            Accepts the same visibility / password / org / lifetime_seconds / \
+                            burn_after_read / burn_window_seconds / max_views / publish_at / \
+                            slug / slug_strategy / tags options as pastedev_publish.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "file_path": { "type": "string" },
                     "type": { "type": "string", "enum": ["code", "markdown", "html"] },
                     "name": { "type": "string" },
-                    "visibility": { "type": "string", "enum": ["public", "private"] },
+                    "slug": { "type": "string" },
+                    "slug_strategy": { "type": "string" },
+                    "visibility": { "type": "string", "enum": ["public", "private", "password", "team"] },
+                    "password": { "type": "string" },
+                    "org": { "type": "string" },
                     "lifetime_seconds": {
                         "type": "integer",
                         "minimum": LIFETIME_SECONDS_MIN,
                         "maximum": LIFETIME_SECONDS_MAX
                     },
-                    "burn_after_read": { "type": "boolean" }
+                    "burn_after_read": { "type": "boolean" },
+                    "burn_window_seconds": {
+                        "type": "integer",
+                        "minimum": BURN_WINDOW_ONE_SHOT,
+                        "maximum": BURN_WINDOW_SECONDS_MAX
+                    },
+                    "max_views": { "type": "integer", "minimum": 1 },
+                    "publish_at": { "type": "string", "format": "date-time" },
+                    "tags": { "type": "array", "items": { "type": "string" }, "maxItems": MAX_TAGS }
                 },
                 "required": ["file_path"],
                 "additionalProperties": false
@@ -172,7 +211,8 @@ fn tool_definitions() -> Vec<Value> {
             "name": "pastedev_get",
             "description": "Fetch a snippet by slug. Response includes `visibility`, \
                             `burn_after_read`, and `expires_at` so callers can reason \
-                            about its sharing policy.",
+                            about its sharing policy, and the `etag` that \
+                            `pastedev_edit` needs.",
             "inputSchema": {
                 "type": "object",
                 "properties": { "slug": { "type": "string" } },
@@ -183,33 +223,73 @@ fn tool_definitions() -> Vec<Value> {
         }),
         json!({
             "name": "pastedev_list",
-            "description": "List the caller's snippets, optionally filtered by type. \
-                            Each item includes `visibility`, `burn_after_read`, and \
-                            `expires_at`.",
+            "description": "List the caller's snippets. Filter by `type`, `visibility`, \
+                            `burn_after_read`, or `expired` (true = past expiry but still \
+                            owned); order with `sort` / `order`. Each item includes \
+                            `visibility`, `burn_after_read`, and `expires_at`. Pass \
+                            `next_cursor` back as `cursor` with the same sort to page. \
+                            Pass `org` to list that org's snippets instead, `tags` to \
+                            keep only snippets carrying all of them, or `collection` to \
+                            keep only those in one of the caller's collections.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "type": { "type": "string", "enum": ["code", "markdown", "html"] },
+                    "visibility": { "type": "string", "enum": ["public", "private", "password", "team"] },
+                    "burn_after_read": { "type": "boolean" },
+                    "expired": { "type": "boolean" },
+                    "sort": {
+                        "type": "string",
+                        "enum": ["created", "updated", "views", "size", "expires_at"]
+                    },
+                    "order": { "type": "string", "enum": ["asc", "desc"] },
                     "cursor": { "type": "string" },
-                    "limit": { "type": "integer", "minimum": 1, "maximum": 200 }
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 200 },
+                    "org": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "collection": { "type": "string" }
                 },
                 "additionalProperties": false
             },
             "annotations": { "readOnlyHint": true },
         }),
+        json!({
+            "name": "pastedev_search",
+            "description": "Search the caller's snippets by name and body, best match \
+                            first. `query` accepts plain words, \"quoted phrases\", `or`, and \
+                            `-excluded` words; substrings of identifiers match too. Each \
+                            result has the list-item fields plus `rank` and a `highlight` \
+                            excerpt with matches wrapped in « ».",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "type": { "type": "string", "enum": ["code", "markdown", "html"] },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 100 }
+                },
+                "required": ["query"],
+                "additionalProperties": false
+            },
+            "annotations": { "readOnlyHint": true },
+        }),
         json!({
             "name": "pastedev_edit",
             "description": "Edit an existing snippet by slug. Provide `body` to replace the \
                             content and/or `name` to rename (empty string clears the name). \
-                            At least one of `body` or `name` must be set.",
+                            At least one of `body` or `name` must be set. `etag` is the \
+                            one from the `pastedev_get` (or previous `pastedev_edit`) the \
+                            edit is based on: if someone changed the snippet since, the \
+                            edit fails with `precondition_failed` instead of overwriting \
+                            their change — fetch it again and redo the edit on top.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "slug": { "type": "string" },
+                    "etag": { "type": "string" },
                     "body": { "type": "string" },
                     "name": { "type": "string" }
                 },
-                "required": ["slug"],
+                "required": ["slug", "etag"],
                 "additionalProperties": false
             },
             "annotations": { "readOnlyHint": false, "destructiveHint": false },
@@ -217,24 +297,38 @@ fn tool_definitions() -> Vec<Value> {
         json!({
             "name": "pastedev_settings",
             "description": "Update an existing snippet's sharing policy. Any subset \
-                            of `visibility`, `lifetime_seconds`, `burn_after_read` may \
-                            be supplied (at least one is required). Omitted fields \
-                            stay as-is. `lifetime_seconds: null` clears the expiry; \
+                            of `visibility`, `password`, `lifetime_seconds`, \
+                            `burn_after_read`, `burn_window_seconds`, `max_views` may be supplied (at least one is \
+                            required). Omitted fields stay as-is. `password` sets or \
+                            replaces the passphrase of a password-protected snippet. \
+                            `lifetime_seconds: null` clears the expiry; \
                             an integer sets `expires_at = now() + n`. Disabling \
                             `burn_after_read` also clears any armed timer, but does \
                             NOT extend an already-tightened `expires_at` — pass \
-                            `lifetime_seconds` to restore a longer lifetime.",
+                            `lifetime_seconds` to restore a longer lifetime. \
+                            `max_views: null` lifts the unique-viewer cap. With \
+                            `etag`, fails with `precondition_failed` if the snippet \
+                            changed since.",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "slug": { "type": "string" },
-                    "visibility": { "type": "string", "enum": ["public", "private"] },
+                    "etag": { "type": "string" },
+                    "visibility": { "type": "string", "enum": ["public", "private", "password", "team"] },
+                    "password": { "type": "string" },
                     "lifetime_seconds": {
                         "type": ["integer", "null"],
                         "minimum": LIFETIME_SECONDS_MIN,
                         "maximum": LIFETIME_SECONDS_MAX
                     },
-                    "burn_after_read": { "type": "boolean" }
+                    "burn_after_read": { "type": "boolean" },
+                    "burn_window_seconds": {
+                        "type": "integer",
+                        "minimum": BURN_WINDOW_ONE_SHOT,
+                        "maximum": BURN_WINDOW_SECONDS_MAX
+                    },
+                    "max_views": { "type": ["integer", "null"], "minimum": 1 },
+                    "publish_at": { "type": ["string", "null"], "format": "date-time" }
                 },
                 "required": ["slug"],
                 "additionalProperties": false
@@ -271,6 +365,7 @@ async fn handle_tool_call(params: Value) -> Result<Value, (i32, String)> {
         "pastedev_publish_file" => call_publish_file(&client, args).await,
         "pastedev_get" => call_get(&client, args).await,
         "pastedev_list" => call_list(&client, args).await,
+        "pastedev_search" => call_search(&client, args).await,
         "pastedev_edit" => call_edit(&client, args).await,
         "pastedev_settings" => call_settings(&client, args).await,
         "pastedev_delete" => call_delete(&client, args).await,
@@ -307,12 +402,23 @@ async fn call_publish(client: &ApiClient, args: Value) -> Result<Value> {
     let (visibility, lifetime_seconds, burn_after_read) = parse_publish_opts(&args)?;
     let snippet = client
         .create_snippet(&CreateSnippetRequest {
-            kind,
             name,
-            body: body.to_string(),
+            content: SnippetContent::Single {
+                kind,
+                body: body.to_string(),
+            },
+            slug: string_arg(&args, "slug"),
+            slug_strategy: slug_strategy_arg(&args)?,
             visibility,
             lifetime_seconds,
             burn_after_read,
+            burn_window_seconds: burn_window_arg(&args)?,
+            max_views: max_views_arg(&args)?,
+            publish_at: publish_at_arg(&args)?,
+            password: string_arg(&args, "password"),
+            encrypted: None,
+            org: string_arg(&args, "org"),
+            tags: tags_arg(&args)?,
         })
         .await?;
     Ok(serde_json::to_value(&snippet)?)
@@ -346,6 +452,86 @@ fn parse_publish_opts(args: &Value) -> Result<(Option<Visibility>, Option<i32>,
     Ok((visibility, lifetime_seconds, burn_after_read))
 }
 
+/// `burn_window_seconds`, when given, must be 0 (one-shot) or within the
+/// server's bounds.
+fn burn_window_arg(args: &Value) -> Result<Option<i32>> {
+    match args.get("burn_window_seconds") {
+        None => Ok(None),
+        Some(v) => {
+            let n = v
+                .as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .filter(|n| is_valid_burn_window(*n))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "burn_window_seconds must be {} (one-shot) or between {} and {}",
+                        BURN_WINDOW_ONE_SHOT,
+                        BURN_WINDOW_SECONDS_MIN,
+                        BURN_WINDOW_SECONDS_MAX
+                    )
+                })?;
+            Ok(Some(n))
+        }
+    }
+}
+
+/// `max_views`, when given, must be a positive integer.
+fn max_views_arg(args: &Value) -> Result<Option<i32>> {
+    match args.get("max_views") {
+        None => Ok(None),
+        Some(v) => {
+            let n = v
+                .as_i64()
+                .and_then(|n| i32::try_from(n).ok())
+                .filter(|n| *n >= 1)
+                .ok_or_else(|| anyhow!("max_views must be a positive integer"))?;
+            Ok(Some(n))
+        }
+    }
+}
+
+/// `publish_at`, when given, must be an RFC 3339 timestamp.
+fn publish_at_arg(args: &Value) -> Result<Option<OffsetDateTime>> {
+    match args.get("publish_at") {
+        None => Ok(None),
+        Some(v) => {
+            let at = v
+                .as_str()
+                .and_then(|s| OffsetDateTime::parse(s, &Rfc3339).ok())
+                .ok_or_else(|| anyhow!("publish_at must be an RFC 3339 timestamp"))?;
+            Ok(Some(at))
+        }
+    }
+}
+
+fn slug_strategy_arg(args: &Value) -> Result<Option<SlugStrategy>> {
+    match args.get("slug_strategy") {
+        None => Ok(None),
+        Some(v) => {
+            let s = v.as_str().context("slug_strategy must be a string")?;
+            Ok(Some(s.parse::<SlugStrategy>().map_err(|e| anyhow!(e))?))
+        }
+    }
+}
+
+/// `tags`, when given, must be an array of strings; the server normalizes
+/// and checks each one.
+fn tags_arg(args: &Value) -> Result<Vec<String>> {
+    match args.get("tags") {
+        None => Ok(Vec::new()),
+        Some(v) => v
+            .as_array()
+            .context("tags must be an array of strings")?
+            .iter()
+            .map(|t| t.as_str().map(String::from).context("tags must be an array of strings"))
+            .collect(),
+    }
+}
+
+fn string_arg(args: &Value, key: &str) -> Option<String> {
+    args.get(key).and_then(|v| v.as_str()).map(String::from)
+}
+
 async fn call_publish_file(client: &ApiClient, args: Value) -> Result<Value> {
     let path_str = args
         .get("file_path")
@@ -353,14 +539,6 @@ async fn call_publish_file(client: &ApiClient, args: Value) -> Result<Value> {
         .context("file_path is required")?;
     let path = std::path::PathBuf::from(path_str);
     let bytes = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
-    if bytes.len() > pastedev_core::MAX_SNIPPET_BYTES {
-        return Err(anyhow!(
-            "{} is {} bytes, max is {}",
-            path.display(),
-            bytes.len(),
-            pastedev_core::MAX_SNIPPET_BYTES
-        ));
-    }
     let body = String::from_utf8(bytes).context("file is not valid UTF-8")?;
     let kind = args
         .get("type")
@@ -376,12 +554,20 @@ async fn call_publish_file(client: &ApiClient, args: Value) -> Result<Value> {
     let (visibility, lifetime_seconds, burn_after_read) = parse_publish_opts(&args)?;
     let snippet = client
         .create_snippet(&CreateSnippetRequest {
-            kind,
             name,
-            body,
+            content: SnippetContent::Single { kind, body },
+            slug: string_arg(&args, "slug"),
+            slug_strategy: slug_strategy_arg(&args)?,
             visibility,
             lifetime_seconds,
             burn_after_read,
+            burn_window_seconds: burn_window_arg(&args)?,
+            max_views: max_views_arg(&args)?,
+            publish_at: publish_at_arg(&args)?,
+            password: string_arg(&args, "password"),
+            encrypted: None,
+            org: string_arg(&args, "org"),
+            tags: tags_arg(&args)?,
         })
         .await?;
     Ok(serde_json::to_value(&snippet)?)
@@ -398,8 +584,17 @@ fn infer_kind(path: &std::path::Path) -> Option<SnippetType> {
 
 async fn call_get(client: &ApiClient, args: Value) -> Result<Value> {
     let slug = args.get("slug").and_then(|v| v.as_str()).context("slug is required")?;
-    let snippet = client.get_snippet(slug).await?;
-    Ok(serde_json::to_value(&snippet)?)
+    let (snippet, etag) = client.get_snippet(slug).await?;
+    with_etag(&snippet, etag)
+}
+
+/// A snippet as tool output, with its `etag` alongside the API fields.
+fn with_etag(snippet: &Snippet, etag: Option<String>) -> Result<Value> {
+    let mut value = serde_json::to_value(snippet)?;
+    if let (Some(obj), Some(etag)) = (value.as_object_mut(), etag) {
+        obj.insert("etag".into(), Value::String(etag));
+    }
+    Ok(value)
 }
 
 async fn call_list(client: &ApiClient, args: Value) -> Result<Value> {
@@ -407,22 +602,67 @@ async fn call_list(client: &ApiClient, args: Value) -> Result<Value> {
         .get("type")
         .and_then(|v| v.as_str())
         .and_then(|s| s.parse::<SnippetType>().ok());
+    let visibility = match args.get("visibility").and_then(|v| v.as_str()) {
+        None => None,
+        Some(s) => Some(s.parse::<Visibility>().map_err(|_| anyhow!("invalid visibility"))?),
+    };
+    let sort = match args.get("sort").and_then(|v| v.as_str()) {
+        None => None,
+        Some(s) => Some(s.parse::<SnippetSort>().map_err(|_| anyhow!("invalid sort"))?),
+    };
+    let order = match args.get("order").and_then(|v| v.as_str()) {
+        None => None,
+        Some(s) => Some(s.parse::<SortOrder>().map_err(|_| anyhow!("invalid order"))?),
+    };
     let cursor = args.get("cursor").and_then(|v| v.as_str()).map(String::from);
     let limit = args.get("limit").and_then(|v| v.as_u64()).map(|n| n as u32);
-    let list = client.list_snippets(kind, cursor.as_deref(), limit).await?;
+    let org = string_arg(&args, "org");
+    let tags = tags_arg(&args)?;
+    let collection = string_arg(&args, "collection");
+    let list = client
+        .list_snippets(&ListOptions {
+            kind,
+            visibility,
+            burn_after_read: args.get("burn_after_read").and_then(|v| v.as_bool()),
+            expired: args.get("expired").and_then(|v| v.as_bool()),
+            sort,
+            order,
+            cursor: cursor.as_deref(),
+            limit,
+            org: org.as_deref(),
+            tags: &tags,
+            collection: collection.as_deref(),
+        })
+        .await?;
     Ok(serde_json::to_value(&list)?)
 }
 
+async fn call_search(client: &ApiClient, args: Value) -> Result<Value> {
+    let query = args.get("query").and_then(|v| v.as_str()).context("query is required")?;
+    let kind = match args.get("type").and_then(|v| v.as_str()) {
+        None => None,
+        Some(s) => Some(s.parse::<SnippetType>().map_err(|_| anyhow!("invalid type"))?),
+    };
+    let limit = args.get("limit").and_then(|v| v.as_u64()).map(|n| n as u32);
+    let results = client.search_snippets(query, kind, limit).await?;
+    Ok(serde_json::to_value(&results)?)
+}
+
 async fn call_edit(client: &ApiClient, args: Value) -> Result<Value> {
     let slug = args.get("slug").and_then(|v| v.as_str()).context("slug is required")?;
+    // Required so an edit based on a stale read can't clobber a newer one.
+    let etag = args
+        .get("etag")
+        .and_then(|v| v.as_str())
+        .context("etag is required; take it from pastedev_get")?;
     let body = args.get("body").and_then(|v| v.as_str()).map(String::from);
     let name = args.get("name").and_then(|v| v.as_str()).map(String::from);
     if body.is_none() && name.is_none() {
         return Err(anyhow!("at least one of `body` or `name` must be provided"));
     }
     let patch = PatchSnippetRequest { body, name };
-    let snippet = client.update_snippet(slug, &patch).await?;
-    Ok(serde_json::to_value(&snippet)?)
+    let (snippet, etag) = client.update_snippet(slug, &patch, Some(etag)).await?;
+    with_etag(&snippet, etag)
 }
 
 async fn call_settings(client: &ApiClient, args: Value) -> Result<Value> {
@@ -452,22 +692,44 @@ async fn call_settings(client: &ApiClient, args: Value) -> Result<Value> {
         }
     };
     let burn_after_read = args.get("burn_after_read").and_then(|v| v.as_bool());
-    if visibility.is_none() && lifetime_seconds.is_none() && burn_after_read.is_none() {
+    let burn_window_seconds = burn_window_arg(&args)?;
+    let max_views = match args.get("max_views") {
+        Some(v) if v.is_null() => Some(None),
+        _ => max_views_arg(&args)?.map(Some),
+    };
+    let publish_at = match args.get("publish_at") {
+        Some(v) if v.is_null() => Some(None),
+        _ => publish_at_arg(&args)?.map(Some),
+    };
+    let password = string_arg(&args, "password");
+    if visibility.is_none()
+        && password.is_none()
+        && lifetime_seconds.is_none()
+        && burn_after_read.is_none()
+        && burn_window_seconds.is_none()
+        && max_views.is_none()
+        && publish_at.is_none()
+    {
         return Err(anyhow!(
-            "at least one of `visibility`, `lifetime_seconds`, `burn_after_read` must be set"
+            "at least one of `visibility`, `password`, `lifetime_seconds`, `burn_after_read`, `burn_window_seconds`, `max_views`, `publish_at` must be set"
         ));
     }
-    let snippet = client
+    let (snippet, etag) = client
         .update_settings(
             slug,
             &SettingsRequest {
                 visibility,
                 lifetime_seconds,
+                publish_at,
                 burn_after_read,
+                burn_window_seconds,
+                max_views,
+                password,
             },
+            args.get("etag").and_then(|v| v.as_str()),
         )
         .await?;
-    Ok(serde_json::to_value(&snippet)?)
+    with_etag(&snippet, etag)
 }
 
 async fn call_delete(client: &ApiClient, args: Value) -> Result<Value> {
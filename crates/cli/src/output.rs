@@ -42,3 +42,20 @@ pub fn fmt_ago(at: time::OffsetDateTime) -> String {
         format!("{}d ago", secs / 86_400)
     }
 }
+
+/// Counterpart of [`fmt_ago`] for timestamps in the future: `in 3h`. Past
+/// timestamps read `now`.
+pub fn fmt_in(at: time::OffsetDateTime) -> String {
+    let secs = (at - time::OffsetDateTime::now_utc()).whole_seconds();
+    if secs <= 0 {
+        "now".to_string()
+    } else if secs < 60 {
+        format!("in {secs}s")
+    } else if secs < 3_600 {
+        format!("in {}m", secs / 60)
+    } else if secs < 86_400 {
+        format!("in {}h", secs / 3_600)
+    } else {
+        format!("in {}d", secs / 86_400)
+    }
+}
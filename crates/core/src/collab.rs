@@ -0,0 +1,435 @@
+//! Wire types for collaborative editing on `GET /api/v1/snippets/:slug/collab`.
+//!
+//! The socket carries JSON text frames: [`ClientMessage`] up,
+//! [`ServerMessage`] down. Edits are operational transforms in the format of
+//! ot.js: a [`TextOp`] is a JSON array in which a positive number keeps that
+//! many characters, a negative one deletes that many, and a string inserts
+//! itself. Positions count Unicode scalar values (Rust `char`s), not bytes or
+//! UTF-16 units.
+//!
+//! The server's copy is authoritative and numbers its versions. A client
+//! sends each op against the last revision it has seen, with at most one op
+//! in flight: the server transforms it past whatever landed in between,
+//! applies it, acknowledges it with `ack`, and sends it to everyone else as
+//! `op`. Meanwhile the client transforms incoming ops past its own unacked
+//! and pending ones with [`TextOp::transform`], the client's op going first
+//! (`transform(mine, theirs)`). An `op` without a `client_id` didn't come
+//! from a peer but from outside the session, such as an append.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ErrorCode;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Component {
+    Retain(usize),
+    Insert(String),
+    Delete(usize),
+}
+
+/// An edit of a whole text: it spans [`TextOp::base_len`] characters before
+/// and [`TextOp::target_len`] after. Built with [`TextOp::retain`],
+/// [`TextOp::insert`] and [`TextOp::delete`], which keep it in its one
+/// normal form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextOp {
+    ops: Vec<Component>,
+    base_len: usize,
+    target_len: usize,
+}
+
+impl TextOp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Length of the text this applies to.
+    pub fn base_len(&self) -> usize {
+        self.base_len
+    }
+
+    /// Length of the text it results in.
+    pub fn target_len(&self) -> usize {
+        self.target_len
+    }
+
+    /// Whether applying it changes nothing.
+    pub fn is_noop(&self) -> bool {
+        self.ops.iter().all(|c| matches!(c, Component::Retain(_)))
+    }
+
+    /// Keep the next `n` characters.
+    pub fn retain(&mut self, n: usize) -> &mut Self {
+        if n == 0 {
+            return self;
+        }
+        self.base_len += n;
+        self.target_len += n;
+        match self.ops.last_mut() {
+            Some(Component::Retain(m)) => *m += n,
+            _ => self.ops.push(Component::Retain(n)),
+        }
+        self
+    }
+
+    /// Insert `text` here. An insert next to a delete always goes first.
+    pub fn insert(&mut self, text: &str) -> &mut Self {
+        if text.is_empty() {
+            return self;
+        }
+        self.target_len += text.chars().count();
+        match self.ops.as_mut_slice() {
+            [.., Component::Insert(s)] | [.., Component::Insert(s), Component::Delete(_)] => {
+                s.push_str(text)
+            }
+            [.., Component::Delete(_)] => {
+                let at = self.ops.len() - 1;
+                self.ops.insert(at, Component::Insert(text.to_string()));
+            }
+            _ => self.ops.push(Component::Insert(text.to_string())),
+        }
+        self
+    }
+
+    /// Delete the next `n` characters.
+    pub fn delete(&mut self, n: usize) -> &mut Self {
+        if n == 0 {
+            return self;
+        }
+        self.base_len += n;
+        match self.ops.last_mut() {
+            Some(Component::Delete(m)) => *m += n,
+            _ => self.ops.push(Component::Delete(n)),
+        }
+        self
+    }
+
+    /// The text after the edit; `None` if `text` isn't [`Self::base_len`]
+    /// characters long.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        if text.chars().count() != self.base_len {
+            return None;
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        for c in &self.ops {
+            match c {
+                Component::Retain(n) => out.extend(chars.by_ref().take(*n)),
+                Component::Insert(s) => out.push_str(s),
+                Component::Delete(n) => {
+                    chars.by_ref().take(*n).for_each(drop);
+                }
+            }
+        }
+        Some(out)
+    }
+
+    /// Rebase two concurrent edits of the same text onto each other:
+    /// `(a', b')` such that `b'` after `a` and `a'` after `b` give the same
+    /// text. Where both insert at the same spot, `a`'s text comes first.
+    /// `None` if they don't start from texts of the same length.
+    pub fn transform(a: &TextOp, b: &TextOp) -> Option<(TextOp, TextOp)> {
+        if a.base_len != b.base_len {
+            return None;
+        }
+        let (mut a1, mut b1) = (TextOp::new(), TextOp::new());
+        let (mut ia, mut ib) = (a.ops.iter().cloned(), b.ops.iter().cloned());
+        let (mut op1, mut op2) = (ia.next(), ib.next());
+        loop {
+            if let Some(Component::Insert(s)) = &op1 {
+                a1.insert(s);
+                b1.retain(s.chars().count());
+                op1 = ia.next();
+                continue;
+            }
+            if let Some(Component::Insert(s)) = &op2 {
+                a1.retain(s.chars().count());
+                b1.insert(s);
+                op2 = ib.next();
+                continue;
+            }
+            let (x, y) = match (op1.take(), op2.take()) {
+                (None, None) => return Some((a1, b1)),
+                (Some(x), Some(y)) => (x, y),
+                // Equal base lengths make the two run out together.
+                _ => return None,
+            };
+            let (n, m) = (x.len(), y.len());
+            let k = n.min(m);
+            match (&x, &y) {
+                (Component::Retain(_), Component::Retain(_)) => {
+                    a1.retain(k);
+                    b1.retain(k);
+                }
+                (Component::Delete(_), Component::Retain(_)) => {
+                    a1.delete(k);
+                }
+                (Component::Retain(_), Component::Delete(_)) => {
+                    b1.delete(k);
+                }
+                // Both deleted the same characters.
+                (Component::Delete(_), Component::Delete(_)) => {}
+                _ => unreachable!("inserts handled above"),
+            }
+            op1 = if n > k {
+                Some(x.with_len(n - k))
+            } else {
+                ia.next()
+            };
+            op2 = if m > k {
+                Some(y.with_len(m - k))
+            } else {
+                ib.next()
+            };
+        }
+    }
+
+    /// Where a position in the text before this edit ends up after it. Text
+    /// inserted right at the position goes before it.
+    pub fn transform_index(&self, index: usize) -> usize {
+        let mut left = index as isize;
+        let mut new_index = index;
+        for c in &self.ops {
+            match c {
+                Component::Retain(n) => left -= *n as isize,
+                Component::Insert(s) => new_index += s.chars().count(),
+                Component::Delete(n) => {
+                    new_index -= (left.max(0) as usize).min(*n);
+                    left -= *n as isize;
+                }
+            }
+            if left < 0 {
+                break;
+            }
+        }
+        new_index
+    }
+}
+
+impl Component {
+    fn len(&self) -> usize {
+        match self {
+            Component::Retain(n) | Component::Delete(n) => *n,
+            Component::Insert(s) => s.chars().count(),
+        }
+    }
+
+    fn with_len(&self, n: usize) -> Component {
+        match self {
+            Component::Retain(_) => Component::Retain(n),
+            Component::Delete(_) => Component::Delete(n),
+            Component::Insert(_) => unreachable!("inserts are never split"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawComponent {
+    Count(i64),
+    Text(String),
+}
+
+impl Serialize for TextOp {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let raw: Vec<RawComponent> = self
+            .ops
+            .iter()
+            .map(|c| match c {
+                Component::Retain(n) => RawComponent::Count(*n as i64),
+                Component::Delete(n) => RawComponent::Count(-(*n as i64)),
+                Component::Insert(t) => RawComponent::Text(t.clone()),
+            })
+            .collect();
+        raw.serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for TextOp {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let raw = Vec::<RawComponent>::deserialize(d)?;
+        let mut op = TextOp::new();
+        for c in raw {
+            match c {
+                RawComponent::Count(0) => return Err(de::Error::custom("empty component")),
+                RawComponent::Count(n) if n > 0 => op.retain(n as usize),
+                RawComponent::Count(n) => op.delete(n.unsigned_abs() as usize),
+                RawComponent::Text(t) if t.is_empty() => {
+                    return Err(de::Error::custom("empty component"))
+                }
+                RawComponent::Text(t) => op.insert(&t),
+            };
+        }
+        Ok(op)
+    }
+}
+
+/// A cursor or selected range, in characters. `anchor == head` is a bare
+/// cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Selection {
+    pub anchor: usize,
+    pub head: usize,
+}
+
+impl Selection {
+    /// The same selection in the text after `op`.
+    pub fn transform(self, op: &TextOp) -> Selection {
+        Selection {
+            anchor: op.transform_index(self.anchor),
+            head: op.transform_index(self.head),
+        }
+    }
+}
+
+/// Someone in the session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Peer {
+    pub client_id: u32,
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection: Option<Selection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// An edit of the text as of `revision`.
+    Op { revision: u64, op: TextOp },
+    /// Where the sender's cursor is in the text as of `revision`; `None`
+    /// when it has none (e.g. the editor lost focus).
+    Cursor {
+        revision: u64,
+        selection: Option<Selection>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// First message on a socket: the text as of `revision`, who the client
+    /// is, and who else is there.
+    Hello {
+        client_id: u32,
+        revision: u64,
+        body: String,
+        peers: Vec<Peer>,
+    },
+    /// The client's op was applied as `revision`.
+    Ack {
+        revision: u64,
+    },
+    /// Someone else's op, applied as `revision`. No `client_id` for a
+    /// change from outside the session.
+    Op {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_id: Option<u32>,
+        revision: u64,
+        op: TextOp,
+    },
+    /// A peer's cursor, as of `revision`.
+    Cursor {
+        client_id: u32,
+        revision: u64,
+        selection: Option<Selection>,
+    },
+    Join {
+        peer: Peer,
+    },
+    Leave {
+        client_id: u32,
+    },
+    /// The last message before the server closes the socket, or a rejected
+    /// message when `fatal` is false.
+    Error {
+        code: ErrorCode,
+        message: String,
+        fatal: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(json: &str) -> TextOp {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn ops_round_trip_in_ot_js_format() {
+        let o = op(r#"[2, "héllo", -3, 1]"#);
+        assert_eq!((o.base_len(), o.target_len()), (6, 8));
+        assert_eq!(serde_json::to_string(&o).unwrap(), r#"[2,"héllo",-3,1]"#);
+        assert_eq!(o.apply("abcdef").as_deref(), Some("abhéllof"));
+        assert_eq!(o.apply("abc"), None);
+        assert!(serde_json::from_str::<TextOp>("[0]").is_err());
+        assert!(serde_json::from_str::<TextOp>(r#"[""]"#).is_err());
+        // Inserts are normalized to come before an adjacent delete.
+        assert_eq!(op(r#"[-2, "x"]"#), op(r#"["x", -2]"#));
+    }
+
+    #[test]
+    fn transform_converges() {
+        let text = "the quick brown fox";
+        let ops = [
+            r#"[4, "very ", 15]"#,
+            r#"[4, -6, 9]"#,
+            r#"[10, -5, "red", 4]"#,
+            r#"["> ", 19]"#,
+            r#"[19, "!"]"#,
+            r#"[-19, "gone"]"#,
+            r#"[4, "slow ", 15]"#,
+            r#"[19]"#,
+        ];
+        for a in ops {
+            for b in ops {
+                let (a, b) = (op(a), op(b));
+                let (a1, b1) = TextOp::transform(&a, &b).unwrap();
+                let left = b1.apply(&a.apply(text).unwrap()).unwrap();
+                let right = a1.apply(&b.apply(text).unwrap()).unwrap();
+                assert_eq!(left, right, "{a:?} vs {b:?}");
+            }
+        }
+        let (a1, _) = TextOp::transform(&op(r#"[4, "A", 15]"#), &op(r#"[4, "B", 15]"#)).unwrap();
+        assert_eq!(
+            a1.apply("the Bquick brown fox").unwrap(),
+            "the ABquick brown fox"
+        );
+        assert!(TextOp::transform(&op("[3]"), &op("[4]")).is_none());
+    }
+
+    #[test]
+    fn selections_follow_edits() {
+        let o = op(r#"[2, "xyz", -3, 5]"#);
+        assert_eq!(o.transform_index(0), 0);
+        assert_eq!(o.transform_index(2), 5);
+        assert_eq!(o.transform_index(4), 5);
+        assert_eq!(o.transform_index(6), 6);
+        assert_eq!(o.transform_index(10), 10);
+        let s = Selection { anchor: 1, head: 8 }.transform(&o);
+        assert_eq!(s, Selection { anchor: 1, head: 8 });
+    }
+
+    #[test]
+    fn messages_are_tagged() {
+        let m: ClientMessage =
+            serde_json::from_str(r#"{"type":"op","revision":3,"op":[1,"a"]}"#).unwrap();
+        assert_eq!(
+            m,
+            ClientMessage::Op {
+                revision: 3,
+                op: op(r#"[1, "a"]"#)
+            }
+        );
+        let out = ServerMessage::Op {
+            client_id: None,
+            revision: 4,
+            op: op(r#"[1, "a"]"#),
+        };
+        assert_eq!(
+            serde_json::to_string(&out).unwrap(),
+            r#"{"type":"op","revision":4,"op":[1,"a"]}"#
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::SnippetListItem;
+
+/// Longest collection name, in chars.
+pub const COLLECTION_NAME_MAX_LEN: usize = 64;
+pub const COLLECTION_DESCRIPTION_MAX_LEN: usize = 1024;
+
+/// Collection names are free text scoped to their owner — `Runbooks`,
+/// `Q3 incident notes` — so anything printable goes except `/`, which
+/// would make them awkward in a URL path.
+pub fn is_valid_collection_name(name: &str) -> bool {
+    let len = name.chars().count();
+    (1..=COLLECTION_NAME_MAX_LEN).contains(&len)
+        && name.trim() == name
+        && !name.chars().any(|c| c == '/' || c.is_control())
+}
+
+/// A named, ordered group of the caller's own snippets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Live snippets in it.
+    pub snippet_count: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// `GET /api/v1/collections`, by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListCollectionsResponse {
+    pub items: Vec<Collection>,
+}
+
+/// `GET /api/v1/collections/:name`: the collection and its live snippets
+/// in the owner's order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionDetail {
+    #[serde(flatten)]
+    pub collection: Collection,
+    pub items: Vec<SnippetListItem>,
+}
+
+/// Body of `POST /api/v1/collections`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Body of `PATCH /api/v1/collections/:name`. `description: null` clears it;
+/// at least one field is required.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCollectionRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_nullable_string"
+    )]
+    pub description: Option<Option<String>>,
+}
+
+/// Present-but-null must stay `Some(None)`; `default` covers absent.
+fn deserialize_nullable_string<'de, D>(d: D) -> Result<Option<Option<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(d).map(Some)
+}
+
+/// Body of `PATCH /api/v1/collections/:name/items`. Added slugs go to the
+/// end in the order given; slugs already in (or absent from) the collection
+/// are no-ops. At least one field is required.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollectionItemsRequest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+}
+
+/// Body of `PUT /api/v1/collections/:name/items`: the complete contents,
+/// in order. Snippets left out are removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetCollectionItemsRequest {
+    pub slugs: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collection_names() {
+        assert!(is_valid_collection_name("Runbooks"));
+        assert!(is_valid_collection_name("Q3 incident notes"));
+        assert!(!is_valid_collection_name(""));
+        assert!(!is_valid_collection_name(" padded"));
+        assert!(!is_valid_collection_name("a/b"));
+        assert!(!is_valid_collection_name("a\tb"));
+        assert!(!is_valid_collection_name(&"x".repeat(COLLECTION_NAME_MAX_LEN + 1)));
+    }
+
+    #[test]
+    fn update_description_tells_null_from_absent() {
+        let clear: UpdateCollectionRequest =
+            serde_json::from_str(r#"{"description":null}"#).unwrap();
+        assert_eq!(clear.description, Some(None));
+        assert_eq!(serde_json::to_string(&clear).unwrap(), r#"{"description":null}"#);
+        let keep: UpdateCollectionRequest = serde_json::from_str(r#"{"name":"x"}"#).unwrap();
+        assert_eq!(keep.description, None);
+    }
+}
@@ -13,6 +13,12 @@ pub enum ErrorCode {
     SetupRequired,
     SetupComplete,
     SnippetTooLarge,
+    /// The snippet is password-protected and the caller hasn't unlocked it.
+    PasswordRequired,
+    /// An `Idempotency-Key` was sent again with a different request.
+    IdempotencyKeyReused,
+    /// An `If-Match` named a version of the snippet that is no longer current.
+    PreconditionFailed,
     RateLimited,
     Internal,
 }
@@ -28,6 +34,9 @@ impl ErrorCode {
             ErrorCode::SetupRequired => 403,
             ErrorCode::SetupComplete => 409,
             ErrorCode::SnippetTooLarge => 413,
+            ErrorCode::PasswordRequired => 401,
+            ErrorCode::IdempotencyKeyReused => 409,
+            ErrorCode::PreconditionFailed => 412,
             ErrorCode::RateLimited => 429,
             ErrorCode::Internal => 500,
         }
@@ -3,22 +3,52 @@
 //! The contract here is the over-the-wire JSON shape — keeping it in one place
 //! prevents the CLI's bindings drifting from the server's request/response types.
 
+pub mod collab;
+pub mod collection;
 pub mod error;
+pub mod org;
 pub mod scope;
 pub mod slug;
 pub mod snippet;
 pub mod user;
+mod wordlist;
 
+pub use collab::{ClientMessage, Peer, Selection, ServerMessage, TextOp};
+pub use collection::{
+    is_valid_collection_name, Collection, CollectionDetail, CollectionItemsRequest,
+    CreateCollectionRequest, ListCollectionsResponse, SetCollectionItemsRequest,
+    UpdateCollectionRequest, COLLECTION_DESCRIPTION_MAX_LEN, COLLECTION_NAME_MAX_LEN,
+};
 pub use error::{ErrorBody, ErrorCode, ErrorEnvelope};
+pub use org::{
+    CreateOrgRequest, ListOrgMembersResponse, ListOrgsResponse, Org, OrgMember, OrgRole,
+    SetOrgMemberRequest,
+};
 pub use scope::Scope;
-pub use slug::{is_valid_slug, SLUG_ALPHABET, SLUG_LEN};
+pub use slug::{
+    is_claimable_slug, is_custom_slug, is_valid_slug, SlugStrategy, CUSTOM_SLUG_MAX_LEN,
+    CUSTOM_SLUG_MIN_LEN, LONG_SLUG_LEN, RANDOM_SLUG_MAX_LEN, RESERVED_SLUG_PREFIXES, SLUG_ALPHABET,
+    SLUG_LEN, WORD_SLUG_DEFAULT_WORDS, WORD_SLUG_MAX_WORDS, WORD_SLUG_MIN_WORDS,
+};
 pub use snippet::{
-    CreateSnippetRequest, ListSnippetsResponse, PatchSnippetRequest, SettingsRequest, Snippet,
-    SnippetListItem, SnippetType, Visibility, BURN_AFTER_READ_WINDOW_SECONDS,
-    LIFETIME_SECONDS_MAX, LIFETIME_SECONDS_MIN,
+    is_valid_burn_window, is_valid_ciphertext, is_valid_file_name, normalize_tag, AppendResponse,
+    CreateAliasRequest, CreateShareLinkRequest, CreateSnippetRequest, ForkRequest,
+    ListAliasesResponse, ListRevisionsResponse, ListShareLinksResponse, ListSharesResponse,
+    ListSnippetsResponse, ListTagsResponse, ListTrashResponse, PatchSnippetRequest, RenameRequest,
+    SearchSnippetsResponse, SettingsRequest, ShareLink, ShareRequest, Snippet, SnippetAlias,
+    SnippetContent, SnippetEvent, SnippetFile, SnippetFileInput, SnippetListItem, SnippetRevision,
+    SnippetRevisionListItem, SnippetSearchHit, SnippetShare, SnippetSort, SnippetType, SortOrder,
+    TagCount, TagsRequest, TagsResponse, TrashedSnippet, UnlockRequest, Visibility,
+    BURN_AFTER_READ_WINDOW_SECONDS, BURN_WINDOW_ONE_SHOT, BURN_WINDOW_SECONDS_MAX,
+    BURN_WINDOW_SECONDS_MIN, CIPHERTEXT_PREFIX, LIFETIME_SECONDS_MAX, LIFETIME_SECONDS_MIN,
+    MAX_BUNDLE_FILES, MAX_FILE_NAME_LEN, MAX_TAGS, SEARCH_MATCH_END, SEARCH_MATCH_START,
+    SHARE_LINK_TTL_MAX, SNIPPET_PASSWORD_MAX_LEN, SNIPPET_PASSWORD_MIN_LEN, TAG_MAX_LEN,
 };
 pub use user::{Role, UserPublic, UserStatus};
 
+/// Default `SNIPPET_MAX_BYTES`, and its ceiling when bodies are kept in
+/// Postgres. Servers with an external body store may accept more, so clients
+/// leave enforcement to the server.
 pub const MAX_SNIPPET_BYTES: usize = 1_048_576;
 
 /// `pds_live_<8 char prefix>_<32 char secret>`
@@ -28,3 +58,16 @@ pub const API_KEY_TOKEN_PREAMBLE: &str = "pds_live_";
 
 /// The session cookie name. Always the same to keep the CLI / docs honest.
 pub const SESSION_COOKIE_NAME: &str = "pds_session";
+
+/// Name prefix of the per-snippet unlock cookies; the slug follows it.
+pub const UNLOCK_COOKIE_PREFIX: &str = "pds_unlock_";
+
+/// Query parameter carrying a signed share link token, e.g.
+/// `/c/<slug>?token=…`.
+pub const SHARE_LINK_PARAM: &str = "token";
+
+/// Request header that makes `POST /api/v1/snippets` and `POST /paste` safe
+/// to retry: a repeat with the same key returns the snippet the first attempt
+/// created. Up to [`IDEMPOTENCY_KEY_MAX_LEN`] visible ASCII characters.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+pub const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A member's role inside an organization, weakest first so roles compare
+/// with `>=`. `member`s publish and edit the org's snippets; `maintainer`s
+/// can also delete any of them and manage plain members; `owner`s manage
+/// everyone, including other owners. An org always keeps at least one owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Member,
+    Maintainer,
+    Owner,
+}
+
+impl OrgRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrgRole::Member => "member",
+            OrgRole::Maintainer => "maintainer",
+            OrgRole::Owner => "owner",
+        }
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "member" => Ok(Self::Member),
+            "maintainer" => Ok(Self::Maintainer),
+            "owner" => Ok(Self::Owner),
+            _ => Err(()),
+        }
+    }
+}
+
+/// An organization as one of its members sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Org {
+    pub name: String,
+    /// The caller's role in it.
+    pub role: OrgRole,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOrgsResponse {
+    pub items: Vec<Org>,
+}
+
+/// Body of `POST /api/v1/orgs`. Names follow the username rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateOrgRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrgMember {
+    pub username: String,
+    pub role: OrgRole,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// Owners first, then by username.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOrgMembersResponse {
+    pub items: Vec<OrgMember>,
+}
+
+/// Body of `PUT /api/v1/orgs/:name/members/:username` — adds the user or
+/// changes their role.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetOrgMemberRequest {
+    pub role: OrgRole,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_order_by_power() {
+        assert!(OrgRole::Owner > OrgRole::Maintainer);
+        assert!(OrgRole::Maintainer > OrgRole::Member);
+        for role in [OrgRole::Member, OrgRole::Maintainer, OrgRole::Owner] {
+            assert_eq!(role.as_str().parse::<OrgRole>(), Ok(role));
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
 use std::sync::OnceLock;
 
+use crate::wordlist::WORDS;
+
+/// Length of the default random slug, about 41.7 bits.
 pub const SLUG_LEN: usize = 7;
 pub const SLUG_ALPHABET: &[char] = &[
     'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
@@ -9,14 +15,164 @@ pub const SLUG_ALPHABET: &[char] = &[
     '5', '6', '7', '8', '9',
 ];
 
+/// Bounds on `random-N`.
+pub const RANDOM_SLUG_MAX_LEN: usize = 48;
+/// Length of a [`SlugStrategy::Long`] slug, about 143 bits.
+pub const LONG_SLUG_LEN: usize = 24;
+
+/// Bounds on `words-N`. Each word carries 8 bits, so the default five are
+/// about as hard to guess as a seven-character random slug.
+pub const WORD_SLUG_MIN_WORDS: usize = 3;
+pub const WORD_SLUG_MAX_WORDS: usize = 8;
+pub const WORD_SLUG_DEFAULT_WORDS: usize = 5;
+
+/// Bounds on an owner-chosen slug, in bytes (they are ASCII).
+pub const CUSTOM_SLUG_MIN_LEN: usize = 3;
+pub const CUSTOM_SLUG_MAX_LEN: usize = 64;
+
+/// Custom slugs starting with one of these are held back for the
+/// deployment itself, so nobody can pass a snippet off as an official page.
+pub const RESERVED_SLUG_PREFIXES: &[&str] = &["admin-", "api-", "pastedev-", "system-"];
+
+/// How the server makes up a slug when the owner didn't pick one. Written
+/// `random-N`, `long` or `words-N` (`random` and `words` alone take the
+/// default length); that is also the wire and `SLUG_STRATEGY` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlugStrategy {
+    /// `N` characters from [`SLUG_ALPHABET`], [`SLUG_LEN`]..=[`RANDOM_SLUG_MAX_LEN`].
+    Random(usize),
+    /// [`LONG_SLUG_LEN`] random characters: for snippets whose slug is the
+    /// only thing keeping them private.
+    Long,
+    /// `N` words from a 256-word list joined by hyphens (`otter-maple-kayak-
+    /// ember-tulip`), easy to read out loud.
+    Words(usize),
+}
+
+impl Default for SlugStrategy {
+    fn default() -> Self {
+        SlugStrategy::Random(SLUG_LEN)
+    }
+}
+
+impl SlugStrategy {
+    pub fn generate(self) -> String {
+        match self {
+            SlugStrategy::Random(len) => nanoid::nanoid!(len, SLUG_ALPHABET),
+            SlugStrategy::Long => nanoid::nanoid!(LONG_SLUG_LEN, SLUG_ALPHABET),
+            // One random byte per word: uniform, since there are 256 words.
+            SlugStrategy::Words(n) => nanoid::rngs::default(n)
+                .into_iter()
+                .map(|b| WORDS[usize::from(b)])
+                .collect::<Vec<_>>()
+                .join("-"),
+        }
+    }
+}
+
+impl fmt::Display for SlugStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlugStrategy::Random(len) => write!(f, "random-{len}"),
+            SlugStrategy::Long => f.write_str("long"),
+            SlugStrategy::Words(n) => write!(f, "words-{n}"),
+        }
+    }
+}
+
+impl FromStr for SlugStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad_count = |what: &str| format!("invalid {what} in slug strategy '{s}'");
+        match s.split_once('-') {
+            None if s == "random" => Ok(SlugStrategy::default()),
+            None if s == "long" => Ok(SlugStrategy::Long),
+            None if s == "words" => Ok(SlugStrategy::Words(WORD_SLUG_DEFAULT_WORDS)),
+            Some(("random", n)) => match n.parse() {
+                Ok(len) if (SLUG_LEN..=RANDOM_SLUG_MAX_LEN).contains(&len) => {
+                    Ok(SlugStrategy::Random(len))
+                }
+                _ => Err(format!(
+                    "{}; random slugs are {SLUG_LEN} to {RANDOM_SLUG_MAX_LEN} characters",
+                    bad_count("length")
+                )),
+            },
+            Some(("words", n)) => match n.parse() {
+                Ok(n) if (WORD_SLUG_MIN_WORDS..=WORD_SLUG_MAX_WORDS).contains(&n) => {
+                    Ok(SlugStrategy::Words(n))
+                }
+                _ => Err(format!(
+                    "{}; word slugs are {WORD_SLUG_MIN_WORDS} to {WORD_SLUG_MAX_WORDS} words",
+                    bad_count("word count")
+                )),
+            },
+            _ => Err(format!(
+                "unknown slug strategy '{s}' (allowed: random-N, long, words-N)"
+            )),
+        }
+    }
+}
+
+impl Serialize for SlugStrategy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SlugStrategy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A slug from the default strategy.
 pub fn generate() -> String {
-    nanoid::nanoid!(SLUG_LEN, SLUG_ALPHABET)
+    SlugStrategy::default().generate()
 }
 
-pub fn is_valid_slug(s: &str) -> bool {
+/// The shape of a random slug, of any length a [`SlugStrategy`] can ask for.
+pub fn is_random_slug(s: &str) -> bool {
     static RE: OnceLock<Regex> = OnceLock::new();
-    let re = RE.get_or_init(|| Regex::new(r"^[A-Za-z0-9]{7}$").unwrap());
-    re.is_match(s)
+    let re = RE.get_or_init(|| Regex::new(r"^[A-Za-z0-9]+$").unwrap());
+    (SLUG_LEN..=RANDOM_SLUG_MAX_LEN).contains(&s.len()) && re.is_match(s)
+}
+
+/// Whether `s` could have come out of [`SlugStrategy::Words`].
+pub fn is_word_slug(s: &str) -> bool {
+    let n = s.split('-').count();
+    (WORD_SLUG_MIN_WORDS..=WORD_SLUG_MAX_WORDS).contains(&n)
+        && s.split('-').all(|w| WORDS.contains(&w))
+}
+
+/// Any shape a [`SlugStrategy`] produces.
+pub fn is_generated_slug(s: &str) -> bool {
+    is_random_slug(s) || is_word_slug(s)
+}
+
+/// The shape of an owner-chosen slug: lowercase ASCII words joined by single
+/// hyphens, at least two of them (`oncall-runbook`). The hyphen keeps custom
+/// slugs out of the random namespace; word slugs share the shape, and
+/// [`is_claimable_slug`] keeps owners off those. Says nothing about
+/// [`RESERVED_SLUG_PREFIXES`] either.
+pub fn is_custom_slug(s: &str) -> bool {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"^[a-z0-9]+(?:-[a-z0-9]+)+$").unwrap());
+    (CUSTOM_SLUG_MIN_LEN..=CUSTOM_SLUG_MAX_LEN).contains(&s.len()) && re.is_match(s)
+}
+
+/// Whether an owner may pick `s` as a slug or alias: custom-shaped, not
+/// reserved, and not something the generator could hand to someone else.
+pub fn is_claimable_slug(s: &str) -> bool {
+    is_custom_slug(s)
+        && !RESERVED_SLUG_PREFIXES.iter().any(|p| s.starts_with(p))
+        && !is_word_slug(s)
+}
+
+/// Anything that can name a snippet in a route: generated or custom.
+pub fn is_valid_slug(s: &str) -> bool {
+    is_random_slug(s) || is_custom_slug(s)
 }
 
 #[cfg(test)]
@@ -38,6 +194,83 @@ mod tests {
         assert!(!is_valid_slug("123"));
         assert!(!is_valid_slug("abcdef-"));
         assert!(!is_valid_slug("../abcd"));
-        assert!(!is_valid_slug("abc12345")); // 8 chars
+        assert!(!is_valid_slug("abc123")); // 6 chars
+        assert!(!is_valid_slug(&"a".repeat(RANDOM_SLUG_MAX_LEN + 1)));
+    }
+
+    #[test]
+    fn custom_slugs_are_hyphenated_lowercase_words() {
+        assert!(is_valid_slug("oncall-runbook"));
+        assert!(is_valid_slug("a-b"));
+        assert!(is_valid_slug("release-2026-q4"));
+        assert!(!is_custom_slug("runbook")); // no hyphen: generator's space
+        assert!(!is_custom_slug("Oncall-Runbook"));
+        assert!(!is_custom_slug("-oncall"));
+        assert!(!is_custom_slug("oncall--runbook"));
+        assert!(!is_custom_slug("on_call-runbook"));
+        assert!(!is_custom_slug(&format!("a-{}", "b".repeat(CUSTOM_SLUG_MAX_LEN))));
+        assert!(!is_generated_slug("oncall-runbook"));
+    }
+
+    #[test]
+    fn reserved_prefixes_are_not_claimable() {
+        assert!(is_claimable_slug("oncall-runbook"));
+        assert!(!is_claimable_slug("admin-panel"));
+        assert!(!is_claimable_slug("pastedev-status"));
+        assert!(is_claimable_slug("sysadmin-notes"));
+        assert!(!is_claimable_slug("AbCdEf1"));
+    }
+
+    #[test]
+    fn every_strategy_produces_valid_unclaimable_slugs() {
+        for strategy in [
+            SlugStrategy::Random(RANDOM_SLUG_MAX_LEN),
+            SlugStrategy::Long,
+            SlugStrategy::Words(WORD_SLUG_MIN_WORDS),
+            SlugStrategy::Words(WORD_SLUG_MAX_WORDS),
+        ] {
+            for _ in 0..200 {
+                let s = strategy.generate();
+                assert!(is_valid_slug(&s), "{strategy}: {s}");
+                assert!(is_generated_slug(&s), "{strategy}: {s}");
+                assert!(!is_claimable_slug(&s), "{strategy}: {s}");
+            }
+        }
+        assert_eq!(SlugStrategy::Long.generate().len(), LONG_SLUG_LEN);
+        assert_eq!(SlugStrategy::Words(4).generate().split('-').count(), 4);
+    }
+
+    #[test]
+    fn strategy_round_trips_through_its_string_form() {
+        for (s, want) in [
+            ("random", SlugStrategy::Random(SLUG_LEN)),
+            ("random-12", SlugStrategy::Random(12)),
+            ("long", SlugStrategy::Long),
+            ("words", SlugStrategy::Words(WORD_SLUG_DEFAULT_WORDS)),
+            ("words-3", SlugStrategy::Words(3)),
+        ] {
+            let got: SlugStrategy = s.parse().unwrap();
+            assert_eq!(got, want);
+            assert_eq!(got.to_string().parse::<SlugStrategy>().unwrap(), want);
+        }
+        for bad in ["", "random-6", "random-49", "words-2", "words-9", "long-3", "uuid"] {
+            assert!(bad.parse::<SlugStrategy>().is_err(), "{bad}");
+        }
+        let json = serde_json::to_string(&SlugStrategy::Words(4)).unwrap();
+        assert_eq!(json, r#""words-4""#);
+        assert!(serde_json::from_str::<SlugStrategy>(r#""random-3""#).is_err());
+    }
+
+    #[test]
+    fn wordlist_is_256_distinct_slug_safe_words() {
+        let distinct: std::collections::HashSet<_> = WORDS.iter().collect();
+        assert_eq!(distinct.len(), WORDS.len());
+        for w in WORDS {
+            assert!((3..=6).contains(&w.len()), "{w}");
+            assert!(w.bytes().all(|b| b.is_ascii_lowercase()), "{w}");
+        }
+        // The longest possible word slug still passes is_valid_slug.
+        let longest = WORDS.iter().map(|w| w.len()).max().unwrap();
+        assert!(WORD_SLUG_MAX_WORDS * (longest + 1) - 1 <= CUSTOM_SLUG_MAX_LEN);
     }
 }
@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use crate::slug::SlugStrategy;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SnippetType {
@@ -36,12 +38,17 @@ impl FromStr for SnippetType {
 
 /// Snippet visibility. `public` is the default — anyone can fetch the slug.
 /// `private` requires the caller to be an authenticated, approved user.
+/// `password` requires everyone but the owner to unlock the snippet with the
+/// passphrase the owner set, account or not. `team` is for snippets owned by
+/// an organization: only its members can read them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum Visibility {
     #[default]
     Public,
     Private,
+    Password,
+    Team,
 }
 
 impl Visibility {
@@ -49,6 +56,8 @@ impl Visibility {
         match self {
             Visibility::Public => "public",
             Visibility::Private => "private",
+            Visibility::Password => "password",
+            Visibility::Team => "team",
         }
     }
 }
@@ -59,6 +68,8 @@ impl FromStr for Visibility {
         match s {
             "public" => Ok(Self::Public),
             "private" => Ok(Self::Private),
+            "password" => Ok(Self::Password),
+            "team" => Ok(Self::Team),
             _ => Err(()),
         }
     }
@@ -71,9 +82,37 @@ pub const LIFETIME_SECONDS_MIN: i32 = 60;
 pub const LIFETIME_SECONDS_MAX: i32 = 365 * 24 * 60 * 60;
 
 /// How long a `burn_after_read` snippet stays readable after the first non-owner
-/// view. Hard-coded — exposed as a constant so the frontend timer + server
-/// computation agree.
-pub const BURN_AFTER_READ_WINDOW_SECONDS: i64 = 15 * 60;
+/// view, unless it was given its own `burn_window_seconds`. Exposed as a
+/// constant so the frontend timer + server default agree.
+pub const BURN_AFTER_READ_WINDOW_SECONDS: i32 = 15 * 60;
+
+/// Bounds for a per-snippet `burn_window_seconds`, besides
+/// [`BURN_WINDOW_ONE_SHOT`].
+pub const BURN_WINDOW_SECONDS_MIN: i32 = 60;
+pub const BURN_WINDOW_SECONDS_MAX: i32 = 7 * 24 * 60 * 60;
+
+/// `burn_window_seconds` value for a one-shot secret: the first non-owner
+/// read is the only one, and the snippet is gone right after it.
+pub const BURN_WINDOW_ONE_SHOT: i32 = 0;
+
+/// Whether `seconds` is an acceptable `burn_window_seconds`.
+pub fn is_valid_burn_window(seconds: i32) -> bool {
+    seconds == BURN_WINDOW_ONE_SHOT
+        || (BURN_WINDOW_SECONDS_MIN..=BURN_WINDOW_SECONDS_MAX).contains(&seconds)
+}
+
+fn default_burn_window() -> i32 {
+    BURN_AFTER_READ_WINDOW_SECONDS
+}
+
+/// Length bounds (in chars) of a snippet passphrase. Same floor as account
+/// passwords; the ceiling only keeps Argon2 input reasonable.
+pub const SNIPPET_PASSWORD_MIN_LEN: usize = 8;
+pub const SNIPPET_PASSWORD_MAX_LEN: usize = 256;
+
+/// Longest a signed share link may stay valid; links are meant to be
+/// temporary, so this is well under the snippet lifetime ceiling.
+pub const SHARE_LINK_TTL_MAX: i32 = 30 * 24 * 60 * 60;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnippetOwner {
@@ -89,22 +128,63 @@ pub struct Snippet {
     pub name: Option<String>,
     pub body: String,
     pub size_bytes: i32,
+    /// Bytes the body takes up at rest, after compression. Equal to
+    /// `size_bytes` for bodies stored as-is.
+    #[serde(default)]
+    pub stored_bytes: i32,
     pub views: i32,
     pub owner: SnippetOwner,
+    /// Name of the organization that owns the snippet; `owner` is then the
+    /// member who published it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
     pub url: String,
     pub raw_url: String,
     #[serde(default)]
     pub visibility: Visibility,
+    /// With `private` visibility, only the owner and the users on the share
+    /// list can read it, instead of any signed-in user.
+    #[serde(default)]
+    pub strict_private: bool,
+    /// `body` is an [`is_valid_ciphertext`] envelope the server can't read;
+    /// the key only ever travels in the `#fragment` of the share URL. `url`
+    /// and `raw_url` use the `/e/` routes.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Embargo: until this time only the owner can read the snippet; to
+    /// everyone else the slug is a 404. `None` = live since creation.
+    #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<OffsetDateTime>,
     /// When true, the first non-owner view stamps `first_viewed_at` and
-    /// tightens `expires_at` down to `now() + 15min`. Stays a separate
-    /// flag (independent of `expires_at`) so the frontend can label the
-    /// snippet "burns after read" before the timer is armed.
+    /// tightens `expires_at` down to `now() + burn_window_seconds`. Stays a
+    /// separate flag (independent of `expires_at`) so the frontend can label
+    /// the snippet "burns after read" before the timer is armed.
     #[serde(default)]
     pub burn_after_read: bool,
+    /// The fuse length once armed; [`BURN_WINDOW_ONE_SHOT`] means the first
+    /// non-owner read is also the last.
+    #[serde(default = "default_burn_window")]
+    pub burn_window_seconds: i32,
     /// When the first non-owner GET landed. `None` until then (only
     /// meaningful when `burn_after_read = true`).
     #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
     pub first_viewed_at: Option<OffsetDateTime>,
+    /// Only this many unique non-owner viewers get to read the snippet;
+    /// once `views` reaches it, everyone else gets a 404.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_views: Option<i32>,
+    /// Slug of the snippet this one was forked from, while that still
+    /// exists. Readers other than the owner only see it when the source is
+    /// public.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<String>,
+    /// [`normalize_tag`]ged labels, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Names of the owner's collections this snippet is in, sorted. Only
+    /// the owner sees them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
     /// Absolute timestamp at which non-owner reads stop resolving. `None`
     /// means the snippet has no expiry (and burn-after-read, if enabled,
     /// hasn't been triggered yet). The frontend ticks a countdown against
@@ -115,6 +195,85 @@ pub struct Snippet {
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
+    /// Every file of a multi-file bundle, in upload order. Empty (and omitted
+    /// on the wire) for single-body snippets. For a bundle, `type` / `body`
+    /// above mirror the first file so single-file readers still get something
+    /// sensible.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<SnippetFile>,
+}
+
+/// One file inside a multi-file snippet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetFile {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: SnippetType,
+    pub body: String,
+    pub size_bytes: i32,
+    #[serde(default)]
+    pub stored_bytes: i32,
+    pub raw_url: String,
+}
+
+/// Upper bound on files per bundle. The byte budget (`snippet_max_bytes`)
+/// applies to the sum of all bodies on top of this.
+pub const MAX_BUNDLE_FILES: usize = 32;
+
+/// Max length of a file name inside a bundle, in bytes.
+pub const MAX_FILE_NAME_LEN: usize = 255;
+
+/// Version tag of the encrypted-body envelope:
+///
+///   `v1.` || base64url-nopad(nonce (24) || XChaCha20-Poly1305 ciphertext || tag (16))
+///
+/// The server never sees the key and only checks the shape, so encrypted
+/// bodies stay text like every other body.
+pub const CIPHERTEXT_PREFIX: &str = "v1.";
+
+/// Smallest possible envelope payload: nonce and tag around an empty
+/// plaintext, base64url-encoded without padding.
+const CIPHERTEXT_MIN_B64_LEN: usize = (24 + 16) * 4 / 3 + 1;
+
+/// Whether `body` is shaped like an encrypted-body envelope (see
+/// [`CIPHERTEXT_PREFIX`]). Says nothing about whether it decrypts.
+pub fn is_valid_ciphertext(body: &str) -> bool {
+    let Some(payload) = body.strip_prefix(CIPHERTEXT_PREFIX) else {
+        return false;
+    };
+    payload.len() >= CIPHERTEXT_MIN_B64_LEN
+        && payload
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// File names travel as the last path segment of `/c/:slug/raw/:filename`, so
+/// they must be a single, non-special segment: no separators, no control
+/// characters, and not `.` / `..`.
+pub fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_FILE_NAME_LEN
+        && name != "."
+        && name != ".."
+        && !name.chars().any(|c| c == '/' || c == '\\' || c.is_control())
+}
+
+/// Tags per snippet, and the longest tag in chars.
+pub const MAX_TAGS: usize = 16;
+pub const TAG_MAX_LEN: usize = 32;
+
+/// The canonical form of a tag, or `None` if `raw` can't be one. Tags are
+/// free-form words — letters and digits in any script plus `-_.+#:/` —
+/// compared case-insensitively, so they are stored lowercased. No spaces or
+/// commas, which lets a list filter take several as `tag=a,b`.
+pub fn normalize_tag(raw: &str) -> Option<String> {
+    let tag = raw.trim().to_lowercase();
+    let ok = !tag.is_empty()
+        && tag.chars().count() <= TAG_MAX_LEN
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '+' | '#' | ':' | '/'));
+    ok.then_some(tag)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,10 +284,24 @@ pub struct SnippetListItem {
     pub name: Option<String>,
     pub size_bytes: i32,
     pub views: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
     #[serde(default)]
     pub visibility: Visibility,
     #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
     pub burn_after_read: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_views: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
+    #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<OffsetDateTime>,
     #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<OffsetDateTime>,
     #[serde(with = "time::serde::rfc3339")]
@@ -137,23 +310,118 @@ pub struct SnippetListItem {
     pub updated_at: OffsetDateTime,
 }
 
+/// Body of `POST /api/v1/snippets`. The content is either a single typed
+/// `body` or a `files` bundle (see [`SnippetContent`]); the sharing-policy
+/// fields apply to the snippet as a whole either way.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateSnippetRequest {
-    #[serde(rename = "type")]
-    pub kind: SnippetType,
     pub name: Option<String>,
-    pub body: String,
+    #[serde(flatten)]
+    pub content: SnippetContent,
+    /// Owner-chosen slug ([`crate::is_claimable_slug`]) instead of a
+    /// generated one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    /// How to generate the slug when `slug` is absent; the server's default
+    /// for the visibility otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug_strategy: Option<SlugStrategy>,
+    /// Publish on behalf of this organization; the caller must be a member.
+    /// An org-scoped API key implies its org.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
     #[serde(default)]
     pub visibility: Option<Visibility>,
+    /// Keep the snippet embargoed until this time (at most
+    /// [`LIFETIME_SECONDS_MAX`] ahead).
+    #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<OffsetDateTime>,
     /// Optional TTL applied at creation — server stores
-    /// `expires_at = now() + lifetime_seconds`. `None` (or omitted) = no
-    /// fixed expiry.
+    /// `expires_at = now() + lifetime_seconds`, counting from `publish_at`
+    /// instead when that is later. `None` (or omitted) = no fixed expiry.
     #[serde(default)]
     pub lifetime_seconds: Option<i32>,
-    /// Burn 15 minutes after the first non-owner view. Independent of
-    /// `lifetime_seconds` — both can be set; whichever fires first wins.
+    /// Burn 15 minutes (or `burn_window_seconds`) after the first non-owner
+    /// view. Independent of `lifetime_seconds` — both can be set; whichever
+    /// fires first wins.
     #[serde(default)]
     pub burn_after_read: Option<bool>,
+    /// Fuse length for `burn_after_read`: [`BURN_WINDOW_ONE_SHOT`] or
+    /// [`BURN_WINDOW_SECONDS_MIN`]..=[`BURN_WINDOW_SECONDS_MAX`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_window_seconds: Option<i32>,
+    /// Readable by this many unique non-owner viewers, then gone. Also
+    /// independent of the other two.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_views: Option<i32>,
+    /// Passphrase for `visibility: password`. Required with it and rejected
+    /// with any other visibility. Only its Argon2 hash is kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// The body was encrypted client-side and must be an
+    /// [`is_valid_ciphertext`] envelope. Single-body snippets only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<bool>,
+    /// Labels to start with, normalized by [`normalize_tag`]; at most
+    /// [`MAX_TAGS`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// What a new snippet is made of.
+///
+/// On the wire the two shapes are told apart by their keys rather than a tag,
+/// so existing `{"type": ..., "body": ...}` clients keep working unchanged:
+///
+/// ```json
+/// {"type": "code", "body": "fn main() {}"}
+/// {"files": [{"name": "Cargo.toml", "type": "code", "body": "..."}, ...]}
+/// ```
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum SnippetContent {
+    Single {
+        #[serde(rename = "type")]
+        kind: SnippetType,
+        body: String,
+    },
+    Bundle { files: Vec<SnippetFileInput> },
+}
+
+// Hand-rolled instead of `#[serde(untagged)]` so a malformed request gets a
+// specific message instead of "data did not match any variant".
+impl<'de> Deserialize<'de> for SnippetContent {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            #[serde(rename = "type")]
+            kind: Option<SnippetType>,
+            body: Option<String>,
+            files: Option<Vec<SnippetFileInput>>,
+        }
+        let raw = Raw::deserialize(d)?;
+        match (raw.kind, raw.body, raw.files) {
+            (Some(kind), Some(body), None) => Ok(Self::Single { kind, body }),
+            (None, None, Some(files)) => Ok(Self::Bundle { files }),
+            (_, _, Some(_)) => Err(serde::de::Error::custom(
+                "`files` cannot be combined with `type` or `body`",
+            )),
+            (None, _, None) => Err(serde::de::Error::missing_field("type")),
+            (Some(_), None, None) => Err(serde::de::Error::missing_field("body")),
+        }
+    }
+}
+
+/// One file of a bundle as submitted on create.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetFileInput {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: SnippetType,
+    pub body: String,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -183,11 +451,254 @@ pub struct SettingsRequest {
         deserialize_with = "deserialize_optional_lifetime"
     )]
     pub lifetime_seconds: Option<Option<i32>>,
+    /// `null` lifts the embargo; a time sets or moves it. A new
+    /// `lifetime_seconds` counts from the embargo when it ends later than now.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_nullable_timestamp",
+        deserialize_with = "deserialize_nullable_timestamp"
+    )]
+    pub publish_at: Option<Option<OffsetDateTime>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub burn_after_read: Option<bool>,
+    /// New fuse length. Applies the next time the fuse is armed; one that
+    /// is already burning keeps its expiry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burn_window_seconds: Option<i32>,
+    /// `null` lifts the cap. Lowering it to at most the current `views`
+    /// closes the snippet to new viewers straight away.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_max_views"
+    )]
+    pub max_views: Option<Option<i32>>,
+    /// New passphrase. Required when switching to `visibility: password`,
+    /// optional (a rotation) when the snippet already is; replacing it logs
+    /// out every reader who unlocked with the old one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
 }
 
-fn deserialize_optional_lifetime<'de, D>(d: D) -> Result<Option<Option<i32>>, D::Error>
+/// Body of `POST /api/v1/snippets/:slug/unlock`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockRequest {
+    pub password: String,
+}
+
+/// Body of `POST /api/v1/snippets/:slug/fork`. The fork copies the source's
+/// name, type and body (every file, for a bundle); these fields only set up
+/// the copy, and `{}` is a valid body. The sharing policy starts fresh, but
+/// without a `visibility` no wider than the source's: public stays public,
+/// private stays private (strict if the source was), a password-protected
+/// source gives a strict private fork, and a team snippet's fork stays in its
+/// org as `team`, or needs a `visibility` to go anywhere else.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ForkRequest {
+    /// Rename the copy instead of keeping the source's name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Owner-chosen slug for the copy, as on create.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// Required with `password` visibility, as on create.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Body of `POST /api/v1/snippets/:slug/rename`. The new slug must be
+/// [`crate::is_claimable_slug`], or one of the snippet's own aliases; the old
+/// one becomes an alias.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameRequest {
+    pub slug: String,
+}
+
+/// Body of `POST /api/v1/snippets/:slug/aliases`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAliasRequest {
+    pub slug: String,
+}
+
+/// Another slug that leads to the same snippet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetAlias {
+    pub slug: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// `GET /api/v1/snippets/:slug/aliases`, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListAliasesResponse {
+    pub items: Vec<SnippetAlias>,
+}
+
+/// Body of `PATCH /api/v1/snippets/:slug/shares`. Usernames already on (or
+/// absent from) the list are no-ops; at least one field is required.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShareRequest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+    /// Turn strict private mode on or off (see [`Snippet::strict_private`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict_private: Option<bool>,
+}
+
+/// One user a snippet is shared with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetShare {
+    pub username: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// `GET` / `PATCH /api/v1/snippets/:slug/shares`, usernames in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSharesResponse {
+    pub strict_private: bool,
+    pub shares: Vec<SnippetShare>,
+}
+
+/// Body of `PATCH /api/v1/snippets/:slug/tags`. Removals apply first, so a
+/// tag in both ends up present; at least one field is required.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TagsRequest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub add: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+}
+
+/// `PATCH /api/v1/snippets/:slug/tags`: the snippet's tags afterwards, sorted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagsResponse {
+    pub tags: Vec<String>,
+}
+
+/// `POST /api/v1/snippets/:slug/append`: the body's size once the text was
+/// added to its end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppendResponse {
+    pub slug: String,
+    pub appended_bytes: i32,
+    pub size_bytes: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// One event on `GET /api/v1/snippets/:slug/events`, sent as the `data` of
+/// an SSE event named after [`SnippetEvent::name`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SnippetEvent {
+    /// `text` was added to the end of the body, starting at byte `offset`.
+    /// `text` is left out when it was too large to pass along; fetch the
+    /// body instead.
+    Append {
+        offset: i32,
+        size_bytes: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+    /// The body was replaced. Also sent when the stream may have missed
+    /// events, so a client that keeps a copy should fetch it again.
+    Edit { size_bytes: i32 },
+    /// The name, slug or sharing settings changed.
+    Settings,
+    /// A burn-after-read snippet was read: it is gone at `expires_at`.
+    BurnArmed {
+        #[serde(with = "time::serde::rfc3339")]
+        expires_at: OffsetDateTime,
+    },
+    /// The burn window ran out. Last event of the stream.
+    Burned,
+    /// The snippet reached its expiry. Last event of the stream, except for
+    /// the owner.
+    Expired,
+    /// The snippet was deleted. Last event of the stream.
+    Deleted,
+}
+
+impl SnippetEvent {
+    /// The SSE event name; the same as the `type` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Append { .. } => "append",
+            Self::Edit { .. } => "edit",
+            Self::Settings => "settings",
+            Self::BurnArmed { .. } => "burn_armed",
+            Self::Burned => "burned",
+            Self::Expired => "expired",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// A tag in use on the caller's snippets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    /// Live snippets carrying it.
+    pub count: i64,
+}
+
+/// `GET /api/v1/tags`, most used first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListTagsResponse {
+    pub items: Vec<TagCount>,
+}
+
+/// Body of `POST /api/v1/snippets/:slug/links`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateShareLinkRequest {
+    /// `LIFETIME_SECONDS_MIN..=SHARE_LINK_TTL_MAX`.
+    pub ttl_seconds: i32,
+    /// Let this many viewers in through the link. `None` = until expiry. A
+    /// viewer who has been in may keep coming back until then: opening the
+    /// snippet in the web viewer, raw copies and rendered page included, is
+    /// one use however often they do it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<i32>,
+}
+
+/// A signed, time-limited link that lets anyone holding it read one snippet
+/// regardless of its visibility.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub id: Uuid,
+    /// The link itself, with the token in its query string. Only returned
+    /// when the link is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    pub max_uses: Option<i32>,
+    /// Viewers let in so far.
+    pub uses: i32,
+    #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// `GET /api/v1/snippets/:slug/links`, newest first, revoked and expired
+/// ones included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListShareLinksResponse {
+    pub items: Vec<ShareLink>,
+}
+
+/// A present-but-nullable integer field: `null` is `Some(None)`, a number
+/// `Some(Some(n))`; an absent field never gets here and stays `None`.
+fn deserialize_nullable_i32<'de, D>(d: D, field: &str) -> Result<Option<Option<i32>>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -197,14 +708,372 @@ where
     }
     let n = v
         .as_i64()
-        .ok_or_else(|| serde::de::Error::custom("lifetime_seconds must be an integer or null"))?;
+        .ok_or_else(|| serde::de::Error::custom(format!("{field} must be an integer or null")))?;
     let n = i32::try_from(n)
-        .map_err(|_| serde::de::Error::custom("lifetime_seconds out of range"))?;
+        .map_err(|_| serde::de::Error::custom(format!("{field} out of range")))?;
     Ok(Some(Some(n)))
 }
 
+fn deserialize_nullable_timestamp<'de, D>(
+    d: D,
+) -> Result<Option<Option<OffsetDateTime>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    time::serde::rfc3339::option::deserialize(d).map(Some)
+}
+
+fn serialize_nullable_timestamp<S>(
+    v: &Option<Option<OffsetDateTime>>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    time::serde::rfc3339::option::serialize(&v.flatten(), s)
+}
+
+fn deserialize_optional_lifetime<'de, D>(d: D) -> Result<Option<Option<i32>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_nullable_i32(d, "lifetime_seconds")
+}
+
+fn deserialize_optional_max_views<'de, D>(d: D) -> Result<Option<Option<i32>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_nullable_i32(d, "max_views")
+}
+
+/// Sort key for `GET /api/v1/snippets`. Ties are always broken by snippet id,
+/// so a page boundary never lands between two rows with the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetSort {
+    #[default]
+    Created,
+    Updated,
+    Views,
+    Size,
+    /// Snippets without an expiry sort as if they expired at the end of time.
+    ExpiresAt,
+}
+
+impl SnippetSort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SnippetSort::Created => "created",
+            SnippetSort::Updated => "updated",
+            SnippetSort::Views => "views",
+            SnippetSort::Size => "size",
+            SnippetSort::ExpiresAt => "expires_at",
+        }
+    }
+
+    /// Direction used when the caller doesn't pick one: newest / biggest /
+    /// most-viewed first, except `expires_at`, where "soonest first" is the
+    /// useful reading.
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            SnippetSort::ExpiresAt => SortOrder::Asc,
+            _ => SortOrder::Desc,
+        }
+    }
+}
+
+impl FromStr for SnippetSort {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created" => Ok(Self::Created),
+            "updated" => Ok(Self::Updated),
+            "views" => Ok(Self::Views),
+            "size" => Ok(Self::Size),
+            "expires_at" => Ok(Self::ExpiresAt),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One page of `GET /api/v1/snippets`. `sort` / `order` echo what the server
+/// actually applied (after defaults), and `next_cursor` is only valid for
+/// that same combination.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListSnippetsResponse {
     pub items: Vec<SnippetListItem>,
     pub next_cursor: Option<String>,
+    #[serde(default)]
+    pub sort: SnippetSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+/// Markers wrapped around each match inside [`SnippetSearchHit::highlight`].
+/// Deliberately not HTML, so a fragment can't smuggle markup into whatever
+/// renders it; clients restyle them as they see fit.
+pub const SEARCH_MATCH_START: &str = "«";
+pub const SEARCH_MATCH_END: &str = "»";
+
+/// One result of `GET /api/v1/snippets/search`: the usual list item plus its
+/// relevance and a short excerpt of the body around the matches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetSearchHit {
+    #[serde(flatten)]
+    pub snippet: SnippetListItem,
+    /// Higher is better. Only meaningful relative to other hits of the same
+    /// query.
+    pub rank: f32,
+    /// Up to a couple of body fragments with matches wrapped in
+    /// [`SEARCH_MATCH_START`] / [`SEARCH_MATCH_END`]. `None` when only the
+    /// name matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>,
+}
+
+/// Best match first. Search is not paginated: it's for finding something,
+/// not enumerating; narrow the query instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSnippetsResponse {
+    pub items: Vec<SnippetSearchHit>,
+}
+
+/// One soft-deleted snippet in `GET /api/v1/snippets/trash`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedSnippet {
+    #[serde(flatten)]
+    pub snippet: SnippetListItem,
+    #[serde(with = "time::serde::rfc3339")]
+    pub deleted_at: OffsetDateTime,
+    /// When the retention sweep will remove it for good. `None` when the
+    /// server keeps trash indefinitely.
+    #[serde(with = "time::serde::rfc3339::option", default, skip_serializing_if = "Option::is_none")]
+    pub purge_at: Option<OffsetDateTime>,
+}
+
+/// Most recently deleted first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListTrashResponse {
+    pub items: Vec<TrashedSnippet>,
+    pub next_cursor: Option<String>,
+}
+
+/// One immutable snapshot from a snippet's edit history. Revision 1 is the
+/// snippet as first published; every `PATCH` that changes `body` or `name`
+/// appends the next number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetRevision {
+    pub revision: i32,
+    pub name: Option<String>,
+    pub body: String,
+    pub size_bytes: i32,
+    /// Who made the write. `None` once the author's account is gone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<SnippetOwner>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// [`SnippetRevision`] without the body — the shape returned by the history
+/// listing so a long-lived snippet doesn't ship every snapshot at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnippetRevisionListItem {
+    pub revision: i32,
+    pub name: Option<String>,
+    pub size_bytes: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<SnippetOwner>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// Newest revision first. `next_cursor` is opaque; pass it back as `?cursor=`
+/// to fetch the next (older) page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListRevisionsResponse {
+    pub items: Vec<SnippetRevisionListItem>,
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_request_single_shape_unchanged() {
+        let req: CreateSnippetRequest =
+            serde_json::from_str(r##"{"type":"markdown","name":"a.md","body":"# hi"}"##).unwrap();
+        assert_eq!(req.name.as_deref(), Some("a.md"));
+        match req.content {
+            SnippetContent::Single { kind, body } => {
+                assert_eq!(kind, SnippetType::Markdown);
+                assert_eq!(body, "# hi");
+            }
+            other => panic!("expected single, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn create_request_bundle_round_trips() {
+        let json = r#"{"name":"repro","files":[
+            {"name":"Cargo.toml","type":"code","body":"[package]"},
+            {"name":"main.rs","type":"code","body":"fn main() {}"}
+        ],"burn_after_read":true}"#;
+        let req: CreateSnippetRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.burn_after_read, Some(true));
+        let SnippetContent::Bundle { files } = &req.content else {
+            panic!("expected bundle");
+        };
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].name, "main.rs");
+
+        // Serializes back to the same key-discriminated shape.
+        let v = serde_json::to_value(&req).unwrap();
+        assert!(v.get("files").is_some());
+        assert!(v.get("body").is_none());
+    }
+
+    #[test]
+    fn create_request_rejects_mixed_or_partial_content() {
+        let mixed = r#"{"type":"code","body":"x","files":[]}"#;
+        assert!(serde_json::from_str::<CreateSnippetRequest>(mixed).is_err());
+        let no_body = r#"{"type":"code"}"#;
+        let err = serde_json::from_str::<CreateSnippetRequest>(no_body).unwrap_err();
+        assert!(err.to_string().contains("body"), "{err}");
+    }
+
+    #[test]
+    fn fork_request_accepts_an_empty_body() {
+        let empty: ForkRequest = serde_json::from_str("{}").unwrap();
+        assert!(empty.name.is_none() && empty.slug.is_none() && empty.visibility.is_none());
+        assert_eq!(serde_json::to_string(&ForkRequest::default()).unwrap(), "{}");
+    }
+
+    #[test]
+    fn settings_max_views_tells_null_from_absent() {
+        let lift: SettingsRequest = serde_json::from_str(r#"{"max_views":null}"#).unwrap();
+        assert_eq!(lift.max_views, Some(None));
+        let set: SettingsRequest = serde_json::from_str(r#"{"max_views":3}"#).unwrap();
+        assert_eq!(set.max_views, Some(Some(3)));
+        let keep: SettingsRequest = serde_json::from_str("{}").unwrap();
+        assert_eq!(keep.max_views, None);
+        let err = serde_json::from_str::<SettingsRequest>(r#"{"max_views":"3"}"#).unwrap_err();
+        assert!(err.to_string().contains("max_views"), "{err}");
+    }
+
+    #[test]
+    fn settings_publish_at_round_trips_null_and_time() {
+        let lift: SettingsRequest = serde_json::from_str(r#"{"publish_at":null}"#).unwrap();
+        assert_eq!(lift.publish_at, Some(None));
+        assert_eq!(serde_json::to_string(&lift).unwrap(), r#"{"publish_at":null}"#);
+
+        let json = r#"{"publish_at":"2026-11-01T09:00:00Z"}"#;
+        let set: SettingsRequest = serde_json::from_str(json).unwrap();
+        let at = set.publish_at.flatten().unwrap();
+        assert_eq!(at.unix_timestamp(), 1_793_523_600);
+        assert_eq!(serde_json::to_string(&set).unwrap(), json);
+
+        let keep: SettingsRequest = serde_json::from_str("{}").unwrap();
+        assert_eq!(keep.publish_at, None);
+        assert_eq!(serde_json::to_string(&keep).unwrap(), "{}");
+    }
+
+    #[test]
+    fn burn_window_bounds_allow_one_shot() {
+        assert!(is_valid_burn_window(BURN_WINDOW_ONE_SHOT));
+        assert!(is_valid_burn_window(BURN_AFTER_READ_WINDOW_SECONDS));
+        assert!(is_valid_burn_window(BURN_WINDOW_SECONDS_MAX));
+        assert!(!is_valid_burn_window(BURN_WINDOW_SECONDS_MIN - 1));
+        assert!(!is_valid_burn_window(BURN_WINDOW_SECONDS_MAX + 1));
+        assert!(!is_valid_burn_window(-1));
+    }
+
+    #[test]
+    fn ciphertext_envelope_shape() {
+        let payload = "A".repeat(54);
+        assert!(is_valid_ciphertext(&format!("v1.{payload}")));
+        assert!(is_valid_ciphertext(&format!("v1.{payload}-_x9")));
+        assert!(!is_valid_ciphertext(&payload));
+        assert!(!is_valid_ciphertext(&format!("v2.{payload}")));
+        assert!(!is_valid_ciphertext("v1.tooshort"));
+        assert!(!is_valid_ciphertext(&format!("v1.{payload}==")));
+        assert!(!is_valid_ciphertext(&format!("v1.{payload} plaintext")));
+    }
+
+    #[test]
+    fn file_names_are_single_segments() {
+        assert!(is_valid_file_name("Cargo.toml"));
+        assert!(is_valid_file_name(".env.example"));
+        assert!(!is_valid_file_name(""));
+        assert!(!is_valid_file_name(".."));
+        assert!(!is_valid_file_name("src/main.rs"));
+        assert!(!is_valid_file_name("a\\b"));
+        assert!(!is_valid_file_name("a\nb"));
+        assert!(!is_valid_file_name(&"x".repeat(MAX_FILE_NAME_LEN + 1)));
+    }
+
+    #[test]
+    fn snippet_events_are_tagged_by_name() {
+        let events = [
+            SnippetEvent::Append { offset: 3, size_bytes: 6, text: Some("abc".into()) },
+            SnippetEvent::Edit { size_bytes: 1 },
+            SnippetEvent::Settings,
+            SnippetEvent::BurnArmed { expires_at: OffsetDateTime::UNIX_EPOCH },
+            SnippetEvent::Burned,
+            SnippetEvent::Expired,
+            SnippetEvent::Deleted,
+        ];
+        for event in events {
+            let json = serde_json::to_value(&event).unwrap();
+            assert_eq!(json["type"], event.name());
+            assert_eq!(serde_json::from_value::<SnippetEvent>(json).unwrap(), event);
+        }
+        let bare: SnippetEvent =
+            serde_json::from_str(r#"{"type":"append","offset":0,"size_bytes":9}"#).unwrap();
+        assert_eq!(bare, SnippetEvent::Append { offset: 0, size_bytes: 9, text: None });
+    }
+
+    #[test]
+    fn tags_normalize_to_lowercase_words() {
+        assert_eq!(normalize_tag("  Rust ").as_deref(), Some("rust"));
+        assert_eq!(normalize_tag("c++").as_deref(), Some("c++"));
+        assert_eq!(normalize_tag("lang:Go").as_deref(), Some("lang:go"));
+        assert_eq!(normalize_tag("Überblick").as_deref(), Some("überblick"));
+        assert_eq!(normalize_tag("ci/cd").as_deref(), Some("ci/cd"));
+        assert!(normalize_tag("").is_none());
+        assert!(normalize_tag("   ").is_none());
+        assert!(normalize_tag("two words").is_none());
+        assert!(normalize_tag("a,b").is_none());
+        assert!(normalize_tag(&"x".repeat(TAG_MAX_LEN + 1)).is_none());
+        assert!(normalize_tag(&"x".repeat(TAG_MAX_LEN)).is_some());
+    }
 }
@@ -0,0 +1,32 @@
+//! Words for [`crate::slug::SlugStrategy::Words`] slugs: short, concrete,
+//! lowercase, and unlikely to be misheard when read out loud. Exactly 256 of
+//! them, so each word carries 8 bits.
+
+pub(crate) const WORDS: [&str; 256] = [
+    "acorn", "amber", "anchor", "apple", "arrow", "aspen", "atlas", "autumn", "badge", "bagel",
+    "banjo", "barley", "basil", "beach", "beacon", "bell", "berry", "birch", "bison", "blade",
+    "blaze", "bloom", "boot", "border", "bottle", "bowl", "breeze", "brick", "bridge", "brook",
+    "bucket", "butter", "cabin", "cactus", "camel", "candle", "canoe", "canyon", "carrot", "castle",
+    "cedar", "cello", "chalk", "cherry", "chess", "cider", "circle", "citrus", "cliff", "cloud",
+    "clover", "cobalt", "cobra", "cocoa", "comet", "copper", "coral", "cotton", "cougar", "crane",
+    "crater", "creek", "crown", "cup", "dahlia", "daisy", "delta", "desert", "dingo", "donkey",
+    "dragon", "drum", "dune", "eagle", "easel", "ember", "engine", "fable", "falcon", "fennel",
+    "fern", "ferry", "finch", "flag", "flint", "flute", "forest", "fork", "fossil", "fox", "frost",
+    "galaxy", "garden", "garnet", "gecko", "ginger", "glider", "goose", "gopher", "grape", "gravel",
+    "grove", "harbor", "harp", "hat", "hatch", "hawk", "hazel", "heron", "honey", "horn", "hornet",
+    "husky", "igloo", "indigo", "iris", "island", "ivory", "jacket", "jaguar", "jar", "jelly",
+    "jungle", "kayak", "kettle", "kite", "kiwi", "koala", "ladder", "lagoon", "lamp", "lark",
+    "lemon", "lilac", "linen", "lizard", "llama", "lotus", "lute", "magnet", "mango", "maple",
+    "marble", "marsh", "meadow", "melon", "meteor", "mint", "mist", "mitten", "mole", "moon",
+    "moose", "mosaic", "muffin", "nectar", "newt", "nickel", "nutmeg", "oasis", "ocean", "olive",
+    "onion", "opal", "orbit", "orchid", "otter", "owl", "oyster", "paddle", "panda", "papaya",
+    "parrot", "pebble", "pecan", "pepper", "piano", "pickle", "pilot", "pine", "planet", "plum",
+    "pond", "pony", "poppy", "prism", "puffin", "puzzle", "quail", "quartz", "rabbit", "radar",
+    "radio", "radish", "raven", "reef", "ribbon", "ridge", "river", "robin", "robot", "rocket",
+    "rope", "ruby", "saddle", "salmon", "sand", "scarf", "seal", "shadow", "shell", "silver",
+    "sketch", "sled", "sloth", "snow", "sofa", "spark", "spoon", "spruce", "squid", "star", "stone",
+    "storm", "sugar", "summit", "swan", "tango", "teapot", "tent", "tiger", "timber", "toad",
+    "toast", "tomato", "topaz", "torch", "tulip", "tundra", "turtle", "twig", "valley", "velvet",
+    "violet", "walnut", "walrus", "wave", "willow", "window", "winter", "wombat", "wren", "yak",
+    "yeti", "zebra", "zinc",
+];
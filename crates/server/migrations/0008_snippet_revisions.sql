@@ -0,0 +1,32 @@
+-- Immutable edit history for snippets.
+--
+-- `PATCH /api/v1/snippets/:slug` used to overwrite `body` / `name` in place,
+-- leaving only an audit row with old/new sizes behind. Every successful write
+-- now appends a full snapshot here in the same transaction as the UPDATE, so
+-- readers can fetch any earlier state and diff two of them.
+--
+-- Revision 1 is the snippet as first published; each PATCH takes the next
+-- number. The UPDATE on `snippets` holds the row lock while the snapshot is
+-- taken, so concurrent PATCHes can't race to the same revision number.
+--
+-- author_id is whoever made the write. It's nullable (and SET NULL on user
+-- removal) so the history survives the author even when the snippet doesn't
+-- belong to them.
+
+CREATE TABLE snippet_revisions (
+    snippet_id  uuid          NOT NULL REFERENCES snippets(id) ON DELETE CASCADE,
+    revision    integer       NOT NULL CHECK (revision >= 1),
+    name        varchar(255),
+    body        text          NOT NULL CHECK (octet_length(body) <= 1048576),
+    size_bytes  integer       NOT NULL,
+    author_id   uuid          REFERENCES users(id) ON DELETE SET NULL,
+    created_at  timestamptz   NOT NULL DEFAULT now(),
+    PRIMARY KEY (snippet_id, revision)
+);
+
+-- Seed revision 1 for every existing snippet from its current state. Earlier
+-- edits were never recorded, so the best we can say is "this is what it
+-- looked like when history started".
+INSERT INTO snippet_revisions (snippet_id, revision, name, body, size_bytes, author_id, created_at)
+SELECT id, 1, name, body, size_bytes, owner_id, updated_at
+FROM snippets;
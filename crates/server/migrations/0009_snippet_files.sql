@@ -0,0 +1,20 @@
+-- Multi-file snippets ("bundles").
+--
+-- A bundle is still one row in `snippets` — one slug, one owner, one sharing
+-- policy — plus N rows here. The parent row's `type` / `body` mirror the
+-- first file so everything that only knows about single-body snippets (the
+-- SPA viewer, `/raw`, older CLIs, revision history) keeps showing something
+-- meaningful; `size_bytes` on the parent is the sum over all files.
+--
+-- Single-body snippets have no rows here.
+
+CREATE TABLE snippet_files (
+    snippet_id  uuid          NOT NULL REFERENCES snippets(id) ON DELETE CASCADE,
+    position    integer       NOT NULL CHECK (position >= 0),
+    name        varchar(255)  NOT NULL,
+    type        varchar(16)   NOT NULL CHECK (type IN ('code', 'markdown', 'html')),
+    body        text          NOT NULL CHECK (octet_length(body) <= 1048576),
+    size_bytes  integer       NOT NULL,
+    PRIMARY KEY (snippet_id, position)
+);
+CREATE UNIQUE INDEX snippet_files_name_uniq ON snippet_files (snippet_id, name);
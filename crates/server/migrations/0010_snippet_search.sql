@@ -0,0 +1,30 @@
+-- Full-text + substring search over an owner's snippets
+-- (`GET /api/v1/snippets/search`).
+--
+-- Two complementary indexes:
+--   * `search_tsv` — a stored tsvector over name (weight A) and body (weight
+--     B), GIN-indexed, for ranked word matches. The `simple` config is used on
+--     purpose: snippets are mostly code and mixed-language prose, where
+--     English stemming / stop words do more harm than good.
+--   * trigram GIN indexes on name and body, so `ILIKE '%needle%'` can find
+--     identifiers and fragments that don't tokenize into whole words
+--     (`snake_case_fn`, `0xdeadbeef`, half a URL).
+--
+-- The tsvector only covers the first 256 KiB of the body. A tsvector is capped
+-- at 1 MiB, and a maximal body full of distinct tokens could blow past that
+-- and make the INSERT fail; the trigram index still covers the full body.
+--
+-- For multi-file bundles only the parent row is indexed, i.e. the first file
+-- (which `snippets.body` mirrors).
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+ALTER TABLE snippets ADD COLUMN search_tsv tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(name, '')), 'A') ||
+        setweight(to_tsvector('simple', left(body, 262144)), 'B')
+    ) STORED;
+
+CREATE INDEX snippets_search_tsv_ix ON snippets USING gin (search_tsv);
+CREATE INDEX snippets_name_trgm_ix  ON snippets USING gin (name gin_trgm_ops);
+CREATE INDEX snippets_body_trgm_ix  ON snippets USING gin (body gin_trgm_ops);
@@ -0,0 +1,41 @@
+-- Trash: restore and permanent purge of soft-deleted snippets.
+--
+-- Soft-deleted rows (0007) can now be listed by their owner, restored, or
+-- purged — by the owner on demand, or by the retention sweep once they have
+-- sat in the trash longer than TRASH_RETENTION_DAYS.
+--
+-- 0007 promised that a slug points to at most one snippet ever. With rows
+-- physically leaving `snippets`, the unique index alone no longer holds that
+-- line, so every hard delete tombstones its slug here and an INSERT reusing a
+-- tombstoned slug fails exactly like a collision on `snippets_slug_uniq` —
+-- slug generation already retries on that.
+
+CREATE TABLE retired_slugs (
+    slug        text          PRIMARY KEY,
+    retired_at  timestamptz   NOT NULL DEFAULT now()
+);
+
+CREATE OR REPLACE FUNCTION retire_snippet_slug() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO retired_slugs (slug) VALUES (OLD.slug) ON CONFLICT DO NOTHING;
+    RETURN OLD;
+END; $$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION reject_retired_slug() RETURNS trigger AS $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM retired_slugs WHERE slug = NEW.slug) THEN
+        RAISE unique_violation USING
+            CONSTRAINT = 'snippets_slug_uniq',
+            MESSAGE = format('slug %s has been retired', NEW.slug);
+    END IF;
+    RETURN NEW;
+END; $$ LANGUAGE plpgsql;
+
+CREATE TRIGGER snippets_retire_slug AFTER DELETE ON snippets
+    FOR EACH ROW EXECUTE FUNCTION retire_snippet_slug();
+CREATE TRIGGER snippets_reject_retired_slug BEFORE INSERT ON snippets
+    FOR EACH ROW EXECUTE FUNCTION reject_retired_slug();
+
+-- The trash listing: one owner's deleted rows, most recently deleted first.
+CREATE INDEX snippets_trash_ix ON snippets (owner_id, deleted_at DESC, id DESC)
+    WHERE deleted_at IS NOT NULL;
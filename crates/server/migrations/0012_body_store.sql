@@ -0,0 +1,35 @@
+-- Pluggable body storage.
+--
+-- Bodies used to live only in the `body` columns, capped at 1 MiB by CHECK.
+-- With BODY_STORE=fs or s3, a body larger than 256 KiB (the same window
+-- `search_tsv` indexes) is written to that store instead, content-addressed
+-- by its SHA-256, and the row records the hex digest in `body_ref`. The
+-- `body` column then keeps just the first 256 KiB so search, highlights, and
+-- link previews work without touching the store.
+--
+-- `body_ref IS NULL` means the column holds the whole body: every row written
+-- before this migration, every row written while BODY_STORE=postgres, and any
+-- body small enough to fit the window.
+--
+-- Revisions and bundle files carry their own reference; identical contents
+-- share one stored object. `size_bytes` keeps counting the full body, and the
+-- 1 MiB CHECK on `body` still holds because only the prefix is inline.
+
+ALTER TABLE snippets          ADD COLUMN body_ref text;
+ALTER TABLE snippet_revisions ADD COLUMN body_ref text;
+ALTER TABLE snippet_files     ADD COLUMN body_ref text;
+
+-- Every object handed to an external store, so unreferenced ones can be
+-- garbage-collected (see the `orphan_blobs` maintenance job). `touched_at` is
+-- bumped each time a writer references the object again, before it uploads,
+-- which keeps the collector away from objects that are about to gain a row.
+CREATE TABLE body_blobs (
+    body_ref    text          PRIMARY KEY,
+    size_bytes  bigint        NOT NULL,
+    touched_at  timestamptz   NOT NULL DEFAULT now()
+);
+CREATE INDEX body_blobs_touched_ix ON body_blobs (touched_at);
+
+CREATE INDEX snippets_body_ref_ix          ON snippets (body_ref)          WHERE body_ref IS NOT NULL;
+CREATE INDEX snippet_revisions_body_ref_ix ON snippet_revisions (body_ref) WHERE body_ref IS NOT NULL;
+CREATE INDEX snippet_files_body_ref_ix     ON snippet_files (body_ref)     WHERE body_ref IS NOT NULL;
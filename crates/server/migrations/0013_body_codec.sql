@@ -0,0 +1,52 @@
+-- zstd compression of snippet bodies at rest.
+--
+-- `body_codec` says how a row's full body is encoded where it is kept:
+--   * 'identity' — plain UTF-8, either the whole `body` column or the stored
+--     object behind `body_ref` (migration 0012).
+--   * 'zstd'     — one zstd frame, in `body_packed` or behind `body_ref`.
+--   * NULL       — written before this migration and not looked at yet;
+--     read exactly like 'identity'. The `compress_bodies` maintenance job
+--     works through these in the background and sets a definite codec.
+--
+-- A packed row keeps the first 64 KiB of the text in `body`, so search,
+-- highlights, and link previews still work without decompressing; for those
+-- rows the tsvector and trigram indexes only see that prefix. Bodies that
+-- don't shrink enough to pay for the prefix stay 'identity'.
+--
+-- `size_bytes` stays the logical (decoded) length; `stored_bytes` is what
+-- the full body occupies at rest. Stored objects are still addressed by the
+-- SHA-256 of their bytes, which for 'zstd' objects are the compressed bytes.
+
+ALTER TABLE snippets          ADD COLUMN body_codec text CHECK (body_codec IN ('identity', 'zstd')),
+                              ADD COLUMN body_packed bytea,
+                              ADD COLUMN stored_bytes integer;
+ALTER TABLE snippet_revisions ADD COLUMN body_codec text CHECK (body_codec IN ('identity', 'zstd')),
+                              ADD COLUMN body_packed bytea,
+                              ADD COLUMN stored_bytes integer;
+ALTER TABLE snippet_files     ADD COLUMN body_codec text CHECK (body_codec IN ('identity', 'zstd')),
+                              ADD COLUMN body_packed bytea,
+                              ADD COLUMN stored_bytes integer;
+
+-- Nothing is compressed yet, so the stored size is the logical size.
+UPDATE snippets          SET stored_bytes = size_bytes;
+UPDATE snippet_revisions SET stored_bytes = size_bytes;
+UPDATE snippet_files     SET stored_bytes = size_bytes;
+ALTER TABLE snippets          ALTER COLUMN stored_bytes SET NOT NULL;
+ALTER TABLE snippet_revisions ALTER COLUMN stored_bytes SET NOT NULL;
+ALTER TABLE snippet_files     ALTER COLUMN stored_bytes SET NOT NULL;
+
+-- The backfill's work queue.
+CREATE INDEX snippets_codec_backlog_ix          ON snippets (id)                          WHERE body_codec IS NULL;
+CREATE INDEX snippet_revisions_codec_backlog_ix ON snippet_revisions (snippet_id, revision) WHERE body_codec IS NULL;
+CREATE INDEX snippet_files_codec_backlog_ix     ON snippet_files (snippet_id, position)   WHERE body_codec IS NULL;
+
+-- Re-encoding a body doesn't change what the owner sees, so the backfill
+-- sets `pastedev.preserve_updated_at` for its transaction to keep
+-- `updated_at` (and with it the "updated" sort) where it was.
+CREATE OR REPLACE FUNCTION bump_updated_at() RETURNS trigger AS $$
+BEGIN
+    IF current_setting('pastedev.preserve_updated_at', true) IS DISTINCT FROM 'on' THEN
+        NEW.updated_at := now();
+    END IF;
+    RETURN NEW;
+END; $$ LANGUAGE plpgsql;
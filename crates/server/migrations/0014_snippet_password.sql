@@ -0,0 +1,21 @@
+-- Password-protected snippets.
+--
+-- visibility 'password': everyone but the owner has to unlock the snippet
+-- with a passphrase first, whether or not they have an account. Only the
+-- Argon2id PHC string is stored. A correct passphrase earns a short-lived
+-- cookie scoped to the one snippet (see `snippets/unlock.rs`); that cookie is
+-- a MAC over the current hash, so changing the passphrase or leaving
+-- 'password' visibility revokes every cookie handed out so far.
+--
+-- The hash exists exactly when the visibility is 'password'; the settings
+-- handler clears it when the owner switches away.
+
+ALTER TABLE snippets DROP CONSTRAINT snippets_visibility_check;
+ALTER TABLE snippets
+    ADD CONSTRAINT snippets_visibility_check
+        CHECK (visibility IN ('public', 'private', 'password'));
+
+ALTER TABLE snippets
+    ADD COLUMN password_hash text,
+    ADD CONSTRAINT snippets_password_hash_check
+        CHECK ((visibility = 'password') = (password_hash IS NOT NULL));
@@ -0,0 +1,33 @@
+-- End-to-end encrypted snippets.
+--
+-- The CLI encrypts the body before upload and keeps the key in the share
+-- URL's `#fragment`, which browsers and HTTP clients never send. The server
+-- only ever holds the `v1.<base64url>` envelope (`pastedev_core::
+-- is_valid_ciphertext`), so everything derived from the body is meaningless
+-- for these rows and is skipped:
+--   * the body half of `search_tsv` and the body trigram index — ciphertext
+--     tokens would only bloat the indexes and produce random matches;
+--   * link-preview descriptions and the typed `/c`, `/m`, `/h` raw routes;
+--     encrypted snippets are served as opaque bytes from `/e/:slug/raw`.
+--
+-- Only single-body snippets can be encrypted, and the flag is fixed at
+-- creation.
+
+ALTER TABLE snippets ADD COLUMN encrypted boolean NOT NULL DEFAULT false;
+
+DROP INDEX snippets_search_tsv_ix;
+DROP INDEX snippets_body_trgm_ix;
+ALTER TABLE snippets DROP COLUMN search_tsv;
+
+ALTER TABLE snippets ADD COLUMN search_tsv tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(name, '')), 'A') ||
+        CASE WHEN encrypted
+             THEN ''::tsvector
+             ELSE setweight(to_tsvector('simple', left(body, 262144)), 'B')
+        END
+    ) STORED;
+
+CREATE INDEX snippets_search_tsv_ix ON snippets USING gin (search_tsv);
+CREATE INDEX snippets_body_trgm_ix  ON snippets USING gin (body gin_trgm_ops)
+    WHERE NOT encrypted;
@@ -0,0 +1,22 @@
+-- Per-user share lists for private snippets.
+--
+-- By default 'private' only keeps anonymous readers out: any approved user
+-- who has the slug can read it. On an instance shared by several teams that
+-- is too loose, so an owner can switch a snippet to strict private mode, in
+-- which only the owner and the users listed here can read it. The list is
+-- kept when strict mode is off (or the snippet isn't private) and simply
+-- doesn't matter until it's back on.
+--
+-- Rows go away with the snippet or the user; a purged user loses access
+-- without leaving dangling ids behind.
+
+ALTER TABLE snippets ADD COLUMN strict_private boolean NOT NULL DEFAULT false;
+
+CREATE TABLE snippet_shares (
+    snippet_id uuid        NOT NULL REFERENCES snippets (id) ON DELETE CASCADE,
+    user_id    uuid        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (snippet_id, user_id)
+);
+
+CREATE INDEX snippet_shares_user_ix ON snippet_shares (user_id);
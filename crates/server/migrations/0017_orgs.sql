@@ -0,0 +1,45 @@
+-- Organizations: snippets owned by a team instead of one user.
+--
+--   * `orgs` / `org_members` — membership with a role. 'member' publishes and
+--     edits the org's snippets, 'maintainer' also deletes them and manages
+--     plain members, 'owner' manages everyone. The handlers keep at least one
+--     owner per org.
+--   * `snippets.org_id` — set for org-owned snippets. `owner_id` stays the
+--     member who published it, so per-user views (trash, search, audit)
+--     keep working; write access follows the org role instead.
+--   * visibility 'team' — only members of the owning org can read it.
+--   * `api_keys.org_id` — an org-scoped key publishes into, lists, and
+--     manages that org's snippets only, and stops working once its user
+--     leaves the org.
+--
+-- An org can't be deleted while it still owns snippets, trashed ones
+-- included; they have to be purged (or moved) first.
+
+CREATE TABLE orgs (
+    id          uuid          PRIMARY KEY DEFAULT gen_random_uuid(),
+    name        varchar(40)   NOT NULL CHECK (name ~ '^[a-z0-9_.\-]{3,40}$'),
+    created_by  uuid          REFERENCES users(id) ON DELETE SET NULL,
+    created_at  timestamptz   NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX orgs_name_uniq ON orgs (name);
+
+CREATE TABLE org_members (
+    org_id      uuid          NOT NULL REFERENCES orgs(id) ON DELETE CASCADE,
+    user_id     uuid          NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role        varchar(16)   NOT NULL CHECK (role IN ('owner', 'maintainer', 'member')),
+    created_at  timestamptz   NOT NULL DEFAULT now(),
+    PRIMARY KEY (org_id, user_id)
+);
+CREATE INDEX org_members_user_ix ON org_members (user_id);
+
+ALTER TABLE snippets ADD COLUMN org_id uuid REFERENCES orgs(id) ON DELETE RESTRICT;
+CREATE INDEX snippets_org_ix ON snippets (org_id) WHERE org_id IS NOT NULL;
+
+ALTER TABLE snippets DROP CONSTRAINT snippets_visibility_check;
+ALTER TABLE snippets
+    ADD CONSTRAINT snippets_visibility_check
+        CHECK (visibility IN ('public', 'private', 'password', 'team')),
+    ADD CONSTRAINT snippets_team_org_check
+        CHECK (visibility <> 'team' OR org_id IS NOT NULL);
+
+ALTER TABLE api_keys ADD COLUMN org_id uuid REFERENCES orgs(id) ON DELETE CASCADE;
@@ -0,0 +1,18 @@
+-- Signed, time-limited share links (see snippets/links.rs).
+--
+-- The link token itself is never stored: it is an HMAC over the slug and
+-- the row's id, expiry and use limit, so it can be checked without a
+-- lookup. The row exists so owners can list and revoke links, and so
+-- `max_uses` can be counted.
+
+CREATE TABLE snippet_links (
+    id          uuid          PRIMARY KEY DEFAULT gen_random_uuid(),
+    snippet_id  uuid          NOT NULL REFERENCES snippets(id) ON DELETE CASCADE,
+    created_by  uuid          REFERENCES users(id) ON DELETE SET NULL,
+    expires_at  timestamptz   NOT NULL,
+    max_uses    integer       CHECK (max_uses > 0),
+    uses        integer       NOT NULL DEFAULT 0,
+    revoked_at  timestamptz,
+    created_at  timestamptz   NOT NULL DEFAULT now()
+);
+CREATE INDEX snippet_links_snippet_ix ON snippet_links (snippet_id, created_at DESC);
@@ -0,0 +1,9 @@
+-- Expire after N unique views.
+--
+-- `max_views` caps how many distinct non-owner viewers (as deduplicated by
+-- `snippet_views`) may read a snippet. Those who got in keep access until
+-- the snippet otherwise expires; everyone after them gets a 404. `views`
+-- is the counter checked against it, bumped under a row lock so concurrent
+-- first reads can't overshoot the cap.
+
+ALTER TABLE snippets ADD COLUMN max_views integer CHECK (max_views > 0);
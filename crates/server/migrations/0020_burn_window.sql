@@ -0,0 +1,10 @@
+-- Per-snippet burn-after-read window.
+--
+-- The fuse used to be a fixed 15 minutes. Each snippet now carries its own
+-- length, with the old value as the default so existing rows keep behaving
+-- the same. 0 is the one-shot mode: the first non-owner read expires the
+-- snippet on the spot. The API enforces the upper bound.
+
+ALTER TABLE snippets
+    ADD COLUMN burn_window_seconds integer NOT NULL DEFAULT 900
+        CHECK (burn_window_seconds >= 0);
@@ -0,0 +1,7 @@
+-- Scheduled publishing.
+--
+-- A snippet with `publish_at` in the future is embargoed: its slug already
+-- exists, but anyone other than its owner gets a 404 until then, the same
+-- as after `expires_at`. NULL means live from creation.
+
+ALTER TABLE snippets ADD COLUMN publish_at timestamptz;
@@ -0,0 +1,74 @@
+-- Custom slugs and slug aliases.
+--
+-- Owners can now pick a readable slug (`oncall-runbook`) and rename a
+-- snippet; the slug it had before stays behind in `snippet_aliases` and keeps
+-- resolving to it, so old links don't break. Owners can add further aliases
+-- too. The format rules live in `pastedev_core::slug`; this migration only
+-- keeps the one namespace unique across `snippets.slug`, `snippet_aliases`
+-- and `retired_slugs`.
+--
+-- The cross-table check runs in triggers, like 0011's, and every claim takes
+-- a transaction-scoped advisory lock on the slug first, so two transactions
+-- claiming the same slug through different tables can't both pass the check.
+-- A violation reports the constraint of the table being written to, which is
+-- what callers already match on.
+
+-- Custom slugs are longer than the generated seven characters.
+ALTER TABLE snippets ALTER COLUMN slug TYPE text;
+
+CREATE TABLE snippet_aliases (
+    slug        text          PRIMARY KEY,
+    snippet_id  uuid          NOT NULL REFERENCES snippets(id) ON DELETE CASCADE,
+    created_at  timestamptz   NOT NULL DEFAULT now()
+);
+
+CREATE INDEX snippet_aliases_snippet_ix ON snippet_aliases (snippet_id, created_at);
+
+-- Supersedes 0011's version: aliases are taken too, and renames are checked.
+CREATE OR REPLACE FUNCTION reject_retired_slug() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext(NEW.slug));
+    IF EXISTS (SELECT 1 FROM retired_slugs WHERE slug = NEW.slug) THEN
+        RAISE unique_violation USING
+            CONSTRAINT = 'snippets_slug_uniq',
+            MESSAGE = format('slug %s has been retired', NEW.slug);
+    END IF;
+    IF EXISTS (SELECT 1 FROM snippet_aliases WHERE slug = NEW.slug) THEN
+        RAISE unique_violation USING
+            CONSTRAINT = 'snippets_slug_uniq',
+            MESSAGE = format('slug %s is an alias', NEW.slug);
+    END IF;
+    RETURN NEW;
+END; $$ LANGUAGE plpgsql;
+
+DROP TRIGGER snippets_reject_retired_slug ON snippets;
+CREATE TRIGGER snippets_reject_retired_slug BEFORE INSERT OR UPDATE OF slug ON snippets
+    FOR EACH ROW EXECUTE FUNCTION reject_retired_slug();
+
+CREATE OR REPLACE FUNCTION reject_taken_alias() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext(NEW.slug));
+    IF EXISTS (SELECT 1 FROM snippets WHERE slug = NEW.slug)
+       OR EXISTS (SELECT 1 FROM retired_slugs WHERE slug = NEW.slug) THEN
+        RAISE unique_violation USING
+            CONSTRAINT = 'snippet_aliases_pkey',
+            MESSAGE = format('slug %s is taken', NEW.slug);
+    END IF;
+    RETURN NEW;
+END; $$ LANGUAGE plpgsql;
+
+CREATE TRIGGER snippet_aliases_reject_taken BEFORE INSERT ON snippet_aliases
+    FOR EACH ROW EXECUTE FUNCTION reject_taken_alias();
+
+-- A purged snippet's aliases are retired along with its slug. BEFORE, so
+-- the rows are still there to read ahead of the cascade.
+CREATE OR REPLACE FUNCTION retire_snippet_aliases() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO retired_slugs (slug)
+        SELECT slug FROM snippet_aliases WHERE snippet_id = OLD.id
+        ON CONFLICT DO NOTHING;
+    RETURN OLD;
+END; $$ LANGUAGE plpgsql;
+
+CREATE TRIGGER snippets_retire_aliases BEFORE DELETE ON snippets
+    FOR EACH ROW EXECUTE FUNCTION retire_snippet_aliases();
@@ -0,0 +1,9 @@
+-- Forks.
+--
+-- `forked_from` points at the snippet a fork was copied from. Purging the
+-- source leaves the fork standing, only without the link back.
+
+ALTER TABLE snippets
+    ADD COLUMN forked_from uuid REFERENCES snippets(id) ON DELETE SET NULL;
+
+CREATE INDEX snippets_forked_from_ix ON snippets (forked_from) WHERE forked_from IS NOT NULL;
@@ -0,0 +1,35 @@
+-- Tags and collections.
+--
+-- Tags are free-form labels on a snippet, stored normalized (see
+-- `pastedev_core::normalize_tag`). Collections are named folders a user
+-- keeps their own snippets in, in an order they choose; a snippet can sit in
+-- any number of them. Both go away with the snippet or the user.
+
+CREATE TABLE snippet_tags (
+    snippet_id  uuid  NOT NULL REFERENCES snippets(id) ON DELETE CASCADE,
+    tag         text  NOT NULL,
+    PRIMARY KEY (snippet_id, tag)
+);
+
+-- The list filter looks snippets up by tag.
+CREATE INDEX snippet_tags_tag_ix ON snippet_tags (tag, snippet_id);
+
+CREATE TABLE collections (
+    id           uuid          PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id     uuid          NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name         text          NOT NULL,
+    description  text,
+    created_at   timestamptz   NOT NULL DEFAULT now(),
+    updated_at   timestamptz   NOT NULL DEFAULT now(),
+    CONSTRAINT collections_owner_name_uniq UNIQUE (owner_id, name)
+);
+
+CREATE TABLE collection_items (
+    collection_id  uuid          NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    snippet_id     uuid          NOT NULL REFERENCES snippets(id) ON DELETE CASCADE,
+    position       integer       NOT NULL,
+    added_at       timestamptz   NOT NULL DEFAULT now(),
+    PRIMARY KEY (collection_id, snippet_id)
+);
+
+CREATE INDEX collection_items_snippet_ix ON collection_items (snippet_id);
@@ -0,0 +1,20 @@
+-- Idempotency keys for snippet creation.
+--
+-- A client that sends `Idempotency-Key` on `POST /api/v1/snippets` or
+-- `POST /paste` can retry the request safely: the first attempt claims the
+-- key with a fingerprint of the request, and a retry within
+-- `IDEMPOTENCY_KEY_TTL_HOURS` gets the snippet it created back instead of a
+-- second one. `snippet_id` stays NULL while that first attempt is running.
+-- Keys are per user, and go away with the user or the snippet.
+
+CREATE TABLE idempotency_keys (
+    user_id      uuid          NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    key          text          NOT NULL,
+    fingerprint  bytea         NOT NULL,
+    snippet_id   uuid          REFERENCES snippets(id) ON DELETE CASCADE,
+    created_at   timestamptz   NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, key)
+);
+
+-- The maintenance sweep drops keys past the window.
+CREATE INDEX idempotency_keys_created_ix ON idempotency_keys (created_at);
@@ -0,0 +1,6 @@
+-- Idempotency fingerprints are now keyed with PASTEDEV_SECRET (a create
+-- request can carry a snippet passphrase, and a plain SHA-256 of it could be
+-- brute-forced from the table). Drop the unkeyed ones: they can't match a
+-- keyed fingerprint anyway, and keys only live for a few hours.
+
+DELETE FROM idempotency_keys;
@@ -0,0 +1,35 @@
+-- Indexes for the snippet listing (`GET /api/v1/snippets`).
+--
+-- A page is a keyset scan: `WHERE owner_id = $1 AND (key, id) < (..)`
+-- `ORDER BY key, id`, with `org_id` in place of `owner_id` for an org's
+-- listing and one statement per sort key. Each (scope, key) pair gets a
+-- btree over exactly those columns so a page reads `limit` rows off the
+-- index in either direction instead of sorting everything the owner has.
+-- Trashed snippets are never listed, so they stay out of these.
+--
+-- The `views` ones take a write on every counted view; that is the price
+-- of a "most viewed" listing that doesn't sort the whole table.
+
+CREATE INDEX snippets_owner_created_ix ON snippets (owner_id, created_at, id)
+    WHERE deleted_at IS NULL;
+CREATE INDEX snippets_owner_updated_ix ON snippets (owner_id, updated_at, id)
+    WHERE deleted_at IS NULL;
+CREATE INDEX snippets_owner_views_ix ON snippets (owner_id, views, id)
+    WHERE deleted_at IS NULL;
+CREATE INDEX snippets_owner_size_ix ON snippets (owner_id, size_bytes, id)
+    WHERE deleted_at IS NULL;
+CREATE INDEX snippets_owner_expires_ix
+    ON snippets (owner_id, COALESCE(expires_at, 'infinity'::timestamptz), id)
+    WHERE deleted_at IS NULL;
+
+CREATE INDEX snippets_org_created_ix ON snippets (org_id, created_at, id)
+    WHERE org_id IS NOT NULL AND deleted_at IS NULL;
+CREATE INDEX snippets_org_updated_ix ON snippets (org_id, updated_at, id)
+    WHERE org_id IS NOT NULL AND deleted_at IS NULL;
+CREATE INDEX snippets_org_views_ix ON snippets (org_id, views, id)
+    WHERE org_id IS NOT NULL AND deleted_at IS NULL;
+CREATE INDEX snippets_org_size_ix ON snippets (org_id, size_bytes, id)
+    WHERE org_id IS NOT NULL AND deleted_at IS NULL;
+CREATE INDEX snippets_org_expires_ix
+    ON snippets (org_id, COALESCE(expires_at, 'infinity'::timestamptz), id)
+    WHERE org_id IS NOT NULL AND deleted_at IS NULL;
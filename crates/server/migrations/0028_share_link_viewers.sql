@@ -0,0 +1,14 @@
+-- Who has used each share link (see snippets/links.rs).
+--
+-- `max_uses` counts viewers, not requests: opening a snippet is an API read
+-- plus a raw fetch or two, all of which a link has to let through. A viewer
+-- is the same per-(viewer, snippet) HMAC `snippet_views` keys on, so the
+-- first request of each counts a use and later ones find their row. Links
+-- minted before this keep the requests they have counted so far.
+
+CREATE TABLE snippet_link_viewers (
+    link_id       uuid          NOT NULL REFERENCES snippet_links(id) ON DELETE CASCADE,
+    viewer_hash   bytea         NOT NULL,
+    first_seen_at timestamptz   NOT NULL DEFAULT now(),
+    PRIMARY KEY (link_id, viewer_hash)
+);
@@ -13,6 +13,7 @@ use crate::{
     auth::{api_key::ApiKeyRow, extract::SessionUser},
     error::AppError,
     http::AppState,
+    orgs,
 };
 
 #[derive(Debug, Deserialize)]
@@ -20,6 +21,9 @@ pub struct CreateRequest {
     pub name: String,
     #[serde(default)]
     pub scopes: Vec<Scope>,
+    /// Scope the key to this org; the caller must be a member.
+    #[serde(default)]
+    pub org: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,6 +38,8 @@ pub struct KeyView {
     pub last_used_at: Option<OffsetDateTime>,
     #[serde(with = "time::serde::rfc3339::option")]
     pub revoked_at: Option<OffsetDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,6 +60,7 @@ fn view_of(row: &ApiKeyRow) -> KeyView {
         created_at: row.created_at,
         last_used_at: row.last_used_at,
         revoked_at: row.revoked_at,
+        org: row.org.clone(),
     }
 }
 
@@ -76,6 +83,10 @@ pub async fn create(
     }
     scopes.sort_by_key(|s| s.as_str());
     scopes.dedup();
+    let org = match req.org.as_deref() {
+        Some(name) => Some(orgs::handlers::membership(&state, name, user.id).await?.0),
+        None => None,
+    };
 
     let minted = crate::auth::api_key::insert(
         &state.pool,
@@ -83,6 +94,7 @@ pub async fn create(
         user.id,
         name,
         &scopes,
+        org.as_ref(),
     )
     .await?;
     let scope_strs: Vec<&'static str> = scopes.iter().map(|s| s.as_str()).collect();
@@ -92,7 +104,11 @@ pub async fn create(
             event: "api_key.create",
             actor_user_id: Some(user.id),
             actor_api_key_id: Some(minted.row.id),
-            payload: Some(serde_json::json!({"prefix": minted.row.prefix, "scopes": scope_strs})),
+            payload: Some(serde_json::json!({
+                "prefix": minted.row.prefix,
+                "scopes": scope_strs,
+                "org": minted.row.org,
+            })),
             ..Default::default()
         },
     );
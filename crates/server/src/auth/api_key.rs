@@ -21,6 +21,7 @@ use tokio::sync::Mutex;
 use uuid::Uuid;
 
 use super::hmac::hmac_sha256;
+use crate::orgs::repo::OrgRow;
 
 const ALPHABET: &[char] = &[
     'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'j', 'k', 'l', 'm', 'n', 'p', 'q', 'r', 's', 't', 'u',
@@ -37,6 +38,8 @@ pub struct ApiKeyRow {
     pub created_at: OffsetDateTime,
     pub last_used_at: Option<OffsetDateTime>,
     pub revoked_at: Option<OffsetDateTime>,
+    /// Name of the org the key is scoped to.
+    pub org: Option<String>,
 }
 
 #[derive(Debug)]
@@ -58,25 +61,28 @@ pub fn mint_token(server_secret: &str) -> (String, String, [u8; 32]) {
 
 /// Mint a new API key for `user_id` and persist it. Returns the plaintext
 /// token alongside the row; the plaintext is not stored anywhere else.
+/// `org` scopes the key to that org's snippets.
 pub async fn insert(
     pool: &PgPool,
     server_secret: &str,
     user_id: Uuid,
     name: &str,
     scopes: &[Scope],
+    org: Option<&OrgRow>,
 ) -> Result<Minted, sqlx::Error> {
     let (token, prefix, hash) = mint_token(server_secret);
     let scope_strs: Vec<String> = scopes.iter().map(|s| s.as_str().to_string()).collect();
     let hash_slice: &[u8] = &hash[..];
     let row = sqlx::query!(
-        "INSERT INTO api_keys (user_id, name, prefix, token_hash, scopes)
-         VALUES ($1, $2, $3, $4, $5)
+        "INSERT INTO api_keys (user_id, name, prefix, token_hash, scopes, org_id)
+         VALUES ($1, $2, $3, $4, $5, $6)
          RETURNING id, created_at",
         user_id,
         name,
         prefix,
         hash_slice,
         &scope_strs,
+        org.map(|o| o.id),
     )
     .fetch_one(pool)
     .await?;
@@ -89,6 +95,7 @@ pub async fn insert(
             created_at: row.created_at,
             last_used_at: None,
             revoked_at: None,
+            org: org.map(|o| o.name.clone()),
         },
         token,
     })
@@ -100,8 +107,11 @@ pub async fn list_for_user(
     user_id: Uuid,
 ) -> Result<Vec<ApiKeyRow>, sqlx::Error> {
     let rows = sqlx::query!(
-        r#"SELECT id, name, prefix, scopes, created_at, last_used_at, revoked_at
-           FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC"#,
+        r#"SELECT k.id, k.name, k.prefix, k.scopes, k.created_at, k.last_used_at, k.revoked_at,
+                  o.name AS "org?"
+           FROM api_keys k
+           LEFT JOIN orgs o ON o.id = k.org_id
+           WHERE k.user_id = $1 ORDER BY k.created_at DESC"#,
         user_id,
     )
     .fetch_all(pool)
@@ -116,6 +126,7 @@ pub async fn list_for_user(
             created_at: r.created_at,
             last_used_at: r.last_used_at,
             revoked_at: r.revoked_at,
+            org: r.org,
         })
         .collect())
 }
@@ -141,6 +152,8 @@ pub struct VerifiedKey {
     pub key_id: Uuid,
     pub user_id: Uuid,
     pub scopes: Vec<Scope>,
+    /// Set for org-scoped keys.
+    pub org_id: Option<Uuid>,
 }
 
 /// Look up the row matching `bearer`'s prefix, constant-time-compare the
@@ -161,7 +174,7 @@ pub async fn verify(
         return Ok(None);
     }
     let row = sqlx::query!(
-        "SELECT id, user_id, token_hash, scopes, revoked_at
+        "SELECT id, user_id, token_hash, scopes, revoked_at, org_id
          FROM api_keys WHERE prefix = $1",
         prefix,
     )
@@ -187,6 +200,7 @@ pub async fn verify(
         key_id: row.id,
         user_id: row.user_id,
         scopes,
+        org_id: row.org_id,
     }))
 }
 
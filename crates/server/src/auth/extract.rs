@@ -27,6 +27,9 @@ pub struct AuthedUser {
     /// to mint new keys reject bearer-auth here.
     pub via_bearer: bool,
     pub key_id: Option<Uuid>,
+    /// The org a bearer key is scoped to. Such a key only acts on that org's
+    /// snippets; `None` for session auth and personal keys.
+    pub key_org_id: Option<Uuid>,
 }
 
 pub struct SessionUser(pub AuthedUser);
@@ -101,6 +104,7 @@ async fn load_user_skel(pool: &PgPool, id: Uuid) -> Result<AuthedUser, AppError>
         key_scopes: Vec::new(),
         via_bearer: false,
         key_id: None,
+        key_org_id: None,
     })
 }
 
@@ -120,9 +124,14 @@ async fn bearer_user(state: &AppState, headers: &HeaderMap) -> Option<AuthedUser
     user.via_bearer = true;
     user.key_id = Some(verified.key_id);
     user.key_scopes = verified.scopes;
+    user.key_org_id = verified.org_id;
     if user.status == UserStatus::Suspended || user.status == UserStatus::Rejected {
         return None;
     }
+    // An org-scoped key dies with its user's membership.
+    if let Some(org_id) = verified.org_id {
+        crate::orgs::repo::role_of(&state.pool, org_id, user.id).await.ok()??;
+    }
     Some(user)
 }
 
@@ -1,12 +1,14 @@
 //! `HMAC-SHA256(PASTEDEV_SECRET, …)` — the one MAC the server uses to bind
 //! sensitive material to a process-wide secret.
 //!
-//! Two callers today:
+//! Callers include:
 //! - API-key minting/verification ([`super::api_key`]) hashes the plaintext
 //!   token; a DB-only leak of `api_keys.token_hash` is useless without the key.
 //! - The snippet view counter hashes (ip, ua, snippet_id) into a stable
 //!   per-viewer identifier so the `snippet_views` table can dedupe without
 //!   storing raw IPs.
+//! - Idempotency-key fingerprints, which cover create requests that may
+//!   carry a snippet passphrase.
 
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
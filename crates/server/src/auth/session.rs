@@ -151,6 +151,24 @@ pub async fn revoke_all_for_user(pool: &PgPool, user_id: Uuid) -> Result<(), sql
     Ok(())
 }
 
+/// Delete up to `batch` sessions past their `expires_at`. [`validate`] already
+/// refuses them; this only reclaims the rows. Returns how many went.
+pub async fn purge_expired(pool: &PgPool, batch: i64) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM sessions
+            WHERE id IN (
+                SELECT id FROM sessions
+                WHERE expires_at < now()
+                ORDER BY expires_at
+                LIMIT $1
+            )",
+        batch,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
 /// Render the `Set-Cookie` header value for the session.
 pub fn build_cookie(config: &Config, value: &str, max_age_seconds: i64) -> String {
     let secure = if config.session_cookie_secure {
@@ -0,0 +1,325 @@
+//! Collections (migration 0024): named folders that group a user's own
+//! snippets in an order they choose.
+//!
+//! Collections are private to their owner. Nobody else can list or open
+//! them, and a snippet's `collections` field is only filled in for its owner,
+//! so a lookup of someone else's collection is a 404 like any other miss.
+//! They are personal, so a key scoped to an org can't reach them at all.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use pastedev_core::{
+    Collection, CollectionDetail, CollectionItemsRequest, CreateCollectionRequest,
+    ListCollectionsResponse, SetCollectionItemsRequest, UpdateCollectionRequest,
+    COLLECTION_DESCRIPTION_MAX_LEN, COLLECTION_NAME_MAX_LEN,
+};
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, AuthedUser, RequiresScope},
+    collections::repo::{self, CollectionRow, ItemsEdit},
+    error::AppError,
+    http::AppState,
+    snippets::{self, handlers::to_list_item},
+};
+
+/// Collections per user.
+const MAX_COLLECTIONS: i64 = 100;
+/// Snippets per collection.
+const MAX_COLLECTION_ITEMS: i64 = 500;
+
+fn check_name(name: &str) -> Result<(), AppError> {
+    if !pastedev_core::is_valid_collection_name(name) {
+        return Err(AppError::Validation(format!(
+            "collection name must be 1 to {COLLECTION_NAME_MAX_LEN} characters without '/' or \
+             leading or trailing spaces"
+        )));
+    }
+    Ok(())
+}
+
+/// Blank descriptions are stored as none.
+fn clean_description(raw: Option<&str>) -> Result<Option<String>, AppError> {
+    let Some(d) = raw.map(str::trim).filter(|d| !d.is_empty()) else {
+        return Ok(None);
+    };
+    if d.chars().count() > COLLECTION_DESCRIPTION_MAX_LEN {
+        return Err(AppError::Validation(format!(
+            "description must be at most {COLLECTION_DESCRIPTION_MAX_LEN} characters"
+        )));
+    }
+    Ok(Some(d.to_owned()))
+}
+
+/// A second collection under the same name is a 409.
+fn map_taken(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err)
+            if db_err
+                .constraint()
+                .is_some_and(|c| c == "collections_owner_name_uniq") =>
+        {
+            AppError::Conflict("collection name already taken")
+        }
+        e => e.into(),
+    }
+}
+
+/// Refuse an org-scoped key, which stands for its user only inside the org.
+pub(crate) fn ensure_personal(user: &AuthedUser) -> Result<(), AppError> {
+    if user.key_org_id.is_some() {
+        return Err(AppError::Forbidden(Some("key is scoped to an org")));
+    }
+    Ok(())
+}
+
+/// Resolve `name` to one of `owner_id`'s collections.
+pub async fn load(state: &AppState, owner_id: Uuid, name: &str) -> Result<CollectionRow, AppError> {
+    repo::by_name(&state.pool, owner_id, name)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+fn to_dto(row: CollectionRow) -> Collection {
+    Collection {
+        name: row.name,
+        description: row.description,
+        snippet_count: row.snippet_count,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+async fn detail(
+    state: &AppState,
+    owner_id: Uuid,
+    name: &str,
+) -> Result<CollectionDetail, AppError> {
+    let row = load(state, owner_id, name).await?;
+    let items = snippets::repo::in_collection(&state.pool, row.id).await?;
+    Ok(CollectionDetail {
+        collection: to_dto(row),
+        items: items.iter().map(to_list_item).collect(),
+    })
+}
+
+/// Look up every slug (aliases included) among the caller's own live
+/// snippets, in order. Anything else is a 400 that doesn't say whether the
+/// snippet exists.
+async fn resolve_snippets(
+    state: &AppState,
+    owner_id: Uuid,
+    slugs: &[String],
+) -> Result<Vec<(Uuid, String)>, AppError> {
+    let mut out: Vec<(Uuid, String)> = Vec::with_capacity(slugs.len());
+    for slug in slugs {
+        let row = match pastedev_core::is_valid_slug(slug) {
+            true => snippets::repo::by_slug(&state.pool, slug).await?,
+            false => None,
+        };
+        let row = row
+            .filter(|r| r.owner_id == owner_id)
+            .ok_or_else(|| AppError::Validation(format!("not one of your snippets: {slug}")))?;
+        if out.iter().any(|(id, _)| *id == row.id) {
+            return Err(AppError::Validation(format!("{slug} is listed twice")));
+        }
+        out.push((row.id, row.slug));
+    }
+    Ok(out)
+}
+
+/// `GET /api/v1/collections`
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+) -> Result<Json<ListCollectionsResponse>, AppError> {
+    ensure_personal(&user.0)?;
+    let rows = repo::list_for_owner(&state.pool, user.0.id).await?;
+    Ok(Json(ListCollectionsResponse {
+        items: rows.into_iter().map(to_dto).collect(),
+    }))
+}
+
+/// `POST /api/v1/collections`
+pub async fn create(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Json(req): Json<CreateCollectionRequest>,
+) -> Result<(StatusCode, Json<Collection>), AppError> {
+    ensure_personal(&user.0)?;
+    check_name(&req.name)?;
+    let description = clean_description(req.description.as_deref())?;
+    if repo::count_for_owner(&state.pool, user.0.id).await? >= MAX_COLLECTIONS {
+        return Err(AppError::Validation(format!(
+            "at most {MAX_COLLECTIONS} collections per user"
+        )));
+    }
+    let row = repo::insert(&state.pool, user.0.id, &req.name, description.as_deref())
+        .await
+        .map_err(map_taken)?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "collection.create",
+            actor_user_id: Some(user.0.id),
+            payload: Some(serde_json::json!({ "collection": row.name })),
+            ..Default::default()
+        },
+    );
+    Ok((StatusCode::CREATED, Json(to_dto(row))))
+}
+
+/// `GET /api/v1/collections/:name` — with its snippets, in order.
+pub async fn get(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<CollectionDetail>, AppError> {
+    ensure_personal(&user.0)?;
+    Ok(Json(detail(&state, user.0.id, &name).await?))
+}
+
+/// `PATCH /api/v1/collections/:name` — rename or redescribe.
+pub async fn update(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateCollectionRequest>,
+) -> Result<Json<Collection>, AppError> {
+    ensure_personal(&user.0)?;
+    let row = load(&state, user.0.id, &name).await?;
+    if req.name.is_none() && req.description.is_none() {
+        return Err(AppError::Validation("at least one field is required".into()));
+    }
+    if let Some(n) = req.name.as_deref() {
+        check_name(n)?;
+    }
+    let description = req
+        .description
+        .as_ref()
+        .map(|d| clean_description(d.as_deref()))
+        .transpose()?;
+    repo::update(
+        &state.pool,
+        row.id,
+        req.name.as_deref(),
+        description.as_ref().map(|d| d.as_deref()),
+    )
+    .await
+    .map_err(map_taken)?;
+    let new_name = req.name.unwrap_or(row.name);
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "collection.update",
+            actor_user_id: Some(user.0.id),
+            payload: Some(serde_json::json!({
+                "collection": new_name,
+                "old": { "name": name, "description": row.description },
+                "new": { "name": new_name, "description": description },
+            })),
+            ..Default::default()
+        },
+    );
+    Ok(Json(to_dto(load(&state, user.0.id, &new_name).await?)))
+}
+
+/// `DELETE /api/v1/collections/:name` — the snippets in it stay.
+pub async fn delete(
+    user: RequiresScope<{ scope_id::DELETE }>,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    ensure_personal(&user.0)?;
+    let row = load(&state, user.0.id, &name).await?;
+    repo::delete(&state.pool, row.id).await?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "collection.delete",
+            actor_user_id: Some(user.0.id),
+            payload: Some(serde_json::json!({
+                "collection": row.name,
+                "snippet_count": row.snippet_count,
+            })),
+            ..Default::default()
+        },
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Apply `edit` to the collection and audit what changed.
+async fn change_items(
+    state: &AppState,
+    user_id: Uuid,
+    row: &CollectionRow,
+    edit: ItemsEdit<'_>,
+) -> Result<(), AppError> {
+    let change = repo::update_items(&state.pool, row.id, edit, MAX_COLLECTION_ITEMS)
+        .await?
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "at most {MAX_COLLECTION_ITEMS} snippets per collection"
+            ))
+        })?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "collection.items",
+            actor_user_id: Some(user_id),
+            payload: Some(serde_json::json!({
+                "collection": row.name,
+                "added": change.added,
+                "removed": change.removed,
+            })),
+            ..Default::default()
+        },
+    );
+    Ok(())
+}
+
+/// `PATCH /api/v1/collections/:name/items` — add (to the end) and remove.
+pub async fn update_items(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<CollectionItemsRequest>,
+) -> Result<Json<CollectionDetail>, AppError> {
+    ensure_personal(&user.0)?;
+    let row = load(&state, user.0.id, &name).await?;
+    if req.add.is_empty() && req.remove.is_empty() {
+        return Err(AppError::Validation("at least one field is required".into()));
+    }
+    let add = resolve_snippets(&state, user.0.id, &req.add).await?;
+    let remove = resolve_snippets(&state, user.0.id, &req.remove).await?;
+    if let Some((_, slug)) = add.iter().find(|(id, _)| remove.iter().any(|(r, _)| r == id)) {
+        return Err(AppError::Validation(format!("{slug} is both added and removed")));
+    }
+    let add_ids: Vec<Uuid> = add.iter().map(|(id, _)| *id).collect();
+    let remove_ids: Vec<Uuid> = remove.iter().map(|(id, _)| *id).collect();
+    let edit = ItemsEdit::Edit {
+        add: &add_ids,
+        remove: &remove_ids,
+    };
+    change_items(&state, user.0.id, &row, edit).await?;
+    Ok(Json(detail(&state, user.0.id, &row.name).await?))
+}
+
+/// `PUT /api/v1/collections/:name/items` — the complete contents, in order.
+pub async fn set_items(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(req): Json<SetCollectionItemsRequest>,
+) -> Result<Json<CollectionDetail>, AppError> {
+    ensure_personal(&user.0)?;
+    let row = load(&state, user.0.id, &name).await?;
+    let items = resolve_snippets(&state, user.0.id, &req.slugs).await?;
+    let ids: Vec<Uuid> = items.iter().map(|(id, _)| *id).collect();
+    change_items(&state, user.0.id, &row, ItemsEdit::Replace(&ids)).await?;
+    Ok(Json(detail(&state, user.0.id, &row.name).await?))
+}
@@ -0,0 +1,228 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::snippets;
+
+#[derive(Debug, Clone)]
+pub struct CollectionRow {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Live snippets only; trashed ones keep their place but aren't counted.
+    pub snippet_count: i64,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub async fn insert(
+    pool: &PgPool,
+    owner_id: Uuid,
+    name: &str,
+    description: Option<&str>,
+) -> Result<CollectionRow, sqlx::Error> {
+    sqlx::query_as!(
+        CollectionRow,
+        r#"INSERT INTO collections (owner_id, name, description) VALUES ($1, $2, $3)
+           RETURNING id, name, description, 0::bigint AS "snippet_count!",
+                     created_at, updated_at"#,
+        owner_id,
+        name,
+        description,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn count_for_owner(pool: &PgPool, owner_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM collections WHERE owner_id = $1"#,
+        owner_id,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn by_name(
+    pool: &PgPool,
+    owner_id: Uuid,
+    name: &str,
+) -> Result<Option<CollectionRow>, sqlx::Error> {
+    sqlx::query_as!(
+        CollectionRow,
+        r#"SELECT c.id, c.name, c.description,
+                  (SELECT count(*) FROM collection_items i
+                     JOIN snippets s ON s.id = i.snippet_id
+                    WHERE i.collection_id = c.id AND s.deleted_at IS NULL) AS "snippet_count!",
+                  c.created_at, c.updated_at
+             FROM collections c
+            WHERE c.owner_id = $1 AND c.name = $2"#,
+        owner_id,
+        name,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The owner's collections, by name.
+pub async fn list_for_owner(
+    pool: &PgPool,
+    owner_id: Uuid,
+) -> Result<Vec<CollectionRow>, sqlx::Error> {
+    sqlx::query_as!(
+        CollectionRow,
+        r#"SELECT c.id, c.name, c.description,
+                  (SELECT count(*) FROM collection_items i
+                     JOIN snippets s ON s.id = i.snippet_id
+                    WHERE i.collection_id = c.id AND s.deleted_at IS NULL) AS "snippet_count!",
+                  c.created_at, c.updated_at
+             FROM collections c
+            WHERE c.owner_id = $1
+            ORDER BY c.name"#,
+        owner_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Rename and/or redescribe. `description: Some(None)` clears it.
+pub async fn update(
+    pool: &PgPool,
+    id: Uuid,
+    name: Option<&str>,
+    description: Option<Option<&str>>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if name.is_some() {
+        touch_items(&mut tx, id).await?;
+    }
+    sqlx::query!(
+        "UPDATE collections
+            SET name = COALESCE($2, name),
+                description = CASE WHEN $3 THEN $4 ELSE description END,
+                updated_at = now()
+          WHERE id = $1",
+        id,
+        name,
+        description.is_some(),
+        description.flatten(),
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    touch_items(&mut tx, id).await?;
+    sqlx::query!("DELETE FROM collections WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// A snippet lists the names of the collections it's in, so renaming or
+/// deleting one is a new version of each snippet in it.
+async fn touch_items(conn: &mut sqlx::PgConnection, collection_id: Uuid) -> Result<(), sqlx::Error> {
+    let ids = sqlx::query_scalar!(
+        "SELECT snippet_id FROM collection_items WHERE collection_id = $1",
+        collection_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    snippets::repo::touch(conn, &ids).await
+}
+
+/// What [`update_items`] actually changed, as slugs.
+#[derive(Debug, Default)]
+pub struct ItemsChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Change a collection's contents in one transaction. `Replace` swaps in a
+/// complete ordering; `Edit` drops `remove` and appends the new ones from
+/// `add`, in order. Returns `None`, having changed nothing, if the collection
+/// would end up holding more than `max` snippets.
+pub async fn update_items(
+    pool: &PgPool,
+    collection_id: Uuid,
+    edit: ItemsEdit<'_>,
+    max: i64,
+) -> Result<Option<ItemsChange>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    // Serializes concurrent edits, so positions stay dense and the cap holds.
+    sqlx::query!("SELECT id FROM collections WHERE id = $1 FOR UPDATE", collection_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let (add, remove_all, remove) = match edit {
+        ItemsEdit::Replace(ids) => (ids, true, &[][..]),
+        ItemsEdit::Edit { add, remove } => (add, false, remove),
+    };
+    let removed = sqlx::query_scalar!(
+        "WITH d AS (
+             DELETE FROM collection_items
+              WHERE collection_id = $1 AND ($2 OR snippet_id = ANY($3::uuid[]))
+             RETURNING snippet_id
+         )
+         SELECT s.slug FROM d JOIN snippets s ON s.id = d.snippet_id",
+        collection_id,
+        remove_all,
+        remove,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let added = sqlx::query_scalar!(
+        "WITH a AS (
+             INSERT INTO collection_items (collection_id, snippet_id, position)
+             SELECT $1, x.id,
+                    COALESCE((SELECT max(position) FROM collection_items
+                               WHERE collection_id = $1), 0) + x.n::int
+               FROM unnest($2::uuid[]) WITH ORDINALITY AS x(id, n)
+             ON CONFLICT DO NOTHING
+             RETURNING snippet_id
+         )
+         SELECT s.slug FROM a JOIN snippets s ON s.id = a.snippet_id",
+        collection_id,
+        add,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let count = sqlx::query_scalar!(
+        r#"SELECT count(*) AS "count!" FROM collection_items WHERE collection_id = $1"#,
+        collection_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if count > max {
+        return Ok(None);
+    }
+    sqlx::query!("UPDATE collections SET updated_at = now() WHERE id = $1", collection_id)
+        .execute(&mut *tx)
+        .await?;
+    // Replacing re-adds whatever stays, which isn't a change either way.
+    let kept: Vec<String> = added.iter().filter(|s| removed.contains(s)).cloned().collect();
+    let mut change = ItemsChange {
+        added: added.into_iter().filter(|s| !kept.contains(s)).collect(),
+        removed: removed.into_iter().filter(|s| !kept.contains(s)).collect(),
+    };
+    let changed = sqlx::query_scalar!(
+        "SELECT id FROM snippets WHERE slug = ANY($1::text[]) OR slug = ANY($2::text[])",
+        &change.added,
+        &change.removed,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    snippets::repo::touch(&mut tx, &changed).await?;
+    tx.commit().await?;
+    change.added.sort_unstable();
+    change.removed.sort_unstable();
+    Ok(Some(change))
+}
+
+pub enum ItemsEdit<'a> {
+    Replace(&'a [Uuid]),
+    Edit { add: &'a [Uuid], remove: &'a [Uuid] },
+}
@@ -1,7 +1,8 @@
-use std::env;
+use std::{env, fmt, path::PathBuf};
 
 use anyhow::{anyhow, Context};
 use ipnetwork::IpNetwork;
+use pastedev_core::{SlugStrategy, Visibility};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -14,8 +15,15 @@ pub struct Config {
     pub session_cookie_samesite: SameSite,
     pub session_cookie_secure: bool,
     pub session_ttl_seconds: i64,
+    /// Lifetime of the cookie a correct snippet passphrase earns (see
+    /// `snippets/unlock.rs`).
+    pub snippet_unlock_ttl_secs: i64,
     pub cors_allowed_origins: Vec<String>,
     pub snippet_max_bytes: usize,
+    /// How slugs are generated when the request doesn't say, for public
+    /// snippets and for the rest. See [`Config::slug_strategy_for`].
+    pub slug_strategy: SlugStrategy,
+    pub slug_strategy_private: SlugStrategy,
     pub pastedev_secret: String,
     pub argon2_m_kib: u32,
     pub argon2_t_cost: u32,
@@ -34,6 +42,64 @@ pub struct Config {
     /// existing deploys keep working; flip to `false` on single-tenant
     /// instances to stop username-squat and audit-queue pollution.
     pub registration_open: bool,
+    /// Days a soft-deleted snippet stays in the owner's trash (restorable)
+    /// before the retention sweep hard-deletes it. `0` keeps trash forever.
+    pub trash_retention_days: u32,
+    /// Days past `expires_at` before an expired snippet is deleted for good.
+    /// Until then its owner can still open it and extend the lifetime.
+    pub expired_snippet_grace_days: u32,
+    /// Hours an `Idempotency-Key` on snippet creation is remembered (see
+    /// `snippets/idempotency.rs`). `0` ignores the header.
+    pub idempotency_key_ttl_hours: u32,
+    /// Seconds between runs of each maintenance job (see `maintenance.rs`).
+    /// `0` turns that job off.
+    pub maintenance_expired_interval_secs: u64,
+    pub maintenance_trash_interval_secs: u64,
+    pub maintenance_views_interval_secs: u64,
+    pub maintenance_sessions_interval_secs: u64,
+    pub maintenance_blobs_interval_secs: u64,
+    pub maintenance_compress_interval_secs: u64,
+    pub maintenance_idempotency_interval_secs: u64,
+    /// Where bodies too large to keep inline go (see `storage/mod.rs`).
+    pub body_store: BodyStoreConfig,
+}
+
+/// `BODY_STORE` and its backend-specific settings.
+#[derive(Debug, Clone)]
+pub enum BodyStoreConfig {
+    /// Everything stays in Postgres, as before external stores existed.
+    Postgres,
+    /// Content-addressed files under `BODY_STORE_PATH`.
+    Fs { root: PathBuf },
+    /// An S3-compatible bucket.
+    S3(S3Config),
+}
+
+#[derive(Clone)]
+pub struct S3Config {
+    /// Base URL, e.g. `https://s3.eu-west-1.amazonaws.com` or
+    /// `http://minio:9000`. Requests use path-style addressing.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Prepended to every object key, e.g. `bodies/`.
+    pub key_prefix: String,
+}
+
+// Hand-written so the secret key never ends up in logs.
+impl fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("bucket", &self.bucket)
+            .field("region", &self.region)
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &"<redacted>")
+            .field("key_prefix", &self.key_prefix)
+            .finish()
+    }
 }
 
 /// Generic "real client IP" header. The operator opts in based on what their
@@ -94,6 +160,11 @@ impl Config {
             .ok()
             .and_then(|v| v.parse::<i64>().ok())
             .unwrap_or(2_592_000);
+        let snippet_unlock_ttl_secs = env::var("SNIPPET_UNLOCK_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|&v| v > 0)
+            .unwrap_or(3_600);
 
         let cors_allowed_origins = env::var("CORS_ALLOWED_ORIGINS")
             .unwrap_or_default()
@@ -107,6 +178,16 @@ impl Config {
             .and_then(|v| v.parse::<usize>().ok())
             .unwrap_or(pastedev_core::MAX_SNIPPET_BYTES);
 
+        let strategy = |name: &str| {
+            env::var(name)
+                .ok()
+                .map(|v| v.trim().parse::<SlugStrategy>())
+                .transpose()
+                .map_err(|e| anyhow!("invalid {name}: {e}"))
+        };
+        let slug_strategy = strategy("SLUG_STRATEGY")?.unwrap_or_default();
+        let slug_strategy_private = strategy("SLUG_STRATEGY_PRIVATE")?.unwrap_or(slug_strategy);
+
         let pastedev_secret = env::var("PASTEDEV_SECRET")
             .context("PASTEDEV_SECRET is required (generate with `openssl rand -base64 48`)")?;
         if pastedev_secret.len() < 16 {
@@ -143,6 +224,71 @@ impl Config {
         let trust_cloudflare = parse_bool("TRUST_CLOUDFLARE", false)?;
         let registration_open = parse_bool("REGISTRATION_OPEN", true)?;
 
+        let trash_retention_days = env::var("TRASH_RETENTION_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(30);
+        let expired_snippet_grace_days = env::var("EXPIRED_SNIPPET_GRACE_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(7);
+        let idempotency_key_ttl_hours = env::var("IDEMPOTENCY_KEY_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(24);
+        let interval = |name: &str, default: u64| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        let maintenance_expired_interval_secs = interval("MAINTENANCE_EXPIRED_INTERVAL_SECS", 3_600);
+        let maintenance_trash_interval_secs = interval("MAINTENANCE_TRASH_INTERVAL_SECS", 3_600);
+        let maintenance_views_interval_secs = interval("MAINTENANCE_VIEWS_INTERVAL_SECS", 21_600);
+        let maintenance_sessions_interval_secs =
+            interval("MAINTENANCE_SESSIONS_INTERVAL_SECS", 3_600);
+        let maintenance_blobs_interval_secs = interval("MAINTENANCE_BLOBS_INTERVAL_SECS", 86_400);
+        let maintenance_compress_interval_secs =
+            interval("MAINTENANCE_COMPRESS_INTERVAL_SECS", 3_600);
+        let maintenance_idempotency_interval_secs =
+            interval("MAINTENANCE_IDEMPOTENCY_INTERVAL_SECS", 3_600);
+
+        let body_store = match env::var("BODY_STORE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "postgres" => BodyStoreConfig::Postgres,
+            "fs" => BodyStoreConfig::Fs {
+                root: env::var("BODY_STORE_PATH")
+                    .context("BODY_STORE=fs requires BODY_STORE_PATH")?
+                    .into(),
+            },
+            "s3" => {
+                let required = |name: &str| {
+                    env::var(name).with_context(|| format!("BODY_STORE=s3 requires {name}"))
+                };
+                BodyStoreConfig::S3(S3Config {
+                    endpoint: required("S3_ENDPOINT")?,
+                    bucket: required("S3_BUCKET")?,
+                    region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".into()),
+                    access_key_id: required("S3_ACCESS_KEY_ID")?,
+                    secret_access_key: required("S3_SECRET_ACCESS_KEY")?,
+                    key_prefix: env::var("S3_KEY_PREFIX").unwrap_or_default(),
+                })
+            }
+            other => return Err(anyhow!("invalid BODY_STORE: {other} (allowed: postgres, fs, s3)")),
+        };
+        if matches!(body_store, BodyStoreConfig::Postgres)
+            && snippet_max_bytes > pastedev_core::MAX_SNIPPET_BYTES
+        {
+            return Err(anyhow!(
+                "SNIPPET_MAX_BYTES above {} needs an external body store — set BODY_STORE=fs or s3",
+                pastedev_core::MAX_SNIPPET_BYTES
+            ));
+        }
+
         let trusted_proxies = env::var("TRUSTED_PROXIES")
             .unwrap_or_default()
             .split(',')
@@ -178,8 +324,11 @@ impl Config {
             session_cookie_samesite,
             session_cookie_secure,
             session_ttl_seconds,
+            snippet_unlock_ttl_secs,
             cors_allowed_origins,
             snippet_max_bytes,
+            slug_strategy,
+            slug_strategy_private,
             pastedev_secret,
             argon2_m_kib,
             argon2_t_cost,
@@ -187,8 +336,31 @@ impl Config {
             trust_cloudflare,
             trusted_proxies,
             registration_open,
+            trash_retention_days,
+            expired_snippet_grace_days,
+            idempotency_key_ttl_hours,
+            maintenance_expired_interval_secs,
+            maintenance_trash_interval_secs,
+            maintenance_views_interval_secs,
+            maintenance_sessions_interval_secs,
+            maintenance_blobs_interval_secs,
+            maintenance_compress_interval_secs,
+            maintenance_idempotency_interval_secs,
+            body_store,
         })
     }
+
+    /// The default strategy for a new snippet. Anything that isn't public
+    /// gets `SLUG_STRATEGY_PRIVATE`, so an instance can give those slugs
+    /// that are hard to guess without lengthening every public link.
+    pub fn slug_strategy_for(&self, visibility: Visibility) -> SlugStrategy {
+        match visibility {
+            Visibility::Public => self.slug_strategy,
+            Visibility::Private | Visibility::Password | Visibility::Team => {
+                self.slug_strategy_private
+            }
+        }
+    }
 }
 
 fn parse_bool(name: &str, default: bool) -> anyhow::Result<bool> {
@@ -24,6 +24,12 @@ pub enum AppError {
     SetupComplete,
     #[error("snippet too large: {size} > {limit}")]
     SnippetTooLarge { size: usize, limit: usize },
+    #[error("password required")]
+    PasswordRequired,
+    #[error("idempotency key was already used for a different request")]
+    IdempotencyKeyReused,
+    #[error("snippet was changed since the given ETag")]
+    PreconditionFailed,
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
     #[error(transparent)]
@@ -48,6 +54,9 @@ impl AppError {
             AppError::SetupRequired => ErrorCode::SetupRequired,
             AppError::SetupComplete => ErrorCode::SetupComplete,
             AppError::SnippetTooLarge { .. } => ErrorCode::SnippetTooLarge,
+            AppError::PasswordRequired => ErrorCode::PasswordRequired,
+            AppError::IdempotencyKeyReused => ErrorCode::IdempotencyKeyReused,
+            AppError::PreconditionFailed => ErrorCode::PreconditionFailed,
             AppError::Sqlx(_) | AppError::Other(_) => ErrorCode::Internal,
         }
     }
@@ -2,13 +2,15 @@ use std::sync::Arc;
 
 use axum::{
     body::Body,
-    extract::{Path, State},
+    extract::DefaultBodyLimit,
+    extract::{Path, Query, State},
     http::{header, HeaderName, HeaderValue, Method, Request, StatusCode},
     middleware,
-    response::{IntoResponse, Response},
+    response::{IntoResponse, Redirect, Response},
     routing::{get, patch, post},
     Json, Router,
 };
+use pastedev_core::SHARE_LINK_PARAM;
 use sqlx::PgPool;
 use tower_http::{
     cors::{AllowOrigin, CorsLayer},
@@ -19,11 +21,20 @@ use tower_http::{
 use crate::{
     api_keys::handlers as key_handlers,
     assets,
+    collections::handlers as collection_handlers,
     config::Config,
     db,
     error::AppError,
+    orgs::{admin as org_admin, handlers as org_handlers},
     setup::{self, SetupGate},
-    snippets::handlers as snippet_handlers,
+    snippets::{
+        aliases as snippet_aliases, append as snippet_append, collab as snippet_collab,
+        events as snippet_events,
+        fork as snippet_fork, handlers as snippet_handlers, links as snippet_links,
+        revisions as snippet_revisions, search as snippet_search, shares as snippet_shares,
+        tags as snippet_tags, trash as snippet_trash, unlock as snippet_unlock,
+    },
+    storage::BodyStore,
     users::{admin as user_admin, handlers as user_handlers},
 };
 
@@ -38,6 +49,9 @@ pub struct AppState {
     pub pool: PgPool,
     pub setup_gate: Arc<SetupGate>,
     pub client_ip: Arc<client_ip::ClientIpResolver>,
+    pub bodies: Arc<dyn BodyStore>,
+    pub events: snippet_events::Hub,
+    pub collab: snippet_collab::Rooms,
 }
 
 pub fn router(state: AppState) -> Router {
@@ -59,8 +73,13 @@ pub fn router(state: AppState) -> Router {
         .route("/auth/me", get(user_handlers::me))
         .with_state(state.clone());
 
-    // The 1 MB limit is also enforced in the snippet handler + DB CHECK; the
-    // tower layer rejects oversized bodies before they're buffered.
+    // SNIPPET_MAX_BYTES is also enforced in the snippet handler; the tower
+    // layer rejects oversized bodies before they're buffered. The slack on top
+    // covers the JSON envelope, including a full bundle's worth of per-file
+    // names and keys. Axum's own 2 MB extractor default is lifted so that
+    // layer is the only cap, which matters with an external body store.
+    let envelope_slack =
+        4096 + pastedev_core::MAX_BUNDLE_FILES * (pastedev_core::MAX_FILE_NAME_LEN + 64);
     let api_snippets = Router::new()
         .route(
             "/snippets",
@@ -68,6 +87,11 @@ pub fn router(state: AppState) -> Router {
                 .layer(rate_limit::for_create_snippet(&state.client_ip))
                 .get(snippet_handlers::list),
         )
+        .route(
+            "/snippets/search",
+            get(snippet_search::search).layer(rate_limit::for_search(&state.client_ip)),
+        )
+        .route("/snippets/trash", get(snippet_trash::list))
         .route(
             "/snippets/{slug}",
             get(snippet_handlers::get)
@@ -79,7 +103,60 @@ pub fn router(state: AppState) -> Router {
             "/snippets/{slug}/settings",
             patch(snippet_handlers::update_settings),
         )
-        .layer(RequestBodyLimitLayer::new(state.config.snippet_max_bytes + 4096))
+        .route(
+            "/snippets/{slug}/shares",
+            get(snippet_shares::list).patch(snippet_shares::update),
+        )
+        .route(
+            "/snippets/{slug}/links",
+            get(snippet_links::list).post(snippet_links::create),
+        )
+        .route(
+            "/snippets/{slug}/links/{id}",
+            axum::routing::delete(snippet_links::revoke),
+        )
+        .route(
+            "/snippets/{slug}/fork",
+            post(snippet_fork::fork).layer(rate_limit::for_create_snippet(&state.client_ip)),
+        )
+        .route("/snippets/{slug}/tags", patch(snippet_tags::update))
+        .route("/snippets/{slug}/append", post(snippet_append::append))
+        .route(
+            "/snippets/{slug}/events",
+            get(snippet_events::stream).layer(rate_limit::for_read_snippet(&state.client_ip)),
+        )
+        .route("/snippets/{slug}/collab", get(snippet_collab::connect))
+        .route("/snippets/{slug}/rename", post(snippet_aliases::rename))
+        .route(
+            "/snippets/{slug}/aliases",
+            get(snippet_aliases::list).post(snippet_aliases::create),
+        )
+        .route(
+            "/snippets/{slug}/aliases/{alias}",
+            axum::routing::delete(snippet_aliases::remove),
+        )
+        .route(
+            "/snippets/{slug}/unlock",
+            post(snippet_unlock::unlock).layer(rate_limit::for_unlock(&state.client_ip)),
+        )
+        .route("/snippets/{slug}/restore", post(snippet_trash::restore))
+        .route("/snippets/{slug}/purge", post(snippet_trash::purge))
+        .route(
+            "/snippets/{slug}/revisions",
+            get(snippet_revisions::list)
+                .layer(rate_limit::for_read_snippet(&state.client_ip))
+                .delete(snippet_revisions::purge),
+        )
+        .route(
+            "/snippets/{slug}/revisions/{n}",
+            get(snippet_revisions::get).layer(rate_limit::for_read_snippet(&state.client_ip)),
+        )
+        .route(
+            "/snippets/{slug}/diff",
+            get(snippet_revisions::diff).layer(rate_limit::for_read_snippet(&state.client_ip)),
+        )
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(state.config.snippet_max_bytes + envelope_slack))
         .with_state(state.clone());
 
     let api_keys = Router::new()
@@ -87,6 +164,33 @@ pub fn router(state: AppState) -> Router {
         .route("/keys/{id}", axum::routing::delete(key_handlers::revoke))
         .with_state(state.clone());
 
+    let api_orgs = Router::new()
+        .route("/orgs", post(org_handlers::create).get(org_handlers::list))
+        .route("/orgs/{name}/members", get(org_handlers::members))
+        .route(
+            "/orgs/{name}/members/{username}",
+            axum::routing::put(org_handlers::set_member).delete(org_handlers::remove_member),
+        )
+        .with_state(state.clone());
+
+    let api_collections = Router::new()
+        .route("/tags", get(snippet_tags::list))
+        .route(
+            "/collections",
+            post(collection_handlers::create).get(collection_handlers::list),
+        )
+        .route(
+            "/collections/{name}",
+            get(collection_handlers::get)
+                .patch(collection_handlers::update)
+                .delete(collection_handlers::delete),
+        )
+        .route(
+            "/collections/{name}/items",
+            patch(collection_handlers::update_items).put(collection_handlers::set_items),
+        )
+        .with_state(state.clone());
+
     let api_admin = Router::new()
         .route("/admin/users", get(user_admin::list_users))
         .route("/admin/users/{id}/approve", post(user_admin::approve))
@@ -99,6 +203,8 @@ pub fn router(state: AppState) -> Router {
             "/admin/users/{id}/reset_password",
             post(user_admin::reset_password),
         )
+        .route("/admin/orgs", get(org_admin::list_orgs))
+        .route("/admin/orgs/{name}", axum::routing::delete(org_admin::delete_org))
         .with_state(state.clone());
 
     // Health is always on; setup-gate middleware below skips it.
@@ -111,13 +217,16 @@ pub fn router(state: AppState) -> Router {
         .merge(api_auth)
         .merge(api_snippets)
         .merge(api_keys)
+        .merge(api_orgs)
+        .merge(api_collections)
         .merge(api_admin)
         .layer(middleware::from_fn_with_state(state.clone(), setup_gate_middleware))
         .layer(middleware::from_fn_with_state(state.clone(), origin_check_middleware));
 
     // CORS: only enabled when CORS_ALLOWED_ORIGINS is non-empty. Same-origin
     // deploys don't need it (browser never sends cross-site requests to the API).
-    let api = if let Some(cors) = build_cors_layer(&state.config) {
+    let cors = build_cors_layer(&state.config);
+    let api = if let Some(cors) = cors.clone() {
         api.layer(cors)
     } else {
         api
@@ -126,10 +235,17 @@ pub fn router(state: AppState) -> Router {
     let raw_routes = Router::new()
         .route("/c/{slug}/raw", get(snippet_handlers::raw_text))
         .route("/m/{slug}/raw", get(snippet_handlers::raw_text))
+        .route("/e/{slug}/raw", get(snippet_handlers::raw_encrypted))
         .route(
             "/h/{slug}/raw",
             get(snippet_handlers::raw_html).layer(rate_limit::for_html_raw(&state.client_ip)),
         )
+        .route("/c/{slug}/raw/{filename}", get(snippet_handlers::raw_file_text))
+        .route("/m/{slug}/raw/{filename}", get(snippet_handlers::raw_file_text))
+        .route(
+            "/h/{slug}/raw/{filename}",
+            get(snippet_handlers::raw_file_html).layer(rate_limit::for_html_raw(&state.client_ip)),
+        )
         .with_state(state.clone());
 
     // Per-snippet SPA shell: serves the same SPA bundle as the fallback but
@@ -140,6 +256,7 @@ pub fn router(state: AppState) -> Router {
         .route("/c/{slug}", get(serve_snippet_shell))
         .route("/m/{slug}", get(serve_snippet_shell))
         .route("/h/{slug}", get(serve_snippet_shell))
+        .route("/e/{slug}", get(serve_snippet_shell))
         .with_state(state.clone());
 
     // Top-level curl alias: `POST /paste` accepts a raw text body and returns
@@ -150,9 +267,16 @@ pub fn router(state: AppState) -> Router {
             "/paste",
             post(snippet_handlers::paste_raw).layer(rate_limit::for_create_snippet(&state.client_ip)),
         )
+        .layer(DefaultBodyLimit::disable())
         .layer(RequestBodyLimitLayer::new(state.config.snippet_max_bytes + 4096))
         .with_state(state.clone())
         .layer(middleware::from_fn_with_state(state.clone(), setup_gate_middleware));
+    // A browser client posts here with the same headers as to the API.
+    let paste_routes = if let Some(cors) = cors {
+        paste_routes.layer(cors)
+    } else {
+        paste_routes
+    };
 
     Router::new()
         .nest("/api/v1", api)
@@ -215,11 +339,34 @@ async fn serve_spa_shell(State(state): State<AppState>) -> Response {
 ///
 /// Invalid slugs, missing snippets, and DB errors fall back to the generic
 /// shell (no meta); the SPA renders its own 404 from there.
+///
+/// A snippet reached through an alias redirects to its canonical URL, share
+/// link token and all, when the caller could see its meta anyway; otherwise
+/// the shell is served as is and the SPA's API calls resolve the alias, so a
+/// redirect never confirms that a hidden snippet exists. The redirect is
+/// temporary: renaming back to an alias swaps the two.
 async fn serve_snippet_shell(
     State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
     Path(slug): Path<String>,
+    Query(link): Query<snippet_links::LinkQuery>,
 ) -> Response {
-    let meta = snippet_meta::build(&state, &slug).await;
+    let meta = snippet_meta::build(&state, &headers, &slug, link.token.as_deref()).await;
+    if let Some(meta) = meta.as_ref().filter(|m| m.slug != slug) {
+        // Tokens are base64url and '.'; anything else can't be a live one
+        // and isn't safe to echo into a header.
+        let to = match link.token.as_deref() {
+            Some(token)
+                if token
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.')) =>
+            {
+                format!("{}?{SHARE_LINK_PARAM}={token}", meta.url)
+            }
+            _ => meta.url.clone(),
+        };
+        return Redirect::temporary(&to).into_response();
+    }
     shell::render(&state.config, meta.as_ref()).into_response()
 }
 
@@ -272,15 +419,19 @@ async fn origin_check_middleware(
         return next.run(req).await;
     }
 
-    let allow_same = matches_self_origin(req.headers(), &state.config.public_base_url);
-    let allow_listed = matches_allowed_origin(req.headers(), &state.config.cors_allowed_origins);
-    if allow_same || allow_listed {
+    if origin_allowed(&state.config, req.headers()) {
         return next.run(req).await;
     }
 
     AppError::Forbidden(Some("origin not allowed")).into_response()
 }
 
+/// Whether the request's `Origin` is this deploy or an allow-listed one.
+pub(crate) fn origin_allowed(config: &Config, headers: &axum::http::HeaderMap) -> bool {
+    matches_self_origin(headers, &config.public_base_url)
+        || matches_allowed_origin(headers, &config.cors_allowed_origins)
+}
+
 fn matches_self_origin(headers: &axum::http::HeaderMap, public_base_url: &str) -> bool {
     let Some(origin) = headers.get("origin").and_then(|v| v.to_str().ok()) else {
         return false;
@@ -401,6 +552,7 @@ fn build_cors_layer(config: &Config) -> Option<CorsLayer> {
         .allow_methods([
             Method::GET,
             Method::POST,
+            Method::PUT,
             Method::PATCH,
             Method::DELETE,
             Method::OPTIONS,
@@ -408,8 +560,12 @@ fn build_cors_layer(config: &Config) -> Option<CorsLayer> {
         .allow_headers([
             header::CONTENT_TYPE,
             header::AUTHORIZATION,
+            header::IF_MATCH,
+            header::IF_NONE_MATCH,
+            HeaderName::from_static("idempotency-key"),
             HeaderName::from_static("x-request-id"),
         ])
+        .expose_headers([header::ETAG])
         .max_age(std::time::Duration::from_secs(600));
     Some(layer)
 }
@@ -69,6 +69,17 @@ pub fn for_read_snippet(r: &Arc<ClientIpResolver>) -> StdLayer {
     make(r, 500, 120)
 }
 
+pub fn for_unlock(r: &Arc<ClientIpResolver>) -> StdLayer {
+    // 10 / minute — each attempt is an Argon2 verify against a passphrase
+    // that may be weaker than an account password.
+    make(r, 6_000, 10)
+}
+
+pub fn for_search(r: &Arc<ClientIpResolver>) -> StdLayer {
+    // 60 / min — each call is a full-text scan over the caller's snippets.
+    make(r, 1_000, 60)
+}
+
 pub fn for_html_raw(r: &Arc<ClientIpResolver>) -> StdLayer {
     // 60 / min
     make(r, 1_000, 60)
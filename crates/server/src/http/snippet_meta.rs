@@ -6,11 +6,15 @@
 //! [`build`] is called from `serve_snippet_shell`; [`SnippetMeta::to_head_html`]
 //! is called from [`super::shell::render`].
 
+use axum::http::HeaderMap;
 use pastedev_core::{SnippetType, Visibility};
 use pulldown_cmark::{Event, Parser, Tag, TagEnd};
 use time::OffsetDateTime;
 
-use crate::{http::AppState, snippets::repo};
+use crate::{
+    http::AppState,
+    snippets::{links, repo, unlock},
+};
 
 /// Description previews are capped to this many characters. Telegram and
 /// most unfurlers truncate around 160–300, so 200 is a comfortable target
@@ -22,8 +26,12 @@ const DESCRIPTION_MAX_CHARS: usize = 200;
 pub struct SnippetMeta {
     pub title: String,
     pub description: Option<String>,
+    /// Canonical page URL, under the snippet's own slug even when it was
+    /// reached through an alias.
     pub url: String,
     pub site_name: String,
+    /// The canonical slug.
+    pub slug: String,
 }
 
 impl SnippetMeta {
@@ -48,7 +56,15 @@ impl SnippetMeta {
 /// Build a [`SnippetMeta`] for a given slug, or `None` if the slug is invalid
 /// or no snippet exists. DB lookup errors degrade to `None` (logged) so the
 /// shell still serves — the SPA will render its own 404 in that case.
-pub async fn build(state: &AppState, slug: &str) -> Option<SnippetMeta> {
+///
+/// `headers` are the shell request's: a password-protected snippet only gets
+/// its meta for a browser holding that snippet's unlock cookie.
+pub async fn build(
+    state: &AppState,
+    headers: &HeaderMap,
+    slug: &str,
+    link: Option<&str>,
+) -> Option<SnippetMeta> {
     if !pastedev_core::is_valid_slug(slug) {
         return None;
     }
@@ -61,38 +77,62 @@ pub async fn build(state: &AppState, slug: &str) -> Option<SnippetMeta> {
         }
     };
 
-    // Don't leak private snippet metadata to unfurlers / anonymous link
-    // previews. Don't render meta for expired snippets either — the SPA will
-    // serve its own 404 from the shell.
-    if row.visibility == Visibility::Private {
+    // Don't leak private or team snippet metadata to unfurlers / anonymous
+    // link previews — unless the URL carries a live share link, which is
+    // meant to be pasted somewhere. Don't render meta for embargoed or
+    // expired snippets either — the SPA will serve its own 404 from the shell.
+    let locked = match row.visibility {
+        Visibility::Public => false,
+        Visibility::Private | Visibility::Team => true,
+        Visibility::Password => {
+            !unlock::is_unlocked(&state.config.pastedev_secret, headers, &row)
+        }
+    };
+    if locked {
+        let linked = match link {
+            Some(token) => links::is_live(state, &row, token, None).await,
+            None => false,
+        };
+        if !linked {
+            return None;
+        }
+    }
+    let now = OffsetDateTime::now_utc();
+    if row.publish_at.is_some_and(|at| at > now) {
         return None;
     }
     if let Some(exp) = row.expires_at {
-        if exp <= OffsetDateTime::now_utc() {
+        if exp <= now {
             return None;
         }
     }
 
     let app = &state.config.app_name;
-    let kind_label = kind_label(row.kind);
+    // An encrypted snippet's type describes the plaintext, which the server
+    // never sees; don't advertise it.
+    let kind_label = if row.encrypted { "Encrypted" } else { kind_label(row.kind) };
     let title = match trimmed_nonempty(row.name.as_deref()) {
         Some(n) => format!("{n} · {kind_label} · {app}"),
         None => format!("Untitled {kind_label} · {app}"),
     };
     let description = match row.kind {
+        // The body is ciphertext; nothing readable to extract.
+        _ if row.encrypted => None,
         // Code bodies make ugly previews (long lines, punctuation soup), so
         // we deliberately skip the description for `/c/` snippets.
         SnippetType::Code => None,
         SnippetType::Markdown => Some(description_from_markdown(&row.body)).filter(|s| !s.is_empty()),
         SnippetType::Html => Some(description_from_html(&row.body)).filter(|s| !s.is_empty()),
     };
-    let url = format!("{}{}{}", state.config.public_base_url, url_prefix(row.kind), slug);
+    let prefix = if row.encrypted { "/e/" } else { url_prefix(row.kind) };
+    let url = format!("{}{}{}", state.config.public_base_url, prefix, row.slug);
 
     Some(SnippetMeta {
         title,
         description,
         url,
         site_name: app.clone(),
+        slug: row.slug,
     })
 }
 
@@ -295,6 +335,7 @@ mod tests {
             description: None,
             url: "https://x/y".into(),
             site_name: "pastedev".into(),
+            slug: "AbCdEf1".into(),
         };
         let html = m.to_head_html();
         assert!(html.contains("<title>T</title>"));
@@ -310,6 +351,7 @@ mod tests {
             description: Some("hello".into()),
             url: "u".into(),
             site_name: "s".into(),
+            slug: "AbCdEf1".into(),
         };
         let html = m.to_head_html();
         assert!(html.contains(r#"<meta property="og:description" content="hello">"#));
@@ -323,6 +365,7 @@ mod tests {
             description: Some(r#""hi""#.into()),
             url: "u".into(),
             site_name: "s".into(),
+            slug: "AbCdEf1".into(),
         };
         let html = m.to_head_html();
         assert!(html.contains("<title>&lt;x&quot;&amp;&gt;</title>"));
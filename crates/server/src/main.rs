@@ -7,12 +7,16 @@ mod api_keys;
 mod assets;
 mod audit;
 mod auth;
+mod collections;
 mod config;
 mod db;
 mod error;
 mod http;
+mod maintenance;
+mod orgs;
 mod setup;
 mod snippets;
+mod storage;
 mod users;
 
 use config::Config;
@@ -40,13 +44,25 @@ async fn main() -> anyhow::Result<()> {
         "client-ip resolver configured"
     );
 
+    let bodies = storage::from_config(&config)
+        .await
+        .context("failed to init body store")?;
+    tracing::info!(store = bodies.name(), "body store configured");
+
+    let events = snippets::events::Hub::spawn(pool.clone());
+
     let state = http::AppState {
         config: Arc::new(config.clone()),
         pool,
         setup_gate: setup::shared_gate(),
         client_ip,
+        bodies,
+        events,
+        collab: snippets::collab::Rooms::default(),
     };
 
+    maintenance::spawn(state.pool.clone(), state.config.clone(), state.bodies.clone());
+
     let app = http::router(state.clone());
 
     let listener = tokio::net::TcpListener::bind(&config.bind_addr)
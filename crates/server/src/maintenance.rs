@@ -0,0 +1,271 @@
+//! In-process scheduler for periodic cleanup.
+//!
+//! Each [`Job`] runs on its own interval (`MAINTENANCE_*_INTERVAL_SECS`, `0`
+//! disables it). Every replica schedules every job, but a run first takes a
+//! session-level Postgres advisory lock keyed on the job name and skips the
+//! round if another instance holds it, so a job never runs twice at once.
+//! Deletes go out in bounded batches to keep each statement's lock footprint
+//! small.
+//!
+//! Every run that got the lock writes one `maintenance.run` audit row with
+//! its outcome. Snippets that are hard-deleted additionally get a
+//! `snippet.purge` row each, like an owner-initiated purge.
+
+use std::{sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::{
+    audit,
+    auth::session,
+    config::Config,
+    snippets::repo,
+    storage::{self, BodyStore},
+};
+
+/// Rows removed per statement.
+const BATCH: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Job {
+    /// Hard-delete snippets past `expires_at` + `EXPIRED_SNIPPET_GRACE_DAYS`.
+    ExpiredSnippets,
+    /// Hard-delete trash older than `TRASH_RETENTION_DAYS`.
+    Trash,
+    /// Drop `snippet_views` rows of trashed or expired snippets.
+    DeadViews,
+    /// Delete expired `sessions` rows.
+    ExpiredSessions,
+    /// Delete stored bodies no row refers to any more.
+    OrphanBlobs,
+    /// Settle the codec of bodies written before compression (migration 0013).
+    CompressBodies,
+    /// Delete `idempotency_keys` rows past `IDEMPOTENCY_KEY_TTL_HOURS`.
+    IdempotencyKeys,
+}
+
+impl Job {
+    const ALL: [Job; 7] = [
+        Job::ExpiredSnippets,
+        Job::Trash,
+        Job::DeadViews,
+        Job::ExpiredSessions,
+        Job::OrphanBlobs,
+        Job::CompressBodies,
+        Job::IdempotencyKeys,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Job::ExpiredSnippets => "expired_snippets",
+            Job::Trash => "trash",
+            Job::DeadViews => "dead_views",
+            Job::ExpiredSessions => "expired_sessions",
+            Job::OrphanBlobs => "orphan_blobs",
+            Job::CompressBodies => "compress_bodies",
+            Job::IdempotencyKeys => "idempotency_keys",
+        }
+    }
+
+    /// Rows per statement. Compressing holds whole bodies in memory, so that
+    /// job takes small bites.
+    fn batch(self) -> i64 {
+        match self {
+            Job::CompressBodies => 20,
+            _ => BATCH,
+        }
+    }
+
+    /// Advisory-lock key, hashed with `hashtext` the same way as setup's
+    /// `pastedev:setup_admin` lock.
+    fn lock_key(self) -> String {
+        format!("pastedev:maintenance:{}", self.name())
+    }
+
+    /// Seconds between runs; `None` when the job is switched off.
+    fn interval(self, config: &Config, bodies: &dyn BodyStore) -> Option<Duration> {
+        let secs = match self {
+            Job::ExpiredSnippets => config.maintenance_expired_interval_secs,
+            // Nothing ever falls out of an unbounded trash.
+            Job::Trash if config.trash_retention_days == 0 => 0,
+            Job::Trash => config.maintenance_trash_interval_secs,
+            Job::DeadViews => config.maintenance_views_interval_secs,
+            Job::ExpiredSessions => config.maintenance_sessions_interval_secs,
+            // The Postgres store has no objects of its own to collect.
+            Job::OrphanBlobs if !bodies.is_external() => 0,
+            Job::OrphanBlobs => config.maintenance_blobs_interval_secs,
+            Job::CompressBodies => config.maintenance_compress_interval_secs,
+            // No keys are stored when the header is ignored.
+            Job::IdempotencyKeys if config.idempotency_key_ttl_hours == 0 => 0,
+            Job::IdempotencyKeys => config.maintenance_idempotency_interval_secs,
+        };
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+}
+
+/// Start one background task per enabled job. The first run of each happens
+/// right away, then every interval after.
+pub fn spawn(pool: PgPool, config: Arc<Config>, bodies: Arc<dyn BodyStore>) {
+    for job in Job::ALL {
+        let Some(every) = job.interval(&config, bodies.as_ref()) else {
+            tracing::info!(job = job.name(), "maintenance job disabled");
+            continue;
+        };
+        let pool = pool.clone();
+        let config = config.clone();
+        let bodies = bodies.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(every);
+            tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tick.tick().await;
+                run_locked(&pool, &config, bodies.as_ref(), job).await;
+            }
+        });
+    }
+}
+
+/// Run `job` once if no other instance is running it, then audit the run.
+async fn run_locked(pool: &PgPool, config: &Config, bodies: &dyn BodyStore, job: Job) {
+    let key = job.lock_key();
+    // A session-level lock belongs to one connection, so hold on to it for the
+    // whole run; the job's own statements use other pool connections.
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::warn!(job = job.name(), error = ?e, "maintenance: no connection");
+            return;
+        }
+    };
+    let locked = sqlx::query_scalar!(
+        r#"SELECT pg_try_advisory_lock(hashtext($1)) AS "locked!""#,
+        key,
+    )
+    .fetch_one(&mut *conn)
+    .await;
+    match locked {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::debug!(job = job.name(), "maintenance: held by another instance");
+            return;
+        }
+        Err(e) => {
+            tracing::warn!(job = job.name(), error = ?e, "maintenance: lock failed");
+            return;
+        }
+    }
+
+    let started = Instant::now();
+    let result = run(pool, config, bodies, job).await;
+    let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    let unlocked = sqlx::query_scalar!("SELECT pg_advisory_unlock(hashtext($1))", key)
+        .fetch_one(&mut *conn)
+        .await;
+    if unlocked.is_err() {
+        // Never hand a connection that may still hold the lock back to the
+        // pool; closing it releases the lock server-side.
+        let _ = conn.close().await;
+    }
+
+    let payload = match &result {
+        Ok(affected) => {
+            if *affected > 0 {
+                tracing::info!(job = job.name(), affected, duration_ms, "maintenance run");
+            }
+            serde_json::json!({
+                "job": job.name(),
+                "affected": affected,
+                "duration_ms": duration_ms,
+            })
+        }
+        Err(e) => {
+            tracing::warn!(job = job.name(), error = ?e, "maintenance run failed");
+            serde_json::json!({
+                "job": job.name(),
+                "error": e.to_string(),
+                "duration_ms": duration_ms,
+            })
+        }
+    };
+    audit::write(
+        pool,
+        audit::Event {
+            event: "maintenance.run",
+            payload: Some(payload),
+            ..Default::default()
+        },
+    )
+    .await;
+}
+
+/// Do the work, batch by batch until a short batch says there's nothing
+/// left. Returns the number of rows (or stored bodies) removed or, for
+/// `CompressBodies`, settled.
+async fn run(
+    pool: &PgPool,
+    config: &Config,
+    bodies: &dyn BodyStore,
+    job: Job,
+) -> Result<u64, sqlx::Error> {
+    let batch = job.batch();
+    let mut total = 0u64;
+    loop {
+        let n = match job {
+            Job::ExpiredSnippets => {
+                let grace = config.expired_snippet_grace_days;
+                let rows = repo::purge_expired(pool, grace, batch).await?;
+                audit_purged(pool, &rows, "expired", ("grace_days", grace)).await;
+                rows.len() as u64
+            }
+            Job::Trash => {
+                let days = config.trash_retention_days;
+                let rows = repo::purge_expired_trash(pool, days, batch).await?;
+                audit_purged(pool, &rows, "retention", ("retention_days", days)).await;
+                rows.len() as u64
+            }
+            Job::DeadViews => repo::prune_dead_views(pool, batch).await?,
+            Job::ExpiredSessions => session::purge_expired(pool, batch).await?,
+            Job::OrphanBlobs => storage::collect_orphans(bodies, pool, batch).await?,
+            Job::CompressBodies => storage::compress_backlog(pool, batch).await?,
+            Job::IdempotencyKeys => {
+                let hours = config.idempotency_key_ttl_hours;
+                repo::purge_idempotency_keys(pool, hours, batch).await?
+            }
+        };
+        total += n;
+        if (n as i64) < batch {
+            return Ok(total);
+        }
+    }
+}
+
+/// One `snippet.purge` row per hard-deleted snippet. The snippet row is gone,
+/// so its id goes in the payload; the owner is the target user. `setting` is
+/// the config knob that made the row eligible, recorded with its value.
+async fn audit_purged(
+    pool: &PgPool,
+    rows: &[repo::PurgedRow],
+    kind: &str,
+    setting: (&str, u32),
+) {
+    for row in rows {
+        let mut payload = serde_json::json!({
+            "kind": kind,
+            "snippet_id": row.id,
+            "slug": row.slug,
+        });
+        payload[setting.0] = setting.1.into();
+        audit::write(
+            pool,
+            audit::Event {
+                event: "snippet.purge",
+                target_user_id: Some(row.owner_id),
+                payload: Some(payload),
+                ..Default::default()
+            },
+        )
+        .await;
+    }
+}
@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    audit,
+    auth::extract::AdminUser,
+    error::AppError,
+    http::AppState,
+    orgs::{handlers::normalize_org_name, repo},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminOrgView {
+    pub name: String,
+    pub created_by: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub members: i64,
+    pub snippets: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListResponse {
+    pub items: Vec<AdminOrgView>,
+}
+
+/// `GET /api/v1/admin/orgs`
+pub async fn list_orgs(
+    AdminUser(_): AdminUser,
+    State(state): State<AppState>,
+    Query(q): Query<ListQuery>,
+) -> Result<Json<ListResponse>, AppError> {
+    let limit = q.limit.unwrap_or(200).clamp(1, 500);
+    let rows = repo::list_all(&state.pool, limit).await?;
+    Ok(Json(ListResponse {
+        items: rows
+            .into_iter()
+            .map(|r| AdminOrgView {
+                name: r.name,
+                created_by: r.created_by,
+                created_at: r.created_at,
+                members: r.members,
+                snippets: r.snippets,
+            })
+            .collect(),
+    }))
+}
+
+/// `DELETE /api/v1/admin/orgs/:name` — refuses while the org still owns
+/// snippets, trashed ones included.
+pub async fn delete_org(
+    AdminUser(actor): AdminUser,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let name = normalize_org_name(&name)?;
+    let org = repo::by_name(&state.pool, &name)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if !repo::delete_if_empty(&state.pool, org.id).await? {
+        return Err(AppError::Conflict("org still owns snippets"));
+    }
+    audit::write(
+        &state.pool,
+        audit::Event {
+            event: "org.delete",
+            actor_user_id: Some(actor.id),
+            payload: Some(serde_json::json!({ "org": org.name })),
+            ..Default::default()
+        },
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
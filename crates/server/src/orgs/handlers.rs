@@ -0,0 +1,243 @@
+//! Org creation and member management for org members themselves. Site
+//! admins get the cross-org view in [`super::admin`].
+//!
+//! Non-members can't tell an org exists: every lookup by name that fails the
+//! membership check is a 404, like an unreadable snippet.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use pastedev_core::{
+    CreateOrgRequest, ListOrgMembersResponse, ListOrgsResponse, Org, OrgMember, OrgRole,
+    SetOrgMemberRequest,
+};
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::extract::{AuthUser, SessionUser},
+    error::AppError,
+    http::AppState,
+    orgs::repo::{self, OrgRow},
+    users::{self, validate::normalize_username},
+};
+
+/// Org names follow the username rules, so `--org acme` and a user called
+/// `acme` read the same way in URLs and CLI flags.
+pub fn normalize_org_name(raw: &str) -> Result<String, AppError> {
+    normalize_username(raw)
+        .map_err(|_| AppError::Validation("org name must match [a-z0-9_.-]{3,40}".into()))
+}
+
+/// Resolve `name` to an org `user_id` belongs to, with their role in it.
+pub async fn membership(
+    state: &AppState,
+    name: &str,
+    user_id: Uuid,
+) -> Result<(OrgRow, OrgRole), AppError> {
+    let name = normalize_org_name(name)?;
+    let org = repo::by_name(&state.pool, &name)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let role = repo::role_of(&state.pool, org.id, user_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok((org, role))
+}
+
+async fn audit_member(
+    state: &AppState,
+    actor: Uuid,
+    target: Uuid,
+    event: &str,
+    payload: serde_json::Value,
+) {
+    audit::write(
+        &state.pool,
+        audit::Event {
+            event,
+            actor_user_id: Some(actor),
+            target_user_id: Some(target),
+            payload: Some(payload),
+            ..Default::default()
+        },
+    )
+    .await;
+}
+
+async fn members_response(state: &AppState, org_id: Uuid) -> Result<ListOrgMembersResponse, AppError> {
+    let rows = repo::members(&state.pool, org_id).await?;
+    Ok(ListOrgMembersResponse {
+        items: rows
+            .into_iter()
+            .map(|m| OrgMember {
+                username: m.username,
+                role: m.role,
+                created_at: m.created_at,
+            })
+            .collect(),
+    })
+}
+
+/// Refuse a change that would leave the org without an owner.
+async fn ensure_last_owner_invariant(
+    state: &AppState,
+    org_id: Uuid,
+    current: Option<OrgRole>,
+    next: Option<OrgRole>,
+) -> Result<(), AppError> {
+    if current != Some(OrgRole::Owner) || next == Some(OrgRole::Owner) {
+        return Ok(());
+    }
+    if repo::count_owners(&state.pool, org_id).await? <= 1 {
+        return Err(AppError::Conflict("last owner"));
+    }
+    Ok(())
+}
+
+/// `POST /api/v1/orgs` — session auth only; the creator becomes its owner.
+pub async fn create(
+    SessionUser(user): SessionUser,
+    State(state): State<AppState>,
+    Json(req): Json<CreateOrgRequest>,
+) -> Result<(StatusCode, Json<Org>), AppError> {
+    let name = normalize_org_name(&req.name)?;
+    if repo::by_name(&state.pool, &name).await?.is_some() {
+        return Err(AppError::Conflict("org name already taken"));
+    }
+    let org = repo::insert(&state.pool, &name, user.id).await?;
+    audit::write(
+        &state.pool,
+        audit::Event {
+            event: "org.create",
+            actor_user_id: Some(user.id),
+            payload: Some(serde_json::json!({ "org": org.name })),
+            ..Default::default()
+        },
+    )
+    .await;
+    Ok((
+        StatusCode::CREATED,
+        Json(Org {
+            name: org.name,
+            role: OrgRole::Owner,
+            created_at: org.created_at,
+        }),
+    ))
+}
+
+/// `GET /api/v1/orgs` — the caller's orgs. An org-scoped key sees only its own.
+pub async fn list(
+    AuthUser(user): AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<ListOrgsResponse>, AppError> {
+    let rows = repo::list_for_user(&state.pool, user.id).await?;
+    Ok(Json(ListOrgsResponse {
+        items: rows
+            .into_iter()
+            .filter(|(org, _)| user.key_org_id.is_none_or(|id| id == org.id))
+            .map(|(org, role)| Org {
+                name: org.name,
+                role,
+                created_at: org.created_at,
+            })
+            .collect(),
+    }))
+}
+
+/// `GET /api/v1/orgs/:name/members` — members only.
+pub async fn members(
+    AuthUser(user): AuthUser,
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<ListOrgMembersResponse>, AppError> {
+    let (org, _) = membership(&state, &name, user.id).await?;
+    if user.key_org_id.is_some_and(|id| id != org.id) {
+        return Err(AppError::NotFound);
+    }
+    Ok(Json(members_response(&state, org.id).await?))
+}
+
+/// `PUT /api/v1/orgs/:name/members/:username` — add a member or change their
+/// role. Owners may set any role; maintainers may only add plain members.
+pub async fn set_member(
+    SessionUser(user): SessionUser,
+    State(state): State<AppState>,
+    Path((name, username)): Path<(String, String)>,
+    Json(req): Json<SetOrgMemberRequest>,
+) -> Result<Json<ListOrgMembersResponse>, AppError> {
+    let (org, actor_role) = membership(&state, &name, user.id).await?;
+    let username = normalize_username(&username)?;
+    let target = users::repo::by_username(&state.pool, &username)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let current = repo::role_of(&state.pool, org.id, target.id).await?;
+
+    let allowed = match actor_role {
+        OrgRole::Owner => true,
+        OrgRole::Maintainer => {
+            req.role == OrgRole::Member && current.is_none_or(|r| r == OrgRole::Member)
+        }
+        OrgRole::Member => false,
+    };
+    if !allowed {
+        return Err(AppError::Forbidden(Some("insufficient org role")));
+    }
+    if current == Some(req.role) {
+        return Ok(Json(members_response(&state, org.id).await?));
+    }
+    ensure_last_owner_invariant(&state, org.id, current, Some(req.role)).await?;
+    repo::set_member(&state.pool, org.id, target.id, req.role).await?;
+    audit_member(
+        &state,
+        user.id,
+        target.id,
+        "org.member.set",
+        serde_json::json!({
+            "org": org.name,
+            "old": current.map(OrgRole::as_str),
+            "new": req.role.as_str(),
+        }),
+    )
+    .await;
+    Ok(Json(members_response(&state, org.id).await?))
+}
+
+/// `DELETE /api/v1/orgs/:name/members/:username` — owners remove anyone,
+/// maintainers remove plain members, and anyone may leave.
+pub async fn remove_member(
+    SessionUser(user): SessionUser,
+    State(state): State<AppState>,
+    Path((name, username)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    let (org, actor_role) = membership(&state, &name, user.id).await?;
+    let username = normalize_username(&username)?;
+    let target = users::repo::by_username(&state.pool, &username)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let current = repo::role_of(&state.pool, org.id, target.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let allowed = target.id == user.id
+        || actor_role == OrgRole::Owner
+        || (actor_role == OrgRole::Maintainer && current == OrgRole::Member);
+    if !allowed {
+        return Err(AppError::Forbidden(Some("insufficient org role")));
+    }
+    ensure_last_owner_invariant(&state, org.id, Some(current), None).await?;
+    if !repo::remove_member(&state.pool, org.id, target.id).await? {
+        return Err(AppError::NotFound);
+    }
+    audit_member(
+        &state,
+        user.id,
+        target.id,
+        "org.member.remove",
+        serde_json::json!({ "org": org.name, "old": current.as_str() }),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,3 @@
+pub mod admin;
+pub mod handlers;
+pub mod repo;
@@ -0,0 +1,214 @@
+use pastedev_core::OrgRole;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct OrgRow {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberRow {
+    pub username: String,
+    pub role: OrgRole,
+    pub created_at: OffsetDateTime,
+}
+
+/// Raw-row destination for `query_as!`; `role` is a CHECK-constrained
+/// `varchar` converted after fetch, like `users.role`.
+struct MemberRowRaw {
+    username: String,
+    role: String,
+    created_at: OffsetDateTime,
+}
+
+fn map_member(r: MemberRowRaw) -> Option<MemberRow> {
+    Some(MemberRow {
+        username: r.username,
+        role: r.role.parse().ok()?,
+        created_at: r.created_at,
+    })
+}
+
+/// Create an org with `owner_id` as its first owner, in one transaction.
+pub async fn insert(pool: &PgPool, name: &str, owner_id: Uuid) -> Result<OrgRow, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let org = sqlx::query_as!(
+        OrgRow,
+        "INSERT INTO orgs (name, created_by) VALUES ($1, $2)
+         RETURNING id, name, created_at",
+        name,
+        owner_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO org_members (org_id, user_id, role) VALUES ($1, $2, 'owner')",
+        org.id,
+        owner_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(org)
+}
+
+pub async fn by_name(pool: &PgPool, name: &str) -> Result<Option<OrgRow>, sqlx::Error> {
+    sqlx::query_as!(
+        OrgRow,
+        "SELECT id, name, created_at FROM orgs WHERE name = $1",
+        name,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The user's role in the org, `None` if they aren't a member.
+pub async fn role_of(
+    pool: &PgPool,
+    org_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<OrgRole>, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT role FROM org_members WHERE org_id = $1 AND user_id = $2",
+        org_id,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(|r| r.role.parse().ok()))
+}
+
+/// Every org the user belongs to, with their role, by name.
+pub async fn list_for_user(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<(OrgRow, OrgRole)>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT o.id, o.name, o.created_at, m.role
+           FROM org_members m
+           JOIN orgs o ON o.id = m.org_id
+          WHERE m.user_id = $1
+          ORDER BY o.name",
+        user_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            let role = r.role.parse().ok()?;
+            Some((
+                OrgRow {
+                    id: r.id,
+                    name: r.name,
+                    created_at: r.created_at,
+                },
+                role,
+            ))
+        })
+        .collect())
+}
+
+/// Owners first, then by username.
+pub async fn members(pool: &PgPool, org_id: Uuid) -> Result<Vec<MemberRow>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        MemberRowRaw,
+        "SELECT u.username, m.role, m.created_at
+           FROM org_members m
+           JOIN users u ON u.id = m.user_id
+          WHERE m.org_id = $1
+          ORDER BY CASE m.role WHEN 'owner' THEN 0 WHEN 'maintainer' THEN 1 ELSE 2 END,
+                   u.username",
+        org_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().filter_map(map_member).collect())
+}
+
+/// Add a member or change their role.
+pub async fn set_member(
+    pool: &PgPool,
+    org_id: Uuid,
+    user_id: Uuid,
+    role: OrgRole,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO org_members (org_id, user_id, role) VALUES ($1, $2, $3)
+         ON CONFLICT (org_id, user_id) DO UPDATE SET role = EXCLUDED.role",
+        org_id,
+        user_id,
+        role.as_str(),
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns `true` iff a membership row was removed.
+pub async fn remove_member(pool: &PgPool, org_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM org_members WHERE org_id = $1 AND user_id = $2",
+        org_id,
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Used to guard against demoting or removing an org's last owner.
+pub async fn count_owners(pool: &PgPool, org_id: Uuid) -> Result<i64, sqlx::Error> {
+    let r = sqlx::query!(
+        "SELECT count(*) AS \"n!\" FROM org_members WHERE org_id = $1 AND role = 'owner'",
+        org_id,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(r.n)
+}
+
+/// One org in the admin listing.
+#[derive(Debug, Clone)]
+pub struct AdminOrgRow {
+    pub name: String,
+    pub created_by: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub members: i64,
+    pub snippets: i64,
+}
+
+/// Every org, newest first, with member and snippet counts (trash included).
+pub async fn list_all(pool: &PgPool, limit: i64) -> Result<Vec<AdminOrgRow>, sqlx::Error> {
+    sqlx::query_as!(
+        AdminOrgRow,
+        r#"SELECT o.name, u.username AS "created_by?", o.created_at,
+                  (SELECT count(*) FROM org_members m WHERE m.org_id = o.id) AS "members!",
+                  (SELECT count(*) FROM snippets s WHERE s.org_id = o.id) AS "snippets!"
+           FROM orgs o
+           LEFT JOIN users u ON u.id = o.created_by
+           ORDER BY o.created_at DESC
+           LIMIT $1"#,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete an org that owns no snippets. `Ok(false)` if it still does (or is
+/// already gone); memberships and org-scoped keys go with it.
+pub async fn delete_if_empty(pool: &PgPool, org_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM orgs o
+          WHERE o.id = $1
+            AND NOT EXISTS (SELECT 1 FROM snippets s WHERE s.org_id = o.id)",
+        org_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
@@ -0,0 +1,167 @@
+//! Custom slugs and slug aliases (migration 0022).
+//!
+//! A snippet has one canonical slug, generated or owner-chosen, and any
+//! number of aliases that lead to it. Renaming on
+//! `POST /api/v1/snippets/:slug/rename` keeps the slug it had before as an
+//! alias, so links to it keep working; more can be added and removed on
+//! `/api/v1/snippets/:slug/aliases`. [`repo::by_slug`] resolves aliases for
+//! every route, and the page shell redirects them to the canonical URL.
+//!
+//! Owners only ever claim custom slugs ([`pastedev_core::is_claimable_slug`]);
+//! anything the generator could produce belongs to it. A removed alias is
+//! retired like a purged snippet's slug and never points anywhere again.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use pastedev_core::{
+    CreateAliasRequest, ListAliasesResponse, OrgRole, RenameRequest, Snippet, SnippetAlias,
+    SnippetEvent,
+};
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, AuthedUser, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        events,
+        handlers::{check_claimable_slug, ensure_can_manage, load_dto, validate_slug},
+        repo::{self, AliasRow, SnippetRow},
+        slug::map_taken,
+    },
+};
+
+/// Aliases per snippet. A rename adds one too, so it is refused at the cap
+/// unless it renames back to an existing alias.
+const MAX_ALIASES: usize = 20;
+
+async fn load_managed(
+    state: &AppState,
+    slug: &str,
+    user: &AuthedUser,
+) -> Result<SnippetRow, AppError> {
+    validate_slug(slug)?;
+    let row = repo::by_slug(&state.pool, slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(state, user, &row, OrgRole::Member).await?;
+    Ok(row)
+}
+
+fn to_dto(row: AliasRow) -> SnippetAlias {
+    SnippetAlias {
+        slug: row.slug,
+        created_at: row.created_at,
+    }
+}
+
+/// `POST /api/v1/snippets/:slug/rename` — move the snippet to a new slug.
+/// The new one must be claimable unless it is already one of the snippet's
+/// aliases, which renames back.
+pub async fn rename(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(req): Json<RenameRequest>,
+) -> Result<Json<Snippet>, AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    if req.slug == row.slug {
+        return Ok(Json(load_dto(&state, &row).await?));
+    }
+    let aliases = repo::aliases(&state.pool, row.id).await?;
+    let renaming_back = aliases.iter().any(|a| a.slug == req.slug);
+    if !renaming_back {
+        check_claimable_slug(&req.slug)?;
+        if aliases.len() >= MAX_ALIASES {
+            return Err(AppError::Validation(format!(
+                "a snippet can have at most {MAX_ALIASES} aliases; remove one first"
+            )));
+        }
+    }
+    let updated = repo::rename(&state.pool, row.id, &req.slug)
+        .await
+        .map_err(map_taken)?
+        .ok_or(AppError::NotFound)?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.rename",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({ "from": row.slug, "to": updated.slug })),
+            ..Default::default()
+        },
+    );
+    events::spawn_notify(state.pool.clone(), row.id, SnippetEvent::Settings);
+    Ok(Json(load_dto(&state, &updated).await?))
+}
+
+/// `GET /api/v1/snippets/:slug/aliases`
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<ListAliasesResponse>, AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    let aliases = repo::aliases(&state.pool, row.id).await?;
+    Ok(Json(ListAliasesResponse {
+        items: aliases.into_iter().map(to_dto).collect(),
+    }))
+}
+
+/// `POST /api/v1/snippets/:slug/aliases`
+pub async fn create(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(req): Json<CreateAliasRequest>,
+) -> Result<(StatusCode, Json<SnippetAlias>), AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    check_claimable_slug(&req.slug)?;
+    if repo::aliases(&state.pool, row.id).await?.len() >= MAX_ALIASES {
+        return Err(AppError::Validation(format!(
+            "a snippet can have at most {MAX_ALIASES} aliases"
+        )));
+    }
+    let alias = repo::insert_alias(&state.pool, row.id, &req.slug)
+        .await
+        .map_err(map_taken)?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.alias.create",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({ "alias": alias.slug })),
+            ..Default::default()
+        },
+    );
+    Ok((StatusCode::CREATED, Json(to_dto(alias))))
+}
+
+/// `DELETE /api/v1/snippets/:slug/aliases/:alias`
+pub async fn remove(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path((slug, alias)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    validate_slug(&alias)?;
+    if !repo::remove_alias(&state.pool, row.id, &alias).await? {
+        return Err(AppError::NotFound);
+    }
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.alias.remove",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({ "alias": alias })),
+            ..Default::default()
+        },
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
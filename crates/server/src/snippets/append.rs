@@ -0,0 +1,114 @@
+//! Appending to a snippet's body.
+//!
+//! `POST /api/v1/snippets/:slug/append` takes a `text/plain` request body and
+//! adds it to the end of the snippet's, under a row lock, so concurrent
+//! appends land one after the other instead of overwriting each other. It is
+//! what `pastedev-cli tee` streams a log through, and followers of the
+//! snippet's event stream get the text as an `append` event.
+//!
+//! An append bumps `updated_at` (and so the ETag) like any edit, but it
+//! records an audit event rather than a revision: a revision snapshots the
+//! whole body, and a log fed in one-second batches would otherwise store its
+//! own history many times over. A `PATCH` afterwards still starts a revision
+//! from whatever was appended.
+//!
+//! Bundles and encrypted snippets are refused: the parent body of a bundle
+//! only mirrors its first file, and plaintext appended to a ciphertext
+//! envelope would break it.
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use pastedev_core::{AppendResponse, OrgRole, SnippetEvent};
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        etag, events,
+        handlers::{ensure_can_manage, validate_slug},
+        repo,
+    },
+    storage::{self, Codec},
+};
+
+/// `POST /api/v1/snippets/:slug/append`
+pub async fn append(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    text: String,
+) -> Result<Response, AppError> {
+    validate_slug(&slug)?;
+    let existing = repo::by_slug(&state.pool, &slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(&state, &user.0, &existing, OrgRole::Member).await?;
+    if text.is_empty() {
+        return Err(AppError::Validation("nothing to append".into()));
+    }
+    if existing.encrypted {
+        return Err(AppError::Validation(
+            "encrypted snippets can't be appended to".into(),
+        ));
+    }
+    if repo::is_bundle(&state.pool, existing.id).await? {
+        return Err(AppError::Validation(
+            "multi-file snippets can't be appended to".into(),
+        ));
+    }
+    let limit = state.config.snippet_max_bytes;
+
+    let mut tx = state.pool.begin().await?;
+    let (inline, location) = repo::lock_body(&mut tx, existing.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let mut body = storage::load(state.bodies.as_ref(), inline, location).await?;
+    let offset = body.len();
+    let size = offset + text.len();
+    if size > limit {
+        return Err(AppError::SnippetTooLarge { size, limit });
+    }
+    body.push_str(&text);
+    let stored = storage::store(state.bodies.as_ref(), &state.pool, &body).await?;
+    repo::replace_body(&mut tx, existing.id, &stored).await?;
+    let event = SnippetEvent::Append {
+        offset: i32::try_from(offset).unwrap_or(i32::MAX),
+        size_bytes: i32::try_from(size).unwrap_or(i32::MAX),
+        text: Some(text.clone()),
+    };
+    events::notify(&mut *tx, existing.id, event).await?;
+    tx.commit().await?;
+
+    let updated = repo::by_id(&state.pool, existing.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.append",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(updated.id),
+            payload: Some(serde_json::json!({
+                "appended_bytes": text.len(),
+                "new_size_bytes": updated.size_bytes,
+                "new_stored_bytes": updated.stored_bytes,
+            })),
+            ..Default::default()
+        },
+    );
+    let tag = etag::etag(&updated, Codec::Identity);
+    let mut response = Json(AppendResponse {
+        slug: updated.slug,
+        appended_bytes: i32::try_from(text.len()).unwrap_or(i32::MAX),
+        size_bytes: updated.size_bytes,
+        updated_at: updated.updated_at,
+    })
+    .into_response();
+    etag::tag(&mut response, &tag);
+    Ok(response)
+}
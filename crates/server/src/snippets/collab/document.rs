@@ -0,0 +1,158 @@
+//! The server's copy of a text being edited, with the recent history that
+//! late ops are transformed against.
+
+use std::collections::VecDeque;
+
+use pastedev_core::{Selection, TextOp};
+
+/// Revisions kept for transforming ops made against an older one. A client
+/// further behind than this is disconnected and starts over.
+const HISTORY: usize = 1_000;
+
+/// Why an op or cursor from a client was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejected {
+    /// The revision it was made against is in the future or no longer kept.
+    Revision,
+    /// It doesn't fit the text it claims to edit.
+    Mismatch,
+    /// The text would grow past the snippet size limit.
+    TooLarge { size: usize, limit: usize },
+}
+
+pub struct Document {
+    text: String,
+    /// `text` in chars, which is what ops count in.
+    len: usize,
+    revision: u64,
+    /// The ops that made the last `history.len()` revisions, oldest first.
+    history: VecDeque<TextOp>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let len = text.chars().count();
+        Self {
+            text,
+            len,
+            revision: 0,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// `op`, made against `revision`, transformed to apply to the current
+    /// text.
+    pub fn rebase(&self, revision: u64, mut op: TextOp) -> Result<TextOp, Rejected> {
+        for done in self.since(revision)? {
+            op = TextOp::transform(&op, done).ok_or(Rejected::Mismatch)?.0;
+        }
+        if op.base_len() != self.len {
+            return Err(Rejected::Mismatch);
+        }
+        Ok(op)
+    }
+
+    /// Apply `op` to the current text as the next revision, unless the
+    /// result is over `limit` bytes.
+    pub fn apply(&mut self, op: TextOp, limit: usize) -> Result<u64, Rejected> {
+        let text = op.apply(&self.text).ok_or(Rejected::Mismatch)?;
+        if text.len() > limit {
+            return Err(Rejected::TooLarge {
+                size: text.len(),
+                limit,
+            });
+        }
+        self.text = text;
+        self.len = op.target_len();
+        self.history.push_back(op);
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+        self.revision += 1;
+        Ok(self.revision)
+    }
+
+    /// `selection`, made against `revision`, moved into the current text.
+    pub fn selection(&self, revision: u64, selection: Selection) -> Result<Selection, Rejected> {
+        let moved = self
+            .since(revision)?
+            .fold(selection, |s, done| s.transform(done));
+        if moved.anchor > self.len || moved.head > self.len {
+            return Err(Rejected::Mismatch);
+        }
+        Ok(moved)
+    }
+
+    /// The ops applied after `revision`.
+    fn since(&self, revision: u64) -> Result<impl Iterator<Item = &TextOp>, Rejected> {
+        let first = self.revision - self.history.len() as u64;
+        if revision < first || revision > self.revision {
+            return Err(Rejected::Revision);
+        }
+        Ok(self.history.iter().skip((revision - first) as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(json: &str) -> TextOp {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn concurrent_ops_are_rebased() {
+        let mut doc = Document::new("hello world".into());
+        // Two clients edit revision 0 at once; the second is rebased past
+        // the first.
+        let first = doc.rebase(0, op(r#"[5, ",", 6]"#)).unwrap();
+        assert_eq!(doc.apply(first, 100), Ok(1));
+        let second = doc.rebase(0, op(r#"[6, -5, "there"]"#)).unwrap();
+        assert_eq!(doc.apply(second, 100), Ok(2));
+        assert_eq!(doc.text(), "hello, there");
+
+        let cursor = Selection {
+            anchor: 11,
+            head: 11,
+        };
+        assert_eq!(
+            doc.selection(0, cursor),
+            Ok(Selection {
+                anchor: 12,
+                head: 12
+            })
+        );
+        assert_eq!(doc.rebase(3, op("[12]")).unwrap_err(), Rejected::Revision);
+        assert_eq!(doc.rebase(2, op("[3]")).unwrap_err(), Rejected::Mismatch);
+    }
+
+    #[test]
+    fn size_limit_and_history_bound() {
+        let mut doc = Document::new("ab".into());
+        assert_eq!(
+            doc.apply(op(r#"[2, "cd"]"#), 3),
+            Err(Rejected::TooLarge { size: 4, limit: 3 })
+        );
+        assert_eq!(doc.text(), "ab");
+        for _ in 0..HISTORY + 1 {
+            let o = doc.rebase(doc.revision(), op(r#"[1, "x", 1]"#)).unwrap();
+            doc.apply(o, usize::MAX).unwrap();
+            let o = doc.rebase(doc.revision(), op(r#"[1, -1, 1]"#)).unwrap();
+            doc.apply(o, usize::MAX).unwrap();
+        }
+        assert_eq!(doc.text(), "ab");
+        assert_eq!(doc.rebase(0, op("[2]")).unwrap_err(), Rejected::Revision);
+        assert!(doc
+            .rebase(doc.revision() - HISTORY as u64, op("[2]"))
+            .is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+//! Collaborative editing.
+//!
+//! `GET /api/v1/snippets/:slug/collab` upgrades to a WebSocket on which
+//! several people edit one snippet at once; `pastedev_core::collab` has the
+//! protocol. Anyone who may `PATCH` the snippet may join: the socket
+//! authenticates like any other request, by session cookie or by an API key
+//! with the `publish` scope. Browsers don't hold WebSockets to the origin
+//! check that guards writes, so a cookie-authenticated upgrade has to come
+//! from an allowed `Origin`.
+//!
+//! Each snippet being edited on a replica has one room: a task that owns the
+//! text, applies ops in the order they arrive, and relays them, along with
+//! cursors and who comes and goes, to everyone else in it. The text is
+//! written back through the same conditional update as a `PATCH` once
+//! typing pauses, at least every few seconds while it doesn't, and when the
+//! last person leaves; each save is a revision.
+//!
+//! Writes from outside the room — a `PATCH`, an append, or another
+//! replica's room for the same snippet — reach it through the event hub. The
+//! room diffs them against what it last saved and merges them into the
+//! session as an op from nobody. The same reload drops anyone who has lost
+//! access, and a deleted snippet ends the session.
+//!
+//! Encrypted snippets and bundles can't be edited this way, for the same
+//! reasons they can't be appended to.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use futures_util::{SinkExt, StreamExt};
+use pastedev_core::{ClientMessage, ErrorCode, OrgRole, Scope, ServerMessage};
+use std::time::Duration;
+
+use crate::{
+    auth::extract::{try_extract_user, AuthedUser},
+    error::AppError,
+    http::{origin_allowed, AppState},
+    snippets::{
+        handlers::{ensure_can_manage, validate_slug},
+        repo::{self, SnippetRow},
+    },
+    users,
+};
+
+mod document;
+mod room;
+
+pub use room::Rooms;
+use room::{Command, Membership};
+
+/// Room for the JSON around an op that inserts a whole snippet.
+const FRAME_SLACK: usize = 64 * 1024;
+
+/// How often an idle socket is pinged, so proxies don't time it out.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `GET /api/v1/snippets/:slug/collab`
+pub async fn connect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    validate_slug(&slug)?;
+    let user = try_extract_user(&state, &headers)
+        .await
+        .ok_or(AppError::Unauthorized)?;
+    if user.via_bearer {
+        if !user.key_scopes.contains(&Scope::Publish) {
+            return Err(AppError::Forbidden(Some("scope missing")));
+        }
+    } else if !origin_allowed(&state.config, &headers) {
+        return Err(AppError::Forbidden(Some("origin not allowed")));
+    }
+    let row = repo::by_slug(&state.pool, &slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(&state, &user, &row, OrgRole::Member).await?;
+    if row.encrypted {
+        return Err(AppError::Validation(
+            "encrypted snippets can't be edited together".into(),
+        ));
+    }
+    if repo::is_bundle(&state.pool, row.id).await? {
+        return Err(AppError::Validation(
+            "multi-file snippets can't be edited together".into(),
+        ));
+    }
+    let username = users::repo::by_id(&state.pool, user.id)
+        .await?
+        .ok_or(AppError::Unauthorized)?
+        .username;
+
+    let max_frame = state.config.snippet_max_bytes + FRAME_SLACK;
+    Ok(ws
+        .max_message_size(max_frame)
+        .max_frame_size(max_frame)
+        .on_upgrade(move |socket| session(socket, state, row, user, username)))
+}
+
+/// Join the room and shuttle messages between it and the socket until
+/// either side is done.
+async fn session(
+    socket: WebSocket,
+    state: AppState,
+    row: SnippetRow,
+    user: AuthedUser,
+    username: String,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let joined = state.collab.join(&state, &row, user, username).await;
+    let Membership {
+        room,
+        client_id,
+        mut outbox,
+    } = match joined {
+        Ok(membership) => membership,
+        Err(e) => {
+            let (code, message) = match &e {
+                AppError::NotFound => (ErrorCode::NotFound, e.to_string()),
+                AppError::Conflict(_) => (ErrorCode::Conflict, e.to_string()),
+                _ => {
+                    tracing::error!(error = ?e, slug = %row.slug, "collab: join failed");
+                    (ErrorCode::Internal, "internal error".to_string())
+                }
+            };
+            let _ = send(
+                &mut sink,
+                &ServerMessage::Error {
+                    code,
+                    message,
+                    fatal: true,
+                },
+            )
+            .await;
+            let _ = sink.send(Message::Close(None)).await;
+            return;
+        }
+    };
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.reset();
+    loop {
+        tokio::select! {
+            out = outbox.recv() => match out {
+                Some(message) => {
+                    if send(&mut sink, &message).await.is_err() {
+                        break;
+                    }
+                }
+                // The room dropped this client, after telling it why if
+                // there was a reason.
+                None => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            incoming = stream.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage>(text.as_str()) {
+                        Ok(message) => {
+                            if room.send(Command::Message { client_id, message }).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            let error = ServerMessage::Error {
+                                code: ErrorCode::ValidationError,
+                                message: format!("bad message: {e}"),
+                                fatal: false,
+                            };
+                            if send(&mut sink, &error).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                Some(Ok(Message::Binary(_))) => {
+                    let error = ServerMessage::Error {
+                        code: ErrorCode::ValidationError,
+                        message: "messages are JSON text frames".into(),
+                        fatal: false,
+                    };
+                    if send(&mut sink, &error).await.is_err() {
+                        break;
+                    }
+                }
+                // Pings are answered by the socket itself.
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+            },
+            _ = ping.tick() => {
+                if sink.send(Message::Ping(Default::default())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = room.send(Command::Leave { client_id }).await;
+}
+
+async fn send(
+    sink: &mut futures_util::stream::SplitSink<WebSocket, Message>,
+    message: &ServerMessage,
+) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).expect("message serializes");
+    sink.send(Message::Text(text.into())).await
+}
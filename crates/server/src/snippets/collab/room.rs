@@ -0,0 +1,703 @@
+//! One editing session per snippet, run as a task that owns the
+//! [`Document`] and talks to each socket over channels.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use pastedev_core::{
+    ClientMessage, ErrorCode, OrgRole, Peer, Selection, ServerMessage, SnippetEvent, TextOp,
+};
+use similar::{DiffOp, TextDiff};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use uuid::Uuid;
+
+use super::document::{Document, Rejected};
+use crate::{
+    audit,
+    auth::extract::AuthedUser,
+    error::AppError,
+    http::AppState,
+    snippets::{
+        events::{self, Watch, Watched},
+        handlers::ensure_can_manage,
+        repo::{self, SnippetPatch, SnippetRow},
+    },
+    storage,
+};
+
+/// Messages a socket may fall behind by before it is dropped.
+const OUTBOX: usize = 256;
+
+/// Commands a room may have queued before sockets wait on it.
+const INBOX: usize = 256;
+
+/// People one snippet can have editing it at once.
+const MAX_PEERS: usize = 32;
+
+/// Edits are saved once nobody has typed for this long...
+const QUIET: Duration = Duration::from_secs(3);
+
+/// ...or when the oldest unsaved one is this old.
+const MAX_UNSAVED: Duration = Duration::from_secs(15);
+
+/// Wait before trying again after a save failed.
+const RETRY: Duration = Duration::from_secs(10);
+
+/// Rounds of reload-and-merge a save gets when others keep writing.
+const SAVE_ATTEMPTS: usize = 3;
+
+/// Longest the diff of an outside write against the session's text may take;
+/// past it the diff is coarser but still correct.
+const DIFF_TIMEOUT: Duration = Duration::from_millis(200);
+
+pub enum Command {
+    Join {
+        user: AuthedUser,
+        username: String,
+        outbox: mpsc::Sender<ServerMessage>,
+        reply: oneshot::Sender<Result<u32, AppError>>,
+    },
+    Message {
+        client_id: u32,
+        message: ClientMessage,
+    },
+    Leave {
+        client_id: u32,
+    },
+}
+
+/// A socket's place in a room.
+pub struct Membership {
+    pub room: mpsc::Sender<Command>,
+    pub client_id: u32,
+    /// What the room sends this client. Closed when the room drops it.
+    pub outbox: mpsc::Receiver<ServerMessage>,
+}
+
+/// The rooms open on this replica, by snippet id.
+#[derive(Clone, Default)]
+pub struct Rooms {
+    rooms: Arc<Mutex<HashMap<Uuid, mpsc::Sender<Command>>>>,
+}
+
+impl Rooms {
+    /// Put `user` in the room for `row`, opening it if needed.
+    pub async fn join(
+        &self,
+        state: &AppState,
+        row: &SnippetRow,
+        user: AuthedUser,
+        username: String,
+    ) -> Result<Membership, AppError> {
+        // A room that is closing drops the join unanswered; the next round
+        // opens a fresh one.
+        for _ in 0..3 {
+            let room = self.open(state, row.id).await?;
+            let (outbox_tx, outbox) = mpsc::channel(OUTBOX);
+            let (reply, replied) = oneshot::channel();
+            let join = Command::Join {
+                user: user.clone(),
+                username: username.clone(),
+                outbox: outbox_tx,
+                reply,
+            };
+            if room.send(join).await.is_ok() {
+                if let Ok(joined) = replied.await {
+                    return joined.map(|client_id| Membership {
+                        room,
+                        client_id,
+                        outbox,
+                    });
+                }
+            }
+            tokio::task::yield_now().await;
+        }
+        Err(anyhow::anyhow!("collab room for {} keeps closing", row.slug).into())
+    }
+
+    async fn open(
+        &self,
+        state: &AppState,
+        snippet_id: Uuid,
+    ) -> Result<mpsc::Sender<Command>, AppError> {
+        if let Some(room) = self.live(snippet_id) {
+            return Ok(room);
+        }
+        // Watch before reading, so no write falls in between.
+        let watch = state.events.watch(snippet_id);
+        let row = repo::by_id(&state.pool, snippet_id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        let body = storage::load(
+            state.bodies.as_ref(),
+            row.body.clone(),
+            row.location.clone(),
+        )
+        .await?;
+
+        let mut rooms = self.rooms.lock().expect("rooms lock");
+        if let Some(room) = rooms.get(&snippet_id).filter(|r| !r.is_closed()) {
+            return Ok(room.clone());
+        }
+        let (tx, commands) = mpsc::channel(INBOX);
+        rooms.insert(snippet_id, tx.clone());
+        let room = Room {
+            state: state.clone(),
+            rooms: self.clone(),
+            row,
+            doc: Document::new(body.clone()),
+            saved: body,
+            unsaved: None,
+            retry_at: None,
+            clients: BTreeMap::new(),
+            next_client_id: 1,
+            reaped: Vec::new(),
+            watch,
+            watching: true,
+            commands,
+        };
+        tokio::spawn(room.run());
+        Ok(tx)
+    }
+
+    fn live(&self, snippet_id: Uuid) -> Option<mpsc::Sender<Command>> {
+        let rooms = self.rooms.lock().expect("rooms lock");
+        rooms.get(&snippet_id).filter(|r| !r.is_closed()).cloned()
+    }
+
+    /// Forget a room that has stopped taking commands.
+    fn remove_closed(&self, snippet_id: Uuid) {
+        let mut rooms = self.rooms.lock().expect("rooms lock");
+        if rooms.get(&snippet_id).is_some_and(|r| r.is_closed()) {
+            rooms.remove(&snippet_id);
+        }
+    }
+}
+
+struct Client {
+    user: AuthedUser,
+    username: String,
+    outbox: mpsc::Sender<ServerMessage>,
+    selection: Option<Selection>,
+}
+
+impl Client {
+    fn peer(&self, client_id: u32) -> Peer {
+        Peer {
+            client_id,
+            username: self.username.clone(),
+            selection: self.selection,
+        }
+    }
+}
+
+/// Edits not yet written back.
+struct Unsaved {
+    first: Instant,
+    last: Instant,
+    /// Who typed last; the save and its revision are credited to them.
+    editor: Uuid,
+}
+
+struct Room {
+    state: AppState,
+    rooms: Rooms,
+    /// The snippet as last read or written.
+    row: SnippetRow,
+    doc: Document,
+    /// The body as of `row.updated_at`.
+    saved: String,
+    unsaved: Option<Unsaved>,
+    retry_at: Option<Instant>,
+    clients: BTreeMap<u32, Client>,
+    /// Starts at 1, leaving 0 to [`Room::broadcast`] from nobody.
+    next_client_id: u32,
+    /// Clients to drop once the current command is handled.
+    reaped: Vec<u32>,
+    watch: Watch,
+    watching: bool,
+    commands: mpsc::Receiver<Command>,
+}
+
+impl Room {
+    async fn run(mut self) {
+        loop {
+            let save_at = self.save_at();
+            tokio::select! {
+                command = self.commands.recv() => match command {
+                    Some(command) => self.handle(command),
+                    None => break,
+                },
+                watched = self.watch.next(), if self.watching => match watched {
+                    Some(Watched::Event(SnippetEvent::Deleted)) => self.close(
+                        ErrorCode::NotFound,
+                        "the snippet was deleted",
+                    ),
+                    Some(Watched::Event(_) | Watched::Missed) => {
+                        self.sync().await;
+                    }
+                    None => self.watching = false,
+                },
+                _ = tokio::time::sleep_until(save_at.unwrap_or_else(Instant::now)),
+                    if save_at.is_some() => self.save().await,
+            }
+            self.reap();
+            if self.clients.is_empty() {
+                break;
+            }
+        }
+        // The last one out saves. Joins that raced it are dropped and retry
+        // in a room that starts from what was saved.
+        self.save().await;
+        self.commands.close();
+        self.rooms.remove_closed(self.row.id);
+    }
+
+    fn save_at(&self) -> Option<Instant> {
+        let unsaved = self.unsaved.as_ref()?;
+        let due = (unsaved.last + QUIET).min(unsaved.first + MAX_UNSAVED);
+        Some(self.retry_at.map_or(due, |at| at.max(due)))
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Join {
+                user,
+                username,
+                outbox,
+                reply,
+            } => {
+                if self.clients.len() >= MAX_PEERS {
+                    let _ = reply.send(Err(AppError::Conflict(
+                        "too many people are editing this snippet",
+                    )));
+                    return;
+                }
+                let client_id = self.next_client_id;
+                self.next_client_id += 1;
+                let hello = ServerMessage::Hello {
+                    client_id,
+                    revision: self.doc.revision(),
+                    body: self.doc.text().to_string(),
+                    peers: self.clients.iter().map(|(id, c)| c.peer(*id)).collect(),
+                };
+                if outbox.try_send(hello).is_err() {
+                    return;
+                }
+                let client = Client {
+                    user,
+                    username,
+                    outbox,
+                    selection: None,
+                };
+                self.broadcast(
+                    client_id,
+                    ServerMessage::Join {
+                        peer: client.peer(client_id),
+                    },
+                );
+                self.clients.insert(client_id, client);
+                if reply.send(Ok(client_id)).is_err() {
+                    self.reaped.push(client_id);
+                }
+            }
+            Command::Message { client_id, message } => {
+                if !self.clients.contains_key(&client_id) {
+                    return;
+                }
+                match message {
+                    ClientMessage::Op { revision, op } => self.edit(client_id, revision, op),
+                    ClientMessage::Cursor {
+                        revision,
+                        selection,
+                    } => self.cursor(client_id, revision, selection),
+                }
+            }
+            Command::Leave { client_id } => self.reaped.push(client_id),
+        }
+    }
+
+    fn edit(&mut self, client_id: u32, revision: u64, op: TextOp) {
+        let limit = self.state.config.snippet_max_bytes;
+        let applied = self
+            .doc
+            .rebase(revision, op)
+            .and_then(|op| Ok((self.doc.apply(op.clone(), limit)?, op)));
+        let (revision, op) = match applied {
+            Ok(applied) => applied,
+            // The client already shows its op, so it can't carry on without
+            // it; it reconnects and starts from the server's text.
+            Err(rejected) => {
+                let (code, message) = match rejected {
+                    Rejected::Revision => (
+                        ErrorCode::Conflict,
+                        "revision is too old or unknown".to_string(),
+                    ),
+                    Rejected::Mismatch => (
+                        ErrorCode::ValidationError,
+                        "op doesn't fit the text".to_string(),
+                    ),
+                    Rejected::TooLarge { size, limit } => (
+                        ErrorCode::SnippetTooLarge,
+                        format!("snippet too large: {size} > {limit}"),
+                    ),
+                };
+                self.send(
+                    client_id,
+                    ServerMessage::Error {
+                        code,
+                        message,
+                        fatal: true,
+                    },
+                );
+                self.reaped.push(client_id);
+                return;
+            }
+        };
+        self.moved(&op);
+        let now = Instant::now();
+        let editor = self.clients[&client_id].user.id;
+        match &mut self.unsaved {
+            Some(unsaved) => {
+                unsaved.last = now;
+                unsaved.editor = editor;
+            }
+            None => {
+                self.unsaved = Some(Unsaved {
+                    first: now,
+                    last: now,
+                    editor,
+                })
+            }
+        }
+        self.send(client_id, ServerMessage::Ack { revision });
+        self.broadcast(
+            client_id,
+            ServerMessage::Op {
+                client_id: Some(client_id),
+                revision,
+                op,
+            },
+        );
+    }
+
+    fn cursor(&mut self, client_id: u32, revision: u64, selection: Option<Selection>) {
+        let selection = match selection
+            .map(|s| self.doc.selection(revision, s))
+            .transpose()
+        {
+            Ok(selection) => selection,
+            // Out of date or out of range; the next one will do.
+            Err(_) => return,
+        };
+        if let Some(client) = self.clients.get_mut(&client_id) {
+            client.selection = selection;
+        }
+        let revision = self.doc.revision();
+        self.broadcast(
+            client_id,
+            ServerMessage::Cursor {
+                client_id,
+                revision,
+                selection,
+            },
+        );
+    }
+
+    /// Move everyone's cursor past `op`.
+    fn moved(&mut self, op: &TextOp) {
+        for client in self.clients.values_mut() {
+            client.selection = client.selection.map(|s| s.transform(op));
+        }
+    }
+
+    fn send(&mut self, client_id: u32, message: ServerMessage) {
+        let Some(client) = self.clients.get(&client_id) else {
+            return;
+        };
+        // A socket this far behind isn't keeping up; it rejoins from
+        // scratch rather than hold the room back.
+        if client.outbox.try_send(message).is_err() {
+            self.reaped.push(client_id);
+        }
+    }
+
+    /// Send `message` to everyone but `from`.
+    fn broadcast(&mut self, from: u32, message: ServerMessage) {
+        let to: Vec<u32> = self
+            .clients
+            .keys()
+            .copied()
+            .filter(|id| *id != from)
+            .collect();
+        for client_id in to {
+            self.send(client_id, message.clone());
+        }
+    }
+
+    /// Drop the clients marked for it, and tell the others.
+    fn reap(&mut self) {
+        while let Some(client_id) = self.reaped.pop() {
+            if self.clients.remove(&client_id).is_some() {
+                self.broadcast(client_id, ServerMessage::Leave { client_id });
+            }
+        }
+    }
+
+    /// Send everyone a last error and drop them.
+    fn close(&mut self, code: ErrorCode, message: &str) {
+        for client in std::mem::take(&mut self.clients).into_values() {
+            let _ = client.outbox.try_send(ServerMessage::Error {
+                code,
+                message: message.to_string(),
+                fatal: true,
+            });
+        }
+        self.unsaved = None;
+    }
+
+    /// Reload the snippet: drop clients who lost access to it, and merge
+    /// into the session whatever was written outside it. `false` if the
+    /// room is done.
+    async fn sync(&mut self) -> bool {
+        let row = match repo::by_id(&self.state.pool, self.row.id).await {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                self.close(ErrorCode::NotFound, "the snippet was deleted");
+                return false;
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, slug = %self.row.slug, "collab: reload failed");
+                return true;
+            }
+        };
+        let mut denied = Vec::new();
+        for (client_id, client) in &self.clients {
+            match ensure_can_manage(&self.state, &client.user, &row, OrgRole::Member).await {
+                Ok(()) => {}
+                Err(AppError::Sqlx(e)) => {
+                    tracing::warn!(error = ?e, slug = %row.slug, "collab: access check failed");
+                }
+                Err(_) => denied.push(*client_id),
+            }
+        }
+        for client_id in denied {
+            self.send(
+                client_id,
+                ServerMessage::Error {
+                    code: ErrorCode::Forbidden,
+                    message: "you can no longer edit this snippet".into(),
+                    fatal: true,
+                },
+            );
+            self.reaped.push(client_id);
+        }
+        if row.updated_at == self.row.updated_at {
+            self.row = row;
+            return true;
+        }
+        let body = match storage::load(
+            self.state.bodies.as_ref(),
+            row.body.clone(),
+            row.location.clone(),
+        )
+        .await
+        {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!(error = ?e, slug = %row.slug, "collab: reload failed");
+                return true;
+            }
+        };
+        // A three-way merge: what the session did since the last save and
+        // what was written meanwhile are both diffs of the saved text. The
+        // session's edits go first where both insert at one spot.
+        let ours = diff(&self.saved, self.doc.text());
+        let theirs = diff(&self.saved, &body);
+        let limit = self.state.config.snippet_max_bytes;
+        let merged = match TextOp::transform(&ours, &theirs) {
+            Some((_, theirs)) if theirs.is_noop() => Ok(None),
+            Some((_, theirs)) => self
+                .doc
+                .apply(theirs.clone(), limit)
+                .map(|revision| Some((revision, theirs))),
+            None => Err(Rejected::Mismatch),
+        };
+        match merged {
+            Ok(None) => {}
+            Ok(Some((revision, theirs))) => {
+                self.moved(&theirs);
+                self.broadcast(
+                    0,
+                    ServerMessage::Op {
+                        client_id: None,
+                        revision,
+                        op: theirs,
+                    },
+                );
+            }
+            // The session can't take the write, so it ends; everyone
+            // reconnects to a room that starts from what was written.
+            Err(Rejected::TooLarge { size, limit }) => {
+                self.close(
+                    ErrorCode::SnippetTooLarge,
+                    &format!("an outside edit made the snippet too large: {size} > {limit}"),
+                );
+                return false;
+            }
+            Err(Rejected::Mismatch | Rejected::Revision) => {
+                tracing::warn!(slug = %row.slug, "collab: outside edit didn't merge");
+                self.close(
+                    ErrorCode::Conflict,
+                    "the snippet was changed outside this session",
+                );
+                return false;
+            }
+        }
+        self.row = row;
+        self.saved = body;
+        if self.doc.text() == self.saved {
+            self.unsaved = None;
+        }
+        true
+    }
+
+    /// Write the session's text back if it has changed, first merging in
+    /// anything written meanwhile.
+    async fn save(&mut self) {
+        for _ in 0..SAVE_ATTEMPTS {
+            let Some(unsaved) = &self.unsaved else {
+                return;
+            };
+            let editor = unsaved.editor;
+            // Snippets can't be empty; it is saved once it has text again.
+            if self.doc.text().is_empty() || self.doc.text() == self.saved {
+                self.unsaved = None;
+                return;
+            }
+            match self.write(editor).await {
+                Ok(true) => {
+                    self.unsaved = None;
+                    self.retry_at = None;
+                    return;
+                }
+                // Written meanwhile (or renamed, which changes the slug).
+                Ok(false) => {
+                    if !self.sync().await {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, slug = %self.row.slug, "collab: save failed");
+                    self.retry_at = Some(Instant::now() + RETRY);
+                    return;
+                }
+            }
+        }
+        self.retry_at = Some(Instant::now() + RETRY);
+    }
+
+    /// Store the text if the row hasn't changed since it was read.
+    async fn write(&mut self, editor: Uuid) -> Result<bool, AppError> {
+        let text = self.doc.text().to_string();
+        let stored = storage::store(self.state.bodies.as_ref(), &self.state.pool, &text).await?;
+        let patch = SnippetPatch {
+            body: Some(stored),
+            name: None,
+            expected_updated_at: Some(self.row.updated_at),
+        };
+        let Some(updated) =
+            repo::update(&self.state.pool, &self.row.slug, self.row.owner_id, editor, patch)
+                .await?
+        else {
+            return Ok(false);
+        };
+        audit::spawn_write(
+            self.state.pool.clone(),
+            audit::OwnedEvent {
+                event: "snippet.update",
+                actor_user_id: Some(editor),
+                target_snippet_id: Some(updated.id),
+                payload: Some(serde_json::json!({
+                    "old_size_bytes": self.row.size_bytes,
+                    "new_size_bytes": updated.size_bytes,
+                    "new_stored_bytes": updated.stored_bytes,
+                    "collab": true,
+                })),
+                ..Default::default()
+            },
+        );
+        events::spawn_notify(
+            self.state.pool.clone(),
+            updated.id,
+            SnippetEvent::Edit {
+                size_bytes: updated.size_bytes,
+            },
+        );
+        self.row = updated;
+        self.saved = text;
+        Ok(true)
+    }
+}
+
+/// The op that turns `old` into `new`.
+fn diff(old: &str, new: &str) -> TextOp {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_chars(old, new);
+    let inserted = |index: usize, len: usize| diff.new_slices()[index..index + len].concat();
+    let mut op = TextOp::new();
+    for d in diff.ops() {
+        match *d {
+            DiffOp::Equal { len, .. } => op.retain(len),
+            DiffOp::Delete { old_len, .. } => op.delete(old_len),
+            DiffOp::Insert {
+                new_index, new_len, ..
+            } => op.insert(&inserted(new_index, new_len)),
+            DiffOp::Replace {
+                old_len,
+                new_index,
+                new_len,
+                ..
+            } => op.delete(old_len).insert(&inserted(new_index, new_len)),
+        };
+    }
+    op
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_turns_old_into_new() {
+        for (old, new) in [
+            ("", "abc"),
+            ("abc", ""),
+            ("the quick brown fox", "the slow brown dog!"),
+            ("héllo wörld", "hello world"),
+        ] {
+            let op = diff(old, new);
+            assert_eq!(op.apply(old).as_deref(), Some(new), "{old:?} -> {new:?}");
+        }
+    }
+
+    #[test]
+    fn outside_writes_merge_with_unsaved_edits() {
+        let saved = "line one\nline two\n";
+        let session = "line one!\nline two\n";
+        let written = "line one\nline two\nline three\n";
+        let ours = diff(saved, session);
+        let (_, theirs) = TextOp::transform(&ours, &diff(saved, written)).unwrap();
+        assert_eq!(
+            theirs.apply(session).as_deref(),
+            Some("line one!\nline two\nline three\n")
+        );
+    }
+}
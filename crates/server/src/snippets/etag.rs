@@ -0,0 +1,157 @@
+//! ETags and conditional requests on snippets.
+//!
+//! A snippet's version is what `updated_at` stamps: an edit, a settings
+//! change, an armed burn timer, or a change to its tags, shares or
+//! collections moves it; a counted view doesn't. Every tag here is strong.
+//! The raw bodies carry the version as theirs. The JSON's also covers the
+//! fork source it links to, which goes away with the source without a new
+//! version. It leaves out the view count: that is a tally kept beside the
+//! snippet, not part of it, and counting a reader's view after their tag was
+//! made would otherwise fail their own next write. A 304 may leave a client's
+//! count behind. `get`, `raw_text` and `raw_html` answer a matching
+//! `If-None-Match` with 304.
+//!
+//! `patch` and `update_settings` honour `If-Match` against the JSON's tag,
+//! compared strongly as RFC 9110 asks: a stale one, or a weak one, is a 412
+//! `precondition_failed`, so an editor working from an old copy can't
+//! overwrite someone else's change. The version is checked once more in the
+//! UPDATE itself, so of two writers holding the same ETag only the first
+//! gets through.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::{error::AppError, snippets::repo::SnippetRow, storage::Codec};
+
+/// The quoted strong ETag of `row` as served with `codec`. A zstd body is a
+/// different representation from the plain one, so it gets its own tag.
+pub fn etag(row: &SnippetRow, codec: Codec) -> String {
+    let version = hex::encode(&version(row).finalize()[..12]);
+    match codec {
+        Codec::Identity => format!("\"{version}\""),
+        Codec::Zstd => format!("\"{version}-zstd\""),
+    }
+}
+
+/// The quoted strong ETag of `row`'s JSON, showing `forked_from` as its
+/// source.
+pub fn json(row: &SnippetRow, forked_from: Option<&str>) -> String {
+    let mut hasher = version(row);
+    hasher.update(b"json");
+    if let Some(slug) = forked_from {
+        hasher.update(slug.as_bytes());
+    }
+    format!("\"{}\"", hex::encode(&hasher.finalize()[..12]))
+}
+
+/// `row`'s version, hashed with its id so tags don't repeat across snippets.
+fn version(row: &SnippetRow) -> Sha256 {
+    let micros = row.updated_at.unix_timestamp_nanos() / 1_000;
+    let mut hasher = Sha256::new();
+    hasher.update(row.id.as_bytes());
+    hasher.update(micros.to_be_bytes());
+    hasher
+}
+
+/// `tag` without its weak marker.
+fn opaque(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+/// The entity tags in a comma-separated `If-Match` / `If-None-Match` list;
+/// `None` for `*`.
+fn listed(headers: &HeaderMap, name: header::HeaderName) -> Option<Vec<&str>> {
+    let mut tags = Vec::new();
+    for value in headers.get_all(name).iter().filter_map(|v| v.to_str().ok()) {
+        for tag in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if tag == "*" {
+                return None;
+            }
+            tags.push(tag);
+        }
+    }
+    Some(tags)
+}
+
+/// Whether an `If-None-Match` lets the client keep its copy. Weak
+/// comparison, as the header calls for.
+pub fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    if !headers.contains_key(header::IF_NONE_MATCH) {
+        return false;
+    }
+    match listed(headers, header::IF_NONE_MATCH) {
+        None => true,
+        Some(tags) => tags.iter().any(|t| opaque(t) == opaque(etag)),
+    }
+}
+
+/// Check an `If-Match` against `row`'s JSON as its editors see it, fork
+/// source and all. `Ok(None)` without the header; otherwise the
+/// `updated_at` the write has to find still in place.
+pub fn expected_version(
+    headers: &HeaderMap,
+    row: &SnippetRow,
+) -> Result<Option<OffsetDateTime>, AppError> {
+    if !headers.contains_key(header::IF_MATCH) {
+        return Ok(None);
+    }
+    let current = json(row, row.forked_from_slug.as_deref());
+    match listed(headers, header::IF_MATCH) {
+        Some(tags) if !tags.contains(&current.as_str()) => Err(AppError::PreconditionFailed),
+        _ => Ok(Some(row.updated_at)),
+    }
+}
+
+/// Attach `etag` to a response.
+pub fn tag(response: &mut Response, etag: &str) {
+    if let Ok(v) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, v);
+    }
+}
+
+/// A bodiless 304 carrying `etag`.
+pub fn not_modified_response(etag: &str) -> Response {
+    let mut response = StatusCode::NOT_MODIFIED.into_response();
+    tag(&mut response, etag);
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=0"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with(name: header::HeaderName, value: &'static str) -> HeaderMap {
+        let mut h = HeaderMap::new();
+        h.insert(name, HeaderValue::from_static(value));
+        h
+    }
+
+    #[test]
+    fn if_none_match_compares_weakly_and_takes_lists() {
+        let tag = "\"abc\"";
+        assert!(!not_modified(&HeaderMap::new(), tag));
+        assert!(not_modified(&with(header::IF_NONE_MATCH, "\"abc\""), tag));
+        assert!(not_modified(&with(header::IF_NONE_MATCH, "W/\"abc\""), tag));
+        assert!(not_modified(&with(header::IF_NONE_MATCH, "\"x\", \"abc\""), tag));
+        assert!(not_modified(&with(header::IF_NONE_MATCH, "*"), tag));
+        assert!(!not_modified(&with(header::IF_NONE_MATCH, "\"abcd\""), tag));
+        assert!(not_modified(&with(header::IF_NONE_MATCH, "\"abc\""), "W/\"abc\""));
+    }
+
+    #[test]
+    fn if_match_lists_and_wildcard() {
+        assert_eq!(listed(&with(header::IF_MATCH, "*"), header::IF_MATCH), None);
+        assert_eq!(
+            listed(&with(header::IF_MATCH, "\"a\" , W/\"b\""), header::IF_MATCH),
+            Some(vec!["\"a\"", "W/\"b\""])
+        );
+    }
+}
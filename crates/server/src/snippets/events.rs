@@ -0,0 +1,443 @@
+//! Live snippet events.
+//!
+//! `GET /api/v1/snippets/:slug/events` is a server-sent event stream of what
+//! happens to one snippet: appended text, edits, settings changes, a burn
+//! timer starting, and the end of the snippet's life. It takes the same
+//! access check as `GET /api/v1/snippets/:slug`, share links included, but
+//! opening it isn't a view: it doesn't use up a link, take a view-cap slot
+//! or arm a burn fuse. In exchange, a reader other than the owner doesn't get
+//! appended text for a snippet with `max_views`, or a burn-after-read one
+//! that nobody has read yet — only the offsets, so they fetch the body, and
+//! that fetch is the read that counts.
+//!
+//! Writes announce themselves with `pg_notify` on [`CHANNEL`], so the stream
+//! sees changes made through any replica. Each replica keeps one
+//! `LISTEN`ing connection ([`Hub`]) and fans its notifications out to the
+//! streams it serves. A notification that can't be trusted to have arrived
+//! (the listener reconnected, or a slow stream fell behind) turns into an
+//! `edit` event, which tells the client to fetch the body again. The same
+//! hub tells collaborative editing rooms (`snippets::collab`) about writes
+//! made outside their session.
+//!
+//! Expiry and burning aren't writes, so each stream keeps its own timer for
+//! the snippet's `expires_at`. A settings change re-runs the access check,
+//! and a reader who lost access has the stream closed.
+
+use std::{convert::Infallible, net::IpAddr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{stream, Stream};
+use pastedev_core::SnippetEvent;
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgExecutor, PgPool};
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    http::{client_ip::ClientIp, AppState},
+    snippets::{
+        handlers::{enforce_access, reader, validate_slug, viewer_hash, Reader},
+        links::{self, LinkQuery},
+        repo::{self, SnippetRow},
+    },
+};
+
+/// The `LISTEN` / `NOTIFY` channel.
+const CHANNEL: &str = "snippet_events";
+
+/// Postgres refuses notification payloads of 8000 bytes or more; appended
+/// text that would push one past this is left out of the event.
+const MAX_PAYLOAD_BYTES: usize = 7_900;
+
+/// Notifications a stream may fall behind by before it has to resync.
+const BACKLOG: usize = 1_024;
+
+/// Wait before reconnecting a listener that failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Notice {
+    snippet_id: Uuid,
+    #[serde(flatten)]
+    event: SnippetEvent,
+}
+
+#[derive(Debug, Clone)]
+enum Signal {
+    Notice(Arc<Notice>),
+    /// Notifications may have been lost.
+    Resync,
+}
+
+/// This replica's end of [`CHANNEL`].
+#[derive(Clone)]
+pub struct Hub {
+    tx: broadcast::Sender<Signal>,
+}
+
+impl Hub {
+    /// Start listening on [`CHANNEL`] in the background.
+    pub fn spawn(pool: PgPool) -> Self {
+        let (tx, _) = broadcast::channel(BACKLOG);
+        tokio::spawn(listen(pool, tx.clone()));
+        Self { tx }
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Signal> {
+        self.tx.subscribe()
+    }
+
+    /// Events on one snippet, for state this replica keeps about it.
+    pub fn watch(&self, snippet_id: Uuid) -> Watch {
+        Watch { snippet_id, rx: self.subscribe() }
+    }
+}
+
+/// What a [`Watch`] saw.
+pub enum Watched {
+    Event(SnippetEvent),
+    /// Events may have been lost; reload the snippet.
+    Missed,
+}
+
+/// A subscription to one snippet's events; see [`Hub::watch`].
+pub struct Watch {
+    snippet_id: Uuid,
+    rx: broadcast::Receiver<Signal>,
+}
+
+impl Watch {
+    /// The next event, or `None` once the hub is gone.
+    pub async fn next(&mut self) -> Option<Watched> {
+        loop {
+            return match self.rx.recv().await {
+                Ok(Signal::Notice(notice)) if notice.snippet_id == self.snippet_id => {
+                    Some(Watched::Event(notice.event.clone()))
+                }
+                Ok(Signal::Notice(_)) => continue,
+                Ok(Signal::Resync) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    Some(Watched::Missed)
+                }
+                Err(broadcast::error::RecvError::Closed) => None,
+            };
+        }
+    }
+}
+
+async fn listen(pool: PgPool, tx: broadcast::Sender<Signal>) {
+    loop {
+        let mut listener = match PgListener::connect_with(&pool).await {
+            Ok(l) => l,
+            Err(e) => {
+                tracing::warn!(error = ?e, "snippet events: listener connect failed");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        if let Err(e) = listener.listen(CHANNEL).await {
+            tracing::warn!(error = ?e, "snippet events: LISTEN failed");
+            tokio::time::sleep(RECONNECT_DELAY).await;
+            continue;
+        }
+        // Whatever was sent while not listening is gone.
+        let _ = tx.send(Signal::Resync);
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(n)) => match serde_json::from_str::<Notice>(n.payload()) {
+                    Ok(notice) => {
+                        let _ = tx.send(Signal::Notice(Arc::new(notice)));
+                    }
+                    Err(e) => tracing::warn!(error = ?e, "snippet events: bad payload"),
+                },
+                // The connection dropped; the next call reconnects and
+                // listens again.
+                Ok(None) => {
+                    let _ = tx.send(Signal::Resync);
+                }
+                Err(e) => {
+                    tracing::warn!(error = ?e, "snippet events: listener failed");
+                    break;
+                }
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Announce `event` on `snippet_id`. Inside a transaction it goes out on
+/// commit. An `append` whose text doesn't fit a notification is sent
+/// without it.
+pub async fn notify<'e>(
+    db: impl PgExecutor<'e>,
+    snippet_id: Uuid,
+    event: SnippetEvent,
+) -> Result<(), sqlx::Error> {
+    let payload = payload(snippet_id, event);
+    sqlx::query!("SELECT pg_notify($1, $2)", CHANNEL, payload)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// [`notify`] after a write that has already happened. Best-effort: a lost
+/// event only delays followers until their next resync.
+pub fn spawn_notify(pool: PgPool, snippet_id: Uuid, event: SnippetEvent) {
+    tokio::spawn(async move {
+        if let Err(e) = notify(&pool, snippet_id, event).await {
+            tracing::warn!(error = ?e, %snippet_id, "snippet events: notify failed");
+        }
+    });
+}
+
+fn payload(snippet_id: Uuid, event: SnippetEvent) -> String {
+    let mut notice = Notice { snippet_id, event };
+    let json = serde_json::to_string(&notice).expect("notice serializes");
+    if json.len() < MAX_PAYLOAD_BYTES {
+        return json;
+    }
+    if let SnippetEvent::Append { text, .. } = &mut notice.event {
+        *text = None;
+    }
+    serde_json::to_string(&notice).expect("notice serializes")
+}
+
+/// `GET /api/v1/snippets/:slug/events`
+pub async fn stream(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    Query(link): Query<LinkQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    validate_slug(&slug)?;
+    let row = repo::by_slug(&state.pool, &slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let rx = state.events.subscribe();
+    let reader = check_access(&state, ip, &headers, &row, link.token.as_deref()).await?;
+
+    let follow = Follow {
+        state,
+        ip,
+        headers,
+        link: link.token,
+        row,
+        reader,
+        rx,
+        deadline_passed: false,
+        done: false,
+    };
+    let events = stream::unfold(follow, |mut f| async move {
+        let event = f.next().await?;
+        let sse = Event::default()
+            .event(event.name())
+            .json_data(&event)
+            .expect("event serializes");
+        Some((Ok(sse), f))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// [`enforce_access`] for `row`, with the share link in `link` standing in
+/// when the reader can't get in otherwise — checked, not used up. A link
+/// used up by now still serves the viewers it was used by.
+async fn check_access(
+    state: &AppState,
+    ip: Option<IpAddr>,
+    headers: &HeaderMap,
+    row: &SnippetRow,
+    link: Option<&str>,
+) -> Result<Reader, AppError> {
+    let mut reader = reader(state, headers, row).await?;
+    let Err(denied) = enforce_access(row, &reader) else {
+        return Ok(reader);
+    };
+    let viewer = viewer_hash(&state.config.pastedev_secret, ip, headers, row.id);
+    match link {
+        Some(token) if links::is_live(state, row, token, Some(&viewer)).await => {
+            reader.linked = true;
+            enforce_access(row, &reader)?;
+            Ok(reader)
+        }
+        _ => Err(denied),
+    }
+}
+
+/// One open event stream.
+struct Follow {
+    state: AppState,
+    ip: Option<IpAddr>,
+    headers: HeaderMap,
+    link: Option<String>,
+    row: SnippetRow,
+    reader: Reader,
+    rx: broadcast::Receiver<Signal>,
+    /// The owner outlives the expiry they were told about.
+    deadline_passed: bool,
+    done: bool,
+}
+
+impl Follow {
+    /// The next event to send; `None` ends the stream.
+    async fn next(&mut self) -> Option<SnippetEvent> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let deadline = self.row.expires_at.filter(|_| !self.deadline_passed);
+            let signal = match deadline {
+                Some(at) => {
+                    let wait = (at - OffsetDateTime::now_utc()).max(time::Duration::ZERO);
+                    tokio::select! {
+                        signal = self.rx.recv() => signal,
+                        _ = tokio::time::sleep(wait.unsigned_abs()) => {
+                            return Some(self.expire());
+                        }
+                    }
+                }
+                None => self.rx.recv().await,
+            };
+            let event = match signal {
+                Ok(Signal::Notice(notice)) if notice.snippet_id == self.row.id => {
+                    notice.event.clone()
+                }
+                Ok(Signal::Notice(_)) => continue,
+                Ok(Signal::Resync) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    return match self.refresh().await {
+                        Refreshed::Live => Some(SnippetEvent::Edit {
+                            size_bytes: self.row.size_bytes,
+                        }),
+                        Refreshed::Ended => Some(self.expire()),
+                        Refreshed::Gone => Some(SnippetEvent::Deleted),
+                        Refreshed::Denied => None,
+                    };
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            return match event {
+                SnippetEvent::Settings | SnippetEvent::BurnArmed { .. } => {
+                    match self.refresh().await {
+                        Refreshed::Live => Some(event),
+                        Refreshed::Ended => Some(self.expire()),
+                        Refreshed::Gone => Some(SnippetEvent::Deleted),
+                        Refreshed::Denied => None,
+                    }
+                }
+                SnippetEvent::Deleted | SnippetEvent::Burned | SnippetEvent::Expired => {
+                    self.done = true;
+                    Some(event)
+                }
+                SnippetEvent::Append { offset, size_bytes, text: Some(_) }
+                    if self.withholds_text() =>
+                {
+                    Some(SnippetEvent::Append { offset, size_bytes, text: None })
+                }
+                SnippetEvent::Append { .. } | SnippetEvent::Edit { .. } => Some(event),
+            };
+        }
+    }
+
+    /// Whether appended text has to be fetched through a counted read.
+    fn withholds_text(&self) -> bool {
+        !self.reader.owns
+            && (self.row.max_views.is_some()
+                || (self.row.burn_after_read && self.row.first_viewed_at.is_none()))
+    }
+
+    /// `expires_at` came: the snippet burned or expired. Only the owner
+    /// still has it afterwards.
+    fn expire(&mut self) -> SnippetEvent {
+        self.deadline_passed = true;
+        self.done = !self.reader.owns;
+        if self.row.burn_after_read && self.row.first_viewed_at.is_some() {
+            SnippetEvent::Burned
+        } else {
+            SnippetEvent::Expired
+        }
+    }
+
+    /// Reload the row and check the reader may still see it. Anything but
+    /// [`Refreshed::Live`] ends the stream.
+    async fn refresh(&mut self) -> Refreshed {
+        let row = match repo::by_id(&self.state.pool, self.row.id).await {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                self.done = true;
+                return Refreshed::Gone;
+            }
+            Err(e) => {
+                tracing::warn!(error = ?e, slug = %self.row.slug, "snippet events: reload failed");
+                return Refreshed::Live;
+            }
+        };
+        let reader = match check_access(&self.state, self.ip, &self.headers, &row, self.link.as_deref())
+            .await
+        {
+            Ok(reader) => reader,
+            Err(AppError::Sqlx(e)) => {
+                tracing::warn!(error = ?e, slug = %row.slug, "snippet events: access check failed");
+                return Refreshed::Live;
+            }
+            // A one-shot burn expires as soon as it is armed.
+            Err(_) if row.expires_at.is_some_and(|at| at <= OffsetDateTime::now_utc()) => {
+                self.row = row;
+                return Refreshed::Ended;
+            }
+            Err(_) => {
+                self.done = true;
+                return Refreshed::Denied;
+            }
+        };
+        if row.expires_at != self.row.expires_at {
+            self.deadline_passed = false;
+        }
+        self.row = row;
+        self.reader = reader;
+        Refreshed::Live
+    }
+}
+
+enum Refreshed {
+    Live,
+    /// Past `expires_at`, which [`Follow::expire`] announces.
+    Ended,
+    /// Deleted, which the reader is told about.
+    Gone,
+    /// The reader lost access, which ends the stream as silently as a
+    /// snippet they can't see would.
+    Denied,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_append_drops_its_text() {
+        let id = Uuid::nil();
+        let small = payload(
+            id,
+            SnippetEvent::Append { offset: 0, size_bytes: 3, text: Some("abc".into()) },
+        );
+        assert!(small.contains("\"text\":\"abc\""));
+
+        // Control characters take six bytes each once escaped.
+        let text = "\u{1}".repeat(MAX_PAYLOAD_BYTES / 6 + 1);
+        let big = payload(
+            id,
+            SnippetEvent::Append { offset: 0, size_bytes: 0, text: Some(text) },
+        );
+        assert!(big.len() < MAX_PAYLOAD_BYTES);
+        let notice: Notice = serde_json::from_str(&big).unwrap();
+        assert_eq!(
+            notice.event,
+            SnippetEvent::Append { offset: 0, size_bytes: 0, text: None }
+        );
+    }
+}
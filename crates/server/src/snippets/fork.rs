@@ -0,0 +1,228 @@
+//! Forks (migration 0023).
+//!
+//! `POST /api/v1/snippets/:slug/fork` copies a snippet the caller can read
+//! into a new one they own and records where it came from in
+//! `forked_from`. Forking is a read of the source: it goes through the same
+//! checks as `GET /api/v1/snippets/:slug`, share links included, and counts
+//! against a view cap or burn-after-read like one.
+//!
+//! Only the content is copied. The fork starts with a sharing policy of its
+//! own (see [`ForkRequest`]), and an encrypted source stays encrypted under
+//! the same key, which the forker already holds. Without a `visibility` the
+//! fork is never more widely readable than the source: see
+//! [`default_policy`]. A fork of a team snippet by one of the team stays in
+//! the team's org unless it names another.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use pastedev_core::{ForkRequest, Snippet, Visibility, BURN_AFTER_READ_WINDOW_SECONDS};
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, AuthedUser, RequiresScope},
+    error::AppError,
+    http::{client_ip::ClientIp, AppState},
+    snippets::{
+        handlers::{
+            admit_capped, arm_burn, authorize_read, check_claimable_slug, check_team_visibility,
+            clean_name, count_view, enforce_access, key_reaches, load_bodies, load_dto,
+            new_password_hash, publish_org, validate_slug, Reader,
+        },
+        links::LinkQuery,
+        repo::{self, FileDraft, SnippetDraft, SnippetRow},
+        slug,
+    },
+    orgs, storage,
+};
+
+/// `POST /api/v1/snippets/:slug/fork`
+pub async fn fork(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    Query(link): Query<LinkQuery>,
+    Json(req): Json<ForkRequest>,
+) -> Result<(StatusCode, Json<Snippet>), AppError> {
+    validate_slug(&slug)?;
+    let mut source = repo::by_slug(&state.pool, &slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    // Everything about the copy is checked before the read is counted, so a
+    // bad request doesn't use up a view or a share link.
+    if let Some(s) = req.slug.as_deref() {
+        check_claimable_slug(s)?;
+    }
+    let name = match req.name.as_deref() {
+        Some(n) => clean_name(Some(n))?,
+        None => source.name.clone(),
+    };
+    let org_id = match req.org.as_deref() {
+        None if req.visibility.is_none() && source.visibility == Visibility::Team => {
+            team_org(&state, &user.0, &source).await?
+        }
+        requested => publish_org(&state, &user.0, requested).await?,
+    };
+    let (visibility, strict_private) = match req.visibility {
+        Some(v) => (v, false),
+        None => default_policy(source.visibility, source.strict_private, source.org_id, org_id)?,
+    };
+    let password_hash = new_password_hash(&state, visibility, req.password.as_deref())?;
+    check_team_visibility(visibility, org_id)?;
+
+    let reader = authorize_read(&state, ip, &headers, &source, link.token.as_deref()).await?;
+    admit_capped(&state, ip, &headers, &source, &reader).await?;
+    arm_burn(&state, &mut source, &reader).await?;
+    count_view(&state, ip, &headers, &source);
+
+    let (body, files) = load_bodies(&state, &source).await?;
+    let mut file_drafts = Vec::with_capacity(files.len());
+    for f in &files {
+        file_drafts.push(FileDraft {
+            name: &f.name,
+            kind: f.kind,
+            body: storage::store(state.bodies.as_ref(), &state.pool, &f.body).await?,
+        });
+    }
+    let body = match file_drafts.first() {
+        Some(first) => first.body.clone(),
+        None => storage::store(state.bodies.as_ref(), &state.pool, &body).await?,
+    };
+    let draft = SnippetDraft {
+        owner_id: user.0.id,
+        kind: source.kind,
+        name: name.as_deref(),
+        body,
+        files: &file_drafts,
+        visibility,
+        password_hash,
+        strict_private,
+        encrypted: source.encrypted,
+        publish_at: None,
+        expires_at: None,
+        burn_after_read: false,
+        burn_window_seconds: BURN_AFTER_READ_WINDOW_SECONDS,
+        max_views: None,
+        org_id,
+        forked_from: Some(source.id),
+        tags: &[],
+    };
+    let row = match req.slug.as_deref() {
+        Some(s) => slug::create_with_slug(&state.pool, s, &draft).await?,
+        None => {
+            let strategy = state.config.slug_strategy_for(visibility);
+            slug::create_with_retry(&state.pool, strategy, &draft).await?
+        }
+    };
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.fork",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({
+                "slug": row.slug,
+                "source": source.slug,
+                "source_id": source.id,
+                "visibility": row.visibility.as_str(),
+                "org": row.org_name,
+            })),
+            ..Default::default()
+        },
+    );
+    Ok((StatusCode::CREATED, Json(load_dto(&state, &row).await?)))
+}
+
+/// Where a fork of the team snippet `source` goes when the request doesn't
+/// say: its org, if `user` is in it and their key reaches it; otherwise
+/// wherever a new snippet of theirs would.
+async fn team_org(
+    state: &AppState,
+    user: &AuthedUser,
+    source: &SnippetRow,
+) -> Result<Option<Uuid>, AppError> {
+    if let Some(org_id) = source.org_id.filter(|_| key_reaches(user, source)) {
+        if orgs::repo::role_of(&state.pool, org_id, user.id).await?.is_some() {
+            return Ok(Some(org_id));
+        }
+    }
+    publish_org(state, user, None).await
+}
+
+/// The visibility and `strict_private` of a fork that didn't ask for any,
+/// going to `org_id`: no wider than the source's. A password isn't the
+/// forker's to copy, so that fork is theirs alone, and a team snippet's fork
+/// only stays `team` within the same org.
+fn default_policy(
+    visibility: Visibility,
+    strict_private: bool,
+    source_org: Option<Uuid>,
+    org_id: Option<Uuid>,
+) -> Result<(Visibility, bool), AppError> {
+    match visibility {
+        Visibility::Public => Ok((Visibility::Public, false)),
+        Visibility::Private => Ok((Visibility::Private, strict_private)),
+        Visibility::Password => Ok((Visibility::Private, true)),
+        Visibility::Team if org_id.is_some() && org_id == source_org => Ok((Visibility::Team, false)),
+        Visibility::Team => Err(AppError::Validation(
+            "a fork of a team snippet outside its org needs a visibility".into(),
+        )),
+    }
+}
+
+/// The `forked_from` that `reader` gets to see on `row`. The owner always
+/// sees where their fork came from; anyone else only while the source is
+/// public and live, since a fork can be readable by people its source isn't.
+pub(crate) async fn visible_source(
+    state: &AppState,
+    row: &SnippetRow,
+    reader: &Reader,
+) -> Result<Option<String>, AppError> {
+    let Some(source_id) = row.forked_from.filter(|_| row.forked_from_slug.is_some()) else {
+        return Ok(None);
+    };
+    if reader.owns {
+        return Ok(row.forked_from_slug.clone());
+    }
+    let Some(source) = repo::by_id(&state.pool, source_id).await? else {
+        return Ok(None);
+    };
+    Ok(enforce_access(&source, &Reader::default())
+        .is_ok()
+        .then_some(source.slug))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_is_no_wider_than_the_source() {
+        let org = Some(Uuid::new_v4());
+        let policy = |v, strict, to| default_policy(v, strict, org, to).ok();
+        assert_eq!(policy(Visibility::Public, false, None), Some((Visibility::Public, false)));
+        assert_eq!(policy(Visibility::Private, false, None), Some((Visibility::Private, false)));
+        assert_eq!(policy(Visibility::Private, true, None), Some((Visibility::Private, true)));
+        assert_eq!(policy(Visibility::Password, false, None), Some((Visibility::Private, true)));
+    }
+
+    #[test]
+    fn team_forks_stay_team_only_in_the_same_org() {
+        let org = Some(Uuid::new_v4());
+        assert_eq!(
+            default_policy(Visibility::Team, false, org, org).ok(),
+            Some((Visibility::Team, false))
+        );
+        for to in [None, Some(Uuid::new_v4())] {
+            assert!(matches!(
+                default_policy(Visibility::Team, false, org, to),
+                Err(AppError::Validation(_))
+            ));
+        }
+    }
+}
@@ -1,14 +1,20 @@
 use axum::{
     body::Body,
-    extract::{Path, Query, State},
+    extract::{Path, Query, RawQuery, State},
     http::{header, HeaderMap, HeaderValue, StatusCode},
-    response::Response,
+    response::{IntoResponse, Response},
     Json,
 };
 use base64::Engine;
+use bytes::Bytes;
+use futures_util::StreamExt;
 use pastedev_core::{
     CreateSnippetRequest, ListSnippetsResponse, PatchSnippetRequest, SettingsRequest, Snippet,
-    SnippetListItem, SnippetType, Visibility, LIFETIME_SECONDS_MAX, LIFETIME_SECONDS_MIN,
+    OrgRole, SlugStrategy, SnippetContent, SnippetEvent, SnippetFile, SnippetFileInput,
+    SnippetListItem, SnippetSort, SnippetType, SortOrder, Visibility,
+    BURN_AFTER_READ_WINDOW_SECONDS, BURN_WINDOW_ONE_SHOT, BURN_WINDOW_SECONDS_MAX, BURN_WINDOW_SECONDS_MIN, CUSTOM_SLUG_MAX_LEN,
+    CUSTOM_SLUG_MIN_LEN, LIFETIME_SECONDS_MAX, LIFETIME_SECONDS_MIN, MAX_BUNDLE_FILES, MAX_TAGS,
+    RESERVED_SLUG_PREFIXES, TAG_MAX_LEN,
 };
 use serde::Deserialize;
 use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
@@ -25,61 +31,200 @@ fn fmt_ts(t: Option<OffsetDateTime>) -> Option<String> {
 use crate::{
     audit,
     auth::{
-        extract::{scope_id, try_extract_user, RequiresScope},
+        extract::{scope_id, try_extract_user, AuthedUser, RequiresScope},
         hmac::hmac_sha256_bytes,
     },
+    collections,
     error::AppError,
     http::{client_ip::ClientIp, AppState},
+    orgs,
     snippets::{
-        repo::{self, ListFilter, SnippetDraft, SnippetPatch, SnippetRow},
-        slug,
+        repo::{
+            self, FileDraft, FileRow, ListCursor, ListFilter, SnippetDraft, SnippetPatch,
+            SnippetRow,
+        },
+        etag, events, fork, idempotency,
+        links::{self, LinkQuery},
+        shares, slug, unlock,
     },
+    storage::{self, Codec, Content, Location},
 };
 
 #[derive(Debug, Deserialize)]
 pub struct ListQuery {
     #[serde(rename = "type")]
     pub kind: Option<String>,
+    pub visibility: Option<String>,
+    pub burn_after_read: Option<bool>,
+    pub expired: Option<bool>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
     pub cursor: Option<String>,
     pub limit: Option<i64>,
+    /// List this org's snippets instead of the caller's own.
+    pub org: Option<String>,
+    /// Comma-separated; only snippets carrying all of them.
+    pub tag: Option<String>,
+    /// Name of one of the caller's collections; only snippets in it.
+    pub collection: Option<String>,
 }
 
-fn validate_slug(slug: &str) -> Result<(), AppError> {
+pub(crate) fn validate_slug(slug: &str) -> Result<(), AppError> {
     if !pastedev_core::is_valid_slug(slug) {
         return Err(AppError::NotFound);
     }
     Ok(())
 }
 
-fn to_dto(row: &SnippetRow, public_base_url: &str) -> Snippet {
-    let prefix = match row.kind {
+/// A slug an owner asks for, on create, rename, or as an alias.
+pub(crate) fn check_claimable_slug(slug: &str) -> Result<(), AppError> {
+    if !pastedev_core::is_custom_slug(slug) {
+        return Err(AppError::Validation(format!(
+            "slug must be lowercase letters and digits in hyphen-separated words, \
+             e.g. oncall-runbook ({CUSTOM_SLUG_MIN_LEN} to {CUSTOM_SLUG_MAX_LEN} characters)"
+        )));
+    }
+    if let Some(prefix) = RESERVED_SLUG_PREFIXES.iter().find(|p| slug.starts_with(*p)) {
+        return Err(AppError::Validation(format!(
+            "slugs starting with '{prefix}' are reserved"
+        )));
+    }
+    if !pastedev_core::is_claimable_slug(slug) {
+        return Err(AppError::Validation(
+            "slug looks like a generated one; add a word that isn't on the slug wordlist".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// The strategy for a new snippet's generated slug. Naming both a slug and
+/// a strategy is a mistake worth pointing out rather than ignoring one.
+fn slug_strategy(
+    state: &AppState,
+    custom_slug: Option<&str>,
+    requested: Option<SlugStrategy>,
+    visibility: Visibility,
+) -> Result<SlugStrategy, AppError> {
+    match (custom_slug, requested) {
+        (Some(_), Some(_)) => Err(AppError::Validation(
+            "give either slug or slug_strategy, not both".into(),
+        )),
+        (_, Some(strategy)) => Ok(strategy),
+        (_, None) => Ok(state.config.slug_strategy_for(visibility)),
+    }
+}
+
+fn route_prefix(kind: SnippetType) -> &'static str {
+    match kind {
         SnippetType::Code => "/c/",
         SnippetType::Markdown => "/m/",
         SnippetType::Html => "/h/",
-    };
+    }
+}
+
+/// Where a row's page and raw routes live. Encrypted snippets only have the
+/// content-type-neutral `/e/` routes: the server can't tell what the
+/// ciphertext decrypts to, so it never serves it as code or HTML.
+pub(crate) fn snippet_prefix(row: &SnippetRow) -> &'static str {
+    if row.encrypted {
+        "/e/"
+    } else {
+        route_prefix(row.kind)
+    }
+}
+
+/// Percent-encode a bundle file name for use as a single URL path segment.
+/// Names are already restricted to one segment by `is_valid_file_name`; this
+/// only escapes what a URL can't carry verbatim.
+fn encode_path_segment(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// `body` and the file bodies must already be resolved (see [`load_dto`]).
+fn to_dto(row: &SnippetRow, body: String, files: &[FileRow], public_base_url: &str) -> Snippet {
+    let prefix = snippet_prefix(row);
     Snippet {
         id: row.id,
         slug: row.slug.clone(),
         kind: row.kind,
         name: row.name.clone(),
-        body: row.body.clone(),
+        body,
         size_bytes: row.size_bytes,
+        stored_bytes: row.stored_bytes,
         views: row.views,
         owner: pastedev_core::snippet::SnippetOwner {
             username: row.owner_username.clone(),
         },
+        org: row.org_name.clone(),
         url: format!("{}{}{}", public_base_url, prefix, row.slug),
         raw_url: format!("{}{}{}/raw", public_base_url, prefix, row.slug),
         visibility: row.visibility,
+        strict_private: row.strict_private,
+        encrypted: row.encrypted,
+        publish_at: row.publish_at,
         burn_after_read: row.burn_after_read,
+        burn_window_seconds: row.burn_window_seconds,
         first_viewed_at: row.first_viewed_at,
+        max_views: row.max_views,
+        forked_from: row.forked_from_slug.clone(),
+        tags: row.tags.clone(),
+        // Filled in by `get`, for the owner only.
+        collections: Vec::new(),
         expires_at: row.expires_at,
         created_at: row.created_at,
         updated_at: row.updated_at,
+        files: files
+            .iter()
+            .map(|f| SnippetFile {
+                name: f.name.clone(),
+                kind: f.kind,
+                body: f.body.clone(),
+                size_bytes: f.size_bytes,
+                stored_bytes: f.stored_bytes,
+                raw_url: format!(
+                    "{}{}{}/raw/{}",
+                    public_base_url,
+                    route_prefix(f.kind),
+                    row.slug,
+                    encode_path_segment(&f.name)
+                ),
+            })
+            .collect(),
     }
 }
 
-fn to_list_item(row: &SnippetRow) -> SnippetListItem {
+/// [`to_dto`] plus the bundle lookup and body-store reads every JSON
+/// read/write response needs.
+pub(crate) async fn load_dto(state: &AppState, row: &SnippetRow) -> Result<Snippet, AppError> {
+    let (body, files) = load_bodies(state, row).await?;
+    Ok(to_dto(row, body, &files, &state.config.public_base_url))
+}
+
+/// `row`'s full body and its bundle files, with their full bodies too.
+pub(crate) async fn load_bodies(
+    state: &AppState,
+    row: &SnippetRow,
+) -> Result<(String, Vec<FileRow>), AppError> {
+    let bodies = state.bodies.as_ref();
+    let body = storage::load(bodies, row.body.clone(), row.location.clone()).await?;
+    let mut files = repo::files(&state.pool, row.id).await?;
+    for f in &mut files {
+        let location = std::mem::take(&mut f.location);
+        f.body = storage::load(bodies, std::mem::take(&mut f.body), location).await?;
+    }
+    Ok((body, files))
+}
+
+pub(crate) fn to_list_item(row: &SnippetRow) -> SnippetListItem {
     SnippetListItem {
         slug: row.slug.clone(),
         kind: row.kind,
@@ -87,7 +232,14 @@ fn to_list_item(row: &SnippetRow) -> SnippetListItem {
         size_bytes: row.size_bytes,
         views: row.views,
         visibility: row.visibility,
+        encrypted: row.encrypted,
+        org: row.org_name.clone(),
         burn_after_read: row.burn_after_read,
+        max_views: row.max_views,
+        forked_from: row.forked_from_slug.clone(),
+        tags: row.tags.clone(),
+        collections: row.collections.clone(),
+        publish_at: row.publish_at,
         expires_at: row.expires_at,
         created_at: row.created_at,
         updated_at: row.updated_at,
@@ -95,14 +247,21 @@ fn to_list_item(row: &SnippetRow) -> SnippetListItem {
 }
 
 /// Validate the user-supplied `lifetime_seconds` input and convert it into an
-/// absolute `expires_at` timestamp by adding it to `now()`. `Ok(None)` means
-/// "no expiry"; `Ok(Some(_))` carries the absolute target. Surfaces 400 for
-/// out-of-range inputs so a malformed CLI flag doesn't reach Postgres.
-fn lifetime_to_expires_at(s: Option<i32>) -> Result<Option<OffsetDateTime>, AppError> {
+/// absolute `expires_at` timestamp by adding it to `now()` — or to the end of
+/// the embargo `publish_at`, when that is later, so a scheduled snippet gets
+/// its whole lifetime once it goes live. `Ok(None)` means "no expiry";
+/// `Ok(Some(_))` carries the absolute target. Surfaces 400 for out-of-range
+/// inputs so a malformed CLI flag doesn't reach Postgres.
+fn lifetime_to_expires_at(
+    s: Option<i32>,
+    publish_at: Option<OffsetDateTime>,
+) -> Result<Option<OffsetDateTime>, AppError> {
     match s {
         None => Ok(None),
         Some(v) if (LIFETIME_SECONDS_MIN..=LIFETIME_SECONDS_MAX).contains(&v) => {
-            Ok(Some(OffsetDateTime::now_utc() + Duration::seconds(v as i64)))
+            let now = OffsetDateTime::now_utc();
+            let start = publish_at.map_or(now, |at| at.max(now));
+            Ok(Some(start + Duration::seconds(v as i64)))
         }
         Some(_) => Err(AppError::Validation(format!(
             "lifetime_seconds must be between {} and {}",
@@ -111,134 +270,590 @@ fn lifetime_to_expires_at(s: Option<i32>) -> Result<Option<OffsetDateTime>, AppE
     }
 }
 
+/// An embargo can't be scheduled further ahead than the longest lifetime.
+fn check_publish_at(publish_at: Option<OffsetDateTime>) -> Result<(), AppError> {
+    let limit = OffsetDateTime::now_utc() + Duration::seconds(LIFETIME_SECONDS_MAX.into());
+    if publish_at.is_some_and(|at| at > limit) {
+        return Err(AppError::Validation(format!(
+            "publish_at can be at most {LIFETIME_SECONDS_MAX} seconds ahead"
+        )));
+    }
+    Ok(())
+}
+
+/// A `burn_window_seconds` input, if any, must be one-shot or within bounds.
+fn check_burn_window(seconds: Option<i32>) -> Result<(), AppError> {
+    if seconds.is_some_and(|s| !pastedev_core::is_valid_burn_window(s)) {
+        return Err(AppError::Validation(format!(
+            "burn_window_seconds must be {BURN_WINDOW_ONE_SHOT} (one-shot) or between \
+             {BURN_WINDOW_SECONDS_MIN} and {BURN_WINDOW_SECONDS_MAX}"
+        )));
+    }
+    Ok(())
+}
+
+/// `max_views` must let at least one viewer in; `None` means no cap.
+fn check_max_views(max_views: Option<i32>) -> Result<(), AppError> {
+    if max_views.is_some_and(|n| n < 1) {
+        return Err(AppError::Validation("max_views must be at least 1".into()));
+    }
+    Ok(())
+}
+
+/// A comma-separated `?tag=` value as a list; blank entries are skipped.
+fn split_tags(param: Option<&str>) -> Vec<&str> {
+    param
+        .into_iter()
+        .flat_map(|p| p.split(','))
+        .filter(|t| !t.trim().is_empty())
+        .collect()
+}
+
+/// Normalize a tag list (see [`pastedev_core::normalize_tag`]), dropping
+/// duplicates. More than [`MAX_TAGS`] is an error, as is any tag that can't
+/// be normalized.
+pub(crate) fn clean_tags<S: AsRef<str>>(raw: &[S]) -> Result<Vec<String>, AppError> {
+    let mut tags = Vec::with_capacity(raw.len());
+    for r in raw {
+        let tag = pastedev_core::normalize_tag(r.as_ref()).ok_or_else(|| {
+            AppError::Validation(format!(
+                "invalid tag {:?}: up to {TAG_MAX_LEN} letters, digits or -_.+#:/",
+                r.as_ref()
+            ))
+        })?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    if tags.len() > MAX_TAGS {
+        return Err(AppError::Validation(format!("at most {MAX_TAGS} tags per snippet")));
+    }
+    Ok(tags)
+}
+
+/// Validate a bundle's file list: count, names (single path segment, unique),
+/// non-empty bodies, and the combined size against `snippet_max_bytes`.
+fn validate_bundle(files: &[SnippetFileInput], max_bytes: usize) -> Result<(), AppError> {
+    if files.is_empty() {
+        return Err(AppError::Validation("files must not be empty".into()));
+    }
+    if files.len() > MAX_BUNDLE_FILES {
+        return Err(AppError::Validation(format!(
+            "at most {} files per snippet",
+            MAX_BUNDLE_FILES
+        )));
+    }
+    let mut seen = std::collections::HashSet::with_capacity(files.len());
+    for f in files {
+        if !pastedev_core::is_valid_file_name(&f.name) {
+            return Err(AppError::Validation(format!("invalid file name: {:?}", f.name)));
+        }
+        if !seen.insert(f.name.as_str()) {
+            return Err(AppError::Validation(format!("duplicate file name: {}", f.name)));
+        }
+        if f.body.is_empty() {
+            return Err(AppError::Validation(format!("{} has an empty body", f.name)));
+        }
+    }
+    let total: usize = files.iter().map(|f| f.body.len()).sum();
+    if total > max_bytes {
+        return Err(AppError::SnippetTooLarge {
+            size: total,
+            limit: max_bytes,
+        });
+    }
+    Ok(())
+}
+
 /// `POST /api/v1/snippets`
 pub async fn create(
     user: RequiresScope<{ scope_id::PUBLISH }>,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(req): Json<CreateSnippetRequest>,
-) -> Result<(StatusCode, Json<Snippet>), AppError> {
-    if req.body.is_empty() {
-        return Err(AppError::Validation("body is required".into()));
+) -> Result<Response, AppError> {
+    let outcome = match idempotency::key(&state, &headers)? {
+        Some(key) => {
+            let request = serde_json::to_vec(&req).map_err(anyhow::Error::from)?;
+            let fingerprint =
+                idempotency::fingerprint(&state.config.pastedev_secret, "api", &[&request]);
+            let create = insert_snippet(state.clone(), user.0.clone(), req);
+            idempotency::run(&state, user.0.id, &key, fingerprint, create).await?
+        }
+        None => idempotency::Outcome {
+            row: insert_snippet(state.clone(), user.0, req).await?,
+            replayed: false,
+        },
+    };
+    let dto = load_dto(&state, &outcome.row).await?;
+    let mut response = (StatusCode::CREATED, Json(dto)).into_response();
+    if outcome.replayed {
+        idempotency::mark_replayed(&mut response);
     }
-    if req.body.len() > state.config.snippet_max_bytes {
-        return Err(AppError::SnippetTooLarge {
-            size: req.body.len(),
-            limit: state.config.snippet_max_bytes,
-        });
+    Ok(response)
+}
+
+/// Validate and store a new snippet. Owns its arguments so an
+/// [`idempotency::run`] can finish it after the client has gone.
+async fn insert_snippet(
+    state: AppState,
+    user: AuthedUser,
+    req: CreateSnippetRequest,
+) -> Result<SnippetRow, AppError> {
+    let max_bytes = state.config.snippet_max_bytes;
+    let encrypted = req.encrypted.unwrap_or(false);
+    let custom_slug = req.slug.as_deref();
+    if let Some(s) = custom_slug {
+        check_claimable_slug(s)?;
     }
-    let name = req
-        .name
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .map(|s| {
-            if s.len() > 255 {
-                Err(AppError::Validation("name too long".into()))
-            } else {
-                Ok(s.to_string())
+    let (kind, body, file_inputs) = match &req.content {
+        SnippetContent::Single { kind, body } => {
+            if body.is_empty() {
+                return Err(AppError::Validation("body is required".into()));
             }
-        })
-        .transpose()?;
+            if body.len() > max_bytes {
+                return Err(AppError::SnippetTooLarge {
+                    size: body.len(),
+                    limit: max_bytes,
+                });
+            }
+            if encrypted && !pastedev_core::is_valid_ciphertext(body) {
+                return Err(AppError::Validation(
+                    "encrypted body must be a v1 ciphertext envelope".into(),
+                ));
+            }
+            (*kind, body.as_str(), &[][..])
+        }
+        SnippetContent::Bundle { files } => {
+            if encrypted {
+                return Err(AppError::Validation(
+                    "multi-file snippets can't be encrypted".into(),
+                ));
+            }
+            validate_bundle(files, max_bytes)?;
+            (files[0].kind, files[0].body.as_str(), files.as_slice())
+        }
+    };
+    let name = clean_name(req.name.as_deref())?;
     let visibility = req.visibility.unwrap_or_default();
-    let expires_at = lifetime_to_expires_at(req.lifetime_seconds)?;
+    let password_hash = new_password_hash(&state, visibility, req.password.as_deref())?;
+    check_publish_at(req.publish_at)?;
+    let expires_at = lifetime_to_expires_at(req.lifetime_seconds, req.publish_at)?;
     let burn_after_read = req.burn_after_read.unwrap_or(false);
+    check_burn_window(req.burn_window_seconds)?;
+    let burn_window_seconds = req
+        .burn_window_seconds
+        .unwrap_or(BURN_AFTER_READ_WINDOW_SECONDS);
+    check_max_views(req.max_views)?;
+    let tags = clean_tags(&req.tags)?;
+    let org_id = publish_org(&state, &user, req.org.as_deref()).await?;
+    check_team_visibility(visibility, org_id)?;
+    let strategy = slug_strategy(&state, custom_slug, req.slug_strategy, visibility)?;
+    // Only now that the request is known to be valid do bodies go to the
+    // store. A bundle's parent body mirrors its first file, so it reuses that
+    // file's stored copy.
+    let mut file_drafts = Vec::with_capacity(file_inputs.len());
+    for f in file_inputs {
+        file_drafts.push(FileDraft {
+            name: &f.name,
+            kind: f.kind,
+            body: storage::store(state.bodies.as_ref(), &state.pool, &f.body).await?,
+        });
+    }
+    let body = match file_drafts.first() {
+        Some(first) => first.body.clone(),
+        None => storage::store(state.bodies.as_ref(), &state.pool, body).await?,
+    };
     let draft = SnippetDraft {
-        owner_id: user.0.id,
-        kind: req.kind,
+        owner_id: user.id,
+        kind,
         name: name.as_deref(),
-        body: &req.body,
+        body,
+        files: &file_drafts,
         visibility,
+        password_hash,
+        strict_private: false,
+        encrypted,
+        publish_at: req.publish_at,
         expires_at,
         burn_after_read,
+        burn_window_seconds,
+        max_views: req.max_views,
+        org_id,
+        forked_from: None,
+        tags: &tags,
+    };
+    let row = match custom_slug {
+        Some(s) => slug::create_with_slug(&state.pool, s, &draft).await?,
+        None => slug::create_with_retry(&state.pool, strategy, &draft).await?,
     };
-    let row = slug::create_with_retry(&state.pool, &draft).await?;
     audit::spawn_write(
         state.pool.clone(),
         audit::OwnedEvent {
             event: "snippet.create",
-            actor_user_id: Some(user.0.id),
+            actor_user_id: Some(user.id),
             target_snippet_id: Some(row.id),
             payload: Some(serde_json::json!({
                 "slug": row.slug,
                 "type": row.kind.as_str(),
                 "size_bytes": row.size_bytes,
+                "stored_bytes": row.stored_bytes,
                 "visibility": row.visibility.as_str(),
+                "encrypted": row.encrypted,
+                "org": row.org_name,
+                "publish_at": fmt_ts(row.publish_at),
                 "expires_at": fmt_ts(row.expires_at),
                 "burn_after_read": row.burn_after_read,
+                "burn_window_seconds": row.burn_window_seconds,
+                "max_views": row.max_views,
+                "tags": row.tags,
+                "files": file_drafts.len(),
             })),
             ..Default::default()
         },
     );
-    Ok((StatusCode::CREATED, Json(to_dto(&row, &state.config.public_base_url))))
+    Ok(row)
 }
 
-/// `GET /api/v1/snippets/:slug`
+/// `GET /api/v1/snippets/:slug` — with an `ETag`; a matching
+/// `If-None-Match` gets a 304 once the read has passed every access check.
 pub async fn get(
     State(state): State<AppState>,
     ClientIp(ip): ClientIp,
     headers: HeaderMap,
     Path(slug): Path<String>,
-) -> Result<Json<Snippet>, AppError> {
+    Query(link): Query<LinkQuery>,
+) -> Result<Response, AppError> {
     validate_slug(&slug)?;
-    let caller = try_extract_user(&state, &headers).await;
-    let caller_id = caller.as_ref().map(|u| u.id);
     let mut row = repo::by_slug(&state.pool, &slug)
         .await?
         .ok_or(AppError::NotFound)?;
-    enforce_access(&row, caller_id)?;
-
-    // First non-owner view of a burn-after-read snippet: stamp
-    // `first_viewed_at` AND tighten `expires_at` down to `now() + 15min` so
-    // the burn window can't outlive any pre-existing lifetime. The owner
-    // browsing their own snippet never triggers the timer.
-    if row.burn_after_read && row.first_viewed_at.is_none() && caller_id != Some(row.owner_id) {
-        match repo::mark_first_view(&state.pool, row.id).await {
-            Ok(Some(fresh)) => row = fresh,
-            Ok(None) => {}
-            Err(e) => tracing::warn!(error = ?e, slug = %row.slug, "mark_first_view failed"),
-        }
+    let reader = authorize_read(&state, ip, &headers, &row, link.token.as_deref()).await?;
+    admit_capped(&state, ip, &headers, &row, &reader).await?;
+
+    arm_burn(&state, &mut row, &reader).await?;
+    count_view(&state, ip, &headers, &row);
+    let forked_from = fork::visible_source(&state, &row, &reader).await?;
+    let tag = etag::json(&row, forked_from.as_deref());
+    if etag::not_modified(&headers, &tag) {
+        return Ok(etag::not_modified_response(&tag));
+    }
+    let mut dto = load_dto(&state, &row).await?;
+    dto.forked_from = forked_from;
+    if reader.id == Some(row.owner_id) && !reader.org_key {
+        dto.collections = row.collections;
     }
+    if let Some(token) = link.token.as_deref().filter(|_| reader.linked) {
+        links::carry(&mut dto, token);
+    }
+    Ok(tagged_json(dto, &tag))
+}
 
-    // Record unique viewer best-effort. The hash is keyed by PASTEDEV_SECRET so
-    // a DB-only leak of `snippet_views.viewer_hash` doesn't reveal which IPs
-    // visited which snippets.
-    {
+/// A JSON snippet response carrying its `ETag`.
+fn tagged_json(dto: Snippet, tag: &str) -> Response {
+    let mut response = Json(dto).into_response();
+    etag::tag(&mut response, tag);
+    response
+}
+
+/// Record a unique viewer best-effort. The hash is keyed by PASTEDEV_SECRET
+/// so a DB-only leak of `snippet_views.viewer_hash` doesn't reveal which IPs
+/// visited which snippets. A capped snippet's viewers were already recorded
+/// on admission, and its owner isn't counted at all.
+pub(crate) fn count_view(
+    state: &AppState,
+    ip: Option<std::net::IpAddr>,
+    headers: &HeaderMap,
+    row: &SnippetRow,
+) {
+    if row.max_views.is_none() {
         let pool = state.pool.clone();
         let snippet_id = row.id;
-        let hash = viewer_hash(&state.config.pastedev_secret, ip, &headers, snippet_id);
+        let hash = viewer_hash(&state.config.pastedev_secret, ip, headers, snippet_id);
         tokio::spawn(async move {
             let _ = repo::record_view(&pool, snippet_id, &hash).await;
         });
     }
-    Ok(Json(to_dto(&row, &state.config.public_base_url)))
+}
+
+/// A display name as given on create: trimmed, blank meaning none.
+pub(crate) fn clean_name(name: Option<&str>) -> Result<Option<String>, AppError> {
+    name.map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if s.len() > 255 {
+                Err(AppError::Validation("name too long".into()))
+            } else {
+                Ok(s.to_string())
+            }
+        })
+        .transpose()
+}
+
+/// The passphrase hash a snippet with `visibility` should store: required
+/// with `password` visibility, refused with any other.
+pub(crate) fn new_password_hash(
+    state: &AppState,
+    visibility: Visibility,
+    password: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    match (visibility, password) {
+        (Visibility::Password, Some(p)) => Ok(Some(unlock::hash_password(&state.config, p)?)),
+        (Visibility::Password, None) => Err(AppError::Validation(
+            "password visibility requires a password".into(),
+        )),
+        (_, Some(_)) => Err(AppError::Validation(
+            "password is only allowed with password visibility".into(),
+        )),
+        (_, None) => Ok(None),
+    }
+}
+
+/// Who is reading a snippet, as far as [`enforce_access`] cares. Built by
+/// [`reader`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reader {
+    pub id: Option<Uuid>,
+    /// The owner — for an org snippet, any member of the org.
+    pub owns: bool,
+    /// Holds an unlock cookie, see [`unlock::is_unlocked`].
+    pub unlocked: bool,
+    /// On the share list, see [`shares::is_shared`].
+    pub shared: bool,
+    /// Came in through a signed share link, see [`links::redeem`].
+    pub linked: bool,
+    /// Signed in with an org-scoped key, which doesn't carry its user's
+    /// personal data such as collections.
+    pub org_key: bool,
+}
+
+/// Resolve the caller (cookie or bearer, best-effort) into a [`Reader`] of
+/// `row`. Only hits the database for org membership and share lists.
+pub(crate) async fn reader(
+    state: &AppState,
+    headers: &HeaderMap,
+    row: &SnippetRow,
+) -> Result<Reader, AppError> {
+    let caller = try_extract_user(state, headers).await;
+    let caller_id = caller.as_ref().map(|u| u.id);
+    // Outside its org, an org-scoped key is just someone signed in: no
+    // owner's shortcuts, and none of its user's personal shares.
+    let in_scope = caller.as_ref().is_none_or(|u| key_reaches(u, row));
+    let owns = match (caller_id, row.org_id) {
+        _ if !in_scope => false,
+        (None, _) => false,
+        (Some(uid), None) => uid == row.owner_id,
+        (Some(uid), Some(org_id)) => orgs::repo::role_of(&state.pool, org_id, uid)
+            .await?
+            .is_some(),
+    };
+    Ok(Reader {
+        id: caller_id,
+        owns,
+        unlocked: unlock::is_unlocked(&state.config.pastedev_secret, headers, row),
+        shared: !owns && in_scope && shares::is_shared(state, row, caller_id).await?,
+        linked: false,
+        org_key: caller.is_some_and(|u| u.key_org_id.is_some()),
+    })
+}
+
+/// Whether `user`'s credentials reach `row` at all: an org-scoped key only
+/// reaches its own org's snippets, whatever its user could do otherwise.
+pub(crate) fn key_reaches(user: &AuthedUser, row: &SnippetRow) -> bool {
+    user.key_org_id.is_none_or(|key_org| row.org_id == Some(key_org))
+}
+
+/// [`reader`] plus [`enforce_access`], falling back to the signed share
+/// link in `link` (the `?token=` query parameter) when the reader can't get
+/// in otherwise. Only that fallback counts as a use of the link, and only
+/// the first time this viewer comes.
+pub(crate) async fn authorize_read(
+    state: &AppState,
+    ip: Option<std::net::IpAddr>,
+    headers: &HeaderMap,
+    row: &SnippetRow,
+    link: Option<&str>,
+) -> Result<Reader, AppError> {
+    let mut reader = reader(state, headers, row).await?;
+    let Err(denied) = enforce_access(row, &reader) else {
+        return Ok(reader);
+    };
+    let Some(token) = link else {
+        return Err(denied);
+    };
+    let viewer = viewer_hash(&state.config.pastedev_secret, ip, headers, row.id);
+    reader.linked = links::redeem(state, row, token, &viewer).await?;
+    enforce_access(row, &reader)?;
+    Ok(reader)
+}
+
+/// Hold a non-owner read of a `max_views` snippet to the cap: a viewer who
+/// doesn't fit anymore gets the same 404 as for an expired snippet. Run after
+/// the access check, so only readers who could otherwise get in take a slot.
+pub(crate) async fn admit_capped(
+    state: &AppState,
+    ip: Option<std::net::IpAddr>,
+    headers: &HeaderMap,
+    row: &SnippetRow,
+    reader: &Reader,
+) -> Result<(), AppError> {
+    if row.max_views.is_none() || reader.owns {
+        return Ok(());
+    }
+    let hash = viewer_hash(&state.config.pastedev_secret, ip, headers, row.id);
+    if !repo::admit_viewer(&state.pool, row.id, &hash).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(())
+}
+
+/// First non-owner view of a burn-after-read snippet: stamp
+/// `first_viewed_at` AND tighten `expires_at` down to `now() + window` so the
+/// burn window can't outlive any pre-existing lifetime, refreshing `row` to
+/// match. The owner browsing their own snippet never triggers the timer.
+///
+/// Arming is best-effort for a timed fuse, but a one-shot snippet is only
+/// served to the reader whose read armed it: losing that race to a
+/// concurrent reader, or failing to arm at all, is a 404.
+pub(crate) async fn arm_burn(
+    state: &AppState,
+    row: &mut SnippetRow,
+    reader: &Reader,
+) -> Result<(), AppError> {
+    if !row.burn_after_read || row.first_viewed_at.is_some() || reader.owns {
+        return Ok(());
+    }
+    let one_shot = row.burn_window_seconds == BURN_WINDOW_ONE_SHOT;
+    match repo::mark_first_view(&state.pool, row.id).await {
+        Ok(Some(fresh)) => {
+            if let Some(expires_at) = fresh.expires_at {
+                let event = SnippetEvent::BurnArmed { expires_at };
+                events::spawn_notify(state.pool.clone(), fresh.id, event);
+            }
+            *row = fresh;
+        }
+        Ok(None) if one_shot => return Err(AppError::NotFound),
+        Ok(None) => {}
+        Err(e) if one_shot => return Err(e.into()),
+        Err(e) => tracing::warn!(error = ?e, slug = %row.slug, "mark_first_view failed"),
+    }
+    Ok(())
 }
 
 /// Centralised access check for read paths. Enforces visibility (private
-/// snippets require an authenticated caller) and expiry (non-owner callers can
-/// no longer read past the effective expiry). The owner can always read their
-/// own snippet, even after it has expired for everyone else.
+/// snippets require an authenticated caller — one on the share list when
+/// `strict_private` is on, see [`shares::is_shared`] — team ones a member of
+/// the owning org, and password-protected ones an unlock cookie, see
+/// [`unlock::is_unlocked`]; a signed share link stands in for all of these)
+/// and the publication window (non-owner callers can't read before
+/// `publish_at` or past the effective expiry). The owner can always read
+/// their own snippet, even while it is embargoed or after it has expired for
+/// everyone else.
 ///
 /// All "you can't see this" outcomes resolve to `NotFound` instead of
 /// `Unauthorized`/`Forbidden` so an external scanner can't tell a private
 /// slug apart from an absent / expired one. The slug itself is the sharing
 /// capability — leaking "this slug exists but is private" leaks structure.
-pub fn enforce_access(row: &SnippetRow, caller_id: Option<Uuid>) -> Result<(), AppError> {
-    let is_owner = caller_id == Some(row.owner_id);
+///
+/// A locked password-protected snippet is the one exception: it answers
+/// `PasswordRequired` so the reader knows to ask for the passphrase. That is
+/// checked after expiry, so an expired one is still a plain 404.
+pub fn enforce_access(row: &SnippetRow, reader: &Reader) -> Result<(), AppError> {
+    let is_owner = reader.owns;
+    let linked = reader.linked;
 
-    if row.visibility == Visibility::Private && !is_owner && caller_id.is_none() {
+    if row.visibility == Visibility::Team && !is_owner && !linked {
+        return Err(AppError::NotFound);
+    }
+    if row.visibility == Visibility::Private && !is_owner && !linked && reader.id.is_none() {
         return Err(AppError::NotFound);
     }
     // Private + non-owner authed caller is allowed: any approved user can read
     // a private snippet whose slug they've been given. "private" gates against
-    // drive-by anonymous access, not against authed lateral sharing.
+    // drive-by anonymous access, not against authed lateral sharing — unless
+    // the owner opted into strict mode, which limits it to the share list.
+    if row.visibility == Visibility::Private
+        && row.strict_private
+        && !is_owner
+        && !linked
+        && !reader.shared
+    {
+        return Err(AppError::NotFound);
+    }
 
     if !is_owner {
+        let now = OffsetDateTime::now_utc();
+        if row.publish_at.is_some_and(|at| at > now) {
+            return Err(AppError::NotFound);
+        }
         if let Some(exp) = row.expires_at {
-            if exp <= OffsetDateTime::now_utc() {
+            if exp <= now {
                 return Err(AppError::NotFound);
             }
         }
     }
+    if row.visibility == Visibility::Password && !is_owner && !linked && !reader.unlocked {
+        return Err(AppError::PasswordRequired);
+    }
+    Ok(())
+}
+
+/// Write access to an existing snippet. A personal snippet is its owner's
+/// alone, and not through an org-scoped key. An org snippet takes an org
+/// role of at least `need` — except that whoever published it may keep
+/// editing and deleting it for as long as they stay a member — and an
+/// org-scoped key only reaches its own org's snippets.
+pub(crate) async fn ensure_can_manage(
+    state: &AppState,
+    user: &AuthedUser,
+    row: &SnippetRow,
+    need: OrgRole,
+) -> Result<(), AppError> {
+    if !key_reaches(user, row) {
+        return Err(AppError::Forbidden(Some(match row.org_id {
+            None => "key is scoped to an org",
+            Some(_) => "key is scoped to another org",
+        })));
+    }
+    let Some(org_id) = row.org_id else {
+        if row.owner_id != user.id {
+            return Err(AppError::Forbidden(None));
+        }
+        return Ok(());
+    };
+    let role = orgs::repo::role_of(&state.pool, org_id, user.id)
+        .await?
+        .ok_or(AppError::Forbidden(None))?;
+    if role < need && row.owner_id != user.id {
+        return Err(AppError::Forbidden(Some("insufficient org role")));
+    }
+    Ok(())
+}
+
+/// The org a new snippet is published into: `requested` by name (the caller
+/// must be a member), else the org the API key is scoped to, else none.
+pub(crate) async fn publish_org(
+    state: &AppState,
+    user: &AuthedUser,
+    requested: Option<&str>,
+) -> Result<Option<Uuid>, AppError> {
+    let Some(name) = requested else {
+        return Ok(user.key_org_id);
+    };
+    let (org, _) = orgs::handlers::membership(state, name, user.id).await?;
+    if user.key_org_id.is_some_and(|id| id != org.id) {
+        return Err(AppError::Forbidden(Some("key is scoped to another org")));
+    }
+    Ok(Some(org.id))
+}
+
+/// `team` visibility means "members of the owning org", so it needs one.
+pub(crate) fn check_team_visibility(visibility: Visibility, org_id: Option<Uuid>) -> Result<(), AppError> {
+    if visibility == Visibility::Team && org_id.is_none() {
+        return Err(AppError::Validation(
+            "team visibility requires an org-owned snippet".into(),
+        ));
+    }
     Ok(())
 }
 
@@ -246,7 +861,7 @@ pub fn enforce_access(row: &SnippetRow, caller_id: Option<Uuid>) -> Result<(), A
 ///   ip_string || 0x00 || user_agent || 0x00 || snippet_id_bytes
 /// — null separators avoid any ambiguity between fields (an IP can't contain
 /// a NUL, neither can a header value Axum has parsed).
-fn viewer_hash(
+pub(crate) fn viewer_hash(
     secret: &str,
     ip: Option<std::net::IpAddr>,
     headers: &HeaderMap,
@@ -266,21 +881,28 @@ fn viewer_hash(
     hmac_sha256_bytes(secret, &material)
 }
 
-/// `PATCH /api/v1/snippets/:slug`
+/// `PATCH /api/v1/snippets/:slug` — honours `If-Match`, see [`etag`].
 pub async fn patch(
     user: RequiresScope<{ scope_id::PUBLISH }>,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(slug): Path<String>,
     Json(req): Json<PatchSnippetRequest>,
-) -> Result<Json<Snippet>, AppError> {
+) -> Result<Response, AppError> {
     validate_slug(&slug)?;
     let existing = repo::by_slug(&state.pool, &slug)
         .await?
         .ok_or(AppError::NotFound)?;
-    if existing.owner_id != user.0.id {
-        return Err(AppError::Forbidden(None));
-    }
+    ensure_can_manage(&state, &user.0, &existing, OrgRole::Member).await?;
+    let expected_updated_at = etag::expected_version(&headers, &existing)?;
     let body_owned = req.body;
+    if body_owned.is_some() && repo::is_bundle(&state.pool, existing.id).await? {
+        // The parent body only mirrors the first file; overwriting it would
+        // silently desync the two.
+        return Err(AppError::Validation(
+            "multi-file snippets can't be edited through `body`".into(),
+        ));
+    }
     if let Some(b) = body_owned.as_deref() {
         if b.is_empty() {
             return Err(AppError::Validation("body cannot be empty".into()));
@@ -291,6 +913,11 @@ pub async fn patch(
                 limit: state.config.snippet_max_bytes,
             });
         }
+        if existing.encrypted && !pastedev_core::is_valid_ciphertext(b) {
+            return Err(AppError::Validation(
+                "encrypted body must be a v1 ciphertext envelope".into(),
+            ));
+        }
     }
     let name_outer = req.name.map(|s| {
         let trimmed = s.trim();
@@ -301,14 +928,25 @@ pub async fn patch(
         }
     });
 
+    let body = match body_owned.as_deref() {
+        Some(b) => Some(storage::store(state.bodies.as_ref(), &state.pool, b).await?),
+        None => None,
+    };
     let patch = SnippetPatch {
-        body: body_owned.as_deref(),
+        body,
         name: name_outer.as_ref().map(|opt| opt.as_deref()),
+        expected_updated_at,
     };
     let old_size = existing.size_bytes;
-    let updated = repo::update(&state.pool, &slug, user.0.id, patch)
-        .await?
-        .ok_or(AppError::NotFound)?;
+    let updated = repo::update(
+        &state.pool,
+        &existing.slug,
+        existing.owner_id,
+        user.0.id,
+        patch,
+    )
+    .await?
+    .ok_or(precondition_or_missing(expected_updated_at))?;
     audit::spawn_write(
         state.pool.clone(),
         audit::OwnedEvent {
@@ -318,58 +956,110 @@ pub async fn patch(
             payload: Some(serde_json::json!({
                 "old_size_bytes": old_size,
                 "new_size_bytes": updated.size_bytes,
+                "new_stored_bytes": updated.stored_bytes,
             })),
             ..Default::default()
         },
     );
-    Ok(Json(to_dto(&updated, &state.config.public_base_url)))
+    let event = match body_owned {
+        Some(_) => SnippetEvent::Edit { size_bytes: updated.size_bytes },
+        None => SnippetEvent::Settings,
+    };
+    events::spawn_notify(state.pool.clone(), updated.id, event);
+    let tag = etag::json(&updated, updated.forked_from_slug.as_deref());
+    Ok(tagged_json(load_dto(&state, &updated).await?, &tag))
+}
+
+/// What a conditional write that matched no row means: with an `If-Match`,
+/// that the version it named is gone (whether edited or deleted).
+fn precondition_or_missing(expected_updated_at: Option<OffsetDateTime>) -> AppError {
+    match expected_updated_at {
+        Some(_) => AppError::PreconditionFailed,
+        None => AppError::NotFound,
+    }
 }
 
 /// `PATCH /api/v1/snippets/:slug/settings` — owner-only sharing-policy mutator.
 ///
 /// Disabling `burn_after_read` also clears `first_viewed_at` so the
-/// previously-started 15-min fuse is fully cancelled (otherwise re-enabling
+/// previously-started fuse is fully cancelled (otherwise re-enabling
 /// it later would re-arm an already-spent timer). It does *not* roll back
 /// any `expires_at` that the burn already tightened — that original
 /// pre-burn lifetime isn't recorded anywhere. If the owner wants the
 /// snippet to live longer again, they have to pass `lifetime_seconds` in
 /// the same (or a follow-up) request and re-stamp expiry explicitly.
+///
+/// Honours `If-Match` like [`patch`].
 pub async fn update_settings(
     user: RequiresScope<{ scope_id::PUBLISH }>,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path(slug): Path<String>,
     Json(req): Json<SettingsRequest>,
-) -> Result<Json<Snippet>, AppError> {
+) -> Result<Response, AppError> {
     validate_slug(&slug)?;
 
     let existing = repo::by_slug(&state.pool, &slug)
         .await?
         .ok_or(AppError::NotFound)?;
-    if existing.owner_id != user.0.id {
-        return Err(AppError::Forbidden(None));
-    }
+    ensure_can_manage(&state, &user.0, &existing, OrgRole::Member).await?;
+    let expected_updated_at = etag::expected_version(&headers, &existing)?;
 
-    if req.visibility.is_none() && req.lifetime_seconds.is_none() && req.burn_after_read.is_none() {
+    if req.visibility.is_none()
+        && req.lifetime_seconds.is_none()
+        && req.publish_at.is_none()
+        && req.burn_after_read.is_none()
+        && req.burn_window_seconds.is_none()
+        && req.max_views.is_none()
+        && req.password.is_none()
+    {
         return Err(AppError::Validation("at least one field is required".into()));
     }
 
+    // The hash follows the visibility the snippet ends up with: a new
+    // passphrase replaces it, leaving `password` visibility drops it.
+    let visibility = req.visibility.unwrap_or(existing.visibility);
+    check_team_visibility(visibility, existing.org_id)?;
+    let password_hash = match (visibility, req.password.as_deref()) {
+        (Visibility::Password, None) if existing.password_hash.is_some() => None,
+        (Visibility::Password, _) | (_, Some(_)) => {
+            Some(new_password_hash(&state, visibility, req.password.as_deref())?)
+        }
+        (_, None) => existing.password_hash.is_some().then_some(None),
+    };
+
     // Convert the user's `lifetime_seconds` choice into an absolute
     // `expires_at = now() + lifetime` so picking "15 min" on an
     // already-expired snippet means "alive for 15 more minutes from now",
     // not "15 minutes after creation" (which would still be in the past).
+    // A new lifetime counts from the end of the embargo the snippet ends up
+    // with, if that is later.
+    if let Some(publish_at) = req.publish_at {
+        check_publish_at(publish_at)?;
+    }
+    let publish_at = req.publish_at.unwrap_or(existing.publish_at);
     let expires_at = match req.lifetime_seconds {
-        Some(opt) => Some(lifetime_to_expires_at(opt)?),
+        Some(opt) => Some(lifetime_to_expires_at(opt, publish_at)?),
         None => None,
     };
+    check_burn_window(req.burn_window_seconds)?;
+    if let Some(max_views) = req.max_views {
+        check_max_views(max_views)?;
+    }
 
     let patch = repo::SettingsPatch {
         visibility: req.visibility,
+        password_hash,
         expires_at,
+        publish_at: req.publish_at,
         burn_after_read: req.burn_after_read,
+        burn_window_seconds: req.burn_window_seconds,
+        max_views: req.max_views,
+        expected_updated_at,
     };
-    let updated = repo::update_settings(&state.pool, &slug, user.0.id, patch)
+    let updated = repo::update_settings(&state.pool, &existing.slug, existing.owner_id, patch)
         .await?
-        .ok_or(AppError::NotFound)?;
+        .ok_or(precondition_or_missing(expected_updated_at))?;
     audit::spawn_write(
         state.pool.clone(),
         audit::OwnedEvent {
@@ -377,21 +1067,30 @@ pub async fn update_settings(
             actor_user_id: Some(user.0.id),
             target_snippet_id: Some(updated.id),
             payload: Some(serde_json::json!({
+                "password_changed": req.password.is_some(),
                 "old": {
                     "visibility": existing.visibility.as_str(),
+                    "publish_at": fmt_ts(existing.publish_at),
                     "expires_at": fmt_ts(existing.expires_at),
                     "burn_after_read": existing.burn_after_read,
+                    "burn_window_seconds": existing.burn_window_seconds,
+                    "max_views": existing.max_views,
                 },
                 "new": {
                     "visibility": updated.visibility.as_str(),
+                    "publish_at": fmt_ts(updated.publish_at),
                     "expires_at": fmt_ts(updated.expires_at),
                     "burn_after_read": updated.burn_after_read,
+                    "burn_window_seconds": updated.burn_window_seconds,
+                    "max_views": updated.max_views,
                 },
             })),
             ..Default::default()
         },
     );
-    Ok(Json(to_dto(&updated, &state.config.public_base_url)))
+    events::spawn_notify(state.pool.clone(), updated.id, SnippetEvent::Settings);
+    let tag = etag::json(&updated, updated.forked_from_slug.as_deref());
+    Ok(tagged_json(load_dto(&state, &updated).await?, &tag))
 }
 
 /// `DELETE /api/v1/snippets/:slug`
@@ -404,10 +1103,8 @@ pub async fn delete(
     let existing = repo::by_slug(&state.pool, &slug)
         .await?
         .ok_or(AppError::NotFound)?;
-    if existing.owner_id != user.0.id {
-        return Err(AppError::Forbidden(None));
-    }
-    let removed = repo::delete(&state.pool, &slug, user.0.id).await?;
+    ensure_can_manage(&state, &user.0, &existing, OrgRole::Maintainer).await?;
+    let removed = repo::delete(&state.pool, &existing.slug, existing.owner_id).await?;
     if !removed {
         return Err(AppError::NotFound);
     }
@@ -421,10 +1118,14 @@ pub async fn delete(
             ..Default::default()
         },
     );
+    events::spawn_notify(state.pool.clone(), existing.id, SnippetEvent::Deleted);
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// `GET /api/v1/snippets` — caller's own snippets.
+/// `GET /api/v1/snippets` — caller's own snippets (or, with `?org=`, that
+/// org's), keyset-paginated on
+/// `(sort_key, id)` so rows sharing a sort key are never skipped or repeated
+/// across pages.
 pub async fn list(
     user: RequiresScope<{ scope_id::READ }>,
     State(state): State<AppState>,
@@ -435,28 +1136,88 @@ pub async fn list(
         .as_deref()
         .map(|s| s.parse::<SnippetType>().map_err(|_| AppError::Validation("invalid type".into())))
         .transpose()?;
+    let visibility = q
+        .visibility
+        .as_deref()
+        .map(|s| s.parse::<Visibility>().map_err(|_| AppError::Validation("invalid visibility".into())))
+        .transpose()?;
+    let sort = q
+        .sort
+        .as_deref()
+        .map(|s| s.parse::<SnippetSort>().map_err(|_| AppError::Validation("invalid sort".into())))
+        .transpose()?
+        .unwrap_or_default();
+    let order = q
+        .order
+        .as_deref()
+        .map(|s| s.parse::<SortOrder>().map_err(|_| AppError::Validation("invalid order".into())))
+        .transpose()?
+        .unwrap_or_else(|| sort.default_order());
     let cursor = q
         .cursor
         .as_deref()
-        .map(decode_cursor)
+        .map(|c| decode_cursor(c, sort, order))
         .transpose()?;
     let limit = q.limit.unwrap_or(50).clamp(1, 200);
+    let tags = clean_tags(&split_tags(q.tag.as_deref()))?;
+    let collection_id = match q.collection.as_deref() {
+        Some(name) => {
+            collections::handlers::ensure_personal(&user.0)?;
+            Some(collections::handlers::load(&state, user.0.id, name).await?.id)
+        }
+        None => None,
+    };
+    // An org-scoped key only ever lists its org.
+    let org_id = match q.org.as_deref() {
+        Some(name) => {
+            let (org, _) = orgs::handlers::membership(&state, name, user.0.id).await?;
+            if user.0.key_org_id.is_some_and(|id| id != org.id) {
+                return Err(AppError::Forbidden(Some("key is scoped to another org")));
+            }
+            Some(org.id)
+        }
+        None => user.0.key_org_id,
+    };
 
     let filter = ListFilter {
         owner_id: user.0.id,
+        org_id,
         kind,
+        visibility,
+        burn_after_read: q.burn_after_read,
+        expired: q.expired,
+        tags: &tags,
+        collection_id,
+        sort,
+        order,
         cursor: cursor.as_ref(),
         limit: limit + 1, // peek for next-page presence
     };
     let mut rows = repo::list_for_user(&state.pool, filter).await?;
     let mut next_cursor = None;
     if rows.len() as i64 > limit {
-        let extra = rows.pop().expect("len > limit ⇒ pop");
-        next_cursor = Some(encode_cursor(&extra.created_at));
+        rows.pop();
+        next_cursor = rows.last().map(|last| {
+            let position = ListCursor {
+                key: repo::sort_key(last, sort),
+                id: last.id,
+            };
+            encode_cursor(sort, order, &position)
+        });
+    }
+    // Collections are per user; fellow org members don't see each other's,
+    // and an org-scoped key doesn't see its user's.
+    for row in rows
+        .iter_mut()
+        .filter(|r| r.owner_id != user.0.id || user.0.key_org_id.is_some())
+    {
+        row.collections.clear();
     }
     Ok(Json(ListSnippetsResponse {
         items: rows.iter().map(to_list_item).collect(),
         next_cursor,
+        sort,
+        order,
     }))
 }
 
@@ -467,6 +1228,12 @@ pub struct PasteQuery {
     pub visibility: Option<String>,
     pub lifetime_seconds: Option<i32>,
     pub burn_after_read: Option<bool>,
+    pub burn_window_seconds: Option<i32>,
+    pub max_views: Option<i32>,
+    pub slug: Option<String>,
+    pub slug_strategy: Option<String>,
+    /// Comma-separated.
+    pub tag: Option<String>,
 }
 
 /// `POST /paste` — curl-friendly alias for snippet creation.
@@ -477,9 +1244,52 @@ pub struct PasteQuery {
 pub async fn paste_raw(
     user: RequiresScope<{ scope_id::PUBLISH }>,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(q): Query<PasteQuery>,
+    RawQuery(query): RawQuery,
     body: String,
 ) -> Result<Response, AppError> {
+    let outcome = match idempotency::key(&state, &headers)? {
+        Some(key) => {
+            let query = query.unwrap_or_default();
+            let fingerprint = idempotency::fingerprint(
+                &state.config.pastedev_secret,
+                "paste",
+                &[query.as_bytes(), body.as_bytes()],
+            );
+            let create = insert_paste(state.clone(), user.0.clone(), q, body);
+            idempotency::run(&state, user.0.id, &key, fingerprint, create).await?
+        }
+        None => idempotency::Outcome {
+            row: insert_paste(state.clone(), user.0, q, body).await?,
+            replayed: false,
+        },
+    };
+    let row = outcome.row;
+    let url = format!(
+        "{}{}{}\n",
+        state.config.public_base_url,
+        route_prefix(row.kind),
+        row.slug
+    );
+    let mut response = Response::new(Body::from(url));
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    if outcome.replayed {
+        idempotency::mark_replayed(&mut response);
+    }
+    Ok(response)
+}
+
+/// [`insert_snippet`] for `POST /paste`.
+async fn insert_paste(
+    state: AppState,
+    user: AuthedUser,
+    q: PasteQuery,
+    body: String,
+) -> Result<SnippetRow, AppError> {
     let kind = q
         .kind
         .as_deref()
@@ -495,29 +1305,63 @@ pub async fn paste_raw(
             limit: state.config.snippet_max_bytes,
         });
     }
+    let custom_slug = q.slug.as_deref();
+    if let Some(s) = custom_slug {
+        check_claimable_slug(s)?;
+    }
     let visibility = q
         .visibility
         .as_deref()
         .map(|s| s.parse::<Visibility>().map_err(|_| AppError::Validation("invalid visibility".into())))
         .transpose()?
         .unwrap_or_default();
-    let expires_at = lifetime_to_expires_at(q.lifetime_seconds)?;
+    // No passphrase field here: a query string ends up in logs and shell
+    // history. Password-protected snippets are created through the JSON API.
+    let password_hash = new_password_hash(&state, visibility, None)?;
+    let expires_at = lifetime_to_expires_at(q.lifetime_seconds, None)?;
     let burn_after_read = q.burn_after_read.unwrap_or(false);
+    check_burn_window(q.burn_window_seconds)?;
+    let burn_window_seconds = q
+        .burn_window_seconds
+        .unwrap_or(BURN_AFTER_READ_WINDOW_SECONDS);
+    check_max_views(q.max_views)?;
+    let tags = clean_tags(&split_tags(q.tag.as_deref()))?;
+    let org_id = publish_org(&state, &user, None).await?;
+    check_team_visibility(visibility, org_id)?;
+    let requested = q
+        .slug_strategy
+        .as_deref()
+        .map(|s| s.parse::<SlugStrategy>().map_err(AppError::Validation))
+        .transpose()?;
+    let strategy = slug_strategy(&state, custom_slug, requested, visibility)?;
     let draft = SnippetDraft {
-        owner_id: user.0.id,
+        owner_id: user.id,
         kind,
         name: None,
-        body: &body,
+        body: storage::store(state.bodies.as_ref(), &state.pool, &body).await?,
+        files: &[],
         visibility,
+        password_hash,
+        strict_private: false,
+        encrypted: false,
+        publish_at: None,
         expires_at,
         burn_after_read,
+        burn_window_seconds,
+        max_views: q.max_views,
+        org_id,
+        forked_from: None,
+        tags: &tags,
+    };
+    let row = match custom_slug {
+        Some(s) => slug::create_with_slug(&state.pool, s, &draft).await?,
+        None => slug::create_with_retry(&state.pool, strategy, &draft).await?,
     };
-    let row = slug::create_with_retry(&state.pool, &draft).await?;
     audit::spawn_write(
         state.pool.clone(),
         audit::OwnedEvent {
             event: "snippet.create",
-            actor_user_id: Some(user.0.id),
+            actor_user_id: Some(user.id),
             target_snippet_id: Some(row.id),
             payload: Some(serde_json::json!({
                 "slug": row.slug,
@@ -526,60 +1370,158 @@ pub async fn paste_raw(
                 "visibility": row.visibility.as_str(),
                 "expires_at": fmt_ts(row.expires_at),
                 "burn_after_read": row.burn_after_read,
+                "org": row.org_name,
+                "tags": row.tags,
                 "via": "paste",
             })),
             ..Default::default()
         },
     );
-    let prefix = match row.kind {
-        SnippetType::Code => "/c/",
-        SnippetType::Markdown => "/m/",
-        SnippetType::Html => "/h/",
+    Ok(row)
+}
+
+/// Shared prologue of the raw routes: resolve the slug, enforce access and
+/// `max_views`, and start the burn timer on a first non-owner read. `encrypted` is the route
+/// family the request came in on (`/e/` or not); a row of the other kind is
+/// a 404, so ciphertext is never served as text or HTML.
+async fn load_for_raw(
+    state: &AppState,
+    ip: Option<std::net::IpAddr>,
+    headers: &HeaderMap,
+    slug: &str,
+    encrypted: bool,
+    link: &LinkQuery,
+) -> Result<SnippetRow, AppError> {
+    validate_slug(slug)?;
+    let mut row = repo::by_slug(&state.pool, slug)
+        .await?
+        .filter(|r| r.encrypted == encrypted)
+        .ok_or(AppError::NotFound)?;
+    let reader = authorize_read(state, ip, headers, &row, link.token.as_deref()).await?;
+    admit_capped(state, ip, headers, &row, &reader).await?;
+
+    // Same first-view stamping as the JSON GET: raw fetches by non-owners also
+    // start the burn timer so `curl /c/<slug>/raw` doesn't bypass it.
+    arm_burn(state, &mut row, &reader).await?;
+    Ok(row)
+}
+
+/// Whether `Accept-Encoding` lists `zstd` (without `q=0`).
+fn accepts_zstd(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|item| {
+            let mut parts = item.split(';').map(str::trim);
+            let is_zstd = parts.next().is_some_and(|c| c.eq_ignore_ascii_case("zstd"));
+            let refused = parts.any(|p| {
+                p.strip_prefix("q=").and_then(|q| q.trim().parse::<f32>().ok()) == Some(0.0)
+            });
+            is_zstd && !refused
+        })
+}
+
+/// A row's full body as a response body, plus the encoding it is in. Large
+/// bodies stream from the body store instead of being buffered whole; with
+/// `accept_zstd` a compressed body goes out exactly as it is stored.
+async fn raw_body(
+    state: &AppState,
+    inline: String,
+    location: Location,
+    accept_zstd: bool,
+) -> Result<(Body, Codec), AppError> {
+    let (content, codec) =
+        storage::open(state.bodies.as_ref(), inline, location, accept_zstd).await?;
+    let body = match content {
+        Content::Whole(bytes) => Body::from(bytes),
+        Content::Stream(stream) => Body::from_stream(stream),
     };
-    let url = format!("{}{}{}\n", state.config.public_base_url, prefix, row.slug);
-    let mut response = Response::new(Body::from(url));
+    Ok((body, codec))
+}
+
+fn raw_response(body: Body, codec: Codec, content_type: &'static str) -> Response {
+    let mut response = Response::new(body);
     response.headers_mut().insert(
         header::CONTENT_TYPE,
-        HeaderValue::from_static("text/plain; charset=utf-8"),
+        HeaderValue::from_static(content_type),
     );
-    Ok(response)
+    if codec == Codec::Zstd {
+        response.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static("zstd"),
+        );
+    }
+    response.headers_mut().insert(
+        header::VARY,
+        HeaderValue::from_static("accept-encoding"),
+    );
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=0"),
+    );
+    response
 }
 
-/// Raw `/c/:slug/raw` and `/m/:slug/raw` — `text/plain`.
+/// Raw `/c/:slug/raw` and `/m/:slug/raw` — `text/plain`, with an `ETag`.
 pub async fn raw_text(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
     headers: HeaderMap,
     Path(slug): Path<String>,
+    Query(link): Query<LinkQuery>,
 ) -> Result<Response, AppError> {
-    validate_slug(&slug)?;
-    let caller = try_extract_user(&state, &headers).await;
-    let caller_id = caller.as_ref().map(|u| u.id);
-    let mut row = repo::by_slug(&state.pool, &slug)
-        .await?
-        .ok_or(AppError::NotFound)?;
-    enforce_access(&row, caller_id)?;
-
-    // Same first-view stamping as the JSON GET: raw fetches by non-owners also
-    // start the burn timer so `curl /c/<slug>/raw` doesn't bypass it.
-    if row.burn_after_read && row.first_viewed_at.is_none() && caller_id != Some(row.owner_id) {
-        if let Ok(Some(fresh)) = repo::mark_first_view(&state.pool, row.id).await {
-            row = fresh;
-        }
-    }
+    let row = load_for_raw(&state, ip, &headers, &slug, false, &link).await?;
     // The prefix in the URL is informational; we don't enforce it. The HTML
     // sandbox route is a separate handler below — anything not html lands here.
-    let mut response = Response::new(Body::from(row.body));
-    response.headers_mut().insert(
-        header::CONTENT_TYPE,
-        HeaderValue::from_static("text/plain; charset=utf-8"),
-    );
+    let accept_zstd = accepts_zstd(&headers);
+    let tag = etag::etag(&row, storage::served_codec(&row.location, accept_zstd));
+    if etag::not_modified(&headers, &tag) {
+        return Ok(etag::not_modified_response(&tag));
+    }
+    let (body, codec) = raw_body(&state, row.body, row.location, accept_zstd).await?;
+    let mut response = raw_response(body, codec, "text/plain; charset=utf-8");
+    etag::tag(&mut response, &tag);
+    Ok(response)
+}
+
+/// Raw `/e/:slug/raw` — an encrypted snippet's ciphertext envelope as opaque
+/// bytes. `nosniff` keeps browsers from guessing a type for it.
+pub async fn raw_encrypted(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    Query(link): Query<LinkQuery>,
+) -> Result<Response, AppError> {
+    let row = load_for_raw(&state, ip, &headers, &slug, true, &link).await?;
+    let (body, codec) = raw_body(&state, row.body, row.location, accepts_zstd(&headers)).await?;
+    let mut response = raw_response(body, codec, "application/octet-stream");
     response.headers_mut().insert(
-        header::CACHE_CONTROL,
-        HeaderValue::from_static("private, max-age=0"),
+        axum::http::HeaderName::from_static("x-content-type-options"),
+        HeaderValue::from_static("nosniff"),
     );
     Ok(response)
 }
 
+/// Raw `/c/:slug/raw/:filename` and `/m/:slug/raw/:filename` — one file of a
+/// bundle as `text/plain`, whatever its type.
+pub async fn raw_file_text(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Path((slug, filename)): Path<(String, String)>,
+    Query(link): Query<LinkQuery>,
+) -> Result<Response, AppError> {
+    let row = load_for_raw(&state, ip, &headers, &slug, false, &link).await?;
+    let file = repo::file_by_name(&state.pool, row.id, &filename)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let (body, codec) = raw_body(&state, file.body, file.location, accepts_zstd(&headers)).await?;
+    Ok(raw_response(body, codec, "text/plain; charset=utf-8"))
+}
+
 /// Exact CSP value the `/h/:slug/raw` route emits. Kept as a constant so the
 /// regression test can assert byte-for-byte equality.
 pub const HTML_SANDBOX_CSP: &str = "sandbox allow-scripts allow-popups";
@@ -622,32 +1564,70 @@ fn inject_size_reporter(body: String) -> String {
     }
 }
 
-/// Raw `/h/:slug/raw` — `text/html` with the sandbox CSP header.
+/// [`raw_body`], always decoded, with the size reporter added. A streamed
+/// body is too big to buffer for the `</body>` splice, so the script goes
+/// after the last byte instead; browsers still run a script that trails the
+/// closing tags.
+async fn html_body(state: &AppState, inline: String, location: Location) -> Result<Body, AppError> {
+    match storage::open(state.bodies.as_ref(), inline, location, false).await?.0 {
+        Content::Whole(bytes) => {
+            let text = String::from_utf8(bytes.into()).map_err(|e| AppError::Other(e.into()))?;
+            Ok(Body::from(inject_size_reporter(text)))
+        }
+        Content::Stream(stream) => {
+            let reporter = futures_util::stream::once(async {
+                Ok(Bytes::from_static(HTML_SIZE_REPORTER.as_bytes()))
+            });
+            Ok(Body::from_stream(stream.chain(reporter)))
+        }
+    }
+}
+
+/// Raw `/h/:slug/raw` — `text/html` with the sandbox CSP header and an
+/// `ETag`.
 pub async fn raw_html(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
     headers: HeaderMap,
     Path(slug): Path<String>,
+    Query(link): Query<LinkQuery>,
 ) -> Result<Response, AppError> {
-    validate_slug(&slug)?;
-    let caller = try_extract_user(&state, &headers).await;
-    let caller_id = caller.as_ref().map(|u| u.id);
-    let mut row = repo::by_slug(&state.pool, &slug)
-        .await?
-        .ok_or(AppError::NotFound)?;
-    enforce_access(&row, caller_id)?;
-
-    if row.burn_after_read && row.first_viewed_at.is_none() && caller_id != Some(row.owner_id) {
-        if let Ok(Some(fresh)) = repo::mark_first_view(&state.pool, row.id).await {
-            row = fresh;
-        }
-    }
+    let row = load_for_raw(&state, ip, &headers, &slug, false, &link).await?;
     // Only render as HTML if the snippet is actually html. Wrong type returns
     // 404 so we don't accidentally promote a code snippet into HTML execution.
     if row.kind != SnippetType::Html {
         return Err(AppError::NotFound);
     }
-    let body = inject_size_reporter(row.body);
-    let mut response = Response::new(Body::from(body));
+    let tag = etag::etag(&row, Codec::Identity);
+    if etag::not_modified(&headers, &tag) {
+        return Ok(etag::not_modified_response(&tag));
+    }
+    let body = html_body(&state, row.body, row.location).await?;
+    let mut response = html_response(body);
+    etag::tag(&mut response, &tag);
+    Ok(response)
+}
+
+/// Raw `/h/:slug/raw/:filename` — one html file of a bundle, sandboxed the
+/// same way as `/h/:slug/raw`. Non-html files 404 here for the same reason.
+pub async fn raw_file_html(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Path((slug, filename)): Path<(String, String)>,
+    Query(link): Query<LinkQuery>,
+) -> Result<Response, AppError> {
+    let row = load_for_raw(&state, ip, &headers, &slug, false, &link).await?;
+    let file = repo::file_by_name(&state.pool, row.id, &filename)
+        .await?
+        .filter(|f| f.kind == SnippetType::Html)
+        .ok_or(AppError::NotFound)?;
+    let body = html_body(&state, file.body, file.location).await?;
+    Ok(html_response(body))
+}
+
+fn html_response(body: Body) -> Response {
+    let mut response = Response::new(body);
     let headers = response.headers_mut();
     headers.insert(
         header::CONTENT_TYPE,
@@ -673,32 +1653,307 @@ pub async fn raw_html(
         axum::http::HeaderName::from_static("x-frame-options"),
         HeaderValue::from_static("SAMEORIGIN"),
     );
-    Ok(response)
+    response
+}
+
+/// Cursor wire format, base64url without padding:
+///   sort tag (1) || order tag (1) || sort key, i64 BE (8) || id (16)
+/// The sort and order are baked in so a cursor can't be replayed against a
+/// different ordering, where its position would be meaningless.
+const CURSOR_LEN: usize = 26;
+
+fn sort_tag(sort: SnippetSort) -> u8 {
+    match sort {
+        SnippetSort::Created => 0,
+        SnippetSort::Updated => 1,
+        SnippetSort::Views => 2,
+        SnippetSort::Size => 3,
+        SnippetSort::ExpiresAt => 4,
+    }
+}
+
+fn order_tag(order: SortOrder) -> u8 {
+    match order {
+        SortOrder::Asc => 0,
+        SortOrder::Desc => 1,
+    }
 }
 
-fn encode_cursor(at: &OffsetDateTime) -> String {
-    let nanos = at.unix_timestamp_nanos();
-    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(nanos.to_be_bytes())
+fn encode_cursor(sort: SnippetSort, order: SortOrder, position: &ListCursor) -> String {
+    let mut buf = Vec::with_capacity(CURSOR_LEN);
+    buf.push(sort_tag(sort));
+    buf.push(order_tag(order));
+    buf.extend_from_slice(&position.key.to_be_bytes());
+    buf.extend_from_slice(position.id.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(buf)
 }
 
-fn decode_cursor(s: &str) -> Result<OffsetDateTime, AppError> {
+fn decode_cursor(s: &str, sort: SnippetSort, order: SortOrder) -> Result<ListCursor, AppError> {
     let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode(s.trim())
         .map_err(|_| AppError::Validation("invalid cursor".into()))?;
-    if bytes.len() != 16 {
+    if bytes.len() != CURSOR_LEN {
+        return Err(AppError::Validation("invalid cursor".into()));
+    }
+    if bytes[0] != sort_tag(sort) || bytes[1] != order_tag(order) {
+        return Err(AppError::Validation(
+            "cursor was issued for a different sort or order".into(),
+        ));
+    }
+    let mut key = [0u8; 8];
+    key.copy_from_slice(&bytes[2..10]);
+    let id = Uuid::from_slice(&bytes[10..]).map_err(|_| AppError::Validation("invalid cursor".into()))?;
+    let key = i64::from_be_bytes(key);
+    // The SQL turns a timestamp key back into a timestamp, so one no row
+    // could have produced would fail there rather than here.
+    let timestamp = matches!(
+        sort,
+        SnippetSort::Created | SnippetSort::Updated | SnippetSort::ExpiresAt
+    );
+    let unbounded = sort == SnippetSort::ExpiresAt && key == i64::MAX;
+    if timestamp
+        && !unbounded
+        && OffsetDateTime::from_unix_timestamp_nanos(i128::from(key) * 1_000).is_err()
+    {
         return Err(AppError::Validation("invalid cursor".into()));
     }
-    let mut buf = [0u8; 16];
-    buf.copy_from_slice(&bytes);
-    let nanos = i128::from_be_bytes(buf);
-    OffsetDateTime::from_unix_timestamp_nanos(nanos)
-        .map_err(|_| AppError::Validation("invalid cursor".into()))
+    Ok(ListCursor { key, id })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn private_row(strict_private: bool) -> SnippetRow {
+        let now = OffsetDateTime::now_utc();
+        SnippetRow {
+            id: Uuid::new_v4(),
+            slug: "AbCdEf1".into(),
+            owner_id: Uuid::new_v4(),
+            owner_username: "owner".into(),
+            kind: SnippetType::Code,
+            name: None,
+            body: "x".into(),
+            location: Default::default(),
+            size_bytes: 1,
+            stored_bytes: 1,
+            views: 0,
+            visibility: Visibility::Private,
+            password_hash: None,
+            strict_private,
+            encrypted: false,
+            publish_at: None,
+            expires_at: None,
+            burn_after_read: false,
+            burn_window_seconds: BURN_AFTER_READ_WINDOW_SECONDS,
+            max_views: None,
+            first_viewed_at: None,
+            created_at: now,
+            updated_at: now,
+            org_id: None,
+            org_name: None,
+            forked_from: None,
+            forked_from_slug: None,
+            tags: Vec::new(),
+            collections: Vec::new(),
+        }
+    }
+
+    fn reader(id: Option<Uuid>, owns: bool, shared: bool) -> Reader {
+        Reader {
+            id,
+            owns,
+            unlocked: false,
+            shared,
+            linked: false,
+            org_key: false,
+        }
+    }
+
+    #[test]
+    fn strict_private_limits_readers_to_the_share_list() {
+        let stranger = Some(Uuid::new_v4());
+        let loose = private_row(false);
+        assert!(enforce_access(&loose, &reader(None, false, false)).is_err());
+        assert!(enforce_access(&loose, &reader(stranger, false, false)).is_ok());
+
+        let strict = private_row(true);
+        assert!(enforce_access(&strict, &reader(Some(strict.owner_id), true, false)).is_ok());
+        assert!(enforce_access(&strict, &reader(stranger, false, true)).is_ok());
+        assert!(matches!(
+            enforce_access(&strict, &reader(stranger, false, false)),
+            Err(AppError::NotFound)
+        ));
+        assert!(enforce_access(&strict, &reader(None, false, true)).is_err());
+    }
+
+    #[test]
+    fn team_visibility_is_members_only() {
+        let mut team = private_row(false);
+        team.visibility = Visibility::Team;
+        team.org_id = Some(Uuid::new_v4());
+        let stranger = Some(Uuid::new_v4());
+        assert!(enforce_access(&team, &reader(stranger, true, false)).is_ok());
+        assert!(matches!(
+            enforce_access(&team, &reader(stranger, false, false)),
+            Err(AppError::NotFound)
+        ));
+        assert!(enforce_access(&team, &reader(stranger, false, true)).is_err());
+        assert!(enforce_access(&team, &reader(None, false, false)).is_err());
+    }
+
+    #[test]
+    fn org_keys_only_reach_their_org() {
+        let mut row = private_row(false);
+        let mut user = AuthedUser {
+            id: row.owner_id,
+            role: pastedev_core::Role::User,
+            status: pastedev_core::UserStatus::Approved,
+            key_scopes: Vec::new(),
+            via_bearer: true,
+            key_id: None,
+            key_org_id: None,
+        };
+        assert!(key_reaches(&user, &row));
+        // The owner's own personal snippet, through a key scoped to an org.
+        let org = Uuid::new_v4();
+        user.key_org_id = Some(org);
+        assert!(!key_reaches(&user, &row));
+        row.org_id = Some(Uuid::new_v4());
+        assert!(!key_reaches(&user, &row));
+        row.org_id = Some(org);
+        assert!(key_reaches(&user, &row));
+    }
+
+    #[test]
+    fn if_match_compares_strongly_against_the_json_tag() {
+        let mut row = private_row(false);
+        let tag = etag::json(&row, None);
+        let if_match = |tag: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_MATCH, tag.parse().unwrap());
+            headers
+        };
+        let failed = |headers: &HeaderMap, row: &SnippetRow| {
+            matches!(etag::expected_version(headers, row), Err(AppError::PreconditionFailed))
+        };
+        assert!(!tag.starts_with("W/"));
+        assert_eq!(etag::expected_version(&HeaderMap::new(), &row).unwrap(), None);
+        assert_eq!(etag::expected_version(&if_match(&tag), &row).unwrap(), Some(row.updated_at));
+        // Never weakly, and not with a raw body's tag.
+        assert!(failed(&if_match(&format!("W/{tag}")), &row));
+        assert!(failed(&if_match(&etag::etag(&row, Codec::Identity)), &row));
+        // A counted view isn't a new version; the fork source shown is.
+        row.views += 1;
+        assert_eq!(etag::expected_version(&if_match(&tag), &row).unwrap(), Some(row.updated_at));
+        row.forked_from_slug = Some("source".into());
+        assert!(failed(&if_match(&tag), &row));
+        let tag = etag::json(&row, Some("source"));
+        assert_eq!(etag::expected_version(&if_match(&tag), &row).unwrap(), Some(row.updated_at));
+        // A tags, shares or collections change is a new version too.
+        row.updated_at += Duration::microseconds(1);
+        assert!(failed(&if_match(&tag), &row));
+    }
+
+    #[test]
+    fn share_links_open_any_visibility_until_expiry() {
+        let anon_link = Reader {
+            linked: true,
+            ..Reader::default()
+        };
+        let mut row = private_row(true);
+        assert!(enforce_access(&row, &anon_link).is_ok());
+        row.visibility = Visibility::Team;
+        assert!(enforce_access(&row, &anon_link).is_ok());
+        row.visibility = Visibility::Password;
+        assert!(enforce_access(&row, &anon_link).is_ok());
+        row.expires_at = Some(OffsetDateTime::now_utc() - Duration::minutes(1));
+        assert!(matches!(enforce_access(&row, &anon_link), Err(AppError::NotFound)));
+    }
+
+    #[test]
+    fn embargo_hides_the_snippet_from_everyone_but_the_owner() {
+        let mut row = private_row(false);
+        row.visibility = Visibility::Public;
+        row.publish_at = Some(OffsetDateTime::now_utc() + Duration::hours(1));
+        let owner = reader(Some(row.owner_id), true, false);
+        let linked = Reader {
+            linked: true,
+            ..Reader::default()
+        };
+        assert!(enforce_access(&row, &owner).is_ok());
+        assert!(matches!(
+            enforce_access(&row, &reader(None, false, false)),
+            Err(AppError::NotFound)
+        ));
+        assert!(enforce_access(&row, &linked).is_err());
+
+        row.publish_at = Some(OffsetDateTime::now_utc() - Duration::seconds(1));
+        assert!(enforce_access(&row, &reader(None, false, false)).is_ok());
+    }
+
+    #[test]
+    fn tag_filters_split_on_commas_and_dedupe() {
+        let tags = clean_tags(&split_tags(Some("Rust, ci,,rust"))).expect("valid");
+        assert_eq!(tags, ["rust", "ci"]);
+        assert!(clean_tags(&split_tags(None)).expect("valid").is_empty());
+        assert!(clean_tags(&["two words"]).is_err());
+        let many: Vec<String> = (0..=MAX_TAGS).map(|i| format!("t{i}")).collect();
+        assert!(clean_tags(&many).is_err());
+    }
+
+    #[test]
+    fn list_cursor_round_trips() {
+        let position = ListCursor {
+            key: 1_700_000_000_123_456,
+            id: Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef),
+        };
+        let c = encode_cursor(SnippetSort::Updated, SortOrder::Asc, &position);
+        let back = decode_cursor(&c, SnippetSort::Updated, SortOrder::Asc).expect("decodes");
+        assert_eq!(back, position);
+    }
+
+    #[test]
+    fn list_cursor_rejects_other_orderings_and_garbage() {
+        let position = ListCursor {
+            key: -1,
+            id: Uuid::nil(),
+        };
+        let c = encode_cursor(SnippetSort::Views, SortOrder::Desc, &position);
+        assert!(decode_cursor(&c, SnippetSort::Size, SortOrder::Desc).is_err());
+        assert!(decode_cursor(&c, SnippetSort::Views, SortOrder::Asc).is_err());
+        assert!(decode_cursor("not base64!", SnippetSort::Views, SortOrder::Desc).is_err());
+        // The old timestamp-only cursor (16 bytes) is no longer accepted.
+        let legacy = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode([0u8; 16]);
+        assert!(decode_cursor(&legacy, SnippetSort::Created, SortOrder::Desc).is_err());
+        // A key no timestamp has is refused up front; "never" is only an
+        // expiry.
+        let never = ListCursor {
+            key: i64::MAX,
+            id: Uuid::nil(),
+        };
+        let c = encode_cursor(SnippetSort::Created, SortOrder::Desc, &never);
+        assert!(decode_cursor(&c, SnippetSort::Created, SortOrder::Desc).is_err());
+        let c = encode_cursor(SnippetSort::ExpiresAt, SortOrder::Asc, &never);
+        assert!(decode_cursor(&c, SnippetSort::ExpiresAt, SortOrder::Asc).is_ok());
+    }
+
+    #[test]
+    fn accept_encoding_zstd_detection() {
+        let with = |v: &'static str| {
+            let mut h = HeaderMap::new();
+            h.insert(header::ACCEPT_ENCODING, HeaderValue::from_static(v));
+            accepts_zstd(&h)
+        };
+        assert!(with("zstd"));
+        assert!(with("gzip, deflate, br, zstd"));
+        assert!(with("gzip;q=1.0, ZSTD;q=0.5"));
+        assert!(!with("gzip, br"));
+        assert!(!with("zstd;q=0"));
+        assert!(!with("zstdx"));
+        assert!(!accepts_zstd(&HeaderMap::new()));
+    }
+
     #[test]
     fn html_sandbox_csp_is_exact() {
         // The two flags allowed (scripts + popups) and nothing else. A regression
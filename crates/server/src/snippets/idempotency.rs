@@ -0,0 +1,170 @@
+//! `Idempotency-Key` on snippet creation (migration 0025).
+//!
+//! A client that retries `POST /api/v1/snippets` or `POST /paste` after a
+//! timeout sends the same key both times. The first request claims the key
+//! along with a fingerprint of the request; a repeat within
+//! `IDEMPOTENCY_KEY_TTL_HOURS` gets back the snippet the first one created,
+//! marked `Idempotent-Replayed: true`, instead of making a second one. The
+//! same key on a different request is a 409 `idempotency_key_reused`; a
+//! repeat while the first request is still running is a plain 409. Keys are
+//! per user.
+//!
+//! The request can carry a snippet passphrase, so the fingerprint is keyed
+//! with `PASTEDEV_SECRET`: a plain hash in the table would let anyone who
+//! reads it guess the passphrase far faster than its Argon2 hash allows.
+
+use std::future::Future;
+
+use axum::{
+    http::{HeaderMap, HeaderName, HeaderValue},
+    response::Response,
+};
+use pastedev_core::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENCY_KEY_MAX_LEN};
+use uuid::Uuid;
+
+use crate::{
+    auth::hmac::hmac_sha256_bytes,
+    error::AppError,
+    http::AppState,
+    snippets::repo::{self, SnippetRow},
+};
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= IDEMPOTENCY_KEY_MAX_LEN
+        && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// The key the request carries, if any. Always `None` when the instance
+/// has `IDEMPOTENCY_KEY_TTL_HOURS=0`.
+pub fn key(state: &AppState, headers: &HeaderMap) -> Result<Option<String>, AppError> {
+    if state.config.idempotency_key_ttl_hours == 0 {
+        return Ok(None);
+    }
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value.to_str().ok().filter(|k| is_valid_key(k)).ok_or_else(|| {
+        AppError::Validation(format!(
+            "{IDEMPOTENCY_KEY_HEADER} must be 1 to {IDEMPOTENCY_KEY_MAX_LEN} visible ASCII \
+             characters"
+        ))
+    })?;
+    Ok(Some(key.to_owned()))
+}
+
+/// What a repeat has to match: the route plus everything that shapes the
+/// snippet, MACed with the server secret. Each part is length-prefixed so
+/// neighbours can't run together.
+pub fn fingerprint(secret: &str, route: &str, parts: &[&[u8]]) -> Vec<u8> {
+    let mut material = Vec::new();
+    for part in std::iter::once(route.as_bytes()).chain(parts.iter().copied()) {
+        material.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        material.extend_from_slice(part);
+    }
+    hmac_sha256_bytes(secret, &material).to_vec()
+}
+
+/// The snippet a keyed request answers with.
+pub struct Outcome {
+    pub row: SnippetRow,
+    /// Made by an earlier request with the same key, not this one.
+    pub replayed: bool,
+}
+
+/// Run `create` under `key`, or hand back what an earlier run of the same
+/// request created.
+pub async fn run<F>(
+    state: &AppState,
+    user_id: Uuid,
+    key: &str,
+    fingerprint: Vec<u8>,
+    create: F,
+) -> Result<Outcome, AppError>
+where
+    F: Future<Output = Result<SnippetRow, AppError>> + Send + 'static,
+{
+    let ttl_hours = state.config.idempotency_key_ttl_hours;
+    let held =
+        repo::claim_idempotency_key(&state.pool, user_id, key, &fingerprint, ttl_hours).await?;
+    if let Some(held) = held {
+        if held.fingerprint != fingerprint {
+            return Err(AppError::IdempotencyKeyReused);
+        }
+        let Some(id) = held.snippet_id else {
+            return Err(AppError::Conflict(
+                "a request with this idempotency key is still in progress",
+            ));
+        };
+        // Making a new one would defeat the key, so a deleted snippet stays
+        // deleted.
+        let row = repo::by_id(&state.pool, id).await?.ok_or(AppError::Conflict(
+            "the snippet created with this idempotency key has been deleted",
+        ))?;
+        return Ok(Outcome { row, replayed: true });
+    }
+    // Spawned so a client that gives up and hangs up doesn't cancel the
+    // create halfway and leave the key claimed; its retry finds the result.
+    let pool = state.pool.clone();
+    let key = key.to_owned();
+    tokio::spawn(async move {
+        let result = create.await;
+        let settled = match &result {
+            Ok(row) => repo::complete_idempotency_key(&pool, user_id, &key, row.id).await,
+            Err(_) => repo::release_idempotency_key(&pool, user_id, &key).await,
+        };
+        if let Err(e) = settled {
+            tracing::warn!(error = ?e, "idempotency key left pending");
+        }
+        result.map(|row| Outcome { row, replayed: false })
+    })
+    .await
+    .map_err(|e| AppError::Other(e.into()))?
+}
+
+/// Tag a replayed response so the client can tell.
+pub fn mark_replayed(response: &mut Response) {
+    response.headers_mut().insert(
+        HeaderName::from_static("idempotent-replayed"),
+        HeaderValue::from_static("true"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_visible_ascii() {
+        assert!(is_valid_key("ci-run-4812/attempt"));
+        assert!(is_valid_key(&"k".repeat(IDEMPOTENCY_KEY_MAX_LEN)));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("has space"));
+        assert!(!is_valid_key("clé"));
+        assert!(!is_valid_key(&"k".repeat(IDEMPOTENCY_KEY_MAX_LEN + 1)));
+    }
+
+    #[test]
+    fn fingerprint_separates_routes_and_parts() {
+        const SECRET: &str = "server-secret-one-one-one";
+        let base = fingerprint(SECRET, "paste", &[b"type=code", b"hello"]);
+        assert_eq!(base, fingerprint(SECRET, "paste", &[b"type=code", b"hello"]));
+        assert_ne!(base, fingerprint(SECRET, "api", &[b"type=code", b"hello"]));
+        assert_ne!(base, fingerprint(SECRET, "paste", &[b"type=codeh", b"ello"]));
+    }
+
+    #[test]
+    fn fingerprint_is_keyed() {
+        let parts: &[&[u8]] = &[b"visibility=password&password=hunter2"];
+        let a = fingerprint("server-secret-one-one-one", "paste", parts);
+        let b = fingerprint("server-secret-two-two-two", "paste", parts);
+        assert_ne!(a, b);
+        // Not the unkeyed hash of the same bytes.
+        let mut material = Vec::new();
+        for part in [b"paste".as_slice(), parts[0]] {
+            material.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            material.extend_from_slice(part);
+        }
+        assert_ne!(a, <sha2::Sha256 as sha2::Digest>::digest(&material).to_vec());
+    }
+}
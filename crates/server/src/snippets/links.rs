@@ -0,0 +1,305 @@
+//! Signed share links (migration 0018).
+//!
+//! An owner mints a link on `POST /api/v1/snippets/:slug/links`; anyone
+//! holding it can read that one snippet until it expires, runs out of uses,
+//! or is revoked, whatever the snippet's visibility. The link carries
+//!
+//!   `?token=<base64url payload>.<base64url MAC>`
+//!
+//! where the payload is link id (16) || expiry, Unix seconds i64 BE (8) ||
+//! use limit u32 BE, 0 = none (4), and the MAC is `HMAC(PASTEDEV_SECRET, …)`
+//! over the slug and the payload. A forged or stale token is rejected
+//! without touching the database; a good one is then counted against its
+//! `snippet_links` row, which is where revocation and `max_uses` live.
+//! [`super::handlers::authorize_read`] only redeems a link when the reader
+//! couldn't get in without it, so owners and public reads never use one up.
+//!
+//! A use is a viewer, not a request (migration 0028): the SPA's API read and
+//! the raw copies it loads after are one view, so `max_uses: 1` lets one
+//! person see the snippet in full. Viewers are told apart by the same
+//! IP-and-user-agent hash that counts views, with the same blind spot for
+//! readers sharing both.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use base64::Engine;
+use constant_time_eq::constant_time_eq;
+use pastedev_core::{
+    CreateShareLinkRequest, ListShareLinksResponse, OrgRole, ShareLink, Snippet, Visibility,
+    LIFETIME_SECONDS_MIN, SHARE_LINK_PARAM, SHARE_LINK_TTL_MAX,
+};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::{
+        extract::{scope_id, AuthedUser, RequiresScope},
+        hmac::hmac_sha256_bytes,
+    },
+    error::AppError,
+    http::AppState,
+    snippets::{
+        handlers::{ensure_can_manage, snippet_prefix, validate_slug},
+        repo::{self, LinkRow, SnippetRow},
+    },
+};
+
+const PAYLOAD_LEN: usize = 16 + 8 + 4;
+
+/// Query string of the read routes that accept a link. The field name is
+/// [`SHARE_LINK_PARAM`].
+#[derive(Debug, Default, Deserialize)]
+pub struct LinkQuery {
+    pub token: Option<String>,
+}
+
+/// MAC material: tag || 0x00 || slug || 0x00 || payload.
+fn mac(secret: &str, slug: &str, payload: &[u8]) -> [u8; 32] {
+    let mut material = Vec::with_capacity(16 + slug.len() + PAYLOAD_LEN);
+    material.extend_from_slice(b"snippet-link");
+    material.push(0);
+    material.extend_from_slice(slug.as_bytes());
+    material.push(0);
+    material.extend_from_slice(payload);
+    hmac_sha256_bytes(secret, &material)
+}
+
+fn mint(secret: &str, slug: &str, id: Uuid, expires: i64, max_uses: Option<i32>) -> String {
+    let mut payload = Vec::with_capacity(PAYLOAD_LEN);
+    payload.extend_from_slice(id.as_bytes());
+    payload.extend_from_slice(&expires.to_be_bytes());
+    payload.extend_from_slice(&max_uses.map_or(0, |n| n as u32).to_be_bytes());
+    let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    format!("{}.{}", b64.encode(&payload), b64.encode(mac(secret, slug, &payload)))
+}
+
+/// The link id of a well-formed, correctly signed, unexpired token for
+/// `slug`; `None` for anything else.
+fn verify(secret: &str, slug: &str, token: &str, now: i64) -> Option<Uuid> {
+    let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let (payload, tag) = token.split_once('.')?;
+    let payload = b64.decode(payload).ok().filter(|p| p.len() == PAYLOAD_LEN)?;
+    let tag = b64.decode(tag).ok()?;
+    if !constant_time_eq(&tag, &mac(secret, slug, &payload)) {
+        return None;
+    }
+    let id = Uuid::from_slice(&payload[..16]).ok()?;
+    let expires = i64::from_be_bytes(payload[16..24].try_into().ok()?);
+    (expires > now).then_some(id)
+}
+
+/// [`verify`] against `row`'s slug, then against its aliases: a link minted
+/// before a rename was signed over the old slug and should keep working.
+async fn verify_any(
+    state: &AppState,
+    row: &SnippetRow,
+    token: &str,
+    now: i64,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let secret = &state.config.pastedev_secret;
+    if let Some(id) = verify(secret, &row.slug, token, now) {
+        return Ok(Some(id));
+    }
+    let aliases = repo::aliases(&state.pool, row.id).await?;
+    Ok(aliases.iter().find_map(|a| verify(secret, &a.slug, token, now)))
+}
+
+/// Let `viewer` (a [`super::handlers::viewer_hash`]) read `row` through
+/// `token`, counting a use if they haven't been in through it before.
+/// `Ok(false)` when the token is bad, expired, revoked or used up by others,
+/// or the snippet isn't readable by anyone but its owner right now
+/// (embargoed or expired) — the caller then denies as usual.
+pub async fn redeem(
+    state: &AppState,
+    row: &SnippetRow,
+    token: &str,
+    viewer: &[u8; 32],
+) -> Result<bool, AppError> {
+    let now = OffsetDateTime::now_utc();
+    if row.publish_at.is_some_and(|at| at > now) || row.expires_at.is_some_and(|exp| exp <= now) {
+        return Ok(false);
+    }
+    let Some(id) = verify_any(state, row, token, now.unix_timestamp()).await? else {
+        return Ok(false);
+    };
+    Ok(repo::consume_link(&state.pool, id, row.id, viewer).await?)
+}
+
+/// Whether `token` would currently open `row` for `viewer`, or for anyone
+/// with `None`, without using it up. For the link-preview builder and event
+/// streams, which shouldn't count as a read.
+pub async fn is_live(
+    state: &AppState,
+    row: &SnippetRow,
+    token: &str,
+    viewer: Option<&[u8; 32]>,
+) -> bool {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    match verify_any(state, row, token, now).await {
+        Ok(Some(id)) => repo::link_is_live(&state.pool, id, row.id, viewer)
+            .await
+            .unwrap_or(false),
+        Ok(None) | Err(_) => false,
+    }
+}
+
+/// Put `token` on the URLs of a snippet read through it, so the raw and
+/// embedded copies a viewer loads next get in the same way.
+pub fn carry(dto: &mut Snippet, token: &str) {
+    let param = format!("?{SHARE_LINK_PARAM}={token}");
+    dto.url.push_str(&param);
+    dto.raw_url.push_str(&param);
+    for file in &mut dto.files {
+        file.raw_url.push_str(&param);
+    }
+}
+
+fn to_dto(row: LinkRow, url: Option<String>) -> ShareLink {
+    ShareLink {
+        id: row.id,
+        url,
+        expires_at: row.expires_at,
+        max_uses: row.max_uses,
+        uses: row.uses,
+        revoked_at: row.revoked_at,
+        created_at: row.created_at,
+    }
+}
+
+async fn load_managed(
+    state: &AppState,
+    slug: &str,
+    user: &AuthedUser,
+) -> Result<SnippetRow, AppError> {
+    validate_slug(slug)?;
+    let row = repo::by_slug(&state.pool, slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(state, user, &row, OrgRole::Member).await?;
+    Ok(row)
+}
+
+/// `POST /api/v1/snippets/:slug/links` — mint a link. The response is the
+/// only time its URL is shown.
+pub async fn create(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(req): Json<CreateShareLinkRequest>,
+) -> Result<(StatusCode, Json<ShareLink>), AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    if row.visibility == Visibility::Public {
+        return Err(AppError::Validation(
+            "public snippets don't need a share link".into(),
+        ));
+    }
+    if !(LIFETIME_SECONDS_MIN..=SHARE_LINK_TTL_MAX).contains(&req.ttl_seconds) {
+        return Err(AppError::Validation(format!(
+            "ttl_seconds must be between {LIFETIME_SECONDS_MIN} and {SHARE_LINK_TTL_MAX}"
+        )));
+    }
+    if req.max_uses.is_some_and(|n| n < 1) {
+        return Err(AppError::Validation("max_uses must be at least 1".into()));
+    }
+    // Whole seconds, so the row and the token agree on the expiry.
+    let expires = OffsetDateTime::now_utc().unix_timestamp() + i64::from(req.ttl_seconds);
+    let expires_at = OffsetDateTime::from_unix_timestamp(expires)
+        .map_err(|e| AppError::Other(e.into()))?;
+    let link = repo::insert_link(&state.pool, row.id, user.0.id, expires_at, req.max_uses).await?;
+    let token = mint(&state.config.pastedev_secret, &row.slug, link.id, expires, link.max_uses);
+    let url = format!(
+        "{}{}{}?{SHARE_LINK_PARAM}={token}",
+        state.config.public_base_url,
+        snippet_prefix(&row),
+        row.slug
+    );
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.link.create",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({
+                "link_id": link.id,
+                "ttl_seconds": req.ttl_seconds,
+                "max_uses": link.max_uses,
+            })),
+            ..Default::default()
+        },
+    );
+    Ok((StatusCode::CREATED, Json(to_dto(link, Some(url)))))
+}
+
+/// `GET /api/v1/snippets/:slug/links`
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<ListShareLinksResponse>, AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    let links = repo::links(&state.pool, row.id).await?;
+    Ok(Json(ListShareLinksResponse {
+        items: links.into_iter().map(|l| to_dto(l, None)).collect(),
+    }))
+}
+
+/// `DELETE /api/v1/snippets/:slug/links/:id`
+pub async fn revoke(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path((slug, id)): Path<(String, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    let row = load_managed(&state, &slug, &user.0).await?;
+    if !repo::revoke_link(&state.pool, id, row.id).await? {
+        return Err(AppError::NotFound);
+    }
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.link.revoke",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({ "link_id": id })),
+            ..Default::default()
+        },
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret-at-least-sixteen-chars";
+
+    #[test]
+    fn token_round_trips_for_its_slug_only() {
+        let id = Uuid::new_v4();
+        let token = mint(SECRET, "AbCdEf1", id, 2_000, Some(3));
+        assert_eq!(verify(SECRET, "AbCdEf1", &token, 1_000), Some(id));
+        assert_eq!(verify(SECRET, "ZyXwVu9", &token, 1_000), None);
+        assert_eq!(verify("another-secret-sixteen-chars", "AbCdEf1", &token, 1_000), None);
+    }
+
+    #[test]
+    fn expired_or_tampered_tokens_are_rejected() {
+        let token = mint(SECRET, "AbCdEf1", Uuid::new_v4(), 2_000, None);
+        assert_eq!(verify(SECRET, "AbCdEf1", &token, 2_000), None);
+
+        // Stretch the expiry without re-signing.
+        let b64 = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let (payload, tag) = token.split_once('.').unwrap();
+        let mut payload = b64.decode(payload).unwrap();
+        payload[16..24].copy_from_slice(&i64::MAX.to_be_bytes());
+        let forged = format!("{}.{tag}", b64.encode(&payload));
+        assert_eq!(verify(SECRET, "AbCdEf1", &forged, 1_000), None);
+
+        assert_eq!(verify(SECRET, "AbCdEf1", "garbage", 1_000), None);
+        assert_eq!(verify(SECRET, "AbCdEf1", "", 1_000), None);
+    }
+}
@@ -1,3 +1,17 @@
+pub mod aliases;
+pub mod append;
+pub mod collab;
+pub mod etag;
+pub mod events;
+pub mod fork;
 pub mod handlers;
+pub mod idempotency;
+pub mod links;
 pub mod repo;
+pub mod revisions;
+pub mod search;
+pub mod shares;
 pub mod slug;
+pub mod tags;
+pub mod trash;
+pub mod unlock;
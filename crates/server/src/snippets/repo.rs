@@ -1,42 +1,146 @@
-use pastedev_core::{SnippetType, Visibility, BURN_AFTER_READ_WINDOW_SECONDS};
+use pastedev_core::{SnippetSort, SnippetType, SortOrder, Visibility};
 use sqlx::PgPool;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use crate::storage::{self, Codec, Location, StoredBody};
+
 #[derive(Debug, Clone)]
 pub struct SnippetRow {
     pub id: Uuid,
     pub slug: String,
     pub owner_id: Uuid,
     pub owner_username: String,
+    /// Owning organization (migration 0017), with its name for the DTO.
+    /// `owner_id` is then the member who published the snippet.
+    pub org_id: Option<Uuid>,
+    pub org_name: Option<String>,
     pub kind: SnippetType,
     pub name: Option<String>,
+    /// The whole body, or only a prefix of it when `location` says the full
+    /// text is elsewhere — resolve with `storage::load` before serving it.
     pub body: String,
+    pub location: Location,
     pub size_bytes: i32,
+    /// What the full body takes at rest, after compression (migration 0013).
+    pub stored_bytes: i32,
     pub views: i32,
     pub visibility: Visibility,
+    /// Argon2id hash of the passphrase; set exactly when `visibility` is
+    /// `Password` (migration 0014).
+    pub password_hash: Option<String>,
+    /// Private snippets: readable only by the owner and the share list
+    /// (migration 0016) instead of by any signed-in user.
+    pub strict_private: bool,
+    /// Body is a client-side encrypted envelope (migration 0015); nothing
+    /// derived from it means anything.
+    pub encrypted: bool,
+    /// Embargo (migration 0021): non-owner reads don't resolve before this.
+    pub publish_at: Option<OffsetDateTime>,
     /// Absolute timestamp at which non-owner reads stop resolving. `None`
     /// = never expires. Set at creation as `now() + lifetime`; refreshed by
     /// the same formula on `/settings` updates; tightened down to
-    /// `LEAST(expires_at, now() + 15min)` when burn-after-read fires.
+    /// `LEAST(expires_at, now() + burn_window_seconds)` when burn-after-read
+    /// fires.
     pub expires_at: Option<OffsetDateTime>,
     pub burn_after_read: bool,
+    /// Fuse length (migration 0020); `BURN_WINDOW_ONE_SHOT` expires the
+    /// snippet on the first non-owner read.
+    pub burn_window_seconds: i32,
+    /// Unique non-owner viewers allowed in before reads stop resolving
+    /// (migration 0019); checked against `views` by [`admit_viewer`].
+    pub max_views: Option<i32>,
     pub first_viewed_at: Option<OffsetDateTime>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    /// The snippet this one was forked from (migration 0023), with its slug
+    /// while it is live.
+    pub forked_from: Option<Uuid>,
+    pub forked_from_slug: Option<String>,
+    /// Normalized tags (migration 0024), sorted.
+    pub tags: Vec<String>,
+    /// Names of the owner's collections holding it, sorted. For the owner's
+    /// eyes only.
+    pub collections: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct SnippetDraft<'a> {
     pub owner_id: Uuid,
+    pub org_id: Option<Uuid>,
     pub kind: SnippetType,
     pub name: Option<&'a str>,
-    pub body: &'a str,
+    pub body: StoredBody<'a>,
+    /// Bundle members, in order. Empty for a single-body snippet; otherwise
+    /// `kind` / `body` are expected to mirror `files[0]`.
+    pub files: &'a [FileDraft<'a>],
     pub visibility: Visibility,
+    pub password_hash: Option<String>,
+    /// Only meaningful with `Private`, as on [`SnippetRow`].
+    pub strict_private: bool,
+    pub encrypted: bool,
+    pub publish_at: Option<OffsetDateTime>,
     /// Already-resolved absolute expiry. The handler converts the user's
     /// `lifetime_seconds` choice into `now() + lifetime` before calling us.
     pub expires_at: Option<OffsetDateTime>,
     pub burn_after_read: bool,
+    pub burn_window_seconds: i32,
+    pub max_views: Option<i32>,
+    pub forked_from: Option<Uuid>,
+    /// Already normalized and deduplicated.
+    pub tags: &'a [String],
+}
+
+#[derive(Debug)]
+pub struct FileDraft<'a> {
+    pub name: &'a str,
+    pub kind: SnippetType,
+    pub body: StoredBody<'a>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileRow {
+    pub name: String,
+    pub kind: SnippetType,
+    pub body: String,
+    pub location: Location,
+    pub size_bytes: i32,
+    pub stored_bytes: i32,
+}
+
+struct FileRowRaw {
+    name: String,
+    kind: String,
+    body: String,
+    body_codec: Option<String>,
+    body_packed: Option<Vec<u8>>,
+    body_ref: Option<String>,
+    size_bytes: i32,
+    stored_bytes: i32,
+}
+
+/// Rows from before migration 0013 have no codec yet and are plain text.
+fn location(
+    codec: Option<String>,
+    packed: Option<Vec<u8>>,
+    body_ref: Option<String>,
+) -> Option<Location> {
+    Some(Location {
+        codec: codec.map(|c| c.parse::<Codec>()).transpose().ok()?.unwrap_or_default(),
+        packed,
+        body_ref,
+    })
+}
+
+fn map_file(r: FileRowRaw) -> Option<FileRow> {
+    Some(FileRow {
+        name: r.name,
+        kind: r.kind.parse().ok()?,
+        body: r.body,
+        location: location(r.body_codec, r.body_packed, r.body_ref)?,
+        size_bytes: r.size_bytes,
+        stored_bytes: r.stored_bytes,
+    })
 }
 
 struct SnippetRowRaw {
@@ -44,17 +148,33 @@ struct SnippetRowRaw {
     slug: String,
     owner_id: Uuid,
     owner_username: String,
+    org_id: Option<Uuid>,
+    org_name: Option<String>,
     kind: String,
     name: Option<String>,
     body: String,
+    body_codec: Option<String>,
+    body_packed: Option<Vec<u8>>,
+    body_ref: Option<String>,
     size_bytes: i32,
+    stored_bytes: i32,
     views: i32,
     visibility: String,
+    password_hash: Option<String>,
+    strict_private: bool,
+    encrypted: bool,
+    publish_at: Option<OffsetDateTime>,
     expires_at: Option<OffsetDateTime>,
     burn_after_read: bool,
+    burn_window_seconds: i32,
+    max_views: Option<i32>,
     first_viewed_at: Option<OffsetDateTime>,
     created_at: OffsetDateTime,
     updated_at: OffsetDateTime,
+    forked_from: Option<Uuid>,
+    forked_from_slug: Option<String>,
+    tags: Vec<String>,
+    collections: Vec<String>,
 }
 
 fn map(r: SnippetRowRaw) -> Option<SnippetRow> {
@@ -63,74 +183,148 @@ fn map(r: SnippetRowRaw) -> Option<SnippetRow> {
         slug: r.slug,
         owner_id: r.owner_id,
         owner_username: r.owner_username,
+        org_id: r.org_id,
+        org_name: r.org_name,
         kind: r.kind.parse().ok()?,
         name: r.name,
         body: r.body,
+        location: location(r.body_codec, r.body_packed, r.body_ref)?,
         size_bytes: r.size_bytes,
+        stored_bytes: r.stored_bytes,
         views: r.views,
         visibility: r.visibility.parse().ok()?,
+        password_hash: r.password_hash,
+        strict_private: r.strict_private,
+        encrypted: r.encrypted,
+        publish_at: r.publish_at,
         expires_at: r.expires_at,
         burn_after_read: r.burn_after_read,
+        burn_window_seconds: r.burn_window_seconds,
+        max_views: r.max_views,
         first_viewed_at: r.first_viewed_at,
         created_at: r.created_at,
         updated_at: r.updated_at,
+        forked_from: r.forked_from,
+        forked_from_slug: r.forked_from_slug,
+        tags: r.tags,
+        collections: r.collections,
     })
 }
 
 /// Insert a snippet at the given `slug`. The slug is generated by the caller
 /// (see [`super::slug::create_with_retry`]) so we can react to collisions.
+/// Revision 1 and any bundle files are written in the same transaction, so
+/// every live snippet has at least one history entry and a bundle is never
+/// visible half-written.
 pub async fn insert<'a>(
     pool: &PgPool,
     slug: &str,
     draft: &SnippetDraft<'a>,
 ) -> Result<SnippetRow, sqlx::Error> {
-    let size = i32::try_from(draft.body.len()).unwrap_or(i32::MAX);
+    let total = if draft.files.is_empty() {
+        draft.body.size
+    } else {
+        draft.files.iter().map(|f| f.body.size).sum()
+    };
+    let size = i32::try_from(total).unwrap_or(i32::MAX);
+    let stored = i32::try_from(draft.body.stored_size).unwrap_or(i32::MAX);
+    let mut tx = pool.begin().await?;
     let inserted_id = sqlx::query!(
-        "INSERT INTO snippets (slug, owner_id, type, name, body, size_bytes,
-                               visibility, expires_at, burn_after_read)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "INSERT INTO snippets (slug, owner_id, type, name, body, body_codec, body_packed,
+                               body_ref, size_bytes, stored_bytes,
+                               visibility, password_hash, encrypted, publish_at, expires_at,
+                               burn_after_read, burn_window_seconds, max_views, org_id,
+                               forked_from, strict_private)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+                 $17, $18, $19, $20, $21)
          RETURNING id",
         slug,
         draft.owner_id,
         draft.kind.as_str(),
         draft.name,
-        draft.body,
+        draft.body.inline,
+        draft.body.codec.as_str(),
+        draft.body.packed.as_deref(),
+        draft.body.body_ref,
         size,
+        stored,
         draft.visibility.as_str(),
+        draft.password_hash,
+        draft.encrypted,
+        draft.publish_at,
         draft.expires_at,
         draft.burn_after_read,
+        draft.burn_window_seconds,
+        draft.max_views,
+        draft.org_id,
+        draft.forked_from,
+        draft.strict_private,
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
+    for (position, file) in draft.files.iter().enumerate() {
+        let position = i32::try_from(position).unwrap_or(i32::MAX);
+        let file_size = i32::try_from(file.body.size).unwrap_or(i32::MAX);
+        let file_stored = i32::try_from(file.body.stored_size).unwrap_or(i32::MAX);
+        sqlx::query!(
+            "INSERT INTO snippet_files (snippet_id, position, name, type, body, body_codec,
+                                        body_packed, body_ref, size_bytes, stored_bytes)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            inserted_id.id,
+            position,
+            file.name,
+            file.kind.as_str(),
+            file.body.inline,
+            file.body.codec.as_str(),
+            file.body.packed.as_deref(),
+            file.body.body_ref,
+            file_size,
+            file_stored,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    if !draft.tags.is_empty() {
+        sqlx::query!(
+            "INSERT INTO snippet_tags (snippet_id, tag) SELECT $1, unnest($2::text[])",
+            inserted_id.id,
+            draft.tags,
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    record_revision(&mut tx, inserted_id.id, Some(draft.owner_id)).await?;
+    tx.commit().await?;
     by_id(pool, inserted_id.id)
         .await?
         .ok_or(sqlx::Error::RowNotFound)
 }
 
 /// Atomically handle a non-owner's first read on a `burn_after_read` snippet:
-///   1. stamp `first_viewed_at = now()` (no-op if already set)
-///   2. tighten `expires_at` down to `LEAST(expires_at, now() + 15min)` so any
-///      pre-existing lifetime doesn't outlive the burn window
+///   1. stamp `first_viewed_at = now()`
+///   2. tighten `expires_at` down to `LEAST(expires_at, now() + window)`, with
+///      the snippet's own `burn_window_seconds`, so any pre-existing lifetime
+///      doesn't outlive the burn window
 ///
 /// Returns the fresh row so the handler can serve a DTO that reflects the new
-/// (now imminent) expiry. `Ok(None)` means the snippet either isn't
-/// burn-after-read or has been soft-deleted.
+/// (now imminent) expiry. `Ok(None)` means the snippet isn't burn-after-read,
+/// has been soft-deleted, or another reader armed the fuse first — only one
+/// caller ever gets the row back, which is what makes one-shot snippets safe.
 pub async fn mark_first_view(
     pool: &PgPool,
     snippet_id: Uuid,
 ) -> Result<Option<SnippetRow>, sqlx::Error> {
-    let burn_window = BURN_AFTER_READ_WINDOW_SECONDS as f64;
     let row = sqlx::query_scalar!(
         "UPDATE snippets
-            SET first_viewed_at = COALESCE(first_viewed_at, now()),
+            SET first_viewed_at = now(),
                 expires_at = LEAST(
-                    COALESCE(expires_at, now() + make_interval(secs => $2)),
-                    now() + make_interval(secs => $2)
+                    COALESCE(expires_at, now() + make_interval(secs => burn_window_seconds)),
+                    now() + make_interval(secs => burn_window_seconds)
                 )
-            WHERE id = $1 AND burn_after_read = true AND deleted_at IS NULL
+            WHERE id = $1 AND burn_after_read = true AND first_viewed_at IS NULL
+              AND deleted_at IS NULL
             RETURNING id",
         snippet_id,
-        burn_window,
     )
     .fetch_optional(pool)
     .await?;
@@ -145,11 +339,22 @@ pub async fn by_id(pool: &PgPool, id: Uuid) -> Result<Option<SnippetRow>, sqlx::
     let row = sqlx::query_as!(
         SnippetRowRaw,
         r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
-                  s.type AS kind, s.name, s.body, s.size_bytes, s.views,
-                  s.visibility, s.expires_at, s.burn_after_read,
-                  s.first_viewed_at, s.created_at, s.updated_at
+                  s.org_id, o.name AS "org_name?",
+                  s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, s.views,
+                  s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                  s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                  s.first_viewed_at, s.created_at, s.updated_at,
+                  s.forked_from, f.slug AS "forked_from_slug?",
+                  ARRAY(SELECT t.tag FROM snippet_tags t
+                        WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                  ARRAY(SELECT c.name FROM collection_items ci
+                        JOIN collections c ON c.id = ci.collection_id
+                        WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!"
            FROM snippets s
            JOIN users u ON u.id = s.owner_id
+           LEFT JOIN orgs o ON o.id = s.org_id
+           LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
            WHERE s.id = $1 AND s.deleted_at IS NULL"#,
         id,
     )
@@ -158,17 +363,32 @@ pub async fn by_id(pool: &PgPool, id: Uuid) -> Result<Option<SnippetRow>, sqlx::
     Ok(row.and_then(map))
 }
 
-/// Fetch a snippet by its public slug, joined to its owner's username.
+/// Fetch a snippet by its public slug or one of its aliases (migration
+/// 0022), joined to its owner's username. The row always carries the
+/// canonical slug, so callers can tell an alias hit by comparing.
 pub async fn by_slug(pool: &PgPool, slug: &str) -> Result<Option<SnippetRow>, sqlx::Error> {
     let row = sqlx::query_as!(
         SnippetRowRaw,
         r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
-                  s.type AS kind, s.name, s.body, s.size_bytes, s.views,
-                  s.visibility, s.expires_at, s.burn_after_read,
-                  s.first_viewed_at, s.created_at, s.updated_at
+                  s.org_id, o.name AS "org_name?",
+                  s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, s.views,
+                  s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                  s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                  s.first_viewed_at, s.created_at, s.updated_at,
+                  s.forked_from, f.slug AS "forked_from_slug?",
+                  ARRAY(SELECT t.tag FROM snippet_tags t
+                        WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                  ARRAY(SELECT c.name FROM collection_items ci
+                        JOIN collections c ON c.id = ci.collection_id
+                        WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!"
            FROM snippets s
            JOIN users u ON u.id = s.owner_id
-           WHERE s.slug = $1 AND s.deleted_at IS NULL"#,
+           LEFT JOIN orgs o ON o.id = s.org_id
+           LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
+           WHERE s.deleted_at IS NULL
+             AND (s.slug = $1
+                  OR s.id = (SELECT snippet_id FROM snippet_aliases WHERE slug = $1))"#,
         slug,
     )
     .fetch_optional(pool)
@@ -176,6 +396,52 @@ pub async fn by_slug(pool: &PgPool, slug: &str) -> Result<Option<SnippetRow>, sq
     Ok(row.and_then(map))
 }
 
+/// All files of a bundle in upload order. Empty for single-body snippets.
+pub async fn files(pool: &PgPool, snippet_id: Uuid) -> Result<Vec<FileRow>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        FileRowRaw,
+        r#"SELECT name, type AS kind, body, body_codec, body_packed, body_ref,
+                  size_bytes, stored_bytes
+           FROM snippet_files
+           WHERE snippet_id = $1
+           ORDER BY position"#,
+        snippet_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().filter_map(map_file).collect())
+}
+
+/// One bundle file by its exact name.
+pub async fn file_by_name(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    name: &str,
+) -> Result<Option<FileRow>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        FileRowRaw,
+        r#"SELECT name, type AS kind, body, body_codec, body_packed, body_ref,
+                  size_bytes, stored_bytes
+           FROM snippet_files
+           WHERE snippet_id = $1 AND name = $2"#,
+        snippet_id,
+        name,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(map_file))
+}
+
+/// Whether the snippet is a multi-file bundle.
+pub async fn is_bundle(pool: &PgPool, snippet_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM snippet_files WHERE snippet_id = $1) AS "exists!""#,
+        snippet_id,
+    )
+    .fetch_one(pool)
+    .await
+}
+
 /// Soft-delete a snippet owned by `owner_id`. Returns `true` iff a live row
 /// was stamped. Stamping is idempotent — a second DELETE on the same slug
 /// just returns `false` because the row is already marked.
@@ -191,20 +457,412 @@ pub async fn delete(pool: &PgPool, slug: &str, owner_id: Uuid) -> Result<bool, s
     Ok(res.rows_affected() > 0)
 }
 
+/// [`SnippetRowRaw`] plus the soft-delete stamp, for the trash listing.
+struct TrashRowRaw {
+    id: Uuid,
+    slug: String,
+    owner_id: Uuid,
+    owner_username: String,
+    org_id: Option<Uuid>,
+    org_name: Option<String>,
+    kind: String,
+    name: Option<String>,
+    body: String,
+    body_codec: Option<String>,
+    body_packed: Option<Vec<u8>>,
+    body_ref: Option<String>,
+    size_bytes: i32,
+    stored_bytes: i32,
+    views: i32,
+    visibility: String,
+    password_hash: Option<String>,
+    strict_private: bool,
+    encrypted: bool,
+    publish_at: Option<OffsetDateTime>,
+    expires_at: Option<OffsetDateTime>,
+    burn_after_read: bool,
+    burn_window_seconds: i32,
+    max_views: Option<i32>,
+    first_viewed_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    forked_from: Option<Uuid>,
+    forked_from_slug: Option<String>,
+    tags: Vec<String>,
+    collections: Vec<String>,
+    deleted_at: OffsetDateTime,
+}
+
+/// One page of the owner's soft-deleted snippets, most recently deleted
+/// first, keyset-paginated on `(deleted_at, id)`. `cursor.key` is
+/// `deleted_at` in Unix microseconds.
+pub async fn list_trash(
+    pool: &PgPool,
+    owner_id: Uuid,
+    cursor: Option<&ListCursor>,
+    limit: i64,
+) -> Result<Vec<(SnippetRow, OffsetDateTime)>, sqlx::Error> {
+    let before = cursor.and_then(|c| {
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(c.key) * 1_000)
+            .ok()
+            .map(|at| (at, c.id))
+    });
+    let rows = sqlx::query_as!(
+        TrashRowRaw,
+        r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
+                  s.org_id, o.name AS "org_name?",
+                  s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, s.views,
+                  s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                  s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                  s.first_viewed_at, s.created_at, s.updated_at,
+                  s.forked_from, f.slug AS "forked_from_slug?",
+                  ARRAY(SELECT t.tag FROM snippet_tags t
+                        WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                  ARRAY(SELECT c.name FROM collection_items ci
+                        JOIN collections c ON c.id = ci.collection_id
+                        WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!",
+                  s.deleted_at AS "deleted_at!"
+           FROM snippets s
+           JOIN users u ON u.id = s.owner_id
+           LEFT JOIN orgs o ON o.id = s.org_id
+           LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
+           WHERE s.owner_id = $1
+             AND s.deleted_at IS NOT NULL
+             AND ($2::timestamptz IS NULL OR (s.deleted_at, s.id) < ($2, $3::uuid))
+           ORDER BY s.deleted_at DESC, s.id DESC
+           LIMIT $4"#,
+        owner_id,
+        before.map(|(at, _)| at),
+        before.map(|(_, id)| id),
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            let deleted_at = r.deleted_at;
+            map(SnippetRowRaw {
+                id: r.id,
+                slug: r.slug,
+                owner_id: r.owner_id,
+                owner_username: r.owner_username,
+                org_id: r.org_id,
+                org_name: r.org_name,
+                kind: r.kind,
+                name: r.name,
+                body: r.body,
+                body_codec: r.body_codec,
+                body_packed: r.body_packed,
+                body_ref: r.body_ref,
+                size_bytes: r.size_bytes,
+                stored_bytes: r.stored_bytes,
+                views: r.views,
+                visibility: r.visibility,
+                password_hash: r.password_hash,
+                strict_private: r.strict_private,
+                encrypted: r.encrypted,
+                publish_at: r.publish_at,
+                expires_at: r.expires_at,
+                burn_after_read: r.burn_after_read,
+                burn_window_seconds: r.burn_window_seconds,
+                max_views: r.max_views,
+                first_viewed_at: r.first_viewed_at,
+                created_at: r.created_at,
+                updated_at: r.updated_at,
+                forked_from: r.forked_from,
+                forked_from_slug: r.forked_from_slug,
+                tags: r.tags,
+                collections: r.collections,
+            })
+            .map(|row| (row, deleted_at))
+        })
+        .collect())
+}
+
+/// Fetch a soft-deleted snippet by slug — the counterpart of [`by_slug`],
+/// which only sees live rows.
+pub async fn trashed_by_slug(pool: &PgPool, slug: &str) -> Result<Option<SnippetRow>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        SnippetRowRaw,
+        r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
+                  s.org_id, o.name AS "org_name?",
+                  s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, s.views,
+                  s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                  s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                  s.first_viewed_at, s.created_at, s.updated_at,
+                  s.forked_from, f.slug AS "forked_from_slug?",
+                  ARRAY(SELECT t.tag FROM snippet_tags t
+                        WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                  ARRAY(SELECT c.name FROM collection_items ci
+                        JOIN collections c ON c.id = ci.collection_id
+                        WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!"
+           FROM snippets s
+           JOIN users u ON u.id = s.owner_id
+           LEFT JOIN orgs o ON o.id = s.org_id
+           LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
+           WHERE s.slug = $1 AND s.deleted_at IS NOT NULL"#,
+        slug,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(map))
+}
+
+/// Undo [`delete`]. Returns `true` iff a trashed row owned by `owner_id` was
+/// brought back. Expiry and burn state are left as they were, so a snippet
+/// that expired while in the trash comes back expired.
+pub async fn restore(pool: &PgPool, slug: &str, owner_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE snippets SET deleted_at = NULL
+            WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NOT NULL",
+        slug,
+        owner_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Permanently remove a trashed snippet owned by `owner_id`, with its
+/// revisions, files and views (all `ON DELETE CASCADE`). Live snippets are
+/// never purged directly — they go through the trash first. The slug is
+/// retired by trigger (migration 0011) so it is never handed out again.
+pub async fn purge(pool: &PgPool, slug: &str, owner_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM snippets
+            WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NOT NULL",
+        slug,
+        owner_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// A row removed by [`purge_expired_trash`] or [`purge_expired`].
+#[derive(Debug)]
+pub struct PurgedRow {
+    pub id: Uuid,
+    pub slug: String,
+    pub owner_id: Uuid,
+}
+
+/// Hard-delete up to `batch` snippets that have been in the trash for more
+/// than `retention_days`, oldest first. Callers loop until a short batch
+/// comes back; bounding each statement keeps a large backlog from holding
+/// locks in one long transaction.
+pub async fn purge_expired_trash(
+    pool: &PgPool,
+    retention_days: u32,
+    batch: i64,
+) -> Result<Vec<PurgedRow>, sqlx::Error> {
+    let days = i32::try_from(retention_days).unwrap_or(i32::MAX);
+    sqlx::query_as!(
+        PurgedRow,
+        r#"DELETE FROM snippets
+           WHERE id IN (
+               SELECT id FROM snippets
+               WHERE deleted_at < now() - make_interval(days => $1)
+               ORDER BY deleted_at
+               LIMIT $2
+               FOR UPDATE SKIP LOCKED
+           )
+           RETURNING id, slug, owner_id"#,
+        days,
+        batch,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Hard-delete up to `batch` snippets whose `expires_at` passed more than
+/// `grace_days` ago, oldest expiry first. Until then the owner can still open
+/// an expired snippet and extend it via `/settings`. Trashed rows are
+/// included — expiry wins over the trash retention window.
+pub async fn purge_expired(
+    pool: &PgPool,
+    grace_days: u32,
+    batch: i64,
+) -> Result<Vec<PurgedRow>, sqlx::Error> {
+    let days = i32::try_from(grace_days).unwrap_or(i32::MAX);
+    sqlx::query_as!(
+        PurgedRow,
+        r#"DELETE FROM snippets
+           WHERE id IN (
+               SELECT id FROM snippets
+               WHERE expires_at < now() - make_interval(days => $1)
+               ORDER BY expires_at
+               LIMIT $2
+               FOR UPDATE SKIP LOCKED
+           )
+           RETURNING id, slug, owner_id"#,
+        days,
+        batch,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Drop up to `batch` unique-viewer rows belonging to snippets nobody can
+/// view any more: trashed or expired. Hard-deleted snippets lose theirs by
+/// cascade. The `views` counter on the snippet is left alone; a snippet that
+/// is later restored or extended just starts deduplicating viewers afresh.
+pub async fn prune_dead_views(pool: &PgPool, batch: i64) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query!(
+        "DELETE FROM snippet_views
+            WHERE (snippet_id, viewer_hash) IN (
+                SELECT v.snippet_id, v.viewer_hash
+                FROM snippet_views v
+                JOIN snippets s ON s.id = v.snippet_id
+                WHERE s.deleted_at IS NOT NULL OR s.expires_at <= now()
+                LIMIT $1
+            )",
+        batch,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+/// A key somebody else's request already holds (see `idempotency.rs`).
+/// `snippet_id` is `None` while that request is still running.
+#[derive(Debug, Clone)]
+pub struct IdempotencyKeyRow {
+    pub fingerprint: Vec<u8>,
+    pub snippet_id: Option<Uuid>,
+}
+
+/// Claim `key` for a new request. Returns `None` if the caller now holds it,
+/// or the row of whichever request got there first. A key older than
+/// `ttl_hours` is taken over, as is one left pending for ten minutes by a
+/// request that never finished (the server went away mid-create).
+pub async fn claim_idempotency_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    key: &str,
+    fingerprint: &[u8],
+    ttl_hours: u32,
+) -> Result<Option<IdempotencyKeyRow>, sqlx::Error> {
+    let hours = i32::try_from(ttl_hours).unwrap_or(i32::MAX);
+    let claimed = sqlx::query_scalar!(
+        r#"INSERT INTO idempotency_keys (user_id, key, fingerprint) VALUES ($1, $2, $3)
+           ON CONFLICT (user_id, key) DO UPDATE
+              SET fingerprint = EXCLUDED.fingerprint, snippet_id = NULL, created_at = now()
+            WHERE idempotency_keys.created_at < now() - make_interval(hours => $4)
+               OR (idempotency_keys.snippet_id IS NULL
+                   AND idempotency_keys.created_at < now() - interval '10 minutes')
+           RETURNING true AS "claimed!""#,
+        user_id,
+        key,
+        fingerprint,
+        hours,
+    )
+    .fetch_optional(pool)
+    .await?;
+    if claimed.is_some() {
+        return Ok(None);
+    }
+    let held = sqlx::query_as!(
+        IdempotencyKeyRow,
+        "SELECT fingerprint, snippet_id FROM idempotency_keys WHERE user_id = $1 AND key = $2",
+        user_id,
+        key,
+    )
+    .fetch_optional(pool)
+    .await?;
+    // Released between the two statements: the other request failed, and
+    // this one is as good as in progress until the client retries.
+    Ok(Some(held.unwrap_or(IdempotencyKeyRow {
+        fingerprint: fingerprint.to_vec(),
+        snippet_id: None,
+    })))
+}
+
+/// Record the snippet a claimed key's request created.
+pub async fn complete_idempotency_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    key: &str,
+    snippet_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE idempotency_keys SET snippet_id = $3 WHERE user_id = $1 AND key = $2",
+        user_id,
+        key,
+        snippet_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Give up a claimed key whose request failed, so a retry runs afresh.
+pub async fn release_idempotency_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    key: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM idempotency_keys
+          WHERE user_id = $1 AND key = $2 AND snippet_id IS NULL",
+        user_id,
+        key,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Delete up to `batch` keys claimed more than `ttl_hours` ago. A claim
+/// already ignores them; this only reclaims the rows.
+pub async fn purge_idempotency_keys(
+    pool: &PgPool,
+    ttl_hours: u32,
+    batch: i64,
+) -> Result<u64, sqlx::Error> {
+    let hours = i32::try_from(ttl_hours).unwrap_or(i32::MAX);
+    let res = sqlx::query!(
+        "DELETE FROM idempotency_keys
+            WHERE (user_id, key) IN (
+                SELECT user_id, key FROM idempotency_keys
+                WHERE created_at < now() - make_interval(hours => $1)
+                ORDER BY created_at
+                LIMIT $2
+            )",
+        hours,
+        batch,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
 /// Patch the sharing-policy fields on a live snippet. `None` on a field
 /// means "leave it alone"; `Some(_)` writes the new value. The handler is
 /// responsible for converting the user's `lifetime_seconds` choice into an
 /// absolute `expires_at` (the inner `Option<DateTime>` allows `Some(None)`
 /// to clear expiry entirely).
 ///
-/// Disabling burn-after-read also clears `first_viewed_at` so the 15-min
+/// Disabling burn-after-read also clears `first_viewed_at` so the burn
 /// timer is fully cancelled (a later re-enable shouldn't fire an
 /// already-spent fuse).
 #[derive(Debug, Default)]
 pub struct SettingsPatch {
     pub visibility: Option<Visibility>,
+    /// Outer `None` leaves the hash alone; `Some(None)` clears it.
+    pub password_hash: Option<Option<String>>,
     pub expires_at: Option<Option<OffsetDateTime>>,
+    /// Outer `None` leaves the embargo alone; `Some(None)` lifts it.
+    pub publish_at: Option<Option<OffsetDateTime>>,
     pub burn_after_read: Option<bool>,
+    pub burn_window_seconds: Option<i32>,
+    /// Outer `None` leaves the cap alone; `Some(None)` lifts it.
+    pub max_views: Option<Option<i32>>,
+    /// Only write if `updated_at` is still this, see [`SnippetPatch`].
+    pub expected_updated_at: Option<OffsetDateTime>,
 }
 
 pub async fn update_settings(
@@ -226,6 +884,21 @@ pub async fn update_settings(
         None => (false, false),
     };
 
+    let (set_password, password_value) = match patch.password_hash {
+        Some(opt) => (true, opt),
+        None => (false, None),
+    };
+
+    let (set_max_views, max_views_value) = match patch.max_views {
+        Some(opt) => (true, opt),
+        None => (false, None),
+    };
+
+    let (set_publish_at, publish_at_value) = match patch.publish_at {
+        Some(opt) => (true, opt),
+        None => (false, None),
+    };
+
     let res = sqlx::query!(
         "UPDATE snippets
             SET visibility = CASE WHEN $3 THEN $4 ELSE visibility END,
@@ -234,8 +907,13 @@ pub async fn update_settings(
                 first_viewed_at = CASE
                     WHEN $7 AND $8 = false THEN NULL
                     ELSE first_viewed_at
-                END
-            WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL",
+                END,
+                password_hash = CASE WHEN $9 THEN $10 ELSE password_hash END,
+                max_views = CASE WHEN $11 THEN $12 ELSE max_views END,
+                burn_window_seconds = COALESCE($13, burn_window_seconds),
+                publish_at = CASE WHEN $14 THEN $15 ELSE publish_at END
+            WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL
+              AND updated_at = COALESCE($16, updated_at)",
         slug,
         owner_id,
         set_visibility,
@@ -244,6 +922,14 @@ pub async fn update_settings(
         expires_value,
         set_burn,
         burn_value,
+        set_password,
+        password_value,
+        set_max_views,
+        max_views_value,
+        patch.burn_window_seconds,
+        set_publish_at,
+        publish_at_value,
+        patch.expected_updated_at,
     )
     .execute(pool)
     .await?;
@@ -253,187 +939,1190 @@ pub async fn update_settings(
     by_slug(pool, slug).await
 }
 
-#[derive(Debug, Default)]
-pub struct SnippetPatch<'a> {
-    pub body: Option<&'a str>,
-    pub name: Option<Option<&'a str>>, // double Option: outer = "did caller supply", inner = the value (NULL allowed)
+#[derive(Debug, Clone)]
+pub struct ShareRow {
+    pub username: String,
+    pub created_at: OffsetDateTime,
 }
 
-/// Update body, name, or both. Split into 4 static queries (3 distinct UPDATEs
-/// plus an early-return for the "no fields" case) so each SQL string is fully
-/// known at compile time and can be macro-checked.
-pub async fn update(
+/// Everyone a snippet is shared with, by username.
+pub async fn shares(pool: &PgPool, snippet_id: Uuid) -> Result<Vec<ShareRow>, sqlx::Error> {
+    sqlx::query_as!(
+        ShareRow,
+        "SELECT u.username, sh.created_at
+           FROM snippet_shares sh
+           JOIN users u ON u.id = sh.user_id
+          WHERE sh.snippet_id = $1
+          ORDER BY u.username",
+        snippet_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether `user_id` is on the snippet's share list.
+pub async fn is_shared_with(
     pool: &PgPool,
-    slug: &str,
-    owner_id: Uuid,
-    patch: SnippetPatch<'_>,
-) -> Result<Option<SnippetRow>, sqlx::Error> {
-    let body = patch.body;
-    let size = body.map(|b| i32::try_from(b.len()).unwrap_or(i32::MAX));
-    let (set_name, name_value) = match patch.name {
-        Some(v) => (true, v),
-        None => (false, None),
-    };
+    snippet_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let r = sqlx::query!(
+        r#"SELECT EXISTS (
+               SELECT 1 FROM snippet_shares WHERE snippet_id = $1 AND user_id = $2
+           ) AS "shared!""#,
+        snippet_id,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(r.shared)
+}
 
-    let rows_affected = match (body, set_name) {
-        (Some(b), true) => sqlx::query!(
-            "UPDATE snippets SET body = $3, size_bytes = $4, name = $5
-             WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL",
-            slug,
-            owner_id,
-            b,
-            size.unwrap(),
-            name_value,
-        )
-        .execute(pool)
-        .await?
-        .rows_affected(),
-        (Some(b), false) => sqlx::query!(
-            "UPDATE snippets SET body = $3, size_bytes = $4
-             WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL",
-            slug,
-            owner_id,
-            b,
-            size.unwrap(),
-        )
-        .execute(pool)
-        .await?
-        .rows_affected(),
-        (None, true) => sqlx::query!(
-            "UPDATE snippets SET name = $3
-             WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL",
-            slug,
-            owner_id,
-            name_value,
-        )
-        .execute(pool)
-        .await?
-        .rows_affected(),
-        (None, false) => {
-            return by_slug(pool, slug)
-                .await
-                .map(|opt| opt.filter(|r| r.owner_id == owner_id));
-        }
-    };
-    if rows_affected == 0 {
-        return Ok(None);
-    }
-    by_slug(pool, slug).await
+/// What [`update_shares`] actually changed.
+#[derive(Debug, Default)]
+pub struct SharesChange {
+    pub added: Vec<Uuid>,
+    pub removed: Vec<Uuid>,
 }
 
-/// Record a unique-viewer hit. Inserts into `snippet_views` and bumps
-/// `snippets.views` only when the row was actually new.
-pub async fn record_view(
+/// Add and remove share-list entries and optionally flip `strict_private`,
+/// in one transaction. Adding an existing entry or removing a missing one is
+/// a no-op and doesn't show up in the returned change.
+pub async fn update_shares(
     pool: &PgPool,
     snippet_id: Uuid,
-    viewer_hash: &[u8; 32],
-) -> Result<bool, sqlx::Error> {
-    let hash_slice: &[u8] = viewer_hash;
-    let inserted = sqlx::query!(
-        "INSERT INTO snippet_views (snippet_id, viewer_hash)
-         VALUES ($1, $2)
-         ON CONFLICT DO NOTHING",
+    add: &[Uuid],
+    remove: &[Uuid],
+    strict_private: Option<bool>,
+) -> Result<SharesChange, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let added = sqlx::query_scalar!(
+        "INSERT INTO snippet_shares (snippet_id, user_id)
+         SELECT $1, unnest($2::uuid[])
+         ON CONFLICT DO NOTHING
+         RETURNING user_id",
         snippet_id,
-        hash_slice,
+        add,
     )
-    .execute(pool)
+    .fetch_all(&mut *tx)
     .await?;
-    if inserted.rows_affected() == 0 {
-        return Ok(false);
+    let removed = sqlx::query_scalar!(
+        "DELETE FROM snippet_shares
+          WHERE snippet_id = $1 AND user_id = ANY($2::uuid[])
+         RETURNING user_id",
+        snippet_id,
+        remove,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if let Some(strict) = strict_private {
+        sqlx::query!(
+            "UPDATE snippets SET strict_private = $2
+              WHERE id = $1 AND strict_private IS DISTINCT FROM $2",
+            snippet_id,
+            strict,
+        )
+        .execute(&mut *tx)
+        .await?;
     }
+    if !added.is_empty() || !removed.is_empty() {
+        touch(&mut tx, &[snippet_id]).await?;
+    }
+    tx.commit().await?;
+    Ok(SharesChange { added, removed })
+}
+
+/// Move `updated_at` on snippets whose tags, shares or collections changed.
+/// Those live in tables of their own, but the snippet's ETag has to change
+/// with them; the update trigger does the stamping.
+pub async fn touch(
+    conn: &mut sqlx::PgConnection,
+    snippet_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "UPDATE snippets SET views = views + 1 WHERE id = $1",
-        snippet_id,
+        "UPDATE snippets SET updated_at = now() WHERE id = ANY($1::uuid[])",
+        snippet_ids,
     )
-    .execute(pool)
+    .execute(conn)
     .await?;
-    Ok(true)
+    Ok(())
 }
 
-pub struct ListFilter<'a> {
-    pub owner_id: Uuid,
-    pub kind: Option<SnippetType>,
-    pub cursor: Option<&'a OffsetDateTime>,
-    pub limit: i64,
+/// What [`update_tags`] actually changed, and the tags afterwards.
+#[derive(Debug, Default)]
+pub struct TagsChange {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub tags: Vec<String>,
 }
 
-pub async fn list_for_user(
+/// Remove, then add tags in one transaction. Returns `None`, having changed
+/// nothing, if the snippet would end up with more than `max` tags. The row
+/// lock keeps two concurrent updates from both squeezing under the cap.
+pub async fn update_tags(
     pool: &PgPool,
-    filter: ListFilter<'_>,
-) -> Result<Vec<SnippetRow>, sqlx::Error> {
-    let rows = match (filter.kind, filter.cursor) {
-        (Some(k), Some(c)) => sqlx::query_as!(
-            SnippetRowRaw,
-            r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
-                      s.type AS kind, s.name, s.body, s.size_bytes, s.views,
-                      s.visibility, s.expires_at, s.burn_after_read,
-                      s.first_viewed_at, s.created_at, s.updated_at
-               FROM snippets s
-               JOIN users u ON u.id = s.owner_id
-               WHERE s.owner_id = $1 AND s.type = $2 AND s.created_at < $3
-                 AND s.deleted_at IS NULL
-               ORDER BY s.created_at DESC
-               LIMIT $4"#,
-            filter.owner_id,
-            k.as_str(),
-            c,
-            filter.limit,
+    snippet_id: Uuid,
+    add: &[String],
+    remove: &[String],
+    max: i64,
+) -> Result<Option<TagsChange>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SELECT id FROM snippets WHERE id = $1 FOR UPDATE", snippet_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let mut removed = sqlx::query_scalar!(
+        "DELETE FROM snippet_tags
+          WHERE snippet_id = $1 AND tag = ANY($2::text[])
+         RETURNING tag",
+        snippet_id,
+        remove,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut added = sqlx::query_scalar!(
+        "INSERT INTO snippet_tags (snippet_id, tag)
+         SELECT $1, unnest($2::text[])
+         ON CONFLICT DO NOTHING
+         RETURNING tag",
+        snippet_id,
+        add,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let tags = sqlx::query_scalar!(
+        "SELECT tag FROM snippet_tags WHERE snippet_id = $1 ORDER BY tag",
+        snippet_id,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if i64::try_from(tags.len()).unwrap_or(i64::MAX) > max {
+        return Ok(None);
+    }
+    // A tag both removed and re-added didn't change.
+    added.retain(|t| !removed.contains(t));
+    removed.retain(|t| !tags.contains(t));
+    if !added.is_empty() || !removed.is_empty() {
+        touch(&mut tx, &[snippet_id]).await?;
+    }
+    tx.commit().await?;
+    added.sort_unstable();
+    removed.sort_unstable();
+    Ok(Some(TagsChange { added, removed, tags }))
+}
+
+#[derive(Debug, Clone)]
+pub struct TagCountRow {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// Tags on the owner's live snippets, most used first.
+pub async fn tag_counts(pool: &PgPool, owner_id: Uuid) -> Result<Vec<TagCountRow>, sqlx::Error> {
+    sqlx::query_as!(
+        TagCountRow,
+        r#"SELECT t.tag, count(*) AS "count!"
+             FROM snippet_tags t
+             JOIN snippets s ON s.id = t.snippet_id
+            WHERE s.owner_id = $1 AND s.deleted_at IS NULL
+            GROUP BY t.tag
+            ORDER BY 2 DESC, t.tag"#,
+        owner_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkRow {
+    pub id: Uuid,
+    pub expires_at: OffsetDateTime,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub revoked_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+pub async fn insert_link(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    created_by: Uuid,
+    expires_at: OffsetDateTime,
+    max_uses: Option<i32>,
+) -> Result<LinkRow, sqlx::Error> {
+    sqlx::query_as!(
+        LinkRow,
+        "INSERT INTO snippet_links (snippet_id, created_by, expires_at, max_uses)
+         VALUES ($1, $2, $3, $4)
+         RETURNING id, expires_at, max_uses, uses, revoked_at, created_at",
+        snippet_id,
+        created_by,
+        expires_at,
+        max_uses,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Every link minted for a snippet, newest first.
+pub async fn links(pool: &PgPool, snippet_id: Uuid) -> Result<Vec<LinkRow>, sqlx::Error> {
+    sqlx::query_as!(
+        LinkRow,
+        "SELECT id, expires_at, max_uses, uses, revoked_at, created_at
+           FROM snippet_links
+          WHERE snippet_id = $1
+          ORDER BY created_at DESC",
+        snippet_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Let `viewer_hash` in through a live link, counting a use the first time
+/// that viewer comes. A viewer already on `snippet_link_viewers` is always let
+/// back in while the link is live; a new one only while `uses` is under
+/// `max_uses`. The link row is locked first, like the snippet in
+/// [`admit_viewer`], so concurrent first reads can't push `uses` past the
+/// limit. `false` if the link is revoked, expired, used up, or belongs to
+/// another snippet.
+pub async fn consume_link(
+    pool: &PgPool,
+    id: Uuid,
+    snippet_id: Uuid,
+    viewer_hash: &[u8; 32],
+) -> Result<bool, sqlx::Error> {
+    let hash_slice: &[u8] = viewer_hash;
+    let mut tx = pool.begin().await?;
+    let Some(link) = sqlx::query!(
+        "SELECT uses, max_uses FROM snippet_links
+          WHERE id = $1 AND snippet_id = $2
+            AND revoked_at IS NULL AND expires_at > now()
+          FOR UPDATE",
+        id,
+        snippet_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+    let seen = sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+             SELECT 1 FROM snippet_link_viewers WHERE link_id = $1 AND viewer_hash = $2
+           ) AS "seen!""#,
+        id,
+        hash_slice,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if seen {
+        return Ok(true);
+    }
+    if link.max_uses.is_some_and(|max| link.uses >= max) {
+        return Ok(false);
+    }
+    sqlx::query!(
+        "INSERT INTO snippet_link_viewers (link_id, viewer_hash) VALUES ($1, $2)",
+        id,
+        hash_slice,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("UPDATE snippet_links SET uses = uses + 1 WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Like [`consume_link`] without counting a use: whether the link would let
+/// `viewer_hash` in, or with `None`, whether it would let anyone in.
+pub async fn link_is_live(
+    pool: &PgPool,
+    id: Uuid,
+    snippet_id: Uuid,
+    viewer_hash: Option<&[u8; 32]>,
+) -> Result<bool, sqlx::Error> {
+    let hash_slice: Option<&[u8]> = viewer_hash.map(|h| &h[..]);
+    let r = sqlx::query!(
+        r#"SELECT EXISTS (
+               SELECT 1 FROM snippet_links l
+                WHERE l.id = $1 AND l.snippet_id = $2
+                  AND l.revoked_at IS NULL AND l.expires_at > now()
+                  AND (l.max_uses IS NULL OR l.uses < l.max_uses
+                       OR EXISTS (SELECT 1 FROM snippet_link_viewers v
+                                   WHERE v.link_id = l.id AND v.viewer_hash = $3))
+           ) AS "live!""#,
+        id,
+        snippet_id,
+        hash_slice,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(r.live)
+}
+
+/// Returns `true` iff a live (not yet revoked) link was revoked.
+pub async fn revoke_link(pool: &PgPool, id: Uuid, snippet_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "UPDATE snippet_links SET revoked_at = now()
+          WHERE id = $1 AND snippet_id = $2 AND revoked_at IS NULL",
+        id,
+        snippet_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+#[derive(Debug, Clone)]
+pub struct AliasRow {
+    pub slug: String,
+    pub created_at: OffsetDateTime,
+}
+
+/// A snippet's aliases, oldest first.
+pub async fn aliases(pool: &PgPool, snippet_id: Uuid) -> Result<Vec<AliasRow>, sqlx::Error> {
+    sqlx::query_as!(
+        AliasRow,
+        "SELECT slug, created_at FROM snippet_aliases
+          WHERE snippet_id = $1
+          ORDER BY created_at, slug",
+        snippet_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Fails with a unique violation if `slug` is taken anywhere in the slug
+/// namespace (trigger, migration 0022).
+pub async fn insert_alias(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    slug: &str,
+) -> Result<AliasRow, sqlx::Error> {
+    sqlx::query_as!(
+        AliasRow,
+        "INSERT INTO snippet_aliases (slug, snippet_id) VALUES ($1, $2)
+         RETURNING slug, created_at",
+        slug,
+        snippet_id,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Drop an alias and retire its slug, like a purged snippet's. Returns
+/// `true` iff the alias belonged to the snippet.
+pub async fn remove_alias(pool: &PgPool, snippet_id: Uuid, slug: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "WITH gone AS (
+             DELETE FROM snippet_aliases WHERE slug = $1 AND snippet_id = $2
+             RETURNING slug
+         )
+         INSERT INTO retired_slugs (slug) SELECT slug FROM gone
+         ON CONFLICT DO NOTHING",
+        slug,
+        snippet_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Move a live snippet to `new_slug`, keeping its current slug as an alias.
+/// Renaming to one of its own aliases swaps the two. A taken `new_slug`
+/// fails with a unique violation on `snippets_slug_uniq`; `Ok(None)` means
+/// the snippet is gone.
+pub async fn rename(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    new_slug: &str,
+) -> Result<Option<SnippetRow>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(old_slug) = sqlx::query_scalar!(
+        "SELECT slug FROM snippets WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        snippet_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+    sqlx::query!(
+        "DELETE FROM snippet_aliases WHERE slug = $1 AND snippet_id = $2",
+        new_slug,
+        snippet_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "UPDATE snippets SET slug = $2 WHERE id = $1",
+        snippet_id,
+        new_slug,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO snippet_aliases (slug, snippet_id) VALUES ($1, $2)",
+        old_slug,
+        snippet_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    by_id(pool, snippet_id).await
+}
+
+#[derive(Debug, Default)]
+pub struct SnippetPatch<'a> {
+    pub body: Option<StoredBody<'a>>,
+    pub name: Option<Option<&'a str>>, // double Option: outer = "did caller supply", inner = the value (NULL allowed)
+    /// Only write if the row's `updated_at` is still this — the version an
+    /// `If-Match` named. Checked in the UPDATE itself, so of two writers
+    /// holding the same version only the first gets through.
+    pub expected_updated_at: Option<OffsetDateTime>,
+}
+
+/// Update body, name, or both. Split into 3 static UPDATEs plus an
+/// early-return for the "no fields" case so each SQL string is fully known at
+/// compile time and can be macro-checked.
+///
+/// `Ok(None)` covers a missing row and a stale `expected_updated_at` alike.
+///
+/// Every write that lands also snapshots the new state into
+/// `snippet_revisions` inside the same transaction, credited to `author_id`
+/// — who made the edit, not necessarily `owner_id` on an org snippet; the
+/// no-op case records nothing.
+pub async fn update(
+    pool: &PgPool,
+    slug: &str,
+    owner_id: Uuid,
+    author_id: Uuid,
+    patch: SnippetPatch<'_>,
+) -> Result<Option<SnippetRow>, sqlx::Error> {
+    let body = patch.body;
+    let size = body.as_ref().map(|b| i32::try_from(b.size).unwrap_or(i32::MAX));
+    let stored = body.as_ref().map(|b| i32::try_from(b.stored_size).unwrap_or(i32::MAX));
+    let (set_name, name_value) = match patch.name {
+        Some(v) => (true, v),
+        None => (false, None),
+    };
+
+    let expected = patch.expected_updated_at;
+    if body.is_none() && !set_name {
+        return by_slug(pool, slug).await.map(|opt| {
+            opt.filter(|r| r.owner_id == owner_id)
+                .filter(|r| expected.is_none_or(|at| at == r.updated_at))
+        });
+    }
+
+    let mut tx = pool.begin().await?;
+    let updated_id = match (body, set_name) {
+        (Some(b), true) => sqlx::query_scalar!(
+            "UPDATE snippets SET body = $3, body_codec = $4, body_packed = $5, body_ref = $6,
+                                 size_bytes = $7, stored_bytes = $8, name = $9
+             WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL
+               AND updated_at = COALESCE($10, updated_at)
+             RETURNING id",
+            slug,
+            owner_id,
+            b.inline,
+            b.codec.as_str(),
+            b.packed.as_deref(),
+            b.body_ref,
+            size.unwrap(),
+            stored.unwrap(),
+            name_value,
+            expected,
         )
-        .fetch_all(pool)
+        .fetch_optional(&mut *tx)
         .await?,
-        (Some(k), None) => sqlx::query_as!(
-            SnippetRowRaw,
-            r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
-                      s.type AS kind, s.name, s.body, s.size_bytes, s.views,
-                      s.visibility, s.expires_at, s.burn_after_read,
-                      s.first_viewed_at, s.created_at, s.updated_at
-               FROM snippets s
-               JOIN users u ON u.id = s.owner_id
-               WHERE s.owner_id = $1 AND s.type = $2
-                 AND s.deleted_at IS NULL
-               ORDER BY s.created_at DESC
-               LIMIT $3"#,
-            filter.owner_id,
-            k.as_str(),
-            filter.limit,
+        (Some(b), false) => sqlx::query_scalar!(
+            "UPDATE snippets SET body = $3, body_codec = $4, body_packed = $5, body_ref = $6,
+                                 size_bytes = $7, stored_bytes = $8
+             WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL
+               AND updated_at = COALESCE($9, updated_at)
+             RETURNING id",
+            slug,
+            owner_id,
+            b.inline,
+            b.codec.as_str(),
+            b.packed.as_deref(),
+            b.body_ref,
+            size.unwrap(),
+            stored.unwrap(),
+            expected,
         )
-        .fetch_all(pool)
+        .fetch_optional(&mut *tx)
         .await?,
-        (None, Some(c)) => sqlx::query_as!(
-            SnippetRowRaw,
-            r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
-                      s.type AS kind, s.name, s.body, s.size_bytes, s.views,
-                      s.visibility, s.expires_at, s.burn_after_read,
-                      s.first_viewed_at, s.created_at, s.updated_at
-               FROM snippets s
-               JOIN users u ON u.id = s.owner_id
-               WHERE s.owner_id = $1 AND s.created_at < $2
-                 AND s.deleted_at IS NULL
-               ORDER BY s.created_at DESC
-               LIMIT $3"#,
-            filter.owner_id,
-            c,
-            filter.limit,
+        (None, true) => sqlx::query_scalar!(
+            "UPDATE snippets SET name = $3
+             WHERE slug = $1 AND owner_id = $2 AND deleted_at IS NULL
+               AND updated_at = COALESCE($4, updated_at)
+             RETURNING id",
+            slug,
+            owner_id,
+            name_value,
+            expected,
         )
-        .fetch_all(pool)
+        .fetch_optional(&mut *tx)
         .await?,
-        (None, None) => sqlx::query_as!(
+        (None, false) => unreachable!("no-op patch returned above"),
+    };
+    let Some(id) = updated_id else {
+        return Ok(None);
+    };
+    record_revision(&mut tx, id, Some(author_id)).await?;
+    tx.commit().await?;
+    by_id(pool, id).await
+}
+
+/// Lock a live snippet's row for a read-modify-write of its body and return
+/// the body as stored. `None` once the snippet is gone.
+pub async fn lock_body(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    snippet_id: Uuid,
+) -> Result<Option<(String, Location)>, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT body, body_codec, body_packed, body_ref FROM snippets
+         WHERE id = $1 AND deleted_at IS NULL
+         FOR UPDATE",
+        snippet_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(row.and_then(|r| {
+        let location = location(r.body_codec, r.body_packed, r.body_ref)?;
+        Some((r.body, location))
+    }))
+}
+
+/// Swap in a new body for a row locked by [`lock_body`]. Unlike [`update`]
+/// this records no revision.
+pub async fn replace_body(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    snippet_id: Uuid,
+    body: &StoredBody<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE snippets SET body = $2, body_codec = $3, body_packed = $4, body_ref = $5,
+                             size_bytes = $6, stored_bytes = $7
+         WHERE id = $1",
+        snippet_id,
+        body.inline,
+        body.codec.as_str(),
+        body.packed.as_deref(),
+        body.body_ref,
+        i32::try_from(body.size).unwrap_or(i32::MAX),
+        i32::try_from(body.stored_size).unwrap_or(i32::MAX),
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Snapshot the snippet's current `name` / `body` as its next revision.
+/// Callers run this in the same transaction as the write it records, after
+/// the row has been updated (and therefore locked), so two concurrent writers
+/// can't both claim the same revision number.
+async fn record_revision(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    snippet_id: Uuid,
+    author_id: Option<Uuid>,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"INSERT INTO snippet_revisions (snippet_id, revision, name, body, body_codec,
+                                        body_packed, body_ref, size_bytes, stored_bytes,
+                                        author_id)
+           SELECT s.id,
+                  COALESCE((SELECT max(r.revision) FROM snippet_revisions r
+                            WHERE r.snippet_id = s.id), 0) + 1,
+                  s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, $2
+           FROM snippets s
+           WHERE s.id = $1
+           RETURNING revision"#,
+        snippet_id,
+        author_id,
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Delete every revision of the snippet but the latest, which is what it
+/// holds now. Returns how many went.
+pub async fn purge_revisions(pool: &PgPool, snippet_id: Uuid) -> Result<u64, sqlx::Error> {
+    let done = sqlx::query!(
+        "DELETE FROM snippet_revisions
+          WHERE snippet_id = $1
+            AND revision < (SELECT max(revision) FROM snippet_revisions WHERE snippet_id = $1)",
+        snippet_id,
+    )
+    .execute(pool)
+    .await?;
+    Ok(done.rows_affected())
+}
+
+#[derive(Debug, Clone)]
+pub struct RevisionRow {
+    pub revision: i32,
+    pub name: Option<String>,
+    pub body: String,
+    pub location: Location,
+    pub size_bytes: i32,
+    pub author_username: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+struct RevisionRowRaw {
+    revision: i32,
+    name: Option<String>,
+    body: String,
+    body_codec: Option<String>,
+    body_packed: Option<Vec<u8>>,
+    body_ref: Option<String>,
+    size_bytes: i32,
+    author_username: Option<String>,
+    created_at: OffsetDateTime,
+}
+
+/// Body-less history entry for [`list_revisions`].
+#[derive(Debug, Clone)]
+pub struct RevisionMetaRow {
+    pub revision: i32,
+    pub name: Option<String>,
+    pub size_bytes: i32,
+    pub author_username: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+/// One page of a snippet's history, newest first. `before` is the exclusive
+/// upper bound on the revision number (the cursor from the previous page).
+pub async fn list_revisions(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    before: Option<i32>,
+    limit: i64,
+) -> Result<Vec<RevisionMetaRow>, sqlx::Error> {
+    sqlx::query_as!(
+        RevisionMetaRow,
+        r#"SELECT r.revision, r.name, r.size_bytes,
+                  u.username AS "author_username?", r.created_at
+           FROM snippet_revisions r
+           LEFT JOIN users u ON u.id = r.author_id
+           WHERE r.snippet_id = $1 AND ($2::int IS NULL OR r.revision < $2)
+           ORDER BY r.revision DESC
+           LIMIT $3"#,
+        snippet_id,
+        before,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Fetch a single revision, body included.
+pub async fn revision(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    revision: i32,
+) -> Result<Option<RevisionRow>, sqlx::Error> {
+    let row = sqlx::query_as!(
+        RevisionRowRaw,
+        r#"SELECT r.revision, r.name, r.body, r.body_codec, r.body_packed, r.body_ref,
+                  r.size_bytes, u.username AS "author_username?", r.created_at
+           FROM snippet_revisions r
+           LEFT JOIN users u ON u.id = r.author_id
+           WHERE r.snippet_id = $1 AND r.revision = $2"#,
+        snippet_id,
+        revision,
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(|r| {
+        Some(RevisionRow {
+            revision: r.revision,
+            name: r.name,
+            body: r.body,
+            location: location(r.body_codec, r.body_packed, r.body_ref)?,
+            size_bytes: r.size_bytes,
+            author_username: r.author_username,
+            created_at: r.created_at,
+        })
+    }))
+}
+
+/// Highest revision number recorded for a snippet. `None` only for rows
+/// that somehow predate the history table's backfill.
+pub async fn latest_revision(pool: &PgPool, snippet_id: Uuid) -> Result<Option<i32>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT max(revision) FROM snippet_revisions WHERE snippet_id = $1",
+        snippet_id,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Record a unique-viewer hit. Inserts into `snippet_views` and bumps
+/// `snippets.views` only when the row was actually new. A view isn't an
+/// edit: `updated_at`, and with it the snippet's ETag, stays where it was.
+pub async fn record_view(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    viewer_hash: &[u8; 32],
+) -> Result<bool, sqlx::Error> {
+    let hash_slice: &[u8] = viewer_hash;
+    let mut tx = pool.begin().await?;
+    let inserted = sqlx::query!(
+        "INSERT INTO snippet_views (snippet_id, viewer_hash)
+         VALUES ($1, $2)
+         ON CONFLICT DO NOTHING",
+        snippet_id,
+        hash_slice,
+    )
+    .execute(&mut *tx)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        return Ok(false);
+    }
+    storage::preserve_updated_at(&mut tx).await?;
+    sqlx::query!(
+        "UPDATE snippets SET views = views + 1 WHERE id = $1",
+        snippet_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Let a viewer into a `max_views` snippet, recording them like
+/// [`record_view`]. A viewer already on `snippet_views` is always let back
+/// in; a new one only while `views` is under the cap. The snippet row is
+/// locked first, so concurrent first reads are admitted one at a time and
+/// can't push `views` past `max_views`. `Ok(false)` means the snippet is
+/// full (or gone).
+pub async fn admit_viewer(
+    pool: &PgPool,
+    snippet_id: Uuid,
+    viewer_hash: &[u8; 32],
+) -> Result<bool, sqlx::Error> {
+    let hash_slice: &[u8] = viewer_hash;
+    let mut tx = pool.begin().await?;
+    let Some(row) = sqlx::query!(
+        "SELECT views, max_views FROM snippets
+          WHERE id = $1 AND deleted_at IS NULL
+          FOR UPDATE",
+        snippet_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+    let seen = sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+             SELECT 1 FROM snippet_views WHERE snippet_id = $1 AND viewer_hash = $2
+           ) AS "seen!""#,
+        snippet_id,
+        hash_slice,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if seen {
+        return Ok(true);
+    }
+    if row.max_views.is_some_and(|max| row.views >= max) {
+        return Ok(false);
+    }
+    sqlx::query!(
+        "INSERT INTO snippet_views (snippet_id, viewer_hash) VALUES ($1, $2)",
+        snippet_id,
+        hash_slice,
+    )
+    .execute(&mut *tx)
+    .await?;
+    storage::preserve_updated_at(&mut tx).await?;
+    sqlx::query!(
+        "UPDATE snippets SET views = views + 1 WHERE id = $1",
+        snippet_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Position of a row in a keyset-paginated listing: the sort key collapsed to
+/// an `i64` (timestamps as Unix microseconds, the precision Postgres stores),
+/// plus the id as tie-breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListCursor {
+    pub key: i64,
+    pub id: Uuid,
+}
+
+/// Sort key that [`list_for_user`] orders by, computed the same way the SQL
+/// does. Used to build the next-page cursor from the last row on a page.
+pub fn sort_key(row: &SnippetRow, sort: SnippetSort) -> i64 {
+    fn micros(t: OffsetDateTime) -> i64 {
+        i64::try_from(t.unix_timestamp_nanos() / 1_000).unwrap_or(i64::MAX)
+    }
+    match sort {
+        SnippetSort::Created => micros(row.created_at),
+        SnippetSort::Updated => micros(row.updated_at),
+        SnippetSort::Views => i64::from(row.views),
+        SnippetSort::Size => i64::from(row.size_bytes),
+        SnippetSort::ExpiresAt => row.expires_at.map(micros).unwrap_or(i64::MAX),
+    }
+}
+
+/// Every `None` filter is "don't care".
+pub struct ListFilter<'a> {
+    pub owner_id: Uuid,
+    /// List this organization's snippets instead of `owner_id`'s.
+    pub org_id: Option<Uuid>,
+    pub kind: Option<SnippetType>,
+    pub visibility: Option<Visibility>,
+    pub burn_after_read: Option<bool>,
+    /// `Some(true)` = only snippets past their `expires_at` (which only the
+    /// owner can still see), `Some(false)` = only ones still live.
+    pub expired: Option<bool>,
+    /// Only snippets carrying every one of these (normalized, distinct) tags.
+    pub tags: &'a [String],
+    /// Only snippets in this collection.
+    pub collection_id: Option<Uuid>,
+    pub sort: SnippetSort,
+    pub order: SortOrder,
+    pub cursor: Option<&'a ListCursor>,
+    pub limit: i64,
+}
+
+/// One [`list_for_user`] statement: `$scope` picks the owner or org column
+/// `$1` is matched against, `$key` is the sort column (as indexed in
+/// migration 0027), `$bound` turns the cursor's `$6` back into a value of
+/// that column, and `$cmp`/`$dir` give the direction. Every expansion is its
+/// own static query, so the planner sees a plain keyset scan it can serve
+/// from the index rather than one ordered by a computed key.
+macro_rules! list_page {
+    ($pool:expr, $filter:expr, $scope_id:expr,
+     $scope:tt, $key:tt, $bound:tt, $cmp:tt, $dir:tt) => {
+        sqlx::query_as!(
             SnippetRowRaw,
             r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
-                      s.type AS kind, s.name, s.body, s.size_bytes, s.views,
-                      s.visibility, s.expires_at, s.burn_after_read,
-                      s.first_viewed_at, s.created_at, s.updated_at
+                      s.org_id, o.name AS "org_name?",
+                      s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                      s.size_bytes, s.stored_bytes, s.views,
+                      s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                      s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                      s.first_viewed_at, s.created_at, s.updated_at,
+                      s.forked_from, f.slug AS "forked_from_slug?",
+                      ARRAY(SELECT t.tag FROM snippet_tags t
+                            WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                      ARRAY(SELECT c.name FROM collection_items ci
+                            JOIN collections c ON c.id = ci.collection_id
+                            WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!"
                FROM snippets s
                JOIN users u ON u.id = s.owner_id
-               WHERE s.owner_id = $1
+               LEFT JOIN orgs o ON o.id = s.org_id
+               LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
+               WHERE "#
+                + $scope
+                + r#"
                  AND s.deleted_at IS NULL
-               ORDER BY s.created_at DESC
-               LIMIT $2"#,
-            filter.owner_id,
-            filter.limit,
+                 AND ($2::text IS NULL OR s.type = $2)
+                 AND ($3::text IS NULL OR s.visibility = $3)
+                 AND ($4::bool IS NULL OR s.burn_after_read = $4)
+                 AND ($5::bool IS NULL
+                      OR (s.expires_at IS NOT NULL AND s.expires_at <= now()) = $5)
+                 AND (cardinality($9::text[]) = 0
+                      OR (SELECT count(*) FROM snippet_tags t
+                          WHERE t.snippet_id = s.id AND t.tag = ANY($9)) = cardinality($9))
+                 AND ($10::uuid IS NULL
+                      OR EXISTS (SELECT 1 FROM collection_items ci
+                                 WHERE ci.collection_id = $10 AND ci.snippet_id = s.id))
+                 AND ($6::bigint IS NULL OR ("#
+                + $key
+                + ", s.id) "
+                + $cmp
+                + " ("
+                + $bound
+                + ", $7::uuid))
+               ORDER BY "
+                + $key
+                + " "
+                + $dir
+                + ", s.id "
+                + $dir
+                + "
+               LIMIT $8",
+            $scope_id,
+            $filter.kind.map(|k| k.as_str()),
+            $filter.visibility.map(|v| v.as_str()),
+            $filter.burn_after_read,
+            $filter.expired,
+            $filter.cursor.map(|c| c.key),
+            $filter.cursor.map(|c| c.id),
+            $filter.limit,
+            $filter.tags,
+            $filter.collection_id,
         )
-        .fetch_all(pool)
-        .await?,
+        .fetch_all($pool)
+        .await?
+    };
+}
+
+/// `list_page!` for each sort and direction, with the keys and cursor
+/// bounds that [`sort_key`] mirrors. Timestamps come back from microseconds;
+/// a never-expiring snippet sorts as `'infinity'`, which is `i64::MAX`.
+macro_rules! list_sorted {
+    ($pool:expr, $filter:expr, $scope_id:expr, $scope:tt) => {
+        match ($filter.sort, $filter.order) {
+            (SnippetSort::Created, SortOrder::Desc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.created_at",
+                "'epoch'::timestamptz + $6 * interval '1 microsecond'", "<", "DESC"
+            ),
+            (SnippetSort::Created, SortOrder::Asc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.created_at",
+                "'epoch'::timestamptz + $6 * interval '1 microsecond'", ">", "ASC"
+            ),
+            (SnippetSort::Updated, SortOrder::Desc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.updated_at",
+                "'epoch'::timestamptz + $6 * interval '1 microsecond'", "<", "DESC"
+            ),
+            (SnippetSort::Updated, SortOrder::Asc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.updated_at",
+                "'epoch'::timestamptz + $6 * interval '1 microsecond'", ">", "ASC"
+            ),
+            (SnippetSort::Views, SortOrder::Desc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.views", "$6", "<", "DESC"
+            ),
+            (SnippetSort::Views, SortOrder::Asc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.views", "$6", ">", "ASC"
+            ),
+            (SnippetSort::Size, SortOrder::Desc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.size_bytes", "$6", "<", "DESC"
+            ),
+            (SnippetSort::Size, SortOrder::Asc) => list_page!(
+                $pool, $filter, $scope_id, $scope, "s.size_bytes", "$6", ">", "ASC"
+            ),
+            (SnippetSort::ExpiresAt, SortOrder::Desc) => list_page!(
+                $pool, $filter, $scope_id, $scope,
+                "COALESCE(s.expires_at, 'infinity'::timestamptz)",
+                "CASE WHEN $6 = 9223372036854775807 THEN 'infinity'::timestamptz
+                      ELSE 'epoch'::timestamptz + $6 * interval '1 microsecond' END",
+                "<", "DESC"
+            ),
+            (SnippetSort::ExpiresAt, SortOrder::Asc) => list_page!(
+                $pool, $filter, $scope_id, $scope,
+                "COALESCE(s.expires_at, 'infinity'::timestamptz)",
+                "CASE WHEN $6 = 9223372036854775807 THEN 'infinity'::timestamptz
+                      ELSE 'epoch'::timestamptz + $6 * interval '1 microsecond' END",
+                ">", "ASC"
+            ),
+        }
     };
+}
+
+/// One page of the owner's (or an org's) snippets in `(sort_key, id)` order.
+///
+/// Filters are `$n IS NULL OR ...` in every variant; the scope, sort column
+/// and direction each get their own statement (see `list_page!`) so that a
+/// page is an index range scan over `(owner_id | org_id, column, id)`. The
+/// keys and bounds there must stay in step with [`sort_key`].
+pub async fn list_for_user(
+    pool: &PgPool,
+    filter: ListFilter<'_>,
+) -> Result<Vec<SnippetRow>, sqlx::Error> {
+    let rows = match filter.org_id {
+        None => list_sorted!(pool, filter, filter.owner_id, "s.owner_id = $1"),
+        Some(org_id) => list_sorted!(pool, filter, org_id, "s.org_id = $1"),
+    };
+    Ok(rows.into_iter().filter_map(map).collect())
+}
+
+/// The live snippets in a collection (migration 0024), in its order.
+pub async fn in_collection(
+    pool: &PgPool,
+    collection_id: Uuid,
+) -> Result<Vec<SnippetRow>, sqlx::Error> {
+    let rows = sqlx::query_as!(
+        SnippetRowRaw,
+        r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
+                  s.org_id, o.name AS "org_name?",
+                  s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, s.views,
+                  s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                  s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                  s.first_viewed_at, s.created_at, s.updated_at,
+                  s.forked_from, f.slug AS "forked_from_slug?",
+                  ARRAY(SELECT t.tag FROM snippet_tags t
+                        WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                  ARRAY(SELECT c.name FROM collection_items ci
+                        JOIN collections c ON c.id = ci.collection_id
+                        WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!"
+           FROM collection_items i
+           JOIN snippets s ON s.id = i.snippet_id
+           JOIN users u ON u.id = s.owner_id
+           LEFT JOIN orgs o ON o.id = s.org_id
+           LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
+           WHERE i.collection_id = $1 AND s.deleted_at IS NULL
+           ORDER BY i.position, i.added_at"#,
+        collection_id,
+    )
+    .fetch_all(pool)
+    .await?;
     Ok(rows.into_iter().filter_map(map).collect())
 }
+
+/// [`SnippetRowRaw`] plus the relevance computed by [`search_for_user`].
+struct SearchRowRaw {
+    id: Uuid,
+    slug: String,
+    owner_id: Uuid,
+    owner_username: String,
+    org_id: Option<Uuid>,
+    org_name: Option<String>,
+    kind: String,
+    name: Option<String>,
+    body: String,
+    body_codec: Option<String>,
+    body_packed: Option<Vec<u8>>,
+    body_ref: Option<String>,
+    size_bytes: i32,
+    stored_bytes: i32,
+    views: i32,
+    visibility: String,
+    password_hash: Option<String>,
+    strict_private: bool,
+    encrypted: bool,
+    publish_at: Option<OffsetDateTime>,
+    expires_at: Option<OffsetDateTime>,
+    burn_after_read: bool,
+    burn_window_seconds: i32,
+    max_views: Option<i32>,
+    first_viewed_at: Option<OffsetDateTime>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+    forked_from: Option<Uuid>,
+    forked_from_slug: Option<String>,
+    tags: Vec<String>,
+    collections: Vec<String>,
+    rank: f32,
+}
+
+pub struct SearchFilter<'a> {
+    pub owner_id: Uuid,
+    /// Raw user query, interpreted with `websearch_to_tsquery` (quotes,
+    /// `or`, `-term`) for word matches and as a literal substring for the
+    /// trigram match.
+    pub query: &'a str,
+    pub kind: Option<SnippetType>,
+    pub limit: i64,
+}
+
+/// Escape `%`, `_` and the escape character itself so `s` matches literally
+/// inside an `ILIKE` pattern.
+fn like_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Ranked search over the owner's snippets, best match first. Same
+/// visibility rules as [`list_for_user`]: soft-deleted rows are excluded,
+/// expired-but-owned ones are not (the owner can still open them).
+///
+/// A row matches if its tsvector matches the web-search query, or if the raw
+/// query appears as a substring of the name or body (trigram-indexed).
+/// Encrypted rows only match on their name. Rank
+/// is `ts_rank` plus name similarity, so a substring-only hit still sorts
+/// sensibly below proper word matches.
+pub async fn search_for_user(
+    pool: &PgPool,
+    filter: SearchFilter<'_>,
+) -> Result<Vec<(SnippetRow, f32)>, sqlx::Error> {
+    let pattern = format!("%{}%", like_escape(filter.query));
+    let rows = sqlx::query_as!(
+        SearchRowRaw,
+        r#"SELECT s.id, s.slug, s.owner_id, u.username AS owner_username,
+                  s.org_id, o.name AS "org_name?",
+                  s.type AS kind, s.name, s.body, s.body_codec, s.body_packed, s.body_ref,
+                  s.size_bytes, s.stored_bytes, s.views,
+                  s.visibility, s.password_hash, s.strict_private, s.encrypted,
+                  s.publish_at, s.expires_at, s.burn_after_read, s.burn_window_seconds, s.max_views,
+                  s.first_viewed_at, s.created_at, s.updated_at,
+                  s.forked_from, f.slug AS "forked_from_slug?",
+                  ARRAY(SELECT t.tag FROM snippet_tags t
+                        WHERE t.snippet_id = s.id ORDER BY t.tag) AS "tags!",
+                  ARRAY(SELECT c.name FROM collection_items ci
+                        JOIN collections c ON c.id = ci.collection_id
+                        WHERE ci.snippet_id = s.id ORDER BY c.name) AS "collections!",
+                  (ts_rank(s.search_tsv, q.tsq)
+                   + similarity(coalesce(s.name, ''), $2))::real AS "rank!"
+           FROM snippets s
+           JOIN users u ON u.id = s.owner_id
+           LEFT JOIN orgs o ON o.id = s.org_id
+           LEFT JOIN snippets f ON f.id = s.forked_from AND f.deleted_at IS NULL
+           CROSS JOIN (SELECT websearch_to_tsquery('simple', $2) AS tsq) q
+           WHERE s.owner_id = $1
+             AND s.deleted_at IS NULL
+             AND ($4::text IS NULL OR s.type = $4)
+             AND (s.search_tsv @@ q.tsq OR s.name ILIKE $3
+                  OR (NOT s.encrypted AND s.body ILIKE $3))
+           ORDER BY 32 DESC, s.created_at DESC, s.id DESC
+           LIMIT $5"#,
+        filter.owner_id,
+        filter.query,
+        pattern,
+        filter.kind.map(|k| k.as_str()),
+        filter.limit,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|r| {
+            let rank = r.rank;
+            map(SnippetRowRaw {
+                id: r.id,
+                slug: r.slug,
+                owner_id: r.owner_id,
+                owner_username: r.owner_username,
+                org_id: r.org_id,
+                org_name: r.org_name,
+                kind: r.kind,
+                name: r.name,
+                body: r.body,
+                body_codec: r.body_codec,
+                body_packed: r.body_packed,
+                body_ref: r.body_ref,
+                size_bytes: r.size_bytes,
+                stored_bytes: r.stored_bytes,
+                views: r.views,
+                visibility: r.visibility,
+                password_hash: r.password_hash,
+                strict_private: r.strict_private,
+                encrypted: r.encrypted,
+                publish_at: r.publish_at,
+                expires_at: r.expires_at,
+                burn_after_read: r.burn_after_read,
+                burn_window_seconds: r.burn_window_seconds,
+                max_views: r.max_views,
+                first_viewed_at: r.first_viewed_at,
+                created_at: r.created_at,
+                updated_at: r.updated_at,
+                forked_from: r.forked_from,
+                forked_from_slug: r.forked_from_slug,
+                tags: r.tags,
+                collections: r.collections,
+            })
+            .map(|row| (row, rank))
+        })
+        .collect())
+}
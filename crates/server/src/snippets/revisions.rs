@@ -0,0 +1,222 @@
+//! Snippet edit history. Revisions are written by [`repo::insert`] /
+//! [`repo::update`]; these handlers list, fetch and diff them, and purge them.
+//!
+//! History is for the people who may edit the snippet — the same
+//! [`ensure_can_manage`] gate as `PATCH /api/v1/snippets/:slug` — not for
+//! everyone who can read it: an old revision can hold what an edit took out
+//! on purpose, such as a credential pasted by mistake. For the same reason
+//! `DELETE /api/v1/snippets/:slug/revisions` drops all of it but the latest
+//! revision, which is the snippet as it stands; numbering carries on from
+//! there.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+    Json,
+};
+use pastedev_core::{ListRevisionsResponse, OrgRole, SnippetRevision, SnippetRevisionListItem};
+use serde::Deserialize;
+use similar::TextDiff;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, AuthedUser, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        handlers::{ensure_can_manage, validate_slug},
+        repo::{self, RevisionMetaRow, RevisionRow, SnippetRow},
+    },
+    storage,
+};
+
+/// Lines of unchanged context around each hunk, same as `diff -u`.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Wall-clock budget for computing a diff. Past it `similar` settles for a
+/// coarser (still correct) diff, so two large, very different revisions
+/// can't tie up a worker.
+const DIFF_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+
+fn owner_of(username: Option<String>) -> Option<pastedev_core::snippet::SnippetOwner> {
+    username.map(|username| pastedev_core::snippet::SnippetOwner { username })
+}
+
+fn to_list_item(row: RevisionMetaRow) -> SnippetRevisionListItem {
+    SnippetRevisionListItem {
+        revision: row.revision,
+        name: row.name,
+        size_bytes: row.size_bytes,
+        author: owner_of(row.author_username),
+        created_at: row.created_at,
+    }
+}
+
+fn to_dto(row: RevisionRow) -> SnippetRevision {
+    SnippetRevision {
+        revision: row.revision,
+        name: row.name,
+        body: row.body,
+        size_bytes: row.size_bytes,
+        author: owner_of(row.author_username),
+        created_at: row.created_at,
+    }
+}
+
+/// [`repo::revision`] with the body read back from the body store if it
+/// lives there.
+async fn load_revision(
+    state: &AppState,
+    snippet_id: Uuid,
+    n: i32,
+) -> Result<RevisionRow, AppError> {
+    let mut rev = repo::revision(&state.pool, snippet_id, n)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let location = std::mem::take(&mut rev.location);
+    rev.body = storage::load(state.bodies.as_ref(), std::mem::take(&mut rev.body), location).await?;
+    Ok(rev)
+}
+
+/// Resolve `slug` to a snippet whose history `user` may see, or with
+/// `need`, purge.
+async fn load_managed(
+    state: &AppState,
+    user: &AuthedUser,
+    slug: &str,
+    need: OrgRole,
+) -> Result<SnippetRow, AppError> {
+    validate_slug(slug)?;
+    let row = repo::by_slug(&state.pool, slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(state, user, &row, need).await?;
+    Ok(row)
+}
+
+/// `GET /api/v1/snippets/:slug/revisions` — newest first, without bodies.
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(q): Query<ListQuery>,
+) -> Result<Json<ListRevisionsResponse>, AppError> {
+    let row = load_managed(&state, &user.0, &slug, OrgRole::Member).await?;
+    let before = q
+        .cursor
+        .as_deref()
+        .map(|c| {
+            c.trim()
+                .parse::<i32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| AppError::Validation("invalid cursor".into()))
+        })
+        .transpose()?;
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+
+    let mut rows = repo::list_revisions(&state.pool, row.id, before, limit + 1).await?;
+    let mut next_cursor = None;
+    if rows.len() as i64 > limit {
+        rows.pop();
+        next_cursor = rows.last().map(|r| r.revision.to_string());
+    }
+    Ok(Json(ListRevisionsResponse {
+        items: rows.into_iter().map(to_list_item).collect(),
+        next_cursor,
+    }))
+}
+
+/// `GET /api/v1/snippets/:slug/revisions/:n`
+pub async fn get(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path((slug, n)): Path<(String, i32)>,
+) -> Result<Json<SnippetRevision>, AppError> {
+    let row = load_managed(&state, &user.0, &slug, OrgRole::Member).await?;
+    let rev = load_revision(&state, row.id, n).await?;
+    Ok(Json(to_dto(rev)))
+}
+
+/// `GET /api/v1/snippets/:slug/diff?from=&to=` — unified diff between two
+/// revisions as `text/plain`.
+///
+/// `to` defaults to the latest revision and `from` to the one before it, so a
+/// bare request answers "what did the last edit change". An empty body means
+/// the two revisions are identical.
+pub async fn diff(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(q): Query<DiffQuery>,
+) -> Result<Response, AppError> {
+    let row = load_managed(&state, &user.0, &slug, OrgRole::Member).await?;
+    let to = match q.to {
+        Some(n) => n,
+        None => repo::latest_revision(&state.pool, row.id)
+            .await?
+            .ok_or(AppError::NotFound)?,
+    };
+    let from = q.from.unwrap_or((to - 1).max(1));
+
+    let old = load_revision(&state, row.id, from).await?;
+    let new = load_revision(&state, row.id, to).await?;
+
+    let old_header = format!("a/{}@{}", row.slug, old.revision);
+    let new_header = format!("b/{}@{}", row.slug, new.revision);
+    let text = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(&old.body, &new.body)
+        .unified_diff()
+        .context_radius(DIFF_CONTEXT_LINES)
+        .header(&old_header, &new_header)
+        .to_string();
+
+    let mut response = Response::new(Body::from(text));
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, max-age=0"),
+    );
+    Ok(response)
+}
+
+/// `DELETE /api/v1/snippets/:slug/revisions` — everything but the latest
+/// revision. Takes the same org role as deleting the snippet.
+pub async fn purge(
+    user: RequiresScope<{ scope_id::DELETE }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let row = load_managed(&state, &user.0, &slug, OrgRole::Maintainer).await?;
+    let purged = repo::purge_revisions(&state.pool, row.id).await?;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.revisions.purge",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({ "purged": purged })),
+            ..Default::default()
+        },
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
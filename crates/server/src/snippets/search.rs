@@ -0,0 +1,253 @@
+//! `GET /api/v1/snippets/search` — ranked search over the caller's own
+//! snippets. Matching and ranking happen in SQL (see
+//! [`repo::search_for_user`]); the excerpt shown under each hit is cut here,
+//! from the body the row query already returned.
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use pastedev_core::{
+    SearchSnippetsResponse, SnippetSearchHit, SnippetType, SEARCH_MATCH_END, SEARCH_MATCH_START,
+};
+use serde::Deserialize;
+
+use crate::{
+    auth::extract::{scope_id, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        handlers::to_list_item,
+        repo::{self, SearchFilter},
+    },
+};
+
+/// Longest accepted query, in bytes.
+const MAX_QUERY_LEN: usize = 200;
+/// Bytes of context kept on each side of a match in the excerpt.
+const CONTEXT_BYTES: usize = 60;
+/// Excerpt fragments per hit; later matches beyond these are dropped.
+const MAX_FRAGMENTS: usize = 2;
+
+/// Byte range of one match in the body.
+type Span = (usize, usize);
+
+/// An excerpt window `[start, end)` and the matches inside it.
+struct Fragment {
+    start: usize,
+    end: usize,
+    matches: Vec<Span>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// `GET /api/v1/snippets/search?q=&type=&limit=`
+pub async fn search(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Query(q): Query<SearchQuery>,
+) -> Result<Json<SearchSnippetsResponse>, AppError> {
+    if user.0.key_org_id.is_some() {
+        return Err(AppError::Forbidden(Some("key is scoped to an org")));
+    }
+    let query = q.q.as_deref().map(str::trim).unwrap_or("");
+    if query.is_empty() {
+        return Err(AppError::Validation("q is required".into()));
+    }
+    if query.len() > MAX_QUERY_LEN {
+        return Err(AppError::Validation(format!(
+            "q must be at most {} bytes",
+            MAX_QUERY_LEN
+        )));
+    }
+    let kind = q
+        .kind
+        .as_deref()
+        .map(|s| s.parse::<SnippetType>().map_err(|_| AppError::Validation("invalid type".into())))
+        .transpose()?;
+    let limit = q.limit.unwrap_or(20).clamp(1, 100);
+
+    let rows = repo::search_for_user(
+        &state.pool,
+        SearchFilter {
+            owner_id: user.0.id,
+            query,
+            kind,
+            limit,
+        },
+    )
+    .await?;
+    let terms = highlight_terms(query);
+    Ok(Json(SearchSnippetsResponse {
+        items: rows
+            .iter()
+            .map(|(row, rank)| SnippetSearchHit {
+                snippet: to_list_item(row),
+                rank: *rank,
+                // Ciphertext can "contain" a short term by chance.
+                highlight: (!row.encrypted).then(|| highlight(&row.body, &terms)).flatten(),
+            })
+            .collect(),
+    }))
+}
+
+/// Words worth highlighting from a web-search style query: quotes stripped,
+/// `-excluded` terms and the `or` operator dropped, ASCII-lowercased to match
+/// the case-folded haystack in [`highlight`].
+fn highlight_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter(|t| !t.starts_with('-') && !t.eq_ignore_ascii_case("or"))
+        .map(|t| t.trim_matches('"').to_ascii_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Build a short excerpt of `body` around the first matches of `terms`.
+///
+/// Matching is ASCII case-insensitive. `to_ascii_lowercase` keeps byte
+/// offsets identical to the original, so match spans can index `body`
+/// directly; a match of a valid UTF-8 term always lands on char boundaries.
+/// Whitespace inside the excerpt is collapsed so a fragment prints on one
+/// line.
+fn highlight(body: &str, terms: &[String]) -> Option<String> {
+    let hay = body.to_ascii_lowercase();
+    let mut spans: Vec<Span> = Vec::new();
+    for term in terms {
+        let mut from = 0;
+        while let Some(i) = hay[from..].find(term.as_str()) {
+            let start = from + i;
+            spans.push((start, start + term.len()));
+            from = start + term.len();
+        }
+    }
+    if spans.is_empty() {
+        return None;
+    }
+    spans.sort_unstable();
+    // Merge overlapping spans (one term can be a substring of another).
+    let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+    for (s, e) in spans {
+        match merged.last_mut() {
+            Some(last) if s <= last.1 => last.1 = last.1.max(e),
+            _ => merged.push((s, e)),
+        }
+    }
+
+    // Group spans into fragments: a span within the current window's reach
+    // extends it, anything further starts a new fragment.
+    let mut fragments: Vec<Fragment> = Vec::new();
+    for (s, e) in merged {
+        match fragments.last_mut() {
+            Some(frag) if s <= frag.end => {
+                frag.end = frag.end.max(e + CONTEXT_BYTES);
+                frag.matches.push((s, e));
+            }
+            _ => {
+                if fragments.len() == MAX_FRAGMENTS {
+                    break;
+                }
+                fragments.push(Fragment {
+                    start: s.saturating_sub(CONTEXT_BYTES),
+                    end: e + CONTEXT_BYTES,
+                    matches: vec![(s, e)],
+                });
+            }
+        }
+    }
+
+    let mut out: Vec<String> = Vec::with_capacity(fragments.len());
+    for Fragment { start, end, matches } in fragments {
+        let start = floor_char_boundary(body, start);
+        let end = ceil_char_boundary(body, end.min(body.len()));
+        let mut frag = String::new();
+        if start > 0 {
+            frag.push('…');
+        }
+        let mut at = start;
+        for (s, e) in matches {
+            frag.push_str(&body[at..s]);
+            frag.push_str(SEARCH_MATCH_START);
+            frag.push_str(&body[s..e]);
+            frag.push_str(SEARCH_MATCH_END);
+            at = e;
+        }
+        frag.push_str(&body[at..end]);
+        if end < body.len() {
+            frag.push('…');
+        }
+        out.push(frag.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    Some(out.join(" "))
+}
+
+fn floor_char_boundary(s: &str, mut i: usize) -> usize {
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_char_boundary(s: &str, mut i: usize) -> usize {
+    while !s.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(q: &str) -> Vec<String> {
+        highlight_terms(q)
+    }
+
+    #[test]
+    fn terms_drop_operators_and_exclusions() {
+        assert_eq!(terms("Foo or bar -baz"), vec!["foo", "bar"]);
+        assert_eq!(terms("\"tokio runtime\""), vec!["tokio", "runtime"]);
+    }
+
+    #[test]
+    fn highlight_marks_case_insensitive_matches() {
+        let h = highlight("use Tokio::main;\nasync fn main() {}", &terms("tokio")).unwrap();
+        assert_eq!(h, "use «Tokio»::main; async fn main() {}");
+    }
+
+    #[test]
+    fn highlight_trims_long_bodies_with_ellipses() {
+        let body = format!("{}needle{}", "a".repeat(500), "b".repeat(500));
+        let h = highlight(&body, &terms("needle")).unwrap();
+        assert!(h.starts_with('…') && h.ends_with('…'));
+        assert!(h.contains("«needle»"));
+        assert!(h.len() < 200);
+    }
+
+    #[test]
+    fn highlight_caps_fragments() {
+        let gap = "x".repeat(1000);
+        let body = format!("one{gap}one{gap}one{gap}");
+        let h = highlight(&body, &terms("one")).unwrap();
+        assert_eq!(h.matches("«one»").count(), MAX_FRAGMENTS);
+    }
+
+    #[test]
+    fn highlight_respects_char_boundaries() {
+        // Multi-byte characters right at the context edge must not panic.
+        let body = format!("{}key{}", "é".repeat(100), "ü".repeat(100));
+        let h = highlight(&body, &terms("key")).unwrap();
+        assert!(h.contains("«key»"));
+    }
+
+    #[test]
+    fn no_body_match_means_no_highlight() {
+        assert_eq!(highlight("nothing here", &terms("absent")), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Share lists for private snippets (migration 0016).
+//!
+//! Plain `private` visibility lets any approved user read a snippet whose
+//! slug they have. With `strict_private` on, [`super::handlers::enforce_access`]
+//! narrows that to the owner and the users on the snippet's share list. The
+//! owner manages both through `GET` / `PATCH /api/v1/snippets/:slug/shares`.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use pastedev_core::{
+    ListSharesResponse, OrgRole, ShareRequest, SnippetEvent, SnippetShare, Visibility,
+};
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, AuthedUser, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        events,
+        handlers::{ensure_can_manage, validate_slug},
+        repo::{self, SnippetRow},
+    },
+    users::{self, validate::normalize_username},
+};
+
+/// Whether `caller_id` gets past strict private mode on `row` by being on
+/// its share list. Only touches the database when the answer matters: a
+/// strict private snippet read by someone signed in who isn't the owner.
+pub async fn is_shared(
+    state: &AppState,
+    row: &SnippetRow,
+    caller_id: Option<Uuid>,
+) -> Result<bool, AppError> {
+    match caller_id {
+        Some(uid)
+            if row.visibility == Visibility::Private
+                && row.strict_private
+                && uid != row.owner_id =>
+        {
+            Ok(repo::is_shared_with(&state.pool, row.id, uid).await?)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Resolve `slug` to a live snippet the caller may manage.
+async fn load_owned(state: &AppState, slug: &str, user: &AuthedUser) -> Result<SnippetRow, AppError> {
+    validate_slug(slug)?;
+    let row = repo::by_slug(&state.pool, slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(state, user, &row, OrgRole::Member).await?;
+    Ok(row)
+}
+
+async fn response(state: &AppState, strict_private: bool, id: Uuid) -> Result<ListSharesResponse, AppError> {
+    let shares = repo::shares(&state.pool, id).await?;
+    Ok(ListSharesResponse {
+        strict_private,
+        shares: shares
+            .into_iter()
+            .map(|s| SnippetShare {
+                username: s.username,
+                created_at: s.created_at,
+            })
+            .collect(),
+    })
+}
+
+/// Look up every username, keyed by user id. Unknown names are a 400 rather
+/// than a silent no-op, so a typo doesn't leave someone without access.
+async fn resolve_users(
+    state: &AppState,
+    names: &[String],
+) -> Result<HashMap<Uuid, String>, AppError> {
+    let mut out = HashMap::with_capacity(names.len());
+    for raw in names {
+        let name = normalize_username(raw)?;
+        let user = users::repo::by_username(&state.pool, &name)
+            .await?
+            .ok_or_else(|| AppError::Validation(format!("unknown user: {name}")))?;
+        out.insert(user.id, user.username);
+    }
+    Ok(out)
+}
+
+/// `GET /api/v1/snippets/:slug/shares` — owner (or org member) only.
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<ListSharesResponse>, AppError> {
+    let row = load_owned(&state, &slug, &user.0).await?;
+    Ok(Json(response(&state, row.strict_private, row.id).await?))
+}
+
+/// `PATCH /api/v1/snippets/:slug/shares` — owner (or org member) only. Adds and removes
+/// usernames and toggles strict private mode in one go.
+pub async fn update(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(req): Json<ShareRequest>,
+) -> Result<Json<ListSharesResponse>, AppError> {
+    let row = load_owned(&state, &slug, &user.0).await?;
+    if req.add.is_empty() && req.remove.is_empty() && req.strict_private.is_none() {
+        return Err(AppError::Validation("at least one field is required".into()));
+    }
+
+    let add = resolve_users(&state, &req.add).await?;
+    let remove = resolve_users(&state, &req.remove).await?;
+    if add.contains_key(&row.owner_id) {
+        return Err(AppError::Validation(
+            "the owner can always read their own snippet".into(),
+        ));
+    }
+    if let Some(name) = add.keys().find_map(|id| remove.get(id)) {
+        return Err(AppError::Validation(format!("{name} is both added and removed")));
+    }
+
+    let add_ids: Vec<Uuid> = add.keys().copied().collect();
+    let remove_ids: Vec<Uuid> = remove.keys().copied().collect();
+    let change =
+        repo::update_shares(&state.pool, row.id, &add_ids, &remove_ids, req.strict_private).await?;
+    let strict_private = req.strict_private.unwrap_or(row.strict_private);
+
+    let mut added: Vec<&str> = change.added.iter().filter_map(|id| add.get(id)).map(String::as_str).collect();
+    let mut removed: Vec<&str> =
+        change.removed.iter().filter_map(|id| remove.get(id)).map(String::as_str).collect();
+    added.sort_unstable();
+    removed.sort_unstable();
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.share",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(row.id),
+            payload: Some(serde_json::json!({
+                "added": added,
+                "removed": removed,
+                "old": { "strict_private": row.strict_private },
+                "new": { "strict_private": strict_private },
+            })),
+            ..Default::default()
+        },
+    );
+    events::spawn_notify(state.pool.clone(), row.id, SnippetEvent::Settings);
+    Ok(Json(response(&state, strict_private, row.id).await?))
+}
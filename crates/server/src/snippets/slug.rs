@@ -1,3 +1,4 @@
+use pastedev_core::SlugStrategy;
 use sqlx::Postgres;
 
 use crate::error::AppError;
@@ -6,15 +7,16 @@ use super::repo::{self, SnippetDraft, SnippetRow};
 
 const MAX_RETRIES: usize = 5;
 
-/// Generate a slug and INSERT, retrying up to [`MAX_RETRIES`] times on the
+/// Generate a slug with `strategy` and INSERT, retrying up to [`MAX_RETRIES`] times on the
 /// `snippets_slug_uniq` constraint. Surfaces a conflict only after exhausting
 /// the retry budget.
 pub async fn create_with_retry<'a>(
     pool: &sqlx::Pool<Postgres>,
+    strategy: SlugStrategy,
     draft: &SnippetDraft<'a>,
 ) -> Result<SnippetRow, AppError> {
     for attempt in 0..MAX_RETRIES {
-        let slug = pastedev_core::slug::generate();
+        let slug = strategy.generate();
         match repo::insert(pool, &slug, draft).await {
             Ok(row) => return Ok(row),
             Err(sqlx::Error::Database(db_err))
@@ -30,3 +32,27 @@ pub async fn create_with_retry<'a>(
     }
     Err(AppError::Conflict("slug space exhausted after retries"))
 }
+
+/// INSERT under an owner-chosen slug. No retries: a taken slug (including
+/// an alias or a retired one) is the caller's to resolve.
+pub async fn create_with_slug<'a>(
+    pool: &sqlx::Pool<Postgres>,
+    slug: &str,
+    draft: &SnippetDraft<'a>,
+) -> Result<SnippetRow, AppError> {
+    repo::insert(pool, slug, draft).await.map_err(map_taken)
+}
+
+/// A unique violation anywhere in the slug namespace becomes a 409.
+pub fn map_taken(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(db_err)
+            if db_err
+                .constraint()
+                .is_some_and(|c| c == "snippets_slug_uniq" || c == "snippet_aliases_pkey") =>
+        {
+            AppError::Conflict("slug is already taken")
+        }
+        e => e.into(),
+    }
+}
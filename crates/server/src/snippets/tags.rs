@@ -0,0 +1,89 @@
+//! Tags (migration 0024).
+//!
+//! Free-form labels on a snippet, normalized by
+//! [`pastedev_core::normalize_tag`]. They show on the snippet for anyone who
+//! can read it, are set on create or through
+//! `PATCH /api/v1/snippets/:slug/tags`, and filter `GET /api/v1/snippets`.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use pastedev_core::{ListTagsResponse, OrgRole, TagCount, TagsRequest, TagsResponse, MAX_TAGS};
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        handlers::{clean_tags, ensure_can_manage, validate_slug},
+        repo,
+    },
+};
+
+/// `GET /api/v1/tags` — the tags on the caller's own snippets.
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+) -> Result<Json<ListTagsResponse>, AppError> {
+    // Counts span the user's personal snippets too.
+    if user.0.key_org_id.is_some() {
+        return Err(AppError::Forbidden(Some("key is scoped to an org")));
+    }
+    let rows = repo::tag_counts(&state.pool, user.0.id).await?;
+    Ok(Json(ListTagsResponse {
+        items: rows
+            .into_iter()
+            .map(|r| TagCount {
+                tag: r.tag,
+                count: r.count,
+            })
+            .collect(),
+    }))
+}
+
+/// `PATCH /api/v1/snippets/:slug/tags` — owner (or org member) only.
+pub async fn update(
+    user: RequiresScope<{ scope_id::PUBLISH }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(req): Json<TagsRequest>,
+) -> Result<Json<TagsResponse>, AppError> {
+    validate_slug(&slug)?;
+    let row = repo::by_slug(&state.pool, &slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(&state, &user.0, &row, OrgRole::Member).await?;
+    if req.add.is_empty() && req.remove.is_empty() {
+        return Err(AppError::Validation("at least one field is required".into()));
+    }
+    let add = clean_tags(&req.add)?;
+    // Anything that can't be a tag isn't on the snippet either.
+    let remove: Vec<String> = req
+        .remove
+        .iter()
+        .filter_map(|t| pastedev_core::normalize_tag(t))
+        .collect();
+
+    let max = i64::try_from(MAX_TAGS).unwrap_or(i64::MAX);
+    let change = repo::update_tags(&state.pool, row.id, &add, &remove, max)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("at most {MAX_TAGS} tags per snippet")))?;
+    if !change.added.is_empty() || !change.removed.is_empty() {
+        audit::spawn_write(
+            state.pool.clone(),
+            audit::OwnedEvent {
+                event: "snippet.tags",
+                actor_user_id: Some(user.0.id),
+                target_snippet_id: Some(row.id),
+                payload: Some(serde_json::json!({
+                    "added": change.added,
+                    "removed": change.removed,
+                })),
+                ..Default::default()
+            },
+        );
+    }
+    Ok(Json(TagsResponse { tags: change.tags }))
+}
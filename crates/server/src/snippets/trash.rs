@@ -0,0 +1,211 @@
+//! The owner's trash: soft-deleted snippets (see [`repo::delete`]) that can
+//! still be listed, restored, or purged for good.
+//!
+//! Trashed rows never resolve through the normal read paths, so these
+//! handlers are owner-only and go through [`repo::trashed_by_slug`] instead of
+//! `by_slug`. Anything left in the trash longer than `TRASH_RETENTION_DAYS`
+//! is hard-deleted by the maintenance worker (see `maintenance.rs`).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use base64::Engine;
+use pastedev_core::{ListTrashResponse, OrgRole, Snippet, TrashedSnippet};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::extract::{scope_id, AuthedUser, RequiresScope},
+    error::AppError,
+    http::AppState,
+    snippets::{
+        handlers::{ensure_can_manage, load_dto, to_list_item, validate_slug},
+        repo::{self, ListCursor, SnippetRow},
+    },
+};
+
+/// Cursor wire format, base64url without padding:
+///   deleted_at, Unix micros i64 BE (8) || id (16)
+const CURSOR_LEN: usize = 24;
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn micros(t: OffsetDateTime) -> i64 {
+    i64::try_from(t.unix_timestamp_nanos() / 1_000).unwrap_or(i64::MAX)
+}
+
+fn encode_cursor(position: &ListCursor) -> String {
+    let mut buf = Vec::with_capacity(CURSOR_LEN);
+    buf.extend_from_slice(&position.key.to_be_bytes());
+    buf.extend_from_slice(position.id.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn decode_cursor(s: &str) -> Result<ListCursor, AppError> {
+    let invalid = || AppError::Validation("invalid cursor".into());
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(s.trim())
+        .map_err(|_| invalid())?;
+    if bytes.len() != CURSOR_LEN {
+        return Err(invalid());
+    }
+    let mut key = [0u8; 8];
+    key.copy_from_slice(&bytes[..8]);
+    let id = Uuid::from_slice(&bytes[8..]).map_err(|_| invalid())?;
+    Ok(ListCursor {
+        key: i64::from_be_bytes(key),
+        id,
+    })
+}
+
+/// When the trash job becomes due to remove a row deleted at `deleted_at`.
+/// `None` if either the retention window or the job itself is switched off.
+fn purge_at(state: &AppState, deleted_at: OffsetDateTime) -> Option<OffsetDateTime> {
+    match state.config.trash_retention_days {
+        0 => None,
+        _ if state.config.maintenance_trash_interval_secs == 0 => None,
+        days => Some(deleted_at + time::Duration::days(i64::from(days))),
+    }
+}
+
+/// Resolve `slug` to a trashed snippet the caller may restore or purge —
+/// for an org snippet, a maintainer or the member who published it.
+async fn load_owned_trashed(
+    state: &AppState,
+    slug: &str,
+    user: &AuthedUser,
+) -> Result<SnippetRow, AppError> {
+    validate_slug(slug)?;
+    let row = repo::trashed_by_slug(&state.pool, slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    ensure_can_manage(state, user, &row, OrgRole::Maintainer).await?;
+    Ok(row)
+}
+
+/// `GET /api/v1/snippets/trash` — caller's soft-deleted snippets, most
+/// recently deleted first.
+pub async fn list(
+    user: RequiresScope<{ scope_id::READ }>,
+    State(state): State<AppState>,
+    Query(q): Query<ListQuery>,
+) -> Result<Json<ListTrashResponse>, AppError> {
+    if user.0.key_org_id.is_some() {
+        return Err(AppError::Forbidden(Some("key is scoped to an org")));
+    }
+    let cursor = q.cursor.as_deref().map(decode_cursor).transpose()?;
+    let limit = q.limit.unwrap_or(50).clamp(1, 200);
+
+    let mut rows = repo::list_trash(&state.pool, user.0.id, cursor.as_ref(), limit + 1).await?;
+    let mut next_cursor = None;
+    if rows.len() as i64 > limit {
+        rows.pop();
+        next_cursor = rows.last().map(|(row, deleted_at)| {
+            encode_cursor(&ListCursor {
+                key: micros(*deleted_at),
+                id: row.id,
+            })
+        });
+    }
+    Ok(Json(ListTrashResponse {
+        items: rows
+            .iter()
+            .map(|(row, deleted_at)| TrashedSnippet {
+                snippet: to_list_item(row),
+                deleted_at: *deleted_at,
+                purge_at: purge_at(&state, *deleted_at),
+            })
+            .collect(),
+        next_cursor,
+    }))
+}
+
+/// `POST /api/v1/snippets/:slug/restore` — take a snippet back out of the
+/// trash. Responds with the snippet as `GET` would show it to the owner.
+pub async fn restore(
+    user: RequiresScope<{ scope_id::DELETE }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<Snippet>, AppError> {
+    let existing = load_owned_trashed(&state, &slug, &user.0).await?;
+    if !repo::restore(&state.pool, &slug, existing.owner_id).await? {
+        return Err(AppError::NotFound);
+    }
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.restore",
+            actor_user_id: Some(user.0.id),
+            target_snippet_id: Some(existing.id),
+            ..Default::default()
+        },
+    );
+    let row = repo::by_id(&state.pool, existing.id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(load_dto(&state, &row).await?))
+}
+
+/// `POST /api/v1/snippets/:slug/purge` — permanently delete a snippet that
+/// is already in the trash. A live snippet has to be deleted first, so one
+/// stray call can't destroy something that's still shared.
+pub async fn purge(
+    user: RequiresScope<{ scope_id::DELETE }>,
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let existing = match load_owned_trashed(&state, &slug, &user.0).await {
+        Err(AppError::NotFound) => {
+            return match repo::by_slug(&state.pool, &slug).await? {
+                Some(live) if live.owner_id == user.0.id => Err(AppError::Conflict(
+                    "snippet is not in the trash; delete it first",
+                )),
+                _ => Err(AppError::NotFound),
+            };
+        }
+        other => other?,
+    };
+    if !repo::purge(&state.pool, &slug, existing.owner_id).await? {
+        return Err(AppError::NotFound);
+    }
+    // The row is gone, so the audit FK would null out `target_snippet_id`
+    // anyway; the id lives in the payload instead.
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.purge",
+            actor_user_id: Some(user.0.id),
+            payload: Some(serde_json::json!({
+                "kind": "hard",
+                "snippet_id": existing.id,
+                "slug": existing.slug,
+            })),
+            ..Default::default()
+        },
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trash_cursor_round_trips() {
+        let position = ListCursor {
+            key: 1_700_000_000_123_456,
+            id: Uuid::new_v4(),
+        };
+        let back = decode_cursor(&encode_cursor(&position)).expect("decodes");
+        assert_eq!(back, position);
+        assert!(decode_cursor("garbage!").is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Password-protected snippets (`visibility = password`, migration 0014).
+//!
+//! A reader proves they know the passphrase once, on
+//! `POST /api/v1/snippets/:slug/unlock`, and gets back a cookie scoped to
+//! that one snippet:
+//!
+//!   `pds_unlock_<slug>=<expiry, Unix seconds>.<base64url MAC>`
+//!
+//! The MAC is `HMAC(PASTEDEV_SECRET, …)` over the snippet id, the expiry and
+//! the current password hash, so nothing is stored server-side, a cookie is
+//! useless for any other snippet, and changing the passphrase (or leaving
+//! `password` visibility) revokes every cookie issued so far.
+//! [`super::handlers::enforce_access`] and the link-preview builder read it
+//! through [`is_unlocked`].
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::Engine;
+use constant_time_eq::constant_time_eq;
+use pastedev_core::{
+    UnlockRequest, Visibility, SNIPPET_PASSWORD_MAX_LEN, SNIPPET_PASSWORD_MIN_LEN,
+    UNLOCK_COOKIE_PREFIX,
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::{self, extract::try_extract_user, hmac::hmac_sha256_bytes, password},
+    config::Config,
+    error::AppError,
+    http::{client_ip::ClientIp, AppState},
+    snippets::{
+        handlers::validate_slug,
+        repo::{self, SnippetRow},
+    },
+};
+
+/// Validate and hash a new snippet passphrase.
+pub fn hash_password(config: &Config, plain: &str) -> Result<String, AppError> {
+    let len = plain.chars().count();
+    if len < SNIPPET_PASSWORD_MIN_LEN {
+        return Err(AppError::Validation(format!(
+            "password must be at least {SNIPPET_PASSWORD_MIN_LEN} characters"
+        )));
+    }
+    if len > SNIPPET_PASSWORD_MAX_LEN {
+        return Err(AppError::Validation(format!(
+            "password must be at most {SNIPPET_PASSWORD_MAX_LEN} characters"
+        )));
+    }
+    password::hash(plain, config.argon2_m_kib, config.argon2_t_cost)
+        .map_err(|e| AppError::Validation(format!("password hashing: {e}")))
+}
+
+fn cookie_name(slug: &str) -> String {
+    format!("{UNLOCK_COOKIE_PREFIX}{slug}")
+}
+
+/// MAC material: tag || 0x00 || snippet_id || expiry (i64 BE) || hash.
+fn mac(secret: &str, snippet_id: Uuid, expires: i64, password_hash: &str) -> [u8; 32] {
+    let mut material = Vec::with_capacity(16 + 16 + 8 + password_hash.len());
+    material.extend_from_slice(b"snippet-unlock");
+    material.push(0);
+    material.extend_from_slice(snippet_id.as_bytes());
+    material.extend_from_slice(&expires.to_be_bytes());
+    material.extend_from_slice(password_hash.as_bytes());
+    hmac_sha256_bytes(secret, &material)
+}
+
+fn cookie_value(secret: &str, row: &SnippetRow, password_hash: &str, expires: i64) -> String {
+    let tag = mac(secret, row.id, expires, password_hash);
+    format!(
+        "{expires}.{}",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(tag)
+    )
+}
+
+fn find_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    let raw = headers.get(header::COOKIE)?.to_str().ok()?;
+    raw.split(';')
+        .filter_map(|piece| piece.trim().split_once('='))
+        .find(|(n, _)| *n == name)
+        .map(|(_, v)| v)
+}
+
+/// Whether the request carries a live unlock cookie for `row`. Always false
+/// for snippets that aren't password-protected.
+pub fn is_unlocked(secret: &str, headers: &HeaderMap, row: &SnippetRow) -> bool {
+    let Some(password_hash) = row.password_hash.as_deref() else {
+        return false;
+    };
+    let Some(value) = find_cookie(headers, &cookie_name(&row.slug)) else {
+        return false;
+    };
+    let Some((expires, tag)) = value.split_once('.') else {
+        return false;
+    };
+    let Ok(expires) = expires.parse::<i64>() else {
+        return false;
+    };
+    if expires <= OffsetDateTime::now_utc().unix_timestamp() {
+        return false;
+    }
+    let Ok(tag) = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(tag) else {
+        return false;
+    };
+    let expected = mac(secret, row.id, expires, password_hash);
+    constant_time_eq(&tag, &expected)
+}
+
+/// `Set-Cookie` for a fresh unlock. Same `Secure` / `SameSite` policy as the
+/// session cookie; `Path=/` because the API, raw, and shell routes all need it.
+fn build_cookie(config: &Config, row: &SnippetRow, password_hash: &str) -> String {
+    let ttl = config.snippet_unlock_ttl_secs;
+    let expires = OffsetDateTime::now_utc().unix_timestamp() + ttl;
+    let secure = if config.session_cookie_secure {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!(
+        "{name}={value}; Path=/; HttpOnly{secure}; SameSite={samesite}; Max-Age={ttl}",
+        name = cookie_name(&row.slug),
+        value = cookie_value(&config.pastedev_secret, row, password_hash, expires),
+        samesite = config.session_cookie_samesite.as_header_value(),
+    )
+}
+
+/// `POST /api/v1/snippets/:slug/unlock` — trade the passphrase for an unlock
+/// cookie. Anything that isn't a live password-protected snippet is a plain
+/// 404, like on the read paths.
+pub async fn unlock(
+    State(state): State<AppState>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+    Json(req): Json<UnlockRequest>,
+) -> Result<Response, AppError> {
+    validate_slug(&slug)?;
+    let row = repo::by_slug(&state.pool, &slug)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let password_hash = match (row.visibility, row.password_hash.as_deref()) {
+        (Visibility::Password, Some(h)) => h,
+        _ => return Err(AppError::NotFound),
+    };
+    if row.expires_at.is_some_and(|exp| exp <= OffsetDateTime::now_utc()) {
+        return Err(AppError::NotFound);
+    }
+    if !password::verify(&req.password, password_hash) {
+        return Err(AppError::Forbidden(Some("wrong password")));
+    }
+
+    let caller = try_extract_user(&state, &headers).await;
+    audit::spawn_write(
+        state.pool.clone(),
+        audit::OwnedEvent {
+            event: "snippet.unlock",
+            actor_user_id: caller.map(|u| u.id),
+            target_snippet_id: Some(row.id),
+            ip: client_ip.as_ipnetwork(),
+            user_agent: auth::client_user_agent(&headers),
+            ..Default::default()
+        },
+    );
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    if let Ok(v) = HeaderValue::from_str(&build_cookie(&state.config, &row, password_hash)) {
+        response.headers_mut().insert(header::SET_COOKIE, v);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "unlock-test-secret-0123456789";
+
+    fn row(password_hash: Option<&str>) -> SnippetRow {
+        let now = OffsetDateTime::now_utc();
+        SnippetRow {
+            id: Uuid::new_v4(),
+            slug: "AbCdEf1".into(),
+            owner_id: Uuid::new_v4(),
+            owner_username: "owner".into(),
+            kind: pastedev_core::SnippetType::Code,
+            name: None,
+            body: "secret".into(),
+            location: Default::default(),
+            size_bytes: 6,
+            stored_bytes: 6,
+            views: 0,
+            visibility: Visibility::Password,
+            password_hash: password_hash.map(str::to_string),
+            strict_private: false,
+            encrypted: false,
+            publish_at: None,
+            expires_at: None,
+            burn_after_read: false,
+            burn_window_seconds: pastedev_core::BURN_AFTER_READ_WINDOW_SECONDS,
+            max_views: None,
+            first_viewed_at: None,
+            created_at: now,
+            updated_at: now,
+            org_id: None,
+            org_name: None,
+            forked_from: None,
+            forked_from_slug: None,
+            tags: Vec::new(),
+            collections: Vec::new(),
+        }
+    }
+
+    fn with_cookie(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn cookie_unlocks_only_its_snippet_and_hash() {
+        let r = row(Some("$argon2id$one"));
+        let later = OffsetDateTime::now_utc().unix_timestamp() + 60;
+        let value = cookie_value(SECRET, &r, "$argon2id$one", later);
+        let headers = with_cookie(&format!("pds_session=x; pds_unlock_AbCdEf1={value}"));
+        assert!(is_unlocked(SECRET, &headers, &r));
+
+        // A new passphrase revokes it.
+        let rotated = SnippetRow { password_hash: Some("$argon2id$two".into()), ..r.clone() };
+        assert!(!is_unlocked(SECRET, &headers, &rotated));
+        // A different snippet id under the same cookie name is rejected.
+        let other = SnippetRow { id: Uuid::new_v4(), ..r.clone() };
+        assert!(!is_unlocked(SECRET, &headers, &other));
+        // Not password-protected (any more): never unlocked.
+        let public = SnippetRow { password_hash: None, ..r.clone() };
+        assert!(!is_unlocked(SECRET, &headers, &public));
+    }
+
+    #[test]
+    fn expired_or_tampered_cookies_are_rejected() {
+        let r = row(Some("$argon2id$one"));
+        let past = OffsetDateTime::now_utc().unix_timestamp() - 1;
+        let value = cookie_value(SECRET, &r, "$argon2id$one", past);
+        assert!(!is_unlocked(SECRET, &with_cookie(&format!("pds_unlock_AbCdEf1={value}")), &r));
+
+        let later = OffsetDateTime::now_utc().unix_timestamp() + 60;
+        let value = cookie_value(SECRET, &r, "$argon2id$one", later);
+        let (_, tag) = value.split_once('.').unwrap();
+        let stretched = format!("pds_unlock_AbCdEf1={}.{tag}", later + 3_600);
+        assert!(!is_unlocked(SECRET, &with_cookie(&stretched), &r));
+        assert!(!is_unlocked(SECRET, &with_cookie("pds_unlock_AbCdEf1=garbage"), &r));
+    }
+}
@@ -0,0 +1,97 @@
+//! Body encodings at rest (migration 0013). Compression runs on the blocking
+//! pool: a multi-megabyte body takes long enough to stall a runtime worker.
+
+use std::{io, str::FromStr};
+
+use async_compression::tokio::bufread::ZstdDecoder;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use super::BodyStream;
+
+/// Level 3 is zstd's default: most of the ratio of the higher levels on
+/// logs and JSON at a fraction of the CPU.
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Identity,
+    Zstd,
+}
+
+impl Codec {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Codec::Identity => "identity",
+            Codec::Zstd => "zstd",
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "identity" => Ok(Codec::Identity),
+            "zstd" => Ok(Codec::Zstd),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One zstd frame holding `body`.
+pub async fn compress(body: Bytes) -> io::Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || zstd::encode_all(&body[..], ZSTD_LEVEL))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Inverse of [`compress`], for bodies small enough to keep in a row.
+pub async fn decompress(packed: Vec<u8>) -> io::Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || zstd::decode_all(&packed[..]))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Decode a zstd stream on the fly, without buffering the whole body.
+pub fn decode_stream(stream: BodyStream) -> BodyStream {
+    ReaderStream::new(ZstdDecoder::new(StreamReader::new(stream))).boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, TryStreamExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trips_whole_and_streamed() {
+        let body = "2026-10-18T09:58:02Z INFO request served\n".repeat(2_000);
+        let packed = compress(Bytes::from(body.clone())).await.expect("compress");
+        assert!(packed.len() * 10 < body.len(), "repetitive text compresses well");
+
+        let whole = decompress(packed.clone()).await.expect("decompress");
+        assert_eq!(whole, body.as_bytes());
+
+        // Split the frame at awkward points to exercise the streaming decoder.
+        let chunks: Vec<io::Result<Bytes>> = packed
+            .chunks(7)
+            .map(|c| Ok(Bytes::copy_from_slice(c)))
+            .collect();
+        let decoded: Vec<Bytes> = decode_stream(stream::iter(chunks).boxed())
+            .try_collect()
+            .await
+            .expect("decode");
+        assert_eq!(decoded.concat(), body.as_bytes());
+    }
+
+    #[test]
+    fn codec_names_round_trip() {
+        for codec in [Codec::Identity, Codec::Zstd] {
+            assert_eq!(codec.as_str().parse::<Codec>(), Ok(codec));
+        }
+        assert!("gzip".parse::<Codec>().is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Local-filesystem store. Objects live at `<root>/ab/cd/abcd…` (the first
+//! two byte pairs of the digest fan out the directories) and are written to
+//! `<root>/tmp` first, then renamed into place, so a reader never sees a
+//! half-written body.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::{is_valid_ref, BodyStore, BodyStream, StoreError};
+
+pub struct FsStore {
+    root: PathBuf,
+}
+
+impl FsStore {
+    /// Use `root` as the store, creating it if needed.
+    pub async fn open(root: PathBuf) -> io::Result<Self> {
+        tokio::fs::create_dir_all(root.join("tmp")).await?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, body_ref: &str) -> Result<PathBuf, StoreError> {
+        if !is_valid_ref(body_ref) {
+            return Err(StoreError::Backend(format!("invalid body reference {body_ref:?}")));
+        }
+        Ok(self
+            .root
+            .join(&body_ref[..2])
+            .join(&body_ref[2..4])
+            .join(body_ref))
+    }
+}
+
+async fn write_new(tmp: &Path, body: &[u8]) -> io::Result<()> {
+    let mut file = tokio::fs::File::create(tmp).await?;
+    file.write_all(body).await?;
+    file.sync_all().await
+}
+
+#[async_trait]
+impl BodyStore for FsStore {
+    fn name(&self) -> &'static str {
+        "fs"
+    }
+
+    async fn put(&self, body_ref: &str, body: Bytes) -> Result<(), StoreError> {
+        let path = self.path(body_ref)?;
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let tmp = self.root.join("tmp").join(format!("{body_ref}.{}", Uuid::new_v4()));
+        if let Err(e) = write_new(&tmp, &body).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn open(&self, body_ref: &str) -> Result<BodyStream, StoreError> {
+        let path = self.path(body_ref)?;
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => Ok(ReaderStream::new(file).boxed()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                Err(StoreError::NotFound(body_ref.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, body_ref: &str) -> Result<(), StoreError> {
+        let path = self.path(body_ref)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+
+    use super::*;
+    use crate::storage::content_ref;
+
+    #[tokio::test]
+    async fn put_open_delete_round_trip() {
+        let root = std::env::temp_dir().join(format!("pastedev-fs-store-{}", Uuid::new_v4()));
+        let store = FsStore::open(root.clone()).await.expect("open");
+        let body = Bytes::from_static(b"a body worth keeping");
+        let body_ref = content_ref(&body);
+
+        store.put(&body_ref, body.clone()).await.expect("put");
+        // Same content again is a no-op, not an error.
+        store.put(&body_ref, body.clone()).await.expect("put again");
+        let chunks: Vec<Bytes> = store
+            .open(&body_ref)
+            .await
+            .expect("open")
+            .try_collect()
+            .await
+            .expect("read");
+        assert_eq!(chunks.concat(), body.to_vec());
+
+        store.delete(&body_ref).await.expect("delete");
+        store.delete(&body_ref).await.expect("delete is idempotent");
+        assert!(matches!(
+            store.open(&body_ref).await,
+            Err(StoreError::NotFound(_))
+        ));
+        assert!(store.open("../../etc/passwd").await.is_err());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,512 @@
+//! Where snippet bodies live.
+//!
+//! Bodies up to [`INLINE_MAX_BYTES`] always stay in the row's `body` column.
+//! Anything larger goes to the configured [`BodyStore`]: with the Postgres
+//! store that is still the row (so `SNIPPET_MAX_BYTES` stays capped at what a
+//! row comfortably holds); with the filesystem or S3 store it is an object
+//! keyed by the body's SHA-256, and the row keeps the digest in `body_ref`
+//! plus the first [`INLINE_MAX_BYTES`] as a searchable prefix. See migration
+//! 0012.
+//!
+//! Every object handed to an external store is registered in `body_blobs` so
+//! the `orphan_blobs` maintenance job can delete the ones no row points at any
+//! more.
+//!
+//! Independently of where it lives, a full body is zstd-compressed when that
+//! pays off ([`codec`], migration 0013): in the row it goes to `body_packed`
+//! with a [`PACKED_PREFIX_BYTES`] plaintext prefix left in `body`; in a
+//! store the object itself is the compressed frame.
+
+use std::{io, pin::Pin, sync::Arc};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::{
+    config::{BodyStoreConfig, Config},
+    error::AppError,
+};
+
+pub mod codec;
+mod fs;
+mod postgres;
+mod s3;
+
+pub use codec::Codec;
+pub use fs::FsStore;
+pub use postgres::PgStore;
+pub use s3::S3Store;
+
+/// Largest body kept entirely inline when an external store is configured,
+/// and the length of the prefix kept inline for bigger ones. Matches the
+/// `left(body, 262144)` window of `search_tsv` (migration 0010).
+pub const INLINE_MAX_BYTES: usize = 262_144;
+
+/// Plaintext kept in `body` when the full body is packed into the row. Small
+/// enough that packing a compressible body still saves most of its size,
+/// large enough for search and previews to see a meaningful chunk.
+pub const PACKED_PREFIX_BYTES: usize = 65_536;
+
+/// Chunks of a stored body, as read back from a store.
+pub type BodyStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// The reference doesn't resolve. For a reference read from a row this
+    /// means the object was lost, which is a server fault, not a 404.
+    #[error("body {0} not found in store")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Backend(String),
+}
+
+impl From<StoreError> for AppError {
+    fn from(e: StoreError) -> Self {
+        AppError::Other(anyhow::Error::new(e).context("body store"))
+    }
+}
+
+#[async_trait]
+pub trait BodyStore: Send + Sync {
+    /// The `BODY_STORE` value this implementation answers to.
+    fn name(&self) -> &'static str;
+
+    /// Whether bodies over [`INLINE_MAX_BYTES`] leave the row. `false` for
+    /// Postgres, which then never sees `put` / `open` / `delete` calls.
+    fn is_external(&self) -> bool {
+        true
+    }
+
+    /// Store `body` under `body_ref` (its [`content_ref`]). Writing the same
+    /// reference twice is harmless: the contents are identical by
+    /// construction.
+    async fn put(&self, body_ref: &str, body: Bytes) -> Result<(), StoreError>;
+
+    /// Stream a body back.
+    async fn open(&self, body_ref: &str) -> Result<BodyStream, StoreError>;
+
+    /// Remove a body. Deleting something that isn't there is not an error.
+    async fn delete(&self, body_ref: &str) -> Result<(), StoreError>;
+}
+
+/// Build the store selected by `BODY_STORE`.
+pub async fn from_config(config: &Config) -> anyhow::Result<Arc<dyn BodyStore>> {
+    Ok(match &config.body_store {
+        BodyStoreConfig::Postgres => Arc::new(PgStore),
+        BodyStoreConfig::Fs { root } => Arc::new(
+            FsStore::open(root.clone())
+                .await
+                .with_context(|| format!("opening BODY_STORE_PATH {}", root.display()))?,
+        ),
+        BodyStoreConfig::S3(s3) => Arc::new(S3Store::new(s3.clone())?),
+    })
+}
+
+/// Lowercase hex SHA-256 of `body` — the object key in content-addressed
+/// stores.
+pub fn content_ref(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Whether `s` has the shape [`content_ref`] produces. Stores check this
+/// before turning a reference into a path or URL.
+pub fn is_valid_ref(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// The longest prefix of `s` that fits in `max` bytes without splitting a
+/// character.
+fn prefix(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Where a row's full body is when the `body` column doesn't hold all of it:
+/// packed into the row, or in the store. Both `None` = `body` is complete.
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    /// Encoding of `packed` or of the stored object.
+    pub codec: Codec,
+    pub packed: Option<Vec<u8>>,
+    pub body_ref: Option<String>,
+}
+
+/// A body as it is written to a row: what goes in each body column, plus the
+/// full length for `size_bytes` and the length at rest for `stored_bytes`.
+#[derive(Debug, Clone)]
+pub struct StoredBody<'a> {
+    pub inline: &'a str,
+    pub codec: Codec,
+    pub packed: Option<Vec<u8>>,
+    pub body_ref: Option<String>,
+    pub size: usize,
+    pub stored_size: usize,
+}
+
+/// Compress `body` for keeping in the row, if that is worth it: the frame plus
+/// the plaintext prefix left next to it must come out smaller than the body.
+async fn pack(body: &str) -> io::Result<Option<Vec<u8>>> {
+    if body.len() <= PACKED_PREFIX_BYTES {
+        return Ok(None);
+    }
+    let packed = codec::compress(Bytes::copy_from_slice(body.as_bytes())).await?;
+    let kept = prefix(body, PACKED_PREFIX_BYTES).len();
+    Ok((kept + packed.len() < body.len()).then_some(packed))
+}
+
+/// Work out what a row should hold for `body`: compress it if that helps, and
+/// hand it to the store if it's too big to keep inline. An object is
+/// registered in `body_blobs` before it is uploaded; see migration 0012 for
+/// why that order matters.
+pub async fn store<'a>(
+    store: &dyn BodyStore,
+    pool: &PgPool,
+    body: &'a str,
+) -> Result<StoredBody<'a>, AppError> {
+    if !store.is_external() || body.len() <= INLINE_MAX_BYTES {
+        return Ok(match pack(body).await.map_err(StoreError::Io)? {
+            Some(packed) => StoredBody {
+                inline: prefix(body, PACKED_PREFIX_BYTES),
+                codec: Codec::Zstd,
+                stored_size: packed.len(),
+                packed: Some(packed),
+                body_ref: None,
+                size: body.len(),
+            },
+            None => StoredBody {
+                inline: body,
+                codec: Codec::Identity,
+                packed: None,
+                body_ref: None,
+                size: body.len(),
+                stored_size: body.len(),
+            },
+        });
+    }
+    let plain = Bytes::copy_from_slice(body.as_bytes());
+    let packed = codec::compress(plain.clone()).await.map_err(StoreError::Io)?;
+    let (codec, object) = if packed.len() < plain.len() {
+        (Codec::Zstd, Bytes::from(packed))
+    } else {
+        (Codec::Identity, plain)
+    };
+    let body_ref = content_ref(&object);
+    let stored_size = object.len();
+    let size = i64::try_from(stored_size).unwrap_or(i64::MAX);
+    sqlx::query!(
+        "INSERT INTO body_blobs (body_ref, size_bytes) VALUES ($1, $2)
+         ON CONFLICT (body_ref) DO UPDATE SET touched_at = now()",
+        body_ref,
+        size,
+    )
+    .execute(pool)
+    .await?;
+    store.put(&body_ref, object).await?;
+    Ok(StoredBody {
+        inline: prefix(body, INLINE_MAX_BYTES),
+        codec,
+        packed: None,
+        body_ref: Some(body_ref),
+        size: body.len(),
+        stored_size,
+    })
+}
+
+/// A full body ready to send: in one piece, or streamed from the store.
+pub enum Content {
+    Whole(Bytes),
+    Stream(BodyStream),
+}
+
+/// The encoding [`open`] hands a body at `location` out in, known before
+/// anything is read.
+pub fn served_codec(location: &Location, accept_zstd: bool) -> Codec {
+    if accept_zstd && location.codec == Codec::Zstd {
+        Codec::Zstd
+    } else {
+        Codec::Identity
+    }
+}
+
+/// Open a row's full body. With `accept_zstd` a compressed body is returned
+/// as it is kept, and the returned [`Codec`] says which encoding the caller
+/// got; otherwise it is always decoded to plain text.
+pub async fn open(
+    store: &dyn BodyStore,
+    inline: String,
+    location: Location,
+    accept_zstd: bool,
+) -> Result<(Content, Codec), AppError> {
+    let passthrough = served_codec(&location, accept_zstd) == Codec::Zstd;
+    if let Some(body_ref) = &location.body_ref {
+        let stream = store.open(body_ref).await?;
+        return Ok(match location.codec {
+            Codec::Zstd if !passthrough => {
+                (Content::Stream(codec::decode_stream(stream)), Codec::Identity)
+            }
+            codec => (Content::Stream(stream), codec),
+        });
+    }
+    Ok(match location.packed {
+        Some(packed) if passthrough => (Content::Whole(packed.into()), Codec::Zstd),
+        Some(packed) => {
+            let plain = codec::decompress(packed).await.map_err(StoreError::Io)?;
+            (Content::Whole(plain.into()), Codec::Identity)
+        }
+        None => (Content::Whole(inline.into()), Codec::Identity),
+    })
+}
+
+/// The full text of a row, decoded and in memory.
+pub async fn load(
+    store: &dyn BodyStore,
+    inline: String,
+    location: Location,
+) -> Result<String, AppError> {
+    if location.packed.is_none() && location.body_ref.is_none() {
+        return Ok(inline);
+    }
+    let describe = location.body_ref.clone().unwrap_or_else(|| "in row".into());
+    let bytes = match open(store, inline, location, false).await?.0 {
+        Content::Whole(bytes) => bytes.to_vec(),
+        Content::Stream(stream) => {
+            let chunks: Vec<Bytes> = stream.try_collect().await.map_err(StoreError::Io)?;
+            chunks.concat()
+        }
+    };
+    String::from_utf8(bytes)
+        .map_err(|_| StoreError::Backend(format!("body {describe} is not valid UTF-8")).into())
+}
+
+/// Delete up to `batch` registered objects that no snippet, revision, or
+/// bundle file refers to and that nobody has touched for a day, from both
+/// `body_blobs` and the store. Returns how many went.
+///
+/// The registry rows stay locked by the uncommitted DELETE while the store
+/// deletes run, so a writer about to reference one of these objects again
+/// blocks in [`store`] until we are done, then re-registers and re-uploads
+/// it. An object the store fails to delete is put back in the registry and
+/// retried on a later run.
+pub async fn collect_orphans(
+    store: &dyn BodyStore,
+    pool: &PgPool,
+    batch: i64,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query!(
+        "DELETE FROM body_blobs
+            WHERE body_ref IN (
+                SELECT b.body_ref FROM body_blobs b
+                WHERE b.touched_at < now() - interval '1 day'
+                  AND NOT EXISTS (SELECT 1 FROM snippets s WHERE s.body_ref = b.body_ref)
+                  AND NOT EXISTS (SELECT 1 FROM snippet_revisions r WHERE r.body_ref = b.body_ref)
+                  AND NOT EXISTS (SELECT 1 FROM snippet_files f WHERE f.body_ref = b.body_ref)
+                ORDER BY b.touched_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING body_ref, size_bytes",
+        batch,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    let mut deleted = 0u64;
+    for row in &rows {
+        match store.delete(&row.body_ref).await {
+            Ok(()) => deleted += 1,
+            Err(e) => {
+                tracing::warn!(body_ref = %row.body_ref, error = ?e, "orphaned body could not be deleted");
+                sqlx::query!(
+                    "INSERT INTO body_blobs (body_ref, size_bytes) VALUES ($1, $2)",
+                    row.body_ref,
+                    row.size_bytes,
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+/// Backfill for rows written before migration 0013 (`body_codec IS NULL`):
+/// pack up to `batch` of them per table where that pays off and give every
+/// one a definite codec, so it isn't looked at again. Stored objects are left
+/// as they are. Returns how many rows were settled, packed or not.
+///
+/// Rows are locked while they are rewritten, so a concurrent edit either
+/// waits or is skipped until the next run; `updated_at` is left alone.
+pub async fn compress_backlog(pool: &PgPool, batch: i64) -> Result<u64, sqlx::Error> {
+    let mut settled = 0u64;
+
+    let mut tx = pool.begin().await?;
+    preserve_updated_at(&mut tx).await?;
+    let rows = sqlx::query!(
+        "SELECT id, body, body_ref FROM snippets
+            WHERE body_codec IS NULL
+            ORDER BY id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED",
+        batch,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in rows {
+        let p = backlog_pack(&row.body, row.body_ref.is_some()).await;
+        let prefix = p.as_ref().map(|_| prefix(&row.body, PACKED_PREFIX_BYTES));
+        sqlx::query!(
+            "UPDATE snippets
+                SET body_codec = $2,
+                    body = COALESCE($3, body),
+                    body_packed = $4,
+                    stored_bytes = COALESCE($5, stored_bytes)
+                WHERE id = $1",
+            row.id,
+            codec_for(&p),
+            prefix,
+            p.as_deref(),
+            p.as_ref().map(|p| i32::try_from(p.len()).unwrap_or(i32::MAX)),
+        )
+        .execute(&mut *tx)
+        .await?;
+        settled += 1;
+    }
+    tx.commit().await?;
+
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query!(
+        "SELECT snippet_id, revision, body, body_ref FROM snippet_revisions
+            WHERE body_codec IS NULL
+            ORDER BY snippet_id, revision
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED",
+        batch,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in rows {
+        let p = backlog_pack(&row.body, row.body_ref.is_some()).await;
+        let prefix = p.as_ref().map(|_| prefix(&row.body, PACKED_PREFIX_BYTES));
+        sqlx::query!(
+            "UPDATE snippet_revisions
+                SET body_codec = $3,
+                    body = COALESCE($4, body),
+                    body_packed = $5,
+                    stored_bytes = COALESCE($6, stored_bytes)
+                WHERE snippet_id = $1 AND revision = $2",
+            row.snippet_id,
+            row.revision,
+            codec_for(&p),
+            prefix,
+            p.as_deref(),
+            p.as_ref().map(|p| i32::try_from(p.len()).unwrap_or(i32::MAX)),
+        )
+        .execute(&mut *tx)
+        .await?;
+        settled += 1;
+    }
+    tx.commit().await?;
+
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query!(
+        "SELECT snippet_id, position, body, body_ref FROM snippet_files
+            WHERE body_codec IS NULL
+            ORDER BY snippet_id, position
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED",
+        batch,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in rows {
+        let p = backlog_pack(&row.body, row.body_ref.is_some()).await;
+        let prefix = p.as_ref().map(|_| prefix(&row.body, PACKED_PREFIX_BYTES));
+        sqlx::query!(
+            "UPDATE snippet_files
+                SET body_codec = $3,
+                    body = COALESCE($4, body),
+                    body_packed = $5,
+                    stored_bytes = COALESCE($6, stored_bytes)
+                WHERE snippet_id = $1 AND position = $2",
+            row.snippet_id,
+            row.position,
+            codec_for(&p),
+            prefix,
+            p.as_deref(),
+            p.as_ref().map(|p| i32::try_from(p.len()).unwrap_or(i32::MAX)),
+        )
+        .execute(&mut *tx)
+        .await?;
+        settled += 1;
+    }
+    tx.commit().await?;
+
+    Ok(settled)
+}
+
+/// [`pack`] for a backlog row. Rows whose full body is in the store keep it
+/// there as is; a body that fails to compress is just left uncompressed.
+async fn backlog_pack(body: &str, in_store: bool) -> Option<Vec<u8>> {
+    if in_store {
+        return None;
+    }
+    pack(body).await.unwrap_or_else(|e| {
+        tracing::warn!(error = ?e, "compress_bodies: compression failed");
+        None
+    })
+}
+
+fn codec_for(packed: &Option<Vec<u8>>) -> &'static str {
+    match packed {
+        Some(_) => Codec::Zstd.as_str(),
+        None => Codec::Identity.as_str(),
+    }
+}
+
+/// See migration 0013: keeps the `bump_updated_at` trigger off for the rest
+/// of the transaction.
+pub(crate) async fn preserve_updated_at(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("SELECT set_config('pastedev.preserve_updated_at', 'on', true)")
+        .fetch_one(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_ref_is_sha256_hex() {
+        let r = content_ref(b"hello");
+        assert_eq!(
+            r,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert!(is_valid_ref(&r));
+        assert!(!is_valid_ref("../../etc/passwd"));
+        assert!(!is_valid_ref(&r.to_uppercase()));
+    }
+
+    #[test]
+    fn prefix_respects_char_boundaries() {
+        assert_eq!(prefix("abc", 10), "abc");
+        assert_eq!(prefix("aé", 2), "a");
+        assert_eq!(prefix("aé", 3), "aé");
+    }
+}
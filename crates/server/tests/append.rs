@@ -0,0 +1,187 @@
+//! Appending to a snippet: the size limit, snippets past their lifetime or
+//! burned, compressed bodies, and the `append` event followers get.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+use common::Server;
+
+async fn append(server: &Server, slug: &str, text: &str) -> reqwest::Response {
+    server
+        .http
+        .post(format!("{}/api/v1/snippets/{slug}/append", server.base))
+        .bearer_auth(&server.token)
+        .body(text.to_string())
+        .send()
+        .await
+        .expect("append")
+}
+
+/// What an anonymous reader gets for `slug`.
+async fn anonymous(server: &Server, slug: &str) -> StatusCode {
+    reqwest::get(format!("{}/api/v1/snippets/{slug}", server.base))
+        .await
+        .expect("get")
+        .status()
+}
+
+#[tokio::test]
+async fn appends_are_announced_to_followers() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server.create("start\n").await;
+    let mut events = server
+        .http
+        .get(format!("{}/api/v1/snippets/{slug}/events", server.base))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("follow");
+    assert!(events.status().is_success(), "events: {}", events.status());
+
+    let res = append(&server, &slug, "one\n").await;
+    assert!(res.status().is_success(), "append: {}", res.status());
+    assert!(res.headers().contains_key("etag"));
+    let appended: Value = res.json().await.expect("append json");
+    assert_eq!(
+        (&appended["appended_bytes"], &appended["size_bytes"]),
+        (&json!(4), &json!(10))
+    );
+    assert_eq!(server.body(&slug).await, "start\none\n");
+
+    let mut seen = String::new();
+    let event = loop {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), events.chunk())
+            .await
+            .expect("event in time")
+            .expect("stream")
+            .expect("stream open");
+        seen.push_str(std::str::from_utf8(&chunk).expect("utf-8"));
+        if let Some(data) = seen
+            .split("\n\n")
+            .find(|e| e.contains("event: append"))
+            .and_then(|e| e.lines().find_map(|l| l.strip_prefix("data: ")))
+        {
+            break serde_json::from_str::<Value>(data).expect("event json");
+        }
+    };
+    assert_eq!(
+        event,
+        json!({ "type": "append", "offset": 6, "size_bytes": 10, "text": "one\n" })
+    );
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn appends_past_the_size_limit_are_refused() {
+    let Some(server) = Server::start_with(&[("SNIPPET_MAX_BYTES", "32")]).await else {
+        return;
+    };
+    let slug = server.create(&"a".repeat(20)).await;
+    // Up to the limit exactly ...
+    let res = append(&server, &slug, &"b".repeat(12)).await;
+    assert!(res.status().is_success(), "append: {}", res.status());
+    // ... and not a byte past it, leaving the body as it was.
+    let res = append(&server, &slug, "c").await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let error: Value = res.json().await.expect("error json");
+    assert_eq!(error["error"]["code"], "snippet_too_large");
+    assert_eq!(
+        server.body(&slug).await,
+        format!("{}{}", "a".repeat(20), "b".repeat(12))
+    );
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn compressed_bodies_are_appended_to_in_full() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let log: String = (0..10_000).map(|i| format!("line {i}\n")).collect();
+    let slug = server.create(&log).await;
+    let mut db = server.db().await;
+    let packed = |slug: String| {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT body_packed IS NOT NULL FROM snippets WHERE slug = $1",
+        )
+        .bind(slug)
+    };
+    assert!(packed(slug.clone()).fetch_one(&mut db).await.expect("row"));
+
+    let res = append(&server, &slug, "line 10000\n").await;
+    assert!(res.status().is_success(), "append: {}", res.status());
+    let full = format!("{log}line 10000\n");
+    assert_eq!(server.body(&slug).await, full);
+    let raw = server
+        .http
+        .get(format!("{}/c/{slug}/raw", server.base))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("raw")
+        .text()
+        .await
+        .expect("raw body");
+    assert_eq!(raw, full);
+    // Still stored compressed.
+    assert!(packed(slug).fetch_one(&mut db).await.expect("row"));
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn expired_and_burned_snippets_take_appends_like_edits() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let mut db = server.db().await;
+
+    // The owner may still edit a snippet past its lifetime, so they may
+    // append to it too; neither brings it back for anyone else.
+    let expired = server.create("expired\n").await;
+    sqlx::query("UPDATE snippets SET expires_at = now() - interval '1 minute' WHERE slug = $1")
+        .bind(&expired)
+        .execute(&mut db)
+        .await
+        .expect("expire");
+    assert_eq!(anonymous(&server, &expired).await, StatusCode::NOT_FOUND);
+    let res = server
+        .http
+        .patch(format!("{}/api/v1/snippets/{expired}", server.base))
+        .bearer_auth(&server.token)
+        .json(&json!({ "body": "edited\n" }))
+        .send()
+        .await
+        .expect("patch");
+    assert!(res.status().is_success(), "patch: {}", res.status());
+    let res = append(&server, &expired, "appended\n").await;
+    assert!(res.status().is_success(), "append: {}", res.status());
+    assert_eq!(server.body(&expired).await, "edited\nappended\n");
+    assert_eq!(anonymous(&server, &expired).await, StatusCode::NOT_FOUND);
+
+    // A one-shot secret is burned by its first read.
+    let burned = server
+        .create_with(json!({
+            "type": "code",
+            "body": "secret\n",
+            "burn_after_read": true,
+            "burn_window_seconds": 0,
+        }))
+        .await;
+    let burned = burned["slug"].as_str().expect("slug");
+    assert_eq!(anonymous(&server, burned).await, StatusCode::OK);
+    assert_eq!(anonymous(&server, burned).await, StatusCode::NOT_FOUND);
+    let res = append(&server, burned, "more\n").await;
+    assert!(res.status().is_success(), "append: {}", res.status());
+    assert_eq!(server.body(burned).await, "secret\nmore\n");
+    assert_eq!(anonymous(&server, burned).await, StatusCode::NOT_FOUND);
+    server.stop().await;
+}
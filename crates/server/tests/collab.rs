@@ -0,0 +1,391 @@
+//! Collaborative editing against a running server, driven by headless
+//! WebSocket clients.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use pastedev_core::{ClientMessage, ErrorCode, Selection, ServerMessage, TextOp};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, http::HeaderValue, Error as WsError, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+mod common;
+
+use common::Server;
+
+/// The collab socket.
+trait Collab {
+    async fn connect(&self, slug: &str, headers: &[(&'static str, &str)]) -> Result<Client, u16>;
+    async fn connect_bearer(&self, slug: &str) -> Client;
+}
+
+impl Collab for Server {
+    /// Open the collab socket for `slug` with `headers`, or the HTTP status
+    /// the upgrade was refused with.
+    async fn connect(&self, slug: &str, headers: &[(&'static str, &str)]) -> Result<Client, u16> {
+        let url = format!(
+            "{}/api/v1/snippets/{slug}/collab",
+            self.base.replace("http", "ws")
+        );
+        let mut req = url.into_client_request().expect("request");
+        for (name, value) in headers {
+            req.headers_mut()
+                .insert(*name, HeaderValue::from_str(value).expect("header value"));
+        }
+        match tokio_tungstenite::connect_async(req).await {
+            Ok((socket, _)) => Ok(Client::new(socket).await),
+            Err(WsError::Http(res)) => Err(res.status().as_u16()),
+            Err(e) => panic!("connect: {e}"),
+        }
+    }
+
+    async fn connect_bearer(&self, slug: &str) -> Client {
+        let auth = format!("Bearer {}", self.token);
+        self.connect(slug, &[("authorization", auth.as_str())])
+            .await
+            .expect("upgrade")
+    }
+}
+
+/// A bare-bones editor: the text, the revision it is at, and at most one op
+/// awaiting its ack.
+struct Client {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    id: u32,
+    text: String,
+    revision: u64,
+    pending: Option<TextOp>,
+    peers: Vec<u32>,
+}
+
+impl Client {
+    async fn new(socket: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Client {
+        let mut client = Client {
+            socket,
+            id: 0,
+            text: String::new(),
+            revision: 0,
+            pending: None,
+            peers: Vec::new(),
+        };
+        match client.recv().await {
+            ServerMessage::Hello {
+                client_id,
+                revision,
+                body,
+                peers,
+            } => {
+                client.id = client_id;
+                client.revision = revision;
+                client.text = body;
+                client.peers = peers.iter().map(|p| p.client_id).collect();
+            }
+            other => panic!("expected hello, got {other:?}"),
+        }
+        client
+    }
+
+    async fn recv(&mut self) -> ServerMessage {
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(10), self.socket.next())
+                .await
+                .expect("message in time")
+                .expect("socket open")
+                .expect("frame");
+            match frame {
+                Message::Text(text) => return serde_json::from_str(&text).expect("server message"),
+                Message::Ping(_) | Message::Pong(_) => continue,
+                other => panic!("unexpected frame {other:?}"),
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &ClientMessage) {
+        let text = serde_json::to_string(message).unwrap();
+        self.socket
+            .send(Message::Text(text.into()))
+            .await
+            .expect("send");
+    }
+
+    /// Apply `op` locally and send it.
+    async fn edit(&mut self, op: &str) {
+        assert!(self.pending.is_none(), "one op in flight");
+        let op: TextOp = serde_json::from_str(op).unwrap();
+        self.text = op.apply(&self.text).expect("op fits");
+        self.send(&ClientMessage::Op {
+            revision: self.revision,
+            op: op.clone(),
+        })
+        .await;
+        self.pending = Some(op);
+    }
+
+    /// Receive one message, applying ops and acks to the local state.
+    async fn step(&mut self) -> ServerMessage {
+        let message = self.recv().await;
+        match &message {
+            ServerMessage::Ack { revision } => {
+                self.pending = None;
+                self.revision = *revision;
+            }
+            ServerMessage::Op { op, revision, .. } => {
+                let theirs = match self.pending.take() {
+                    Some(mine) => {
+                        let (mine, theirs) = TextOp::transform(&mine, op).unwrap();
+                        self.pending = Some(mine);
+                        theirs
+                    }
+                    None => op.clone(),
+                };
+                self.text = theirs.apply(&self.text).expect("op fits");
+                self.revision = *revision;
+            }
+            ServerMessage::Join { peer } => self.peers.push(peer.client_id),
+            ServerMessage::Leave { client_id } => self.peers.retain(|p| p != client_id),
+            _ => {}
+        }
+        message
+    }
+
+    /// Step until `done` says so.
+    async fn until(&mut self, mut done: impl FnMut(&ServerMessage) -> bool) -> ServerMessage {
+        loop {
+            let message = self.step().await;
+            if done(&message) {
+                return message;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn concurrent_edits_converge_and_persist() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server.create("hello world\n").await;
+
+    let mut a = server.connect_bearer(&slug).await;
+    assert_eq!(a.text, "hello world\n");
+    assert!(a.peers.is_empty());
+    let mut b = server.connect_bearer(&slug).await;
+    assert_eq!(b.peers, vec![a.id]);
+    a.until(|m| matches!(m, ServerMessage::Join { .. })).await;
+    assert_eq!(a.peers, vec![b.id]);
+
+    // Both edit revision 0 at once.
+    a.edit(r#"[5, ",", 7]"#).await;
+    b.edit(r#"[6, -5, "there", 1]"#).await;
+    let b_id = b.id;
+    let a_id = a.id;
+    for c in [&mut a, &mut b] {
+        let mut acked = false;
+        let mut seen = false;
+        while !(acked && seen) {
+            match c.step().await {
+                ServerMessage::Ack { .. } => acked = true,
+                ServerMessage::Op { client_id, .. } => {
+                    assert_eq!(client_id, Some(if c.id == a_id { b_id } else { a_id }));
+                    seen = true;
+                }
+                other => panic!("unexpected {other:?}"),
+            }
+        }
+    }
+    assert_eq!(a.text, "hello, there\n");
+    assert_eq!(b.text, a.text);
+    assert_eq!((a.revision, b.revision), (2, 2));
+
+    let selection = Selection {
+        anchor: 7,
+        head: 12,
+    };
+    a.send(&ClientMessage::Cursor {
+        revision: a.revision,
+        selection: Some(selection),
+    })
+    .await;
+    match b.until(|m| matches!(m, ServerMessage::Cursor { .. })).await {
+        ServerMessage::Cursor {
+            client_id,
+            selection: got,
+            ..
+        } => {
+            assert_eq!((client_id, got), (a.id, Some(selection)));
+        }
+        _ => unreachable!(),
+    }
+
+    // A write from outside the session is merged in as an op from nobody.
+    let res = server
+        .http
+        .post(format!("{}/api/v1/snippets/{slug}/append", server.base))
+        .bearer_auth(&server.token)
+        .body("appended\n")
+        .send()
+        .await
+        .expect("append");
+    assert!(res.status().is_success(), "append: {}", res.status());
+    for c in [&mut a, &mut b] {
+        let op = c.until(|m| matches!(m, ServerMessage::Op { .. })).await;
+        assert!(matches!(
+            op,
+            ServerMessage::Op {
+                client_id: None,
+                ..
+            }
+        ));
+        assert_eq!(c.text, "hello, there\nappended\n");
+    }
+    // Typing pauses, and the merged text is saved while the session goes on.
+    server.saved(&slug, "hello, there\nappended\n").await;
+
+    // The last one out saves.
+    drop(b);
+    a.until(|m| matches!(m, ServerMessage::Leave { .. })).await;
+    assert!(a.peers.is_empty());
+    a.edit(r#"[22, "bye\n"]"#).await;
+    a.until(|m| matches!(m, ServerMessage::Ack { .. })).await;
+    a.socket.close(None).await.expect("close");
+    let body = "hello, there\nappended\nbye\n";
+    server.saved(&slug, body).await;
+
+    // A new session starts from the saved text.
+    let c = server.connect_bearer(&slug).await;
+    assert_eq!((c.text.as_str(), c.revision), (body, 0));
+    drop(c);
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn bad_ops_and_refused_upgrades() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server.create("abc").await;
+
+    assert_eq!(server.connect(&slug, &[]).await.err(), Some(401));
+    let read_only = format!("Bearer {}", server.key(&["read"]).await);
+    assert_eq!(
+        server
+            .connect(&slug, &[("authorization", read_only.as_str())])
+            .await
+            .err(),
+        Some(403)
+    );
+    let cookie = server.cookie.clone();
+    assert_eq!(
+        server
+            .connect(
+                &slug,
+                &[
+                    ("cookie", cookie.as_str()),
+                    ("origin", "https://evil.example")
+                ]
+            )
+            .await
+            .err(),
+        Some(403)
+    );
+    assert_eq!(
+        server
+            .connect(
+                "nosuchslug",
+                &[
+                    ("cookie", cookie.as_str()),
+                    ("origin", server.base.as_str())
+                ]
+            )
+            .await
+            .err(),
+        Some(404)
+    );
+    let mut a = server
+        .connect(
+            &slug,
+            &[
+                ("cookie", cookie.as_str()),
+                ("origin", server.base.as_str()),
+            ],
+        )
+        .await
+        .expect("cookie upgrade");
+    assert_eq!(a.text, "abc");
+
+    // A malformed message is refused without ending the session.
+    a.socket
+        .send(Message::Text("{\"type\":\"op\"}".into()))
+        .await
+        .unwrap();
+    match a.recv().await {
+        ServerMessage::Error { code, fatal, .. } => {
+            assert_eq!((code, fatal), (ErrorCode::ValidationError, false));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    // An op that doesn't fit the text ends it.
+    a.send(&ClientMessage::Op {
+        revision: 0,
+        op: serde_json::from_str("[5]").unwrap(),
+    })
+    .await;
+    match a.recv().await {
+        ServerMessage::Error { code, fatal, .. } => {
+            assert_eq!((code, fatal), (ErrorCode::ValidationError, true));
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    let end = tokio::time::timeout(Duration::from_secs(10), a.socket.next())
+        .await
+        .expect("closed in time");
+    assert!(matches!(
+        end,
+        None | Some(Ok(Message::Close(_))) | Some(Err(_))
+    ));
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn outside_write_past_the_size_limit_ends_the_session() {
+    let Some(server) = Server::start_with(&[("SNIPPET_MAX_BYTES", "64")]).await else {
+        return;
+    };
+    let slug = server.create("abc\n").await;
+    let mut a = server.connect_bearer(&slug).await;
+
+    // Unsaved, and within the limit on its own ...
+    let ours = "s".repeat(39);
+    a.edit(&format!(r#"[4, "{ours}\n"]"#)).await;
+    a.until(|m| matches!(m, ServerMessage::Ack { .. })).await;
+    // ... as is the outside write, but not both.
+    let theirs = format!("{}\n", "t".repeat(39));
+    let res = server
+        .http
+        .post(format!("{}/api/v1/snippets/{slug}/append", server.base))
+        .bearer_auth(&server.token)
+        .body(theirs.clone())
+        .send()
+        .await
+        .expect("append");
+    assert!(res.status().is_success(), "append: {}", res.status());
+    match a.until(|m| matches!(m, ServerMessage::Error { .. })).await {
+        ServerMessage::Error { code, fatal, .. } => {
+            assert_eq!((code, fatal), (ErrorCode::SnippetTooLarge, true));
+        }
+        _ => unreachable!(),
+    }
+
+    // The outside write stands, and a new session starts from it.
+    let body = format!("abc\n{theirs}");
+    assert_eq!(server.body(&slug).await, body);
+    let b = server.connect_bearer(&slug).await;
+    assert_eq!(b.text, body);
+    drop(b);
+    server.stop().await;
+}
@@ -0,0 +1,77 @@
+//! Preflights from an allowed origin, for what the API's own browser
+//! clients send.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+mod common;
+
+use common::Server;
+
+const ORIGIN: &str = "https://app.example";
+
+/// Whether a preflight for `method` on `path` with `headers` is let through.
+async fn preflight(server: &Server, method: &str, path: &str, headers: &str) -> bool {
+    let res = server
+        .http
+        .request(reqwest::Method::OPTIONS, format!("{}{path}", server.base))
+        .header("origin", ORIGIN)
+        .header("access-control-request-method", method)
+        .header("access-control-request-headers", headers)
+        .send()
+        .await
+        .expect("preflight");
+    let allowed = |name: &str| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+    res.status().is_success()
+        && allowed("access-control-allow-origin") == ORIGIN
+        && allowed("access-control-allow-methods").contains(&method.to_ascii_lowercase())
+        && headers
+            .split(',')
+            .all(|h| allowed("access-control-allow-headers").contains(h.trim()))
+}
+
+#[tokio::test]
+async fn allowed_origins_may_call_every_route() {
+    let Some(server) = Server::start_with(&[("CORS_ALLOWED_ORIGINS", ORIGIN)]).await else {
+        return;
+    };
+    assert!(
+        preflight(
+            &server,
+            "PATCH",
+            "/api/v1/snippets/AbCdEf1",
+            "content-type,if-match"
+        )
+        .await
+    );
+    // Creates that may be retried.
+    for path in ["/api/v1/snippets", "/paste"] {
+        assert!(preflight(&server, "POST", path, "content-type,idempotency-key").await);
+    }
+    // Setting an org member's role and a collection's items.
+    assert!(
+        preflight(
+            &server,
+            "PUT",
+            "/api/v1/orgs/acme/members/someone",
+            "content-type"
+        )
+        .await
+    );
+    assert!(
+        preflight(
+            &server,
+            "PUT",
+            "/api/v1/collections/notes/items",
+            "content-type"
+        )
+        .await
+    );
+    server.stop().await;
+}
@@ -0,0 +1,119 @@
+//! Conditional writes: `If-Match` against the strong ETag `GET` hands out,
+//! and two writers holding the same one.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+use common::Server;
+
+/// The ETag of `slug`'s JSON.
+async fn etag(server: &Server, slug: &str) -> String {
+    let res = server
+        .http
+        .get(format!("{}/api/v1/snippets/{slug}", server.base))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("get");
+    assert!(res.status().is_success(), "get: {}", res.status());
+    res.headers()["etag"].to_str().expect("etag").to_string()
+}
+
+async fn patch(server: &Server, slug: &str, if_match: &str, body: Value) -> reqwest::Response {
+    server
+        .http
+        .patch(format!("{}/api/v1/snippets/{slug}", server.base))
+        .bearer_auth(&server.token)
+        .header("if-match", if_match)
+        .json(&body)
+        .send()
+        .await
+        .expect("patch")
+}
+
+#[tokio::test]
+async fn of_two_writers_with_the_same_etag_only_the_first_gets_through() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server.create("start\n").await;
+    let tag = etag(&server, &slug).await;
+    assert!(!tag.starts_with("W/"), "{tag}");
+
+    let first = patch(&server, &slug, &tag, json!({ "body": "first\n" })).await;
+    assert!(first.status().is_success(), "first: {}", first.status());
+    let second = patch(&server, &slug, &tag, json!({ "body": "second\n" })).await;
+    assert_eq!(second.status(), StatusCode::PRECONDITION_FAILED);
+    let error: Value = second.json().await.expect("error json");
+    assert_eq!(error["error"]["code"], "precondition_failed");
+    assert_eq!(server.body(&slug).await, "first\n");
+
+    // The same race with both in flight at once.
+    let tag = etag(&server, &slug).await;
+    let (a, b) = tokio::join!(
+        patch(&server, &slug, &tag, json!({ "body": "a\n" })),
+        patch(&server, &slug, &tag, json!({ "body": "b\n" })),
+    );
+    let mut statuses = [a.status(), b.status()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::PRECONDITION_FAILED]);
+    let won = if a.status().is_success() {
+        "a\n"
+    } else {
+        "b\n"
+    };
+    assert_eq!(server.body(&slug).await, won);
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn if_match_is_a_strong_comparison() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server
+        .create_with(json!({ "type": "code", "body": "start\n", "visibility": "public" }))
+        .await;
+    let slug = slug["slug"].as_str().expect("slug");
+    let tag = etag(&server, slug).await;
+
+    // A weak form of the current tag doesn't match ...
+    let res = patch(
+        &server,
+        slug,
+        &format!("W/{tag}"),
+        json!({ "name": "weak" }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    // ... nor does the raw body's.
+    let raw = server
+        .http
+        .get(format!("{}/c/{slug}/raw", server.base))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("raw");
+    let raw = raw.headers()["etag"].to_str().expect("etag").to_string();
+    assert_ne!(raw, tag);
+    let res = patch(&server, slug, &raw, json!({ "name": "raw" })).await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+
+    // Someone else reading it in between isn't a change.
+    let res = reqwest::get(format!("{}/api/v1/snippets/{slug}", server.base))
+        .await
+        .expect("anonymous get");
+    assert!(res.status().is_success(), "anonymous get: {}", res.status());
+    let res = patch(&server, slug, &tag, json!({ "name": "strong" })).await;
+    assert!(res.status().is_success(), "patch: {}", res.status());
+    // The write hands out the tag a GET now would.
+    let next = res.headers()["etag"].to_str().expect("etag").to_string();
+    assert_ne!(next, tag);
+    assert_eq!(etag(&server, slug).await, next);
+    server.stop().await;
+}
@@ -0,0 +1,124 @@
+//! Share links as an anonymous browser follows them: the viewer page, the
+//! API read the SPA makes from it, and the raw copy it links to, all of
+//! which are one use of the link.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+use serde_json::{json, Value};
+
+mod common;
+
+use common::Server;
+
+#[tokio::test]
+async fn anonymous_reader_follows_a_link_through_the_viewer() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let snippet = server
+        .create_with(json!({
+            "type": "code",
+            "name": "linked-notes",
+            "body": "for link holders\n",
+            "visibility": "private",
+        }))
+        .await;
+    let slug = snippet["slug"].as_str().expect("slug");
+    let link: Value = server
+        .http
+        .post(format!("{}/api/v1/snippets/{slug}/links", server.base))
+        .bearer_auth(&server.token)
+        .json(&json!({ "ttl_seconds": 3600, "max_uses": 1 }))
+        .send()
+        .await
+        .expect("create link")
+        .json()
+        .await
+        .expect("link json");
+    let url = link["url"].as_str().expect("link url");
+    let token = url.split_once("?token=").expect("token in url").1;
+
+    // No cookies, no key: what a browser that was only sent the link has.
+    let anon = reqwest::Client::new();
+    let api = format!("{}/api/v1/snippets/{slug}", server.base);
+    assert!(!anon
+        .get(&api)
+        .send()
+        .await
+        .expect("get")
+        .status()
+        .is_success());
+
+    // The viewer page knows the snippet through the link, and loading it
+    // doesn't use the link up.
+    let page = anon.get(url).send().await.expect("viewer page");
+    assert!(page.status().is_success(), "page: {}", page.status());
+    assert!(page.text().await.expect("page").contains("linked-notes"));
+
+    // The SPA passes the token on to the API ...
+    let res = anon
+        .get(format!("{api}?token={token}"))
+        .send()
+        .await
+        .expect("get with token");
+    assert!(res.status().is_success(), "api: {}", res.status());
+    let read: Value = res.json().await.expect("snippet json");
+    assert_eq!(read["body"], "for link holders\n");
+    // ... which hands it on to the raw copy.
+    let raw_url = read["raw_url"].as_str().expect("raw url");
+    assert!(raw_url.ends_with(&format!("?token={token}")), "{raw_url}");
+    assert!(read["url"]
+        .as_str()
+        .expect("url")
+        .ends_with(&format!("?token={token}")));
+    let raw = anon.get(raw_url).send().await.expect("raw");
+    assert!(raw.status().is_success(), "raw: {}", raw.status());
+    assert_eq!(raw.text().await.expect("raw body"), "for link holders\n");
+
+    // One viewer is one use: they may come back ...
+    let res = anon
+        .get(format!("{api}?token={token}"))
+        .send()
+        .await
+        .expect("get with token");
+    assert!(res.status().is_success(), "again: {}", res.status());
+    // ... but the link is used up for anyone else.
+    let other = reqwest::Client::builder()
+        .user_agent("another-browser")
+        .build()
+        .expect("client");
+    let res = other
+        .get(format!("{api}?token={token}"))
+        .send()
+        .await
+        .expect("get with token");
+    assert!(!res.status().is_success(), "used up: {}", res.status());
+    let res = other.get(raw_url).send().await.expect("raw");
+    assert!(!res.status().is_success(), "used up: {}", res.status());
+    let links: Value = server
+        .http
+        .get(format!("{}/api/v1/snippets/{slug}/links", server.base))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("list links")
+        .json()
+        .await
+        .expect("links json");
+    assert_eq!(links["items"][0]["uses"], 1);
+
+    // The owner's own read isn't a link read, and carries no token.
+    let own: Value = server
+        .http
+        .get(format!("{api}?token={token}"))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("owner get")
+        .json()
+        .await
+        .expect("snippet json");
+    assert!(!own["raw_url"].as_str().expect("raw url").contains("token"));
+    server.stop().await;
+}
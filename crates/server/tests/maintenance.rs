@@ -0,0 +1,200 @@
+//! The maintenance scheduler against a real database: jobs working through
+//! their backlog in batches, skipping a round while another instance holds
+//! the job's lock, and auditing each run.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+use std::time::Duration;
+
+use serde_json::Value;
+use sqlx::{Connection, PgConnection};
+
+mod common;
+
+use common::Server;
+
+/// Every job off but `job`, which runs each second.
+async fn start(job: &str, extra: &[(&str, &str)]) -> Option<Server> {
+    let mut env = vec![
+        ("MAINTENANCE_EXPIRED_INTERVAL_SECS", "0"),
+        ("MAINTENANCE_TRASH_INTERVAL_SECS", "0"),
+        ("MAINTENANCE_VIEWS_INTERVAL_SECS", "0"),
+        ("MAINTENANCE_SESSIONS_INTERVAL_SECS", "0"),
+        ("MAINTENANCE_BLOBS_INTERVAL_SECS", "0"),
+        ("MAINTENANCE_COMPRESS_INTERVAL_SECS", "0"),
+        ("MAINTENANCE_IDEMPOTENCY_INTERVAL_SECS", "0"),
+    ];
+    for (name, value) in env.iter_mut() {
+        if *name == job {
+            *value = "1";
+        }
+    }
+    env.extend_from_slice(extra);
+    Server::start_with(&env).await
+}
+
+/// `n` sessions of the admin's that ran out an hour ago.
+async fn expired_sessions(db: &mut PgConnection, n: i32) {
+    sqlx::query(
+        "INSERT INTO sessions (id, user_id, expires_at)
+         SELECT uuid_send(gen_random_uuid()), u.id, now() - interval '1 hour'
+         FROM users u, generate_series(1, $1)
+         WHERE u.username = 'admin'",
+    )
+    .bind(n)
+    .execute(db)
+    .await
+    .expect("insert sessions");
+}
+
+async fn count_expired_sessions(db: &mut PgConnection) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM sessions WHERE expires_at < now()")
+        .fetch_one(db)
+        .await
+        .expect("count sessions")
+}
+
+/// Payloads of the `maintenance.run` rows for `job`, oldest first.
+async fn runs(db: &mut PgConnection, job: &str) -> Vec<Value> {
+    sqlx::query_scalar(
+        "SELECT payload FROM audit_log
+         WHERE event = 'maintenance.run' AND payload->>'job' = $1
+         ORDER BY id",
+    )
+    .bind(job)
+    .fetch_all(db)
+    .await
+    .expect("audit rows")
+}
+
+/// Wait for a run of `job` that did something, and return what it recorded.
+async fn next_run(db: &mut PgConnection, job: &str, seen: usize) -> Value {
+    for _ in 0..100 {
+        let busy: Vec<Value> = runs(db, job)
+            .await
+            .into_iter()
+            .filter(|r| r["affected"].as_u64() != Some(0))
+            .collect();
+        if let Some(run) = busy.get(seen) {
+            return run.clone();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("no run of {job} did anything");
+}
+
+#[tokio::test]
+async fn jobs_work_in_batches_until_a_short_one() {
+    let Some(server) = start("MAINTENANCE_SESSIONS_INTERVAL_SECS", &[]).await else {
+        return;
+    };
+    let mut db = server.db().await;
+
+    // Exactly two full batches: the third comes back empty and ends the run.
+    expired_sessions(&mut db, 1000).await;
+    let run = next_run(&mut db, "expired_sessions", 0).await;
+    assert_eq!(run["affected"], 1000);
+    assert!(run["duration_ms"].is_u64(), "{run}");
+    assert_eq!(count_expired_sessions(&mut db).await, 0);
+
+    // Two full and a short one.
+    expired_sessions(&mut db, 1203).await;
+    let run = next_run(&mut db, "expired_sessions", 1).await;
+    assert_eq!(run["affected"], 1203);
+    assert_eq!(count_expired_sessions(&mut db).await, 0);
+
+    // Runs with nothing to do are recorded too, one row each.
+    let idle = runs(&mut db, "expired_sessions")
+        .await
+        .into_iter()
+        .filter(|r| r["affected"] == 0)
+        .count();
+    assert!(idle > 0);
+    // Live sessions are left alone.
+    let live: i64 = sqlx::query_scalar("SELECT count(*) FROM sessions")
+        .fetch_one(&mut db)
+        .await
+        .expect("count sessions");
+    assert!(live > 0);
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn a_held_lock_skips_the_round() {
+    let Some(server) = start("MAINTENANCE_SESSIONS_INTERVAL_SECS", &[]).await else {
+        return;
+    };
+    let mut db = server.db().await;
+
+    // Another instance, as far as the server can tell, is running the job.
+    let mut other = PgConnection::connect(&server.database_url)
+        .await
+        .expect("connect");
+    sqlx::query("SELECT pg_advisory_lock(hashtext('pastedev:maintenance:expired_sessions'))")
+        .execute(&mut other)
+        .await
+        .expect("lock");
+    // A run that had the lock before us records itself once it lets go.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let before = runs(&mut db, "expired_sessions").await.len();
+    expired_sessions(&mut db, 10).await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    // The rounds that found it held did nothing, and aren't runs.
+    assert_eq!(count_expired_sessions(&mut db).await, 10);
+    assert_eq!(runs(&mut db, "expired_sessions").await.len(), before);
+
+    // Once it lets go, the next round goes ahead.
+    sqlx::query("SELECT pg_advisory_unlock(hashtext('pastedev:maintenance:expired_sessions'))")
+        .execute(&mut other)
+        .await
+        .expect("unlock");
+    let run = next_run(&mut db, "expired_sessions", 0).await;
+    assert_eq!(run["affected"], 10);
+    assert_eq!(count_expired_sessions(&mut db).await, 0);
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn purged_snippets_are_audited_with_the_run() {
+    let Some(server) = start(
+        "MAINTENANCE_EXPIRED_INTERVAL_SECS",
+        &[("EXPIRED_SNIPPET_GRACE_DAYS", "0")],
+    )
+    .await
+    else {
+        return;
+    };
+    let mut db = server.db().await;
+    let gone = server.create("gone\n").await;
+    let kept = server.create("kept\n").await;
+    sqlx::query("UPDATE snippets SET expires_at = now() - interval '1 minute' WHERE slug = $1")
+        .bind(&gone)
+        .execute(&mut db)
+        .await
+        .expect("expire");
+
+    let run = next_run(&mut db, "expired_snippets", 0).await;
+    assert_eq!(run["affected"], 1);
+    let slugs: Vec<String> = sqlx::query_scalar("SELECT slug FROM snippets")
+        .fetch_all(&mut db)
+        .await
+        .expect("slugs");
+    assert_eq!(slugs, vec![kept]);
+    let purged: Vec<Value> = sqlx::query_scalar(
+        "SELECT payload FROM audit_log WHERE event = 'snippet.purge' ORDER BY id",
+    )
+    .fetch_all(&mut db)
+    .await
+    .expect("audit rows");
+    assert_eq!(purged.len(), 1);
+    assert_eq!(
+        (
+            &purged[0]["slug"],
+            &purged[0]["kind"],
+            &purged[0]["grace_days"]
+        ),
+        (&Value::from(gone), &Value::from("expired"), &Value::from(0))
+    );
+    server.stop().await;
+}
@@ -0,0 +1,272 @@
+//! Edit history: what gets recorded, how it is numbered under concurrent
+//! edits, who may see it, and purging it.
+//!
+//! Like the other server tests, these need `PASTEDEV_TEST_DATABASE_URL`; see
+//! [`common`].
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+mod common;
+
+use common::Server;
+
+async fn patch(server: &Server, slug: &str, body: Value) -> StatusCode {
+    server
+        .http
+        .patch(format!("{}/api/v1/snippets/{slug}", server.base))
+        .bearer_auth(&server.token)
+        .json(&body)
+        .send()
+        .await
+        .expect("patch")
+        .status()
+}
+
+/// Revision numbers, newest first, as `token` sees them.
+async fn revisions(server: &Server, slug: &str, token: &str) -> Result<Vec<i64>, StatusCode> {
+    let res = server
+        .http
+        .get(format!("{}/api/v1/snippets/{slug}/revisions", server.base))
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect("list revisions");
+    if !res.status().is_success() {
+        return Err(res.status());
+    }
+    let list: Value = res.json().await.expect("revisions json");
+    Ok(list["items"]
+        .as_array()
+        .expect("items")
+        .iter()
+        .map(|i| i["revision"].as_i64().expect("revision"))
+        .collect())
+}
+
+async fn revision(server: &Server, slug: &str, n: i64) -> Result<Value, StatusCode> {
+    let res = server
+        .http
+        .get(format!(
+            "{}/api/v1/snippets/{slug}/revisions/{n}",
+            server.base
+        ))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("get revision");
+    if !res.status().is_success() {
+        return Err(res.status());
+    }
+    Ok(res.json().await.expect("revision json"))
+}
+
+#[tokio::test]
+async fn edits_are_recorded_and_numbered_densely() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server.create("one\n").await;
+    assert_eq!(revisions(&server, &slug, &server.token).await, Ok(vec![1]));
+
+    assert!(
+        patch(&server, &slug, json!({ "body": "two\n", "name": "n" }))
+            .await
+            .is_success()
+    );
+    let two = revision(&server, &slug, 2).await.expect("revision 2");
+    assert_eq!((&two["body"], &two["name"]), (&json!("two\n"), &json!("n")));
+    assert_eq!(two["author"]["username"], "admin");
+    assert_eq!(
+        revision(&server, &slug, 1).await.expect("revision 1")["body"],
+        "one\n"
+    );
+
+    // A settings change doesn't touch the text, so it isn't a revision.
+    let res = server
+        .http
+        .patch(format!("{}/api/v1/snippets/{slug}/settings", server.base))
+        .bearer_auth(&server.token)
+        .json(&json!({ "visibility": "private" }))
+        .send()
+        .await
+        .expect("settings");
+    assert!(res.status().is_success(), "settings: {}", res.status());
+    assert_eq!(
+        revisions(&server, &slug, &server.token).await,
+        Ok(vec![2, 1])
+    );
+
+    // Concurrent edits each get their own number, with no gaps.
+    let edits = (0..8).map(|i| patch(&server, &slug, json!({ "body": format!("edit {i}\n") })));
+    for status in futures_util::future::join_all(edits).await {
+        assert!(status.is_success(), "edit: {status}");
+    }
+    let numbers = revisions(&server, &slug, &server.token)
+        .await
+        .expect("list");
+    assert_eq!(numbers, (1..=10).rev().collect::<Vec<_>>());
+    let mut bodies = Vec::new();
+    for n in 3..=10 {
+        bodies.push(revision(&server, &slug, n).await.expect("revision")["body"].clone());
+    }
+    bodies.sort_by_key(|b| b.to_string());
+    let expected: Vec<Value> = (0..8).map(|i| json!(format!("edit {i}\n"))).collect();
+    assert_eq!(bodies, expected);
+    assert_eq!(
+        revision(&server, &slug, 10).await.expect("latest")["body"],
+        server.body(&slug).await
+    );
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn history_is_for_editors_and_can_be_purged() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let slug = server.create("token = hunter2\n").await;
+    assert!(
+        patch(&server, &slug, json!({ "body": "token = <redacted>\n" }))
+            .await
+            .is_success()
+    );
+
+    // Anyone may read the public snippet, but not what was edited out of it.
+    let reader = server.user("reader").await;
+    let res = server
+        .http
+        .get(format!("{}/api/v1/snippets/{slug}", server.base))
+        .bearer_auth(&reader)
+        .send()
+        .await
+        .expect("get");
+    assert!(res.status().is_success(), "get: {}", res.status());
+    assert_eq!(
+        revisions(&server, &slug, &reader).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+    for path in ["revisions/1", "diff"] {
+        let res = server
+            .http
+            .get(format!("{}/api/v1/snippets/{slug}/{path}", server.base))
+            .bearer_auth(&reader)
+            .send()
+            .await
+            .expect("history");
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{path}");
+        let res = server
+            .http
+            .get(format!("{}/api/v1/snippets/{slug}/{path}", server.base))
+            .send()
+            .await
+            .expect("history");
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{path}");
+    }
+
+    let purge = |token: String| {
+        server
+            .http
+            .delete(format!("{}/api/v1/snippets/{slug}/revisions", server.base))
+            .bearer_auth(token)
+            .send()
+    };
+    assert_eq!(
+        purge(reader.clone()).await.expect("purge").status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        purge(server.key(&["read", "publish"]).await)
+            .await
+            .expect("purge")
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        purge(server.token.clone()).await.expect("purge").status(),
+        StatusCode::NO_CONTENT
+    );
+
+    // Only the snippet as it stands is left, and numbering carries on.
+    assert_eq!(revisions(&server, &slug, &server.token).await, Ok(vec![2]));
+    assert_eq!(
+        revision(&server, &slug, 1).await.err(),
+        Some(StatusCode::NOT_FOUND)
+    );
+    assert_eq!(
+        revision(&server, &slug, 2).await.expect("latest")["body"],
+        "token = <redacted>\n"
+    );
+    assert!(patch(&server, &slug, json!({ "body": "three\n" }))
+        .await
+        .is_success());
+    assert_eq!(
+        revisions(&server, &slug, &server.token).await,
+        Ok(vec![3, 2])
+    );
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn org_edits_are_credited_to_whoever_made_them() {
+    let Some(server) = Server::start().await else {
+        return;
+    };
+    let res = server
+        .http
+        .post(format!("{}/api/v1/orgs", server.base))
+        .header("origin", &server.base)
+        .header("cookie", &server.cookie)
+        .json(&json!({ "name": "acme" }))
+        .send()
+        .await
+        .expect("create org");
+    assert!(res.status().is_success(), "org: {}", res.status());
+    let member = server.user("member").await;
+    let res = server
+        .http
+        .put(format!("{}/api/v1/orgs/acme/members/member", server.base))
+        .header("origin", &server.base)
+        .header("cookie", &server.cookie)
+        .json(&json!({ "role": "member" }))
+        .send()
+        .await
+        .expect("add member");
+    assert!(res.status().is_success(), "member: {}", res.status());
+
+    let snippet = server
+        .create_with(json!({ "type": "code", "body": "one\n", "org": "acme" }))
+        .await;
+    let slug = snippet["slug"].as_str().expect("slug");
+    let res = server
+        .http
+        .patch(format!("{}/api/v1/snippets/{slug}", server.base))
+        .bearer_auth(&member)
+        .json(&json!({ "body": "two\n" }))
+        .send()
+        .await
+        .expect("patch");
+    assert!(res.status().is_success(), "patch: {}", res.status());
+
+    let list: Value = server
+        .http
+        .get(format!("{}/api/v1/snippets/{slug}/revisions", server.base))
+        .bearer_auth(&server.token)
+        .send()
+        .await
+        .expect("list revisions")
+        .json()
+        .await
+        .expect("revisions json");
+    let authors: Vec<(i64, &str)> = list["items"]
+        .as_array()
+        .expect("items")
+        .iter()
+        .map(|i| {
+            let author = i["author"]["username"].as_str().expect("author");
+            (i["revision"].as_i64().expect("revision"), author)
+        })
+        .collect();
+    assert_eq!(authors, vec![(2, "member"), (1, "admin")]);
+    server.stop().await;
+}
@@ -154,8 +154,16 @@ export const resetPassword = (id: string, new_password: string) =>
 // snippets
 export const createSnippet = (input: CreateSnippetInput) =>
   call<Snippet>('POST', '/api/v1/snippets', input);
-export const getSnippet = (slug: string) =>
-  call<Snippet>('GET', `/api/v1/snippets/${encodeURIComponent(slug)}`);
+/// `token` is a share link's, passed on from the page URL so that someone
+/// who only holds the link can read the snippet. The server then puts it on
+/// `url` / `raw_url` as well.
+export const getSnippet = (slug: string, token?: string) => {
+  const qs = token ? `?token=${encodeURIComponent(token)}` : '';
+  return call<Snippet>('GET', `/api/v1/snippets/${encodeURIComponent(slug)}${qs}`);
+};
+/// The share-link token of a viewer page opened from a link, if any.
+export const shareToken = (query: Record<string, unknown>) =>
+  typeof query.token === 'string' ? query.token : undefined;
 export const patchSnippet = (slug: string, patch: PatchSnippetInput) =>
   call<Snippet>('PATCH', `/api/v1/snippets/${encodeURIComponent(slug)}`, patch);
 export const deleteSnippet = (slug: string) =>